@@ -0,0 +1,697 @@
+//! NetworkManager backend that talks to `org.freedesktop.NetworkManager`
+//! over D-Bus instead of spawning `nmcli`.
+//!
+//! Reading properties directly avoids the cost of a process per query and
+//! does not depend on nmcli's locale or terse output format.
+//!
+//! # D-Bus Objects Used
+//!
+//! | Interface                                          | Used for                            |
+//! |----------------------------------------------------|-------------------------------------|
+//! | `org.freedesktop.NetworkManager`                   | Device enumeration, activation      |
+//! | `org.freedesktop.NetworkManager.Device`            | Name, type, state, disconnect       |
+//! | `org.freedesktop.NetworkManager.Device.Wireless`   | Scan requests, access point lists   |
+//! | `org.freedesktop.NetworkManager.AccessPoint`       | SSID, strength, security flags      |
+//! | `org.freedesktop.NetworkManager.IP4Config`         | Address and gateway                 |
+//! | `org.freedesktop.NetworkManager.Settings`          | Saved connection profiles           |
+//!
+//! # Testing
+//!
+//! [`DbusBackend::session`] connects to the session bus instead of the system
+//! bus, so a mock service owning `org.freedesktop.NetworkManager` on a private
+//! session bus can stand in for the real daemon. `tests/dbus_backend.rs`
+//! runs such a mock on a private `dbus-daemon`.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use zbus::blocking::{proxy::Builder, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+use super::{iw, NetworkBackend};
+use crate::connection::{ConnectionStatus, LinkQuality};
+use crate::error::WifiProxyError;
+use crate::interface::{is_usb_interface, WifiInterface};
+use crate::scan::{Mode, Network, Security};
+
+/// Well-known bus name of the NetworkManager daemon.
+const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+
+/// Object path of the NetworkManager root object.
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+
+/// Object path of the NetworkManager settings object.
+const NM_SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+
+const NM_IFACE: &str = "org.freedesktop.NetworkManager";
+const DEVICE_IFACE: &str = "org.freedesktop.NetworkManager.Device";
+const WIRELESS_IFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const AP_IFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const IP4_IFACE: &str = "org.freedesktop.NetworkManager.IP4Config";
+const ACTIVE_IFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const SETTINGS_IFACE: &str = "org.freedesktop.NetworkManager.Settings";
+const CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+
+/// `NM_DEVICE_TYPE_WIFI` from NetworkManager's `NMDeviceType`.
+const DEVICE_TYPE_WIFI: u32 = 2;
+
+/// `NM_ACTIVE_CONNECTION_STATE_ACTIVATED`.
+const ACTIVE_STATE_ACTIVATED: u32 = 2;
+
+/// `NM_ACTIVE_CONNECTION_STATE_DEACTIVATED`.
+const ACTIVE_STATE_DEACTIVATED: u32 = 4;
+
+/// Access point flag: the AP requires encryption (`NM_802_11_AP_FLAGS_PRIVACY`).
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+
+/// Security flag: pre-shared key authentication (`NM_802_11_AP_SEC_KEY_MGMT_PSK`).
+const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+
+/// Security flag: enterprise authentication (`NM_802_11_AP_SEC_KEY_MGMT_802_1X`).
+const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+
+/// Security flag: WPA3 personal (`NM_802_11_AP_SEC_KEY_MGMT_SAE`).
+const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+
+/// Access point mode: ad-hoc (`NM_802_11_MODE_ADHOC`).
+const MODE_ADHOC: u32 = 1;
+
+/// Access point mode: infrastructure (`NM_802_11_MODE_INFRA`).
+const MODE_INFRA: u32 = 2;
+
+/// Access point mode: mesh point (`NM_802_11_MODE_MESH`).
+const MODE_MESH: u32 = 4;
+
+/// How long to wait for a requested scan to complete.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for a connection to activate (nmcli waits 90s; robots are close).
+const ACTIVATION_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// Interval between property polls while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Network backend that drives NetworkManager through its D-Bus API.
+#[derive(Debug, Clone)]
+pub struct DbusBackend {
+    /// Bus connection used for all calls (cheap to clone).
+    conn: Connection,
+}
+
+impl DbusBackend {
+    /// Connects to NetworkManager on the system bus.
+    ///
+    /// # Returns
+    /// - `Ok(DbusBackend)` if the system bus is reachable
+    /// - `Err(WifiProxyError::Dbus)` if no system bus connection can be made
+    pub fn system() -> Result<Self> {
+        Connection::system().map(Self::with_connection).map_err(dbus_error)
+    }
+
+    /// Connects to a NetworkManager-compatible service on the session bus.
+    ///
+    /// Intended for tests that run a mock service instead of the real daemon.
+    pub fn session() -> Result<Self> {
+        Connection::session().map(Self::with_connection).map_err(dbus_error)
+    }
+
+    /// Uses an existing bus connection.
+    pub fn with_connection(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    /// Builds an uncached proxy for an object on the NetworkManager service.
+    ///
+    /// Properties are not cached so polling loops always see fresh values.
+    fn proxy(&self, path: &str, interface: &'static str) -> Result<Proxy<'static>> {
+        let path = ObjectPath::try_from(path.to_string()).map_err(|e| dbus_error(e.into()))?;
+        Builder::new(&self.conn)
+            .destination(NM_SERVICE)
+            .and_then(|b| b.path(path))
+            .and_then(|b| b.interface(interface))
+            .map(|b| b.cache_properties(CacheProperties::No))
+            .and_then(|b| b.build())
+            .map_err(dbus_error)
+    }
+
+    /// Reads a property from an object, converting D-Bus errors.
+    fn property<T>(&self, path: &str, interface: &'static str, name: &str) -> Result<T>
+    where
+        T: TryFrom<OwnedValue>,
+        T::Error: Into<zbus::Error>,
+    {
+        self.proxy(path, interface)?
+            .get_property(name)
+            .map_err(dbus_error)
+    }
+
+    /// Returns the object paths of all devices known to NetworkManager.
+    fn devices(&self) -> Result<Vec<OwnedObjectPath>> {
+        self.proxy(NM_PATH, NM_IFACE)?
+            .call("GetDevices", &())
+            .map_err(dbus_error)
+    }
+
+    /// Finds the WiFi device object for an interface name.
+    ///
+    /// # Returns
+    /// - `Ok(OwnedObjectPath)` of the device
+    /// - `Err(WifiProxyError::InterfaceNotFound)` if no device has that name
+    /// - `Err(WifiProxyError::NotWifiInterface)` if the device is not WiFi
+    fn find_device(&self, interface: &str) -> Result<OwnedObjectPath> {
+        for path in self.devices()? {
+            let name: String = self.property(&path, DEVICE_IFACE, "Interface")?;
+            if name != interface {
+                continue;
+            }
+            let device_type: u32 = self.property(&path, DEVICE_IFACE, "DeviceType")?;
+            if device_type != DEVICE_TYPE_WIFI {
+                return Err(WifiProxyError::NotWifiInterface(interface.to_string()).into());
+            }
+            return Ok(path);
+        }
+        Err(WifiProxyError::InterfaceNotFound(interface.to_string()).into())
+    }
+
+    /// Asks the device to scan and waits until `LastScan` changes.
+    ///
+    /// A rejected scan request (e.g., a scan is already running) is not an
+    /// error; the wait still picks up the in-progress scan's completion.
+    fn request_scan(&self, device: &str) -> Result<()> {
+        let wireless = self.proxy(device, WIRELESS_IFACE)?;
+        let last_scan: i64 = wireless.get_property("LastScan").unwrap_or(-1);
+
+        // Options map is empty: scan for all SSIDs
+        let options: HashMap<&str, Value> = HashMap::new();
+        let _ = wireless.call::<_, _, ()>("RequestScan", &(options,));
+
+        // Wait for NetworkManager to publish new results
+        let deadline = Instant::now() + SCAN_TIMEOUT;
+        while Instant::now() < deadline {
+            std::thread::sleep(POLL_INTERVAL);
+            let current: i64 = wireless.get_property("LastScan").unwrap_or(-1);
+            if current != last_scan {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the access point object paths currently visible to a device.
+    fn access_points(&self, device: &str) -> Result<Vec<OwnedObjectPath>> {
+        self.proxy(device, WIRELESS_IFACE)?
+            .call("GetAllAccessPoints", &())
+            .map_err(dbus_error)
+    }
+
+    /// Reads an access point's properties into a scan entry.
+    fn read_access_point(&self, path: &str) -> Result<Network> {
+        let ap = self.proxy(path, AP_IFACE)?;
+        let ssid: Vec<u8> = ap.get_property("Ssid").map_err(dbus_error)?;
+        let signal: u8 = ap.get_property("Strength").map_err(dbus_error)?;
+        let flags: u32 = ap.get_property("Flags").map_err(dbus_error)?;
+        let wpa_flags: u32 = ap.get_property("WpaFlags").map_err(dbus_error)?;
+        let rsn_flags: u32 = ap.get_property("RsnFlags").map_err(dbus_error)?;
+
+        // Radio details are informational; older NetworkManager versions may lack some
+        let frequency: u32 = ap.get_property("Frequency").unwrap_or(0);
+        let max_bitrate: u32 = ap.get_property("MaxBitrate").unwrap_or(0);
+        let mode: u32 = ap.get_property("Mode").unwrap_or(0);
+
+        Ok(Network {
+            ssid: String::from_utf8_lossy(&ssid).into_owned(),
+            bssid: ap.get_property("HwAddress").unwrap_or_default(),
+            signal,
+            security: Security::parse(&security_string(flags, wpa_flags, rsn_flags)),
+            frequency_mhz: (frequency > 0).then_some(frequency),
+            // MaxBitrate is in kbit/s
+            max_rate_mbps: (max_bitrate > 0).then(|| f64::from(max_bitrate) / 1000.0),
+            mode: match mode {
+                MODE_ADHOC => Some(Mode::AdHoc),
+                MODE_INFRA => Some(Mode::Infrastructure),
+                MODE_MESH => Some(Mode::Mesh),
+                _ => None,
+            },
+            ..Network::default()
+        })
+    }
+
+    /// Reads every access point visible to a device.
+    ///
+    /// Access points come and go while the list is read: one that vanishes
+    /// between `GetAllAccessPoints` and reading its properties is skipped
+    /// instead of failing the whole scan.
+    fn visible_networks(&self, device: &str) -> Result<Vec<(OwnedObjectPath, Network)>> {
+        Ok(self
+            .access_points(device)?
+            .into_iter()
+            .filter_map(|path| {
+                let network = self.read_access_point(&path).ok()?;
+                Some((path, network))
+            })
+            .collect())
+    }
+
+    /// Finds a visible access point by SSID, preferring the strongest one.
+    ///
+    /// # Returns
+    /// The access point's object path and scan entry, or `None` if no
+    /// matching access point is visible
+    fn find_access_point(
+        &self,
+        device: &str,
+        ssid: &str,
+        bssid: Option<&str>,
+    ) -> Result<Option<(OwnedObjectPath, Network)>> {
+        let mut best: Option<(OwnedObjectPath, Network)> = None;
+        for (path, network) in self.visible_networks(device)? {
+            let wanted = network.ssid == ssid && bssid.is_none_or(|b| network.bssid.eq_ignore_ascii_case(b));
+            if wanted && best.as_ref().is_none_or(|(_, b)| network.signal > b.signal) {
+                best = Some((path, network));
+            }
+        }
+        Ok(best)
+    }
+
+    /// Creates or updates the profile for an SSID and activates it.
+    ///
+    /// A hidden network's access point cannot be found by its SSID, so the
+    /// profile is marked hidden and NetworkManager picks the access point.
+    /// With a BSSID, that access point is activated and the profile is
+    /// restricted to it; without one, such a restriction is lifted.
+    fn activate(&self, interface: &str, ssid: &str, bssid: Option<&str>, password: &str, hidden: bool) -> Result<()> {
+        let device = self.find_device(interface)?;
+
+        // Look for the access point, rescanning once if it isn't cached yet
+        let (ap, network) = if hidden {
+            (OwnedObjectPath::from(ObjectPath::from_static_str_unchecked("/")), None)
+        } else {
+            let (ap, network) = match self.find_access_point(&device, ssid, bssid)? {
+                Some(found) => found,
+                None => {
+                    self.request_scan(&device)?;
+                    self.find_access_point(&device, ssid, bssid)?.ok_or_else(|| {
+                        let name = match bssid {
+                            Some(bssid) => format!("{} ({})", ssid, bssid),
+                            None => ssid.to_string(),
+                        };
+                        WifiProxyError::NetworkNotFound(name)
+                    })?
+                }
+            };
+            (ap, Some(network))
+        };
+
+        // Only an access point seen advertising no security is known to be open
+        let open = network.is_some_and(|network| !network.security.requires_password());
+
+        // Build the connection profile (same shape nmcli creates)
+        let mut connection: HashMap<&str, Value> = HashMap::new();
+        connection.insert("id", Value::from(ssid));
+        connection.insert("type", Value::from("802-11-wireless"));
+
+        let mut wireless: HashMap<&str, Value> = HashMap::new();
+        wireless.insert("ssid", Value::from(ssid.as_bytes()));
+        wireless.insert("mode", Value::from("infrastructure"));
+        wireless.insert("hidden", Value::from(hidden));
+        if let Some(bssid) = bssid {
+            wireless.insert("bssid", Value::from(bssid_bytes(bssid)?));
+        }
+
+        let mut settings: HashMap<&str, HashMap<&str, Value>> = HashMap::new();
+        if !password.is_empty() {
+            let mut security: HashMap<&str, Value> = HashMap::new();
+            security.insert("key-mgmt", Value::from("wpa-psk"));
+            security.insert("psk", Value::from(password));
+            settings.insert("802-11-wireless-security", security);
+        }
+
+        let nm = self.proxy(NM_PATH, NM_IFACE)?;
+        let active: OwnedObjectPath = match self.find_profile(ssid)? {
+            // Existing profile: update credentials in place, then activate it
+            Some((profile, existing)) => {
+                settings.insert("connection", connection);
+                settings.insert("802-11-wireless", wireless);
+                let mut settings = merge_settings(existing, settings, open)?;
+
+                // A plain connect may use any access point again
+                if bssid.is_none()
+                    && let Some(wireless) = settings.get_mut("802-11-wireless")
+                {
+                    wireless.remove("bssid");
+                }
+
+                self.proxy(&profile, CONNECTION_IFACE)?
+                    .call::<_, _, ()>("Update", &(settings,))
+                    .map_err(|e| WifiProxyError::ConnectionFailed(e.to_string()))?;
+                nm.call("ActivateConnection", &(&profile, &device, &ap))
+                    .map_err(|e| WifiProxyError::ConnectionFailed(e.to_string()))?
+            }
+
+            // No profile yet: let NetworkManager create and activate one
+            None => {
+                settings.insert("connection", connection);
+                settings.insert("802-11-wireless", wireless);
+
+                let (_, active): (OwnedObjectPath, OwnedObjectPath) = nm
+                    .call("AddAndActivateConnection", &(settings, &device, &ap))
+                    .map_err(|e| WifiProxyError::ConnectionFailed(e.to_string()))?;
+                active
+            }
+        };
+
+        self.wait_for_activation(&active)
+    }
+
+    /// Returns `(path, settings)` for every saved connection profile.
+    fn profiles(&self) -> Result<Vec<(OwnedObjectPath, ProfileSettings)>> {
+        let paths: Vec<OwnedObjectPath> = self
+            .proxy(NM_SETTINGS_PATH, SETTINGS_IFACE)?
+            .call("ListConnections", &())
+            .map_err(dbus_error)?;
+
+        let mut profiles = Vec::with_capacity(paths.len());
+        for path in paths {
+            let settings: ProfileSettings = self
+                .proxy(&path, CONNECTION_IFACE)?
+                .call("GetSettings", &())
+                .map_err(dbus_error)?;
+            profiles.push((path, settings));
+        }
+        Ok(profiles)
+    }
+
+    /// Finds a saved profile whose `connection.id` matches the name.
+    fn find_profile(&self, name: &str) -> Result<Option<(OwnedObjectPath, ProfileSettings)>> {
+        Ok(self
+            .profiles()?
+            .into_iter()
+            .find(|(_, settings)| setting_str(settings, "connection", "id").as_deref() == Some(name)))
+    }
+
+    /// Polls an active connection until it is activated, fails, or times out.
+    fn wait_for_activation(&self, active: &str) -> Result<()> {
+        let proxy = self.proxy(active, ACTIVE_IFACE)?;
+        let deadline = Instant::now() + ACTIVATION_TIMEOUT;
+
+        while Instant::now() < deadline {
+            // The object disappears once a failed activation is torn down
+            let state: u32 = match proxy.get_property("State") {
+                Ok(state) => state,
+                Err(_) => ACTIVE_STATE_DEACTIVATED,
+            };
+            match state {
+                ACTIVE_STATE_ACTIVATED => return Ok(()),
+                ACTIVE_STATE_DEACTIVATED => {
+                    return Err(WifiProxyError::ConnectionFailed(
+                        "Activation failed (wrong password or AP rejected the connection)"
+                            .to_string(),
+                    )
+                    .into());
+                }
+                _ => std::thread::sleep(POLL_INTERVAL),
+            }
+        }
+
+        Err(WifiProxyError::ConnectionFailed("Timed out waiting for activation".to_string()).into())
+    }
+}
+
+/// Connection settings as returned by `GetSettings` (`a{sa{sv}}`).
+type ProfileSettings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// Overlays new settings onto a saved profile's settings for `Update`.
+///
+/// `Update` replaces the whole profile, so sending only the wireless groups
+/// would drop the IPv4/IPv6 configuration and everything else set on the
+/// profile. The saved settings are kept and only the given keys replaced.
+///
+/// # Arguments
+/// * `existing` - Settings returned by `GetSettings` (without secrets)
+/// * `changes` - Groups and keys to set
+/// * `open` - Whether the access point advertises no security, dropping any
+///   saved security group
+///
+/// # Returns
+/// The merged settings, or `Err(WifiProxyError::Dbus)` if a value cannot be
+/// converted (e.g., it holds a file descriptor)
+fn merge_settings(
+    existing: ProfileSettings,
+    changes: HashMap<&str, HashMap<&str, Value>>,
+    open: bool,
+) -> Result<ProfileSettings> {
+    let mut merged = existing;
+
+    for (group, values) in changes {
+        let entry = merged.entry(group.to_string()).or_default();
+        for (key, value) in values {
+            let value = value.try_to_owned().map_err(|e| dbus_error(e.into()))?;
+            entry.insert(key.to_string(), value);
+        }
+    }
+
+    // An open access point must not keep a stale key-mgmt; a missing password
+    // alone keeps the saved one
+    if open {
+        merged.remove("802-11-wireless-security");
+        if let Some(wireless) = merged.get_mut("802-11-wireless") {
+            wireless.remove("security");
+        }
+    }
+
+    Ok(merged)
+}
+
+/// Converts a BSSID such as "24:0A:C4:12:34:56" into the six bytes
+/// NetworkManager stores in `802-11-wireless.bssid`.
+fn bssid_bytes(bssid: &str) -> Result<Vec<u8>> {
+    let bytes: Vec<u8> = bssid
+        .split([':', '-'])
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| WifiProxyError::ConnectionFailed(format!("'{}' is not a BSSID", bssid)))?;
+    if bytes.len() != 6 {
+        return Err(WifiProxyError::ConnectionFailed(format!("'{}' is not a BSSID", bssid)).into());
+    }
+    Ok(bytes)
+}
+
+/// Reads a string setting from a profile's settings map.
+fn setting_str(settings: &ProfileSettings, group: &str, key: &str) -> Option<String> {
+    dict_string(settings.get(group)?, key)
+}
+
+/// Reads a string entry from an `a{sv}` dictionary.
+fn dict_string(dict: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    String::try_from(dict.get(key)?.try_clone().ok()?).ok()
+}
+
+/// Converts a zbus error into the library's D-Bus error variant.
+fn dbus_error(e: zbus::Error) -> anyhow::Error {
+    WifiProxyError::Dbus(e.to_string()).into()
+}
+
+/// Returns nmcli's short name for a device state code.
+fn device_state_name(state: u32) -> &'static str {
+    match state {
+        10 => "unmanaged",
+        20 => "unavailable",
+        30 => "disconnected",
+        40 => "connecting (prepare)",
+        50 => "connecting (configuring)",
+        60 => "connecting (need authentication)",
+        70 => "connecting (getting IP configuration)",
+        80 => "connecting (checking IP connectivity)",
+        90 => "connecting (starting secondary connections)",
+        100 => "connected",
+        110 => "deactivating",
+        120 => "connection failed",
+        _ => "unknown",
+    }
+}
+
+/// Builds an nmcli-style security string from access point flags.
+///
+/// Produces values such as `"WPA2"`, `"WPA1 WPA2"`, `"WPA2 WPA3"`,
+/// `"WPA2 802.1X"`, `"WEP"` or `""` for open networks.
+fn security_string(flags: u32, wpa_flags: u32, rsn_flags: u32) -> String {
+    let mut parts = Vec::new();
+
+    // Privacy without WPA/RSN information means legacy WEP
+    if flags & AP_FLAGS_PRIVACY != 0 && wpa_flags == 0 && rsn_flags == 0 {
+        parts.push("WEP");
+    }
+    if wpa_flags != 0 {
+        parts.push("WPA1");
+    }
+    if rsn_flags & (AP_SEC_KEY_MGMT_PSK | AP_SEC_KEY_MGMT_802_1X) != 0 {
+        parts.push("WPA2");
+    }
+    if rsn_flags & AP_SEC_KEY_MGMT_SAE != 0 {
+        parts.push("WPA3");
+    }
+    if (wpa_flags | rsn_flags) & AP_SEC_KEY_MGMT_802_1X != 0 {
+        parts.push("802.1X");
+    }
+
+    parts.join(" ")
+}
+
+impl NetworkBackend for DbusBackend {
+    fn name(&self) -> &'static str {
+        "dbus"
+    }
+
+    fn list_interfaces(&self) -> Result<Vec<WifiInterface>> {
+        let mut interfaces = Vec::new();
+
+        for path in self.devices()? {
+            // Only WiFi devices are of interest
+            let device_type: u32 = self.property(&path, DEVICE_IFACE, "DeviceType")?;
+            if device_type != DEVICE_TYPE_WIFI {
+                continue;
+            }
+
+            let name: String = self.property(&path, DEVICE_IFACE, "Interface")?;
+            let state: u32 = self.property(&path, DEVICE_IFACE, "State")?;
+            let is_usb = is_usb_interface(&name);
+
+            interfaces.push(WifiInterface {
+                name,
+                state: device_state_name(state).to_string(),
+                is_usb,
+            });
+        }
+
+        Ok(interfaces)
+    }
+
+    fn scan(&self, interface: &str) -> Result<Vec<Network>> {
+        let device = self.find_device(interface)?;
+        self.request_scan(&device)?;
+
+        Ok(self
+            .visible_networks(&device)?
+            .into_iter()
+            .map(|(_, network)| network)
+            .collect())
+    }
+
+    fn connect(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        self.activate(interface, ssid, None, password, false)
+    }
+
+    fn connect_hidden(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        self.activate(interface, ssid, None, password, true)
+    }
+
+    fn connect_bssid(&self, interface: &str, ssid: &str, bssid: &str, password: &str) -> Result<()> {
+        self.activate(interface, ssid, Some(bssid), password, false)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        let device = self.find_device(interface)?;
+        self.proxy(&device, DEVICE_IFACE)?
+            .call::<_, _, ()>("Disconnect", &())
+            .map_err(dbus_error)
+    }
+
+    fn status(&self, interface: &str) -> Result<ConnectionStatus> {
+        let device = self.find_device(interface)?;
+        let state: u32 = self.property(&device, DEVICE_IFACE, "State")?;
+
+        let mut status = ConnectionStatus {
+            interface: interface.to_string(),
+            state: format!("{} ({})", state, device_state_name(state)),
+            connection: None,
+            ssid: None,
+            ip_address: None,
+            addresses: Vec::new(),
+            dns: Vec::new(),
+            gateway: None,
+        };
+
+        // Active connection name ("/" means no active connection)
+        let active: OwnedObjectPath = self.property(&device, DEVICE_IFACE, "ActiveConnection")?;
+        if active.as_str() != "/" {
+            status.connection = self.property::<String>(&active, ACTIVE_IFACE, "Id").ok();
+
+            // The profile may be named differently; the access point has the SSID
+            status.ssid = self
+                .property::<OwnedObjectPath>(&device, WIRELESS_IFACE, "ActiveAccessPoint")
+                .ok()
+                .filter(|ap| ap.as_str() != "/")
+                .and_then(|ap| self.property::<Vec<u8>>(&ap, AP_IFACE, "Ssid").ok())
+                .map(|ssid| String::from_utf8_lossy(&ssid).into_owned());
+        }
+
+        // IPv4 configuration ("/" means not configured)
+        let ip4: OwnedObjectPath = self.property(&device, DEVICE_IFACE, "Ip4Config")?;
+        if ip4.as_str() != "/" {
+            let ip4 = self.proxy(&ip4, IP4_IFACE)?;
+
+            // All addresses in CIDR notation, matching nmcli's IP4.ADDRESS[n]
+            let addresses: Vec<HashMap<String, OwnedValue>> =
+                ip4.get_property("AddressData").map_err(dbus_error)?;
+            status.addresses = addresses
+                .iter()
+                .filter_map(|entry| {
+                    let address = dict_string(entry, "address")?;
+                    let prefix = u32::try_from(entry.get("prefix")?.try_clone().ok()?).ok()?;
+                    Some(format!("{}/{}", address, prefix))
+                })
+                .collect();
+            status.ip_address = status.addresses.first().cloned();
+
+            // DNS servers; older NetworkManager versions lack NameserverData
+            let nameservers: Vec<HashMap<String, OwnedValue>> =
+                ip4.get_property("NameserverData").unwrap_or_default();
+            status.dns = nameservers
+                .iter()
+                .filter_map(|entry| dict_string(entry, "address"))
+                .collect();
+
+            let gateway: String = ip4.get_property("Gateway").map_err(dbus_error)?;
+            if !gateway.is_empty() {
+                status.gateway = Some(gateway);
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn link_quality(&self, interface: &str) -> Result<Option<LinkQuality>> {
+        let device = self.find_device(interface)?;
+
+        // "/" means the device is not associated with an access point
+        let ap: OwnedObjectPath = self.property(&device, WIRELESS_IFACE, "ActiveAccessPoint")?;
+        if ap.as_str() == "/" {
+            return Ok(None);
+        }
+
+        // Bitrate is the device's current transmit rate in kbit/s (0 if unknown)
+        let bitrate: u32 = self.property(&device, WIRELESS_IFACE, "Bitrate").unwrap_or(0);
+        let link = LinkQuality {
+            bssid: self.property::<String>(&ap, AP_IFACE, "HwAddress").ok(),
+            signal: self.property(&ap, AP_IFACE, "Strength").ok(),
+            tx_bitrate_mbps: (bitrate > 0).then(|| f64::from(bitrate) / 1000.0),
+            frequency_mhz: self.property(&ap, AP_IFACE, "Frequency").ok(),
+            ..LinkQuality::default()
+        };
+
+        // RSSI and the receive rate are only known to the kernel
+        Ok(Some(link.fill_from(iw::link(interface).unwrap_or_default())))
+    }
+
+    fn delete_profile(&self, name: &str) -> Result<()> {
+        let (path, _) = self
+            .find_profile(name)?
+            .ok_or_else(|| WifiProxyError::NetworkNotFound(name.to_string()))?;
+
+        self.proxy(&path, CONNECTION_IFACE)?
+            .call::<_, _, ()>("Delete", &())
+            .map_err(dbus_error)
+    }
+}
@@ -0,0 +1,84 @@
+//! Reading the current link from the `iw` tool.
+//!
+//! NetworkManager reports the signal of the associated access point as a
+//! percentage, but not the RSSI in dBm or the bitrates the adapter actually
+//! uses. `iw dev <interface> link` reads them from the kernel (nl80211) for
+//! any driver, so the backends use it to complete their own measurements.
+//! `iw` is optional: where it is not installed, the backends report only
+//! what they measure themselves.
+//!
+//! # Command Executed
+//! ```bash
+//! iw dev <interface> link
+//! ```
+
+use std::process::Command;
+
+use crate::connection::LinkQuality;
+
+/// Parses `iw dev <interface> link` output.
+///
+/// Returns `None` when the interface is not connected.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::backend::iw::parse_link;
+///
+/// let output = "Connected to 24:0a:c4:12:34:56 (on wlan1)
+/// \tSSID: WAVESHARE Robot
+/// \tfreq: 2437.0
+/// \tsignal: -64 dBm
+/// \trx bitrate: 65.0 MBit/s MCS 7
+/// \ttx bitrate: 72.2 MBit/s MCS 7 short GI
+/// ";
+/// let link = parse_link(output).unwrap();
+/// assert_eq!(link.bssid.as_deref(), Some("24:0a:c4:12:34:56"));
+/// assert_eq!(link.rssi_dbm, Some(-64));
+/// assert_eq!(link.tx_bitrate_mbps, Some(72.2));
+/// assert_eq!(link.rx_bitrate_mbps, Some(65.0));
+/// assert_eq!(link.frequency_mhz, Some(2437));
+///
+/// assert!(parse_link("Not connected.\n").is_none());
+/// ```
+pub fn parse_link(output: &str) -> Option<LinkQuality> {
+    let mut lines = output.lines();
+
+    // First line: "Connected to <bssid> (on <interface>)"
+    let bssid = lines.next()?.strip_prefix("Connected to ")?.split_whitespace().next()?;
+    let mut link = LinkQuality {
+        bssid: Some(bssid.to_string()),
+        ..LinkQuality::default()
+    };
+
+    // Remaining lines: indented "key: value"
+    for line in lines {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let number = value.split_whitespace().next().unwrap_or_default();
+        match key {
+            "signal" => link.rssi_dbm = number.parse().ok(),
+            "freq" => link.frequency_mhz = number.parse::<f64>().ok().map(|f| f as u32),
+            "rx bitrate" => link.rx_bitrate_mbps = number.parse().ok(),
+            "tx bitrate" => link.tx_bitrate_mbps = number.parse().ok(),
+            _ => {}
+        }
+    }
+
+    Some(link.fill_from(LinkQuality::default()))
+}
+
+/// Reads the interface's link with `iw`.
+///
+/// Returns `None` if `iw` is missing, fails, or reports no connection.
+pub fn link(interface: &str) -> Option<LinkQuality> {
+    let output = Command::new("iw")
+        .args(["dev", interface, "link"])
+        .env("LC_ALL", "C")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_link(&String::from_utf8_lossy(&output.stdout))
+}
@@ -0,0 +1,400 @@
+//! In-memory backend that simulates WiFi adapters and access points.
+//!
+//! Nothing here touches the system. Interfaces, visible networks and their
+//! passwords are configured up front, and connect/disconnect only change the
+//! in-memory state. This makes it possible to exercise the CLI and server on
+//! machines without a USB adapter, and to test code against a predictable
+//! network stack.
+//!
+//! # Example
+//!
+//! ```
+//! use wifi_proxy::backend::memory::MemoryBackend;
+//! use wifi_proxy::backend::NetworkBackend;
+//!
+//! let backend = MemoryBackend::new()
+//!     .with_interface("wlan1", true)
+//!     .with_network("RoboDog-AP", 80, "WPA2", "secret123");
+//!
+//! backend.connect("wlan1", "RoboDog-AP", "secret123").unwrap();
+//! let status = backend.status("wlan1").unwrap();
+//! assert_eq!(status.connection.as_deref(), Some("RoboDog-AP"));
+//! ```
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::NetworkBackend;
+use crate::connection::{channel_from_frequency, ConnectionStatus, LinkQuality};
+use crate::error::WifiProxyError;
+use crate::interface::WifiInterface;
+use crate::scan::{Mode, Network, Security};
+
+/// IPv4 address (with prefix) handed to interfaces on connect.
+const SIMULATED_ADDRESS: &str = "192.168.4.2/24";
+
+/// Gateway reported for every simulated connection (the robot's usual AP address).
+const SIMULATED_GATEWAY: &str = "192.168.4.1";
+
+/// Channel frequency of access points added without one (channel 6).
+const SIMULATED_FREQUENCY: u32 = 2437;
+
+/// Advertised rate and bitrate in Mbit/s of a simulated link at full signal.
+const SIMULATED_MAX_RATE: f64 = 72.2;
+
+/// A simulated access point together with the password it accepts.
+#[derive(Debug, Clone)]
+struct SimulatedNetwork {
+    /// Scan entry returned for this access point.
+    network: Network,
+    /// Password required to connect (empty for open networks).
+    password: String,
+    /// Whether the SSID is left out of beacons (scans report it empty).
+    hidden: bool,
+}
+
+/// Mutable state behind the backend's lock.
+#[derive(Debug, Default)]
+struct MemoryState {
+    /// Known interfaces, in insertion order.
+    interfaces: Vec<WifiInterface>,
+    /// Access points visible on every interface.
+    networks: Vec<SimulatedNetwork>,
+    /// Active connection per interface name.
+    active: HashMap<String, Association>,
+    /// Saved connection profiles (profile name == SSID).
+    profiles: Vec<String>,
+}
+
+/// Access point an interface is connected to.
+#[derive(Debug, Clone)]
+struct Association {
+    /// Network name, which is also the profile name.
+    ssid: String,
+    /// Access point joined, out of those broadcasting the SSID.
+    bssid: String,
+}
+
+/// Network backend holding all interfaces and networks in memory.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryBackend {
+    /// Creates an empty backend with no interfaces or networks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a backend pre-populated with a typical robot setup.
+    ///
+    /// Provides a built-in `wlan0`, a USB `wlan1`, and a robot access point
+    /// named "WAVESHARE Robot" with the factory password "1234567890".
+    pub fn simulated() -> Self {
+        Self::new()
+            .with_interface("wlan0", false)
+            .with_interface("wlan1", true)
+            .with_access_point("WAVESHARE Robot", "24:0A:C4:12:34:56", 2437, 78, "WPA2", "1234567890")
+            .with_access_point("HomeNetwork", "3C:84:6A:01:02:03", 5180, 64, "WPA2 WPA3", "home-password")
+    }
+
+    /// Adds a disconnected WiFi interface.
+    pub fn with_interface(self, name: &str, is_usb: bool) -> Self {
+        self.lock().interfaces.push(WifiInterface {
+            name: name.to_string(),
+            state: "disconnected".to_string(),
+            is_usb,
+        });
+        self
+    }
+
+    /// Adds an access point visible on every interface.
+    ///
+    /// The access point gets a locally administered BSSID and channel 6; use
+    /// [`with_access_point`](Self::with_access_point) to choose them.
+    ///
+    /// # Arguments
+    /// * `ssid` - Network name
+    /// * `signal` - Signal strength percentage (0-100)
+    /// * `security` - Security string as nmcli would report it (empty for open)
+    /// * `password` - Password accepted by the access point
+    pub fn with_network(self, ssid: &str, signal: u8, security: &str, password: &str) -> Self {
+        let bssid = format!("02:00:00:00:00:{:02X}", self.lock().networks.len() + 1);
+        self.with_access_point(ssid, &bssid, SIMULATED_FREQUENCY, signal, security, password)
+    }
+
+    /// Adds an access point with a given BSSID and frequency.
+    ///
+    /// Several access points may share an SSID, e.g. two robots with the
+    /// factory name.
+    ///
+    /// # Arguments
+    /// * `ssid` - Network name
+    /// * `bssid` - MAC address of the access point
+    /// * `frequency_mhz` - Center frequency (e.g., 2437 for channel 6)
+    /// * `signal` - Signal strength percentage (0-100)
+    /// * `security` - Security string as nmcli would report it (empty for open)
+    /// * `password` - Password accepted by the access point
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wifi_proxy::backend::memory::MemoryBackend;
+    /// use wifi_proxy::backend::NetworkBackend;
+    ///
+    /// let backend = MemoryBackend::new()
+    ///     .with_interface("wlan1", true)
+    ///     .with_access_point("Robot", "24:0A:C4:12:34:56", 2437, 78, "WPA2", "secret123")
+    ///     .with_access_point("Robot", "30:AE:A4:00:11:22", 2462, 41, "WPA2", "secret123");
+    ///
+    /// // The link reports the access point joined, not the strongest one
+    /// backend.connect_bssid("wlan1", "Robot", "30:AE:A4:00:11:22", "secret123").unwrap();
+    /// let quality = backend.link_quality("wlan1").unwrap().unwrap();
+    /// assert_eq!(quality.bssid.as_deref(), Some("30:AE:A4:00:11:22"));
+    /// assert_eq!(quality.signal, Some(41));
+    /// ```
+    pub fn with_access_point(
+        self,
+        ssid: &str,
+        bssid: &str,
+        frequency_mhz: u32,
+        signal: u8,
+        security: &str,
+        password: &str,
+    ) -> Self {
+        self.lock().networks.push(SimulatedNetwork {
+            network: Network {
+                ssid: ssid.to_string(),
+                bssid: bssid.to_string(),
+                signal,
+                security: Security::parse(security),
+                frequency_mhz: Some(frequency_mhz),
+                max_rate_mbps: Some(SIMULATED_MAX_RATE),
+                mode: Some(Mode::Infrastructure),
+                ..Network::default()
+            },
+            password: password.to_string(),
+            hidden: false,
+        });
+        self
+    }
+
+    /// Stops the access points of an SSID from broadcasting it.
+    ///
+    /// Scans then report them with an empty SSID, and only
+    /// [`connect_hidden`](NetworkBackend::connect_hidden) can join them.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::backend::memory::MemoryBackend;
+    /// use wifi_proxy::backend::NetworkBackend;
+    ///
+    /// let backend = MemoryBackend::new()
+    ///     .with_interface("wlan1", true)
+    ///     .with_network("RoboDog-AP", 80, "WPA2", "secret123")
+    ///     .with_hidden("RoboDog-AP");
+    ///
+    /// assert_eq!(backend.scan("wlan1").unwrap()[0].ssid, "");
+    /// assert!(backend.connect("wlan1", "RoboDog-AP", "secret123").is_err());
+    /// backend.connect_hidden("wlan1", "RoboDog-AP", "secret123").unwrap();
+    /// ```
+    pub fn with_hidden(self, ssid: &str) -> Self {
+        for network in self.lock().networks.iter_mut().filter(|n| n.network.ssid == ssid) {
+            network.hidden = true;
+        }
+        self
+    }
+
+    /// Changes the signal strength of an access point, e.g. to simulate the
+    /// robot walking out of range.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the access point exists
+    /// - `Err(WifiProxyError::NetworkNotFound)` otherwise
+    pub fn set_signal(&self, ssid: &str, signal: u8) -> Result<()> {
+        let mut state = self.lock();
+        let network = state
+            .networks
+            .iter_mut()
+            .find(|n| n.network.ssid == ssid)
+            .ok_or_else(|| WifiProxyError::NetworkNotFound(ssid.to_string()))?;
+        network.network.signal = signal.min(100);
+        Ok(())
+    }
+
+    /// Connects an interface to a simulated access point.
+    ///
+    /// Hidden access points are only found when probing for them (`hidden`).
+    /// Without a BSSID the strongest access point broadcasting the SSID is
+    /// joined, as a real adapter would.
+    fn join(&self, interface: &str, ssid: &str, bssid: Option<&str>, password: &str, hidden: bool) -> Result<()> {
+        let mut state = self.lock();
+        state.interface_mut(interface)?;
+
+        // The access point must be in range and accept the password
+        let network = state
+            .networks
+            .iter()
+            .filter(|n| {
+                n.network.ssid == ssid
+                    && (hidden || !n.hidden)
+                    && bssid.is_none_or(|b| n.network.bssid.eq_ignore_ascii_case(b))
+            })
+            .max_by_key(|n| n.network.signal)
+            .ok_or_else(|| WifiProxyError::NetworkNotFound(ssid.to_string()))?;
+        if network.password != password {
+            return Err(WifiProxyError::ConnectionFailed(
+                "Secrets were required, but not provided".to_string(),
+            )
+            .into());
+        }
+
+        // Mark the interface connected and remember the profile like NM does
+        let association = Association {
+            ssid: ssid.to_string(),
+            bssid: network.network.bssid.clone(),
+        };
+        state.interface_mut(interface)?.state = "connected".to_string();
+        state.active.insert(interface.to_string(), association);
+        if !state.profiles.iter().any(|p| p == ssid) {
+            state.profiles.push(ssid.to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Locks the state, recovering from a poisoned lock.
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl MemoryState {
+    /// Returns the named interface or `InterfaceNotFound`.
+    fn interface_mut(&mut self, name: &str) -> Result<&mut WifiInterface> {
+        self.interfaces
+            .iter_mut()
+            .find(|i| i.name == name)
+            .ok_or_else(|| WifiProxyError::InterfaceNotFound(name.to_string()).into())
+    }
+
+    /// Returns the access point with a BSSID, if it is still in range.
+    fn access_point(&self, bssid: &str) -> Option<&Network> {
+        self.networks
+            .iter()
+            .map(|n| &n.network)
+            .find(|n| n.bssid.eq_ignore_ascii_case(bssid))
+    }
+}
+
+impl NetworkBackend for MemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn list_interfaces(&self) -> Result<Vec<WifiInterface>> {
+        Ok(self.lock().interfaces.clone())
+    }
+
+    fn scan(&self, interface: &str) -> Result<Vec<Network>> {
+        let mut state = self.lock();
+        state.interface_mut(interface)?;
+        Ok(state
+            .networks
+            .iter()
+            .map(|n| Network {
+                ssid: if n.hidden { String::new() } else { n.network.ssid.clone() },
+                ..n.network.clone()
+            })
+            .collect())
+    }
+
+    fn connect(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        self.join(interface, ssid, None, password, false)
+    }
+
+    fn connect_hidden(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        self.join(interface, ssid, None, password, true)
+    }
+
+    fn connect_bssid(&self, interface: &str, ssid: &str, bssid: &str, password: &str) -> Result<()> {
+        self.join(interface, ssid, Some(bssid), password, false)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        let mut state = self.lock();
+        state.interface_mut(interface)?.state = "disconnected".to_string();
+        state.active.remove(interface);
+        Ok(())
+    }
+
+    fn status(&self, interface: &str) -> Result<ConnectionStatus> {
+        let mut state = self.lock();
+        state.interface_mut(interface)?;
+
+        // Connected interfaces get a fixed address on the robot's subnet
+        let status = match state.active.get(interface) {
+            Some(association) => ConnectionStatus {
+                interface: interface.to_string(),
+                state: "100 (connected)".to_string(),
+                connection: Some(association.ssid.clone()),
+                ssid: Some(association.ssid.clone()),
+                ip_address: Some(SIMULATED_ADDRESS.to_string()),
+                addresses: vec![SIMULATED_ADDRESS.to_string()],
+                dns: vec![SIMULATED_GATEWAY.to_string()],
+                gateway: Some(SIMULATED_GATEWAY.to_string()),
+            },
+            None => ConnectionStatus {
+                interface: interface.to_string(),
+                state: "30 (disconnected)".to_string(),
+                connection: None,
+                ssid: None,
+                ip_address: None,
+                addresses: Vec::new(),
+                dns: Vec::new(),
+                gateway: None,
+            },
+        };
+
+        Ok(status)
+    }
+
+    fn link_quality(&self, interface: &str) -> Result<Option<LinkQuality>> {
+        let mut state = self.lock();
+        state.interface_mut(interface)?;
+
+        // The simulated link is as strong as the joined access point's scan entry
+        let Some(association) = state.active.get(interface) else {
+            return Ok(None);
+        };
+        let Some(network) = state.access_point(&association.bssid) else {
+            return Ok(None);
+        };
+        let signal = network.signal;
+        let frequency = network.frequency_mhz.unwrap_or(SIMULATED_FREQUENCY);
+
+        // Bitrates fall with the signal like a real adapter's rate control
+        let rate = |max: f64| (max * f64::from(signal) / 10.0).round() / 10.0;
+        Ok(Some(LinkQuality {
+            bssid: Some(network.bssid.clone()),
+            signal: Some(signal),
+            rssi_dbm: Some(i32::from(signal) / 2 - 100),
+            tx_bitrate_mbps: Some(rate(SIMULATED_MAX_RATE)),
+            rx_bitrate_mbps: Some(rate(SIMULATED_MAX_RATE * 0.9)),
+            frequency_mhz: Some(frequency),
+            channel: channel_from_frequency(frequency),
+        }))
+    }
+
+    fn delete_profile(&self, name: &str) -> Result<()> {
+        let mut state = self.lock();
+        let before = state.profiles.len();
+        state.profiles.retain(|p| p != name);
+        if state.profiles.len() == before {
+            return Err(WifiProxyError::NetworkNotFound(name.to_string()).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! Pluggable network backends.
+//!
+//! The public free functions in [`crate::connection`], [`crate::scan`] and
+//! [`crate::interface`] do not talk to the system directly. They dispatch to
+//! the currently selected [`NetworkBackend`], which knows how to list WiFi
+//! interfaces, scan, connect, disconnect, report status and delete saved
+//! profiles on a particular network stack.
+//!
+//! # Available Backends
+//!
+//! | Kind     | Type                      | Description                              |
+//! |----------|---------------------------|------------------------------------------|
+//! | `nmcli`  | [`nmcli::NmcliBackend`]   | Spawns NetworkManager's `nmcli` (default) |
+//! | `dbus`   | [`dbus::DbusBackend`]     | Talks to NetworkManager over D-Bus       |
+//! | `memory` | [`memory::MemoryBackend`] | Simulated adapter kept in memory         |
+//! | `wpa_supplicant` | [`wpa_supplicant::WpaSupplicantBackend`] | wpa_supplicant control socket |
+//!
+//! # Example
+//!
+//! ```no_run
+//! use wifi_proxy::backend::{self, BackendKind};
+//!
+//! // Select a backend for the rest of the process
+//! backend::select_backend(BackendKind::Memory).expect("Backend unavailable");
+//!
+//! // Free functions now dispatch through the selected backend
+//! let interfaces = wifi_proxy::list_wifi_interfaces().unwrap();
+//! println!("{} interfaces", interfaces.len());
+//! ```
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+use crate::connection::{ConnectionStatus, LinkQuality};
+use crate::error::WifiProxyError;
+use crate::interface::WifiInterface;
+use crate::scan::Network;
+
+/// NetworkManager backend using the D-Bus API directly.
+pub mod dbus;
+
+/// Helpers reading the current link from the `iw` tool.
+/// Supplements the backends with RSSI and bitrates NetworkManager does not report.
+pub mod iw;
+
+/// In-memory backend simulating a WiFi adapter and nearby networks.
+pub mod memory;
+
+/// NetworkManager backend that spawns the `nmcli` command-line tool.
+pub mod nmcli;
+
+/// wpa_supplicant backend using the control interface socket.
+pub mod wpa_supplicant;
+
+/// Operations a network stack must provide to manage the USB WiFi adapter.
+///
+/// Implementations are used through `Arc<dyn NetworkBackend>` and shared
+/// between threads, so they must be `Send + Sync`. All methods are blocking.
+pub trait NetworkBackend: Send + Sync {
+    /// Returns a short identifier for the backend (e.g., "nmcli").
+    fn name(&self) -> &'static str;
+
+    /// Lists all WiFi interfaces known to the backend.
+    fn list_interfaces(&self) -> Result<Vec<WifiInterface>>;
+
+    /// Scans for networks visible to the interface.
+    ///
+    /// Returns raw results; deduplication and sorting are done by
+    /// [`crate::scan::scan_networks`].
+    fn scan(&self, interface: &str) -> Result<Vec<Network>>;
+
+    /// Connects the interface to a WPA/WPA2 network.
+    fn connect(&self, interface: &str, ssid: &str, password: &str) -> Result<()>;
+
+    /// Connects the interface to a network that does not broadcast its SSID.
+    ///
+    /// The profile is marked hidden, so the adapter probes for the SSID by
+    /// name instead of waiting to see it in beacons.
+    fn connect_hidden(&self, interface: &str, ssid: &str, password: &str) -> Result<()>;
+
+    /// Connects the interface to one access point of a network, chosen by
+    /// its BSSID (e.g., "24:0A:C4:12:34:56").
+    ///
+    /// Used when several access points share an SSID, such as two robots
+    /// with the factory name. The saved profile is restricted to that
+    /// access point, like NetworkManager's `802-11-wireless.bssid` setting.
+    fn connect_bssid(&self, interface: &str, ssid: &str, bssid: &str, password: &str) -> Result<()>;
+
+    /// Disconnects the interface from its current network.
+    fn disconnect(&self, interface: &str) -> Result<()>;
+
+    /// Returns the current connection status of the interface.
+    fn status(&self, interface: &str) -> Result<ConnectionStatus>;
+
+    /// Returns the radio state of the interface's current link, or `None`
+    /// if it is not associated with an access point.
+    ///
+    /// Must not trigger a scan, since it is polled while serving.
+    fn link_quality(&self, interface: &str) -> Result<Option<LinkQuality>>;
+
+    /// Deletes a saved connection profile by name.
+    fn delete_profile(&self, name: &str) -> Result<()>;
+}
+
+/// Identifies one of the built-in backends.
+///
+/// Parsed from strings such as `"nmcli"` so it can be used directly as a
+/// command-line option value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+    /// NetworkManager via the `nmcli` CLI.
+    #[default]
+    Nmcli,
+    /// NetworkManager via its D-Bus API on the system bus.
+    Dbus,
+    /// Simulated in-memory adapter (for development and testing).
+    Memory,
+    /// Plain wpa_supplicant via its control interface socket.
+    WpaSupplicant,
+}
+
+impl BackendKind {
+    /// Returns the identifier used on the command line.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackendKind::Nmcli => "nmcli",
+            BackendKind::Dbus => "dbus",
+            BackendKind::Memory => "memory",
+            BackendKind::WpaSupplicant => "wpa_supplicant",
+        }
+    }
+
+    /// Creates a new instance of this backend.
+    ///
+    /// # Returns
+    /// - `Ok(Arc<dyn NetworkBackend>)` ready to be installed with [`set_backend`]
+    /// - `Err` if the backend cannot be initialized on this system
+    pub fn create(self) -> Result<Arc<dyn NetworkBackend>> {
+        match self {
+            BackendKind::Nmcli => Ok(Arc::new(nmcli::NmcliBackend::new())),
+            BackendKind::Dbus => Ok(Arc::new(dbus::DbusBackend::system()?)),
+            BackendKind::Memory => Ok(Arc::new(memory::MemoryBackend::simulated())),
+            BackendKind::WpaSupplicant => Ok(Arc::new(wpa_supplicant::WpaSupplicantBackend::new())),
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BackendKind {
+    type Err = WifiProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nmcli" => Ok(BackendKind::Nmcli),
+            "dbus" => Ok(BackendKind::Dbus),
+            "memory" => Ok(BackendKind::Memory),
+            "wpa_supplicant" | "wpa" => Ok(BackendKind::WpaSupplicant),
+            _ => Err(WifiProxyError::UnknownBackend(s.to_string())),
+        }
+    }
+}
+
+// The process-wide backend used by the free functions.
+// Defaults to nmcli so existing callers keep their behavior.
+lazy_static! {
+    static ref BACKEND: RwLock<Arc<dyn NetworkBackend>> =
+        RwLock::new(Arc::new(nmcli::NmcliBackend::new()));
+}
+
+/// Returns the currently selected backend.
+pub fn backend() -> Arc<dyn NetworkBackend> {
+    BACKEND
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Replaces the process-wide backend.
+///
+/// Any backend implementation can be installed, including custom ones.
+pub fn set_backend(backend: Arc<dyn NetworkBackend>) {
+    *BACKEND.write().unwrap_or_else(|e| e.into_inner()) = backend;
+}
+
+/// Creates a built-in backend and installs it as the process-wide backend.
+///
+/// # Returns
+/// - `Ok(())` if the backend was created and installed
+/// - `Err` if the backend cannot be initialized on this system
+pub fn select_backend(kind: BackendKind) -> Result<()> {
+    set_backend(kind.create()?);
+    Ok(())
+}
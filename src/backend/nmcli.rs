@@ -0,0 +1,685 @@
+//! NetworkManager backend implemented by spawning the `nmcli` tool.
+//!
+//! This is the default backend. Every operation runs one or more `nmcli`
+//! commands and parses their terse (`-t`) output.
+//!
+//! # Terse Output
+//!
+//! In terse mode nmcli separates fields with `:` and escapes literal colons
+//! and backslashes inside values as `\:` and `\\` (NetworkManager 0.9 did
+//! not escape at all). All output goes through [`split_terse`] so SSIDs such
+//! as `Lab:Dog` survive intact. Commands are spawned with a C locale so that
+//! state strings are never translated.
+//!
+//! The parsers are public and pure, and are checked against synthetic
+//! output modelled on several NetworkManager versions in `fixtures/nmcli/`.
+//!
+//! # Requirements
+//!
+//! - NetworkManager must be installed and running
+//! - The `nmcli` command must be available in PATH
+//! - User must have permission to manage network connections
+
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::Duration;
+
+use super::{iw, NetworkBackend};
+use crate::connection::{ConnectionStatus, LinkQuality};
+use crate::error::WifiProxyError;
+use crate::interface::{is_usb_interface, WifiInterface};
+use crate::scan::{Mode, Network, Security};
+
+/// Device types nmcli reports for WiFi interfaces.
+///
+/// NetworkManager 0.9 used the setting name `802-11-wireless`; 1.x uses `wifi`.
+const WIFI_DEVICE_TYPES: [&str; 2] = ["wifi", "802-11-wireless"];
+
+/// Creates an `nmcli` command running under the C locale.
+///
+/// nmcli translates state strings such as "connected", which would break
+/// parsing on systems with a non-English locale.
+fn nmcli() -> Command {
+    let mut command = Command::new("nmcli");
+    command
+        .env("LC_ALL", "C")
+        .env("LANG", "C")
+        .env("LANGUAGE", "C");
+    command
+}
+
+/// Splits one line of nmcli terse output into unescaped fields.
+///
+/// Fields are separated by unescaped `:`; the escapes `\:` and `\\` are
+/// turned back into `:` and `\`.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::backend::nmcli::split_terse;
+///
+/// assert_eq!(split_terse(r"Lab\:Dog:64:WPA2"), ["Lab:Dog", "64", "WPA2"]);
+/// assert_eq!(split_terse(r"Back\\slash::"), [r"Back\slash", "", ""]);
+/// ```
+pub fn split_terse(line: &str) -> Vec<String> {
+    split_terse_n(line, usize::MAX)
+}
+
+/// Splits a terse line into at most `limit` fields.
+///
+/// The last field receives the rest of the line, with any further
+/// separators kept as literal colons. This is used for `KEY:VALUE` lines,
+/// where NetworkManager 0.9 printed values containing unescaped colons.
+fn split_terse_n(line: &str, limit: usize) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Escaped character: keep the next one literally
+            '\\' => match chars.next() {
+                Some(escaped) => current.push(escaped),
+                None => current.push('\\'),
+            },
+            // Field separator, unless the limit has been reached
+            ':' if fields.len() + 1 < limit => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    fields.push(current);
+    fields
+}
+
+/// Parses `nmcli -t -f DEVICE,TYPE,STATE device` output.
+///
+/// Returns only WiFi devices. `is_usb` is always `false` here because it
+/// requires a sysfs lookup; [`NmcliBackend`] fills it in afterwards.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::backend::nmcli::parse_device_list;
+///
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/0.9.10/device.txt"));
+/// let interfaces = parse_device_list(output);
+///
+/// assert_eq!(interfaces.len(), 2);
+/// assert_eq!(interfaces[1].name, "wlan1");
+/// assert_eq!(interfaces[1].state, "disconnected");
+///
+/// // Newer versions call the type "wifi" and list P2P devices separately
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/1.36/device.txt"));
+/// let names: Vec<_> = parse_device_list(output).into_iter().map(|i| i.name).collect();
+/// assert_eq!(names, ["wlp0s20f3", "wlxdceae760e328"]);
+/// ```
+pub fn parse_device_list(output: &str) -> Vec<WifiInterface> {
+    output
+        .lines()
+        .map(split_terse)
+        .filter(|fields| fields.len() >= 3 && WIFI_DEVICE_TYPES.contains(&fields[1].as_str()))
+        .map(|fields| WifiInterface {
+            name: fields[0].clone(),
+            state: fields[2].clone(),
+            is_usb: false,
+        })
+        .collect()
+}
+
+/// Fields requested from `nmcli device wifi list` for scans.
+///
+/// BSSID comes last: NetworkManager 0.9 does not escape its colons, and as
+/// the last field it receives the rest of the line intact.
+const WIFI_LIST_FIELDS: &str = "SSID,MODE,CHAN,FREQ,RATE,SIGNAL,SECURITY,BSSID";
+
+/// Runs `nmcli device wifi connect` for an interface.
+///
+/// # Arguments
+/// * `interface` - Interface to connect
+/// * `ssid` - Network to connect to
+/// * `password` - Network password; empty for an open network, or to use
+///   the secrets of an existing profile
+/// * `bssid` - Access point to join (`bssid <bssid>`), which also restricts
+///   the profile to it; `None` lets NetworkManager choose
+/// * `hidden` - Whether the profile probes for the SSID (`hidden yes`),
+///   which is required for networks that do not broadcast it
+fn wifi_connect(interface: &str, ssid: &str, bssid: Option<&str>, password: &str, hidden: bool) -> Result<()> {
+    // Execute nmcli command to connect to the WiFi network
+    let mut command = nmcli();
+    command.args([
+        "device",     // Device management command
+        "wifi",       // WiFi-specific operation
+        "connect",    // Connect action
+        ssid,         // Target network SSID
+        "ifname",     // Interface name keyword
+        interface,    // Target interface
+    ]);
+    if !password.is_empty() {
+        command.args(["password", password]);
+    }
+    if let Some(bssid) = bssid {
+        command.args(["bssid", bssid]);
+    }
+    if hidden {
+        command.args(["hidden", "yes"]);
+    }
+    let output = command.output().context("Failed to execute nmcli connect")?;
+
+    // Check if the command succeeded
+    if !output.status.success() {
+        // Extract error message from stderr (preferred) or stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let error_msg = if stderr.is_empty() {
+            stdout.to_string()
+        } else {
+            stderr.to_string()
+        };
+        return Err(WifiProxyError::ConnectionFailed(error_msg).into());
+    }
+
+    Ok(())
+}
+
+/// Time given to a scan when nmcli cannot wait for it (before 1.12).
+const LEGACY_SCAN_WAIT: Duration = Duration::from_secs(3);
+
+/// Runs `nmcli device wifi list` for an interface.
+///
+/// # Arguments
+/// * `interface` - Interface to list access points for
+/// * `rescan` - Whether nmcli scans first and waits for the results
+///   (`--rescan yes`), instead of listing cached ones
+fn wifi_list(interface: &str, rescan: bool) -> Result<std::process::Output> {
+    let mut command = nmcli();
+    command.args([
+        "-t",                    // Terse output (machine-readable)
+        "-f",                    // Specify fields to output
+        WIFI_LIST_FIELDS,        // Fields we want
+        "device",                // Device management command
+        "wifi",                  // WiFi-specific operation
+        "list",                  // List networks
+        "ifname",                // Interface name keyword
+        interface,               // Target interface
+    ]);
+    if rescan {
+        command.args(["--rescan", "yes"]);
+    }
+    command.output().context("Failed to execute nmcli wifi list")
+}
+
+/// Reads the SSID a connection profile connects to.
+///
+/// Profile names are free-form and only default to the SSID, so the setting
+/// is read instead of trusting the name.
+///
+/// # Command Executed
+/// ```bash
+/// nmcli -t -f 802-11-wireless.ssid connection show id <profile>
+/// ```
+///
+/// # Returns
+/// The SSID, or `None` if the profile is not a WiFi profile or cannot be read.
+fn profile_ssid(profile: &str) -> Option<String> {
+    let output = nmcli()
+        .args(["-t", "-f", "802-11-wireless.ssid", "connection", "show", "id", profile])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    // Output is a single KEY:VALUE line
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next()?;
+    split_terse_n(line, 2).pop().filter(|ssid| !ssid.is_empty())
+}
+
+/// Parses `nmcli -t -f SSID,MODE,CHAN,FREQ,RATE,SIGNAL,SECURITY,BSSID device wifi list` output.
+///
+/// Entries are returned as reported, including hidden networks (empty SSID)
+/// and several access points sharing an SSID.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::backend::nmcli::parse_wifi_list;
+/// use wifi_proxy::scan::{Mode, Security};
+///
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/1.36/wifi-list.txt"));
+/// let networks = parse_wifi_list(output);
+///
+/// assert_eq!(networks.len(), 6);
+/// assert_eq!(networks[0].ssid, "Lab:Dog");
+/// assert_eq!(networks[0].bssid, "24:0A:C4:5E:77:10");
+/// assert_eq!(networks[0].signal, 88);
+/// assert_eq!(networks[0].security, Security::Wpa2);
+/// assert_eq!(networks[0].channel, Some(1));
+/// assert_eq!(networks[0].frequency_mhz, Some(2412));
+/// assert_eq!(networks[0].max_rate_mbps, Some(65.0));
+/// assert_eq!(networks[0].mode, Some(Mode::Infrastructure));
+/// assert_eq!(networks[4].ssid, r#"Quote"d \ SSID"#);
+/// assert_eq!(networks[5].security, Security::Open);
+///
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/1.22/wifi-list.txt"));
+/// let ssids: Vec<_> = parse_wifi_list(output).into_iter().map(|n| n.ssid).collect();
+/// assert_eq!(ssids, ["WAVESHARE Robot", "WAVESHARE Robot", "Lab:Dog", "", "CorpNet", r"Back\slash"]);
+///
+/// // NetworkManager 0.9 leaves the BSSID's colons unescaped
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/0.9.10/wifi-list.txt"));
+/// assert_eq!(parse_wifi_list(output)[0].bssid, "24:0A:C4:12:34:56");
+/// ```
+pub fn parse_wifi_list(output: &str) -> Vec<Network> {
+    output
+        .lines()
+        .map(|line| split_terse_n(line, 8))
+        .filter(|fields| fields.len() == 8)
+        .map(|fields| Network {
+            ssid: fields[0].clone(),
+            mode: match fields[1].as_str() {
+                "Infra" => Some(Mode::Infrastructure),
+                "Ad-Hoc" => Some(Mode::AdHoc),
+                "Mesh" => Some(Mode::Mesh),
+                _ => None,
+            },
+            channel: fields[2].parse().ok(),
+            frequency_mhz: leading_number(&fields[3]),
+            max_rate_mbps: leading_number(&fields[4]),
+            // Signal strength, defaulting to 0 if parsing fails
+            signal: fields[5].parse().unwrap_or(0),
+            security: Security::parse(&fields[6]),
+            bssid: fields[7].clone(),
+            band: None,
+        })
+        .collect()
+}
+
+/// Parses the number of a value printed with its unit, e.g. "2437 MHz" or
+/// "54 Mbit/s".
+fn leading_number<T: std::str::FromStr>(field: &str) -> Option<T> {
+    field.split_whitespace().next()?.parse().ok()
+}
+
+/// Parses `nmcli -t -f ACTIVE,BSSID,CHAN,FREQ,SIGNAL device wifi list` output.
+///
+/// Returns the access point the interface is associated with, or `None` if
+/// no entry is marked active. Only the fields nmcli knows are filled in.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::backend::nmcli::parse_active_link;
+///
+/// let output = "no:AA\\:BB\\:CC\\:00\\:00\\:01:11:2462 MHz:64\n\
+///               yes:24\\:0A\\:C4\\:12\\:34\\:56:6:2437 MHz:78\n";
+/// let link = parse_active_link(output).unwrap();
+/// assert_eq!(link.bssid.as_deref(), Some("24:0A:C4:12:34:56"));
+/// assert_eq!(link.signal, Some(78));
+/// assert_eq!(link.channel, Some(6));
+/// assert_eq!(link.frequency_mhz, Some(2437));
+///
+/// assert!(parse_active_link("no:AA\\:BB\\:CC\\:00\\:00\\:01:11:2462 MHz:64\n").is_none());
+/// ```
+pub fn parse_active_link(output: &str) -> Option<LinkQuality> {
+    let fields = output
+        .lines()
+        .map(split_terse)
+        .find(|fields| fields.len() == 5 && fields[0] == "yes")?;
+
+    Some(LinkQuality {
+        bssid: Some(fields[1].clone()).filter(|b| !b.is_empty()),
+        channel: fields[2].parse().ok(),
+        frequency_mhz: leading_number(&fields[3]),
+        signal: fields[4].parse().ok(),
+        ..LinkQuality::default()
+    })
+}
+
+/// Parses `nmcli -t device show <interface>` output.
+///
+/// # Parsed Fields
+/// - `GENERAL.STATE` - Interface state (e.g., "100 (connected)")
+/// - `GENERAL.CONNECTION` - Active connection profile name
+/// - `IP4.ADDRESS[n]` - IPv4 addresses with CIDR, in order
+/// - `IP4.GATEWAY` - IPv4 gateway address
+/// - `IP4.DNS[n]` - DNS servers, in order
+///
+/// NetworkManager 0.9 reported addresses as `ip = <addr>, gw = <gateway>`
+/// without a separate `IP4.GATEWAY` line; both forms are understood.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::backend::nmcli::parse_device_show;
+///
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/1.36/device-show.txt"));
+/// let status = parse_device_show("wlan1", output);
+///
+/// assert_eq!(status.state, "100 (connected)");
+/// assert_eq!(status.connection.as_deref(), Some("Lab:Dog"));
+/// assert_eq!(status.ip_address.as_deref(), Some("192.168.4.2/24"));
+/// assert_eq!(status.addresses, ["192.168.4.2/24", "10.42.0.5/16"]);
+/// assert_eq!(status.gateway.as_deref(), Some("192.168.4.1"));
+/// assert_eq!(status.dns, ["192.168.4.1", "8.8.8.8"]);
+///
+/// // NetworkManager 0.9 embeds the gateway in the address line
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/0.9.10/device-show.txt"));
+/// let status = parse_device_show("wlan1", output);
+/// assert_eq!(status.addresses, ["192.168.4.2/24"]);
+/// assert_eq!(status.gateway.as_deref(), Some("192.168.4.1"));
+///
+/// // Disconnected devices report empty values
+/// let output = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nmcli/1.46/device-show.txt"));
+/// let status = parse_device_show("wlan1", output);
+/// assert_eq!(status.state, "30 (disconnected)");
+/// assert!(status.connection.is_none() && status.gateway.is_none() && status.addresses.is_empty());
+/// ```
+pub fn parse_device_show(interface: &str, output: &str) -> ConnectionStatus {
+    // Initialize status with default values
+    let mut status = ConnectionStatus {
+        interface: interface.to_string(),
+        state: "unknown".to_string(),
+        connection: None,
+        ssid: None,
+        ip_address: None,
+        addresses: Vec::new(),
+        dns: Vec::new(),
+        gateway: None,
+    };
+    let mut legacy_gateway = None;
+
+    // Parse each line of the terse output (format: KEY:VALUE)
+    for line in output.lines() {
+        // Split on the first unescaped colon only (value might contain colons)
+        let fields = split_terse_n(line, 2);
+        let [key, value] = match <[String; 2]>::try_from(fields) {
+            Ok(pair) => pair,
+            Err(_) => continue, // Skip malformed lines
+        };
+
+        // Empty or "--" means the field is not set
+        if value.is_empty() || value == "--" {
+            continue;
+        }
+
+        // Indexed keys such as IP4.ADDRESS[2] are matched by their base name
+        let base = key.split('[').next().unwrap_or(&key);
+
+        match base {
+            // Connection state (e.g., "100 (connected)", "30 (disconnected)")
+            "GENERAL.STATE" => status.state = value,
+
+            // Active connection profile name
+            "GENERAL.CONNECTION" => status.connection = Some(value),
+
+            // IPv4 address, either "192.168.4.2/24" or 0.9's
+            // "ip = 192.168.4.2/24, gw = 192.168.4.1"
+            "IP4.ADDRESS" => match value.strip_prefix("ip = ") {
+                Some(legacy) => {
+                    let mut parts = legacy.split(", gw = ");
+                    if let Some(address) = parts.next() {
+                        status.addresses.push(address.to_string());
+                    }
+                    if legacy_gateway.is_none() {
+                        legacy_gateway = parts.next().map(String::from);
+                    }
+                }
+                None => status.addresses.push(value),
+            },
+
+            // IPv4 gateway address (e.g., "192.168.4.1")
+            "IP4.GATEWAY" => status.gateway = Some(value),
+
+            // DNS servers (e.g., "192.168.4.1")
+            "IP4.DNS" => status.dns.push(value),
+
+            // Ignore other fields
+            _ => {}
+        }
+    }
+
+    // The first address is the primary one
+    status.ip_address = status.addresses.first().cloned();
+
+    // Fall back to the gateway embedded in 0.9-style address lines
+    if status.gateway.is_none() {
+        status.gateway = legacy_gateway.filter(|gw| gw != "0.0.0.0");
+    }
+
+    status
+}
+
+/// Network backend that drives NetworkManager through the `nmcli` CLI.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NmcliBackend;
+
+impl NmcliBackend {
+    /// Creates a new nmcli backend.
+    pub fn new() -> Self {
+        NmcliBackend
+    }
+}
+
+impl NetworkBackend for NmcliBackend {
+    fn name(&self) -> &'static str {
+        "nmcli"
+    }
+
+    /// Lists WiFi interfaces.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli -t -f DEVICE,TYPE,STATE device
+    /// ```
+    ///
+    /// The `-t` flag produces terse output, and `-f` specifies the fields to display.
+    /// Output format is `device:type:state` per line; see [`parse_device_list`].
+    fn list_interfaces(&self) -> Result<Vec<WifiInterface>> {
+        // Execute nmcli to list all network devices in terse format
+        let output = nmcli()
+            .args(["-t", "-f", "DEVICE,TYPE,STATE", "device"])
+            .output()
+            .context("Failed to execute nmcli")?;
+
+        // Check for command execution errors
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
+        }
+
+        // Parse the output and check which interfaces are USB-based
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut interfaces = parse_device_list(&stdout);
+        for interface in &mut interfaces {
+            interface.is_usb = is_usb_interface(&interface.name);
+        }
+
+        Ok(interfaces)
+    }
+
+    /// Scans for networks.
+    ///
+    /// # Commands Executed
+    /// ```bash
+    /// nmcli -t -f SSID,MODE,CHAN,FREQ,RATE,SIGNAL,SECURITY,BSSID device wifi list ifname <interface> --rescan yes
+    /// ```
+    ///
+    /// With `--rescan yes` nmcli requests a scan and waits until
+    /// NetworkManager has published its results, so back-to-back scans
+    /// always return fresh ones. nmcli before 1.12 does not know the option;
+    /// it then falls back to:
+    ///
+    /// ```bash
+    /// nmcli device wifi rescan ifname <interface>
+    /// nmcli -t -f SSID,MODE,CHAN,FREQ,RATE,SIGNAL,SECURITY,BSSID device wifi list ifname <interface>
+    /// ```
+    ///
+    /// # Note
+    /// The rescan may be refused if the interface is busy or doesn't support
+    /// on-demand scanning. Cached results from the last successful scan are
+    /// returned in this case.
+    fn scan(&self, interface: &str) -> Result<Vec<Network>> {
+        // Step 1: Scan and list in one go; nmcli returns once the scan completed
+        let output = wifi_list(interface, true)?;
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            return Ok(parse_wifi_list(&stdout));
+        }
+
+        // Step 2: Older nmcli (or a refused scan): trigger a rescan separately
+        // Result is ignored because rescan can fail if already scanning
+        let _ = nmcli()
+            .args(["device", "wifi", "rescan", "ifname", interface])
+            .output();
+
+        // Step 3: Old NetworkManager offers no way to wait for the scan from
+        // the command line, so give it a fixed time
+        std::thread::sleep(LEGACY_SCAN_WAIT);
+
+        // Step 4: Retrieve the list of discovered access points
+        let output = wifi_list(interface, false)?;
+
+        // Check for command execution errors
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
+        }
+
+        // Step 5: Parse the output (format: SSID:MODE:CHAN:FREQ:RATE:SIGNAL:SECURITY:BSSID)
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(parse_wifi_list(&stdout))
+    }
+
+    /// Connects to a network, creating or updating the profile for the SSID.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli device wifi connect <ssid> ifname <interface> password <password>
+    /// ```
+    ///
+    /// `password` is left out when empty (open networks).
+    fn connect(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        wifi_connect(interface, ssid, None, password, false)
+    }
+
+    /// Connects to a hidden network, creating or updating its profile.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli device wifi connect <ssid> ifname <interface> password <password> hidden yes
+    /// ```
+    fn connect_hidden(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        wifi_connect(interface, ssid, None, password, true)
+    }
+
+    /// Connects to one access point of a network, restricting the profile to it.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli device wifi connect <ssid> ifname <interface> password <password> bssid <bssid>
+    /// ```
+    fn connect_bssid(&self, interface: &str, ssid: &str, bssid: &str, password: &str) -> Result<()> {
+        wifi_connect(interface, ssid, Some(bssid), password, false)
+    }
+
+    /// Disconnects the interface, keeping its connection profile.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli device disconnect <interface>
+    /// ```
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        // Execute nmcli command to disconnect the interface
+        let output = nmcli()
+            .args(["device", "disconnect", interface])
+            .output()
+            .context("Failed to execute nmcli disconnect")?;
+
+        // Check for command execution errors
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Reads the interface's connection status.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli -t device show <interface>
+    /// ```
+    ///
+    /// The `-t` flag produces terse (machine-readable) output with colon-separated
+    /// key:value pairs, one per line. See [`parse_device_show`] for the fields used.
+    fn status(&self, interface: &str) -> Result<ConnectionStatus> {
+        // Execute nmcli to get device information in terse format
+        let output = nmcli()
+            .args(["-t", "device", "show", interface])
+            .output()
+            .context("Failed to execute nmcli device show")?;
+
+        // Check for command execution errors
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
+        }
+
+        // Parse the output and build the status struct
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut status = parse_device_show(interface, &stdout);
+
+        // The device only names the profile; the SSID is one of its settings
+        if let Some(profile) = &status.connection {
+            status.ssid = profile_ssid(profile);
+        }
+        Ok(status)
+    }
+
+    /// Reads the associated access point from the scan cache, completed
+    /// with RSSI and bitrates from `iw` where available.
+    ///
+    /// # Commands Executed
+    /// ```bash
+    /// nmcli -t -f ACTIVE,BSSID,CHAN,FREQ,SIGNAL device wifi list ifname <interface> --rescan no
+    /// iw dev <interface> link
+    /// ```
+    fn link_quality(&self, interface: &str) -> Result<Option<LinkQuality>> {
+        // Use cached results only; a rescan would stall the link
+        let output = nmcli()
+            .args([
+                "-t", "-f", "ACTIVE,BSSID,CHAN,FREQ,SIGNAL", "device", "wifi", "list",
+                "ifname", interface, "--rescan", "no",
+            ])
+            .output()
+            .context("Failed to execute nmcli wifi list")?;
+
+        // Check for command execution errors
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
+        }
+
+        // NetworkManager decides whether the interface is associated
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(parse_active_link(&stdout)
+            .map(|link| link.fill_from(iw::link(interface).unwrap_or_default())))
+    }
+
+    /// Deletes a saved connection profile.
+    ///
+    /// # Command Executed
+    /// ```bash
+    /// nmcli connection delete <name>
+    /// ```
+    fn delete_profile(&self, name: &str) -> Result<()> {
+        // Execute nmcli command to delete the connection profile
+        let output = nmcli()
+            .args(["connection", "delete", name])
+            .output()
+            .context("Failed to execute nmcli connection delete")?;
+
+        // Check for command execution errors
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,600 @@
+//! Backend for hosts running plain wpa_supplicant without NetworkManager.
+//!
+//! Talks to wpa_supplicant over its control interface: one Unix datagram
+//! socket per interface in the control directory (usually
+//! `/var/run/wpa_supplicant/<interface>`). Requests are plain-text commands
+//! and replies are plain text. Address assignment is left to whatever DHCP
+//! client runs alongside (e.g., dhcpcd); the gateway is read from the kernel
+//! routing table.
+//!
+//! # Commands Used
+//!
+//! | Command          | Used for                                   |
+//! |------------------|--------------------------------------------|
+//! | `SCAN`           | Trigger a scan                             |
+//! | `SCAN_RESULTS`   | Read BSSID, frequency, signal, flags, SSID |
+//! | `ADD_NETWORK`    | Create a network block for `connect`       |
+//! | `SET_NETWORK`    | Set SSID, PSK and `scan_ssid` (hidden)     |
+//! | `SELECT_NETWORK` | Connect to the network block               |
+//! | `REMOVE_NETWORK` | Drop replaced or failed network blocks     |
+//! | `SAVE_CONFIG`    | Persist network blocks once connected      |
+//! | `STATUS`         | Connection state, SSID and IP address      |
+//! | `SIGNAL_POLL`    | RSSI, transmit rate and frequency          |
+//!
+//! # Testing
+//!
+//! [`WpaSupplicantBackend::with_ctrl_dir`] points the backend at another
+//! directory, so a fake daemon bound to a datagram socket there can answer
+//! the same commands; `tests/wpa_supplicant_backend.rs` does just that.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use super::{iw, NetworkBackend};
+use crate::connection::{dbm_to_percent, ConnectionStatus, LinkQuality};
+use crate::error::WifiProxyError;
+use crate::interface::{is_usb_interface, WifiInterface};
+use crate::scan::{Mode, Network, Security};
+
+/// Default control interface directory used by most distributions.
+pub const DEFAULT_CTRL_DIR: &str = "/var/run/wpa_supplicant";
+
+/// Timeout for a single request/reply exchange.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for `CTRL-EVENT-SCAN-RESULTS` after `SCAN`.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait for `wpa_state=COMPLETED` after `SELECT_NETWORK`.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between `STATUS` polls while connecting.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Largest reply we expect (`SCAN_RESULTS` in a busy area can be long).
+const MAX_REPLY: usize = 64 * 1024;
+
+/// Counter making client socket paths unique within the process.
+static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Network backend speaking the wpa_supplicant control interface protocol.
+#[derive(Debug, Clone)]
+pub struct WpaSupplicantBackend {
+    /// Directory containing one control socket per interface.
+    ctrl_dir: PathBuf,
+}
+
+impl Default for WpaSupplicantBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WpaSupplicantBackend {
+    /// Creates a backend using the default control directory.
+    pub fn new() -> Self {
+        Self::with_ctrl_dir(DEFAULT_CTRL_DIR)
+    }
+
+    /// Creates a backend using a custom control directory.
+    ///
+    /// # Arguments
+    /// * `ctrl_dir` - Directory holding the `<interface>` control sockets
+    pub fn with_ctrl_dir(ctrl_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            ctrl_dir: ctrl_dir.into(),
+        }
+    }
+
+    /// Opens a control connection to the interface's socket.
+    ///
+    /// # Returns
+    /// - `Err(WifiProxyError::InterfaceNotFound)` if wpa_supplicant does not
+    ///   manage the interface (no socket in the control directory)
+    fn open(&self, interface: &str) -> Result<CtrlSocket> {
+        let path = self.ctrl_dir.join(interface);
+        if !path.exists() {
+            return Err(WifiProxyError::InterfaceNotFound(interface.to_string()).into());
+        }
+        CtrlSocket::connect(&path)
+    }
+
+    /// Sends a command that must be answered with `OK`.
+    fn request_ok(&self, ctrl: &CtrlSocket, command: &str) -> Result<()> {
+        let reply = ctrl.request(command)?;
+        if reply.trim() != "OK" {
+            return Err(WifiProxyError::WpaSupplicant(format!(
+                "{} returned {}",
+                command.split(' ').next().unwrap_or(command),
+                reply.trim()
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns `(id, ssid)` for every configured network block.
+    fn list_networks(&self, ctrl: &CtrlSocket) -> Result<Vec<(String, String)>> {
+        let reply = ctrl.request("LIST_NETWORKS")?;
+
+        // Format: "network id / ssid / bssid / flags" header, then tab-separated rows
+        Ok(reply
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let id = fields.next()?.to_string();
+                let ssid = unescape(fields.next()?);
+                Some((id, ssid))
+            })
+            .collect())
+    }
+
+    /// Reads `STATUS` as key/value pairs.
+    fn read_status(&self, ctrl: &CtrlSocket) -> Result<Vec<(String, String)>> {
+        let reply = ctrl.request("STATUS")?;
+        Ok(reply
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect())
+    }
+
+    /// Adds a network block for an SSID, selects it and waits for the link.
+    ///
+    /// Hidden networks get `scan_ssid 1`, so wpa_supplicant probes for the
+    /// SSID by name, and a given BSSID restricts the block to that access
+    /// point. The new block replaces older blocks for the SSID and
+    /// the configuration is saved only once the link is up; if anything
+    /// fails the new block is removed again, leaving the old ones in place.
+    fn select_network(
+        &self,
+        interface: &str,
+        ssid: &str,
+        bssid: Option<&str>,
+        password: &str,
+        hidden: bool,
+    ) -> Result<()> {
+        let ctrl = self.open(interface)?;
+
+        let id = ctrl.request("ADD_NETWORK")?.trim().to_string();
+        if id.parse::<u32>().is_err() {
+            return Err(WifiProxyError::WpaSupplicant(format!("ADD_NETWORK returned {}", id)).into());
+        }
+
+        // Don't leave a half-configured or failing block behind
+        if let Err(e) = self.join_network(&ctrl, &id, ssid, bssid, password, hidden) {
+            let _ = ctrl.request(&format!("REMOVE_NETWORK {}", id));
+            return Err(e);
+        }
+
+        // Connected: drop older blocks for this SSID
+        for (other, existing) in self.list_networks(&ctrl)? {
+            if existing == ssid && other != id {
+                self.request_ok(&ctrl, &format!("REMOVE_NETWORK {}", other))?;
+            }
+        }
+
+        // Persist like NetworkManager profiles; fails harmlessly without update_config=1
+        let _ = ctrl.request("SAVE_CONFIG");
+        Ok(())
+    }
+
+    /// Configures a new network block, selects it and waits for `COMPLETED`.
+    fn join_network(
+        &self,
+        ctrl: &CtrlSocket,
+        id: &str,
+        ssid: &str,
+        bssid: Option<&str>,
+        password: &str,
+        hidden: bool,
+    ) -> Result<()> {
+        self.request_ok(ctrl, &format!("SET_NETWORK {} ssid {}", id, hex_ssid(ssid)))?;
+        if hidden {
+            self.request_ok(ctrl, &format!("SET_NETWORK {} scan_ssid 1", id))?;
+        }
+        if let Some(bssid) = bssid {
+            self.request_ok(ctrl, &format!("SET_NETWORK {} bssid {}", id, bssid.to_ascii_lowercase()))?;
+        }
+        if password.is_empty() {
+            self.request_ok(ctrl, &format!("SET_NETWORK {} key_mgmt NONE", id))?;
+        } else {
+            // wpa_supplicant answers FAIL for a passphrase outside 8-63 characters
+            let reply = ctrl.request(&format!("SET_NETWORK {} psk \"{}\"", id, password))?;
+            if reply.trim() != "OK" {
+                return Err(WifiProxyError::ConnectionFailed(format!(
+                    "wpa_supplicant rejected the password: {}",
+                    reply.trim()
+                ))
+                .into());
+            }
+        }
+        self.request_ok(ctrl, &format!("SELECT_NETWORK {}", id))?;
+
+        // Wait for association and key exchange to finish
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        while Instant::now() < deadline {
+            let status = self.read_status(ctrl)?;
+            if status.iter().any(|(k, v)| k == "wpa_state" && v == "COMPLETED") {
+                return Ok(());
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        Err(WifiProxyError::ConnectionFailed(format!(
+            "Timed out connecting to '{}' (wrong password or out of range)",
+            ssid
+        ))
+        .into())
+    }
+}
+
+/// A client connection to one wpa_supplicant control socket.
+///
+/// The client side of a datagram control connection needs its own bound
+/// socket path so the daemon can reply; it is removed on drop.
+struct CtrlSocket {
+    socket: UnixDatagram,
+    local_path: PathBuf,
+}
+
+impl CtrlSocket {
+    /// Binds a temporary client socket and connects it to the daemon socket.
+    fn connect(server: &Path) -> Result<Self> {
+        let local_path = std::env::temp_dir().join(format!(
+            "wifi-proxy-wpa-{}-{}",
+            std::process::id(),
+            SOCKET_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        // A stale socket from a crashed run would make bind fail
+        let _ = fs::remove_file(&local_path);
+
+        let socket = UnixDatagram::bind(&local_path)
+            .with_context(|| format!("Failed to bind {}", local_path.display()))?;
+        let ctrl = CtrlSocket { socket, local_path };
+
+        ctrl.socket
+            .connect(server)
+            .map_err(|e| WifiProxyError::WpaSupplicant(format!("{}: {}", server.display(), e)))?;
+        ctrl.socket.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+        Ok(ctrl)
+    }
+
+    /// Sends a command and returns the reply, skipping unsolicited events.
+    fn request(&self, command: &str) -> Result<String> {
+        self.socket
+            .send(command.as_bytes())
+            .map_err(|e| WifiProxyError::WpaSupplicant(e.to_string()))?;
+
+        loop {
+            let message = self.receive(REQUEST_TIMEOUT)?;
+            // Event messages start with a "<level>" prefix, replies never do
+            if !message.starts_with('<') {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Receives one datagram, failing after the timeout.
+    fn receive(&self, timeout: Duration) -> Result<String> {
+        let mut buf = vec![0u8; MAX_REPLY];
+        self.socket.set_read_timeout(Some(timeout))?;
+        let len = self.socket.recv(&mut buf).map_err(|e| {
+            WifiProxyError::WpaSupplicant(format!("no reply from wpa_supplicant: {}", e))
+        })?;
+        Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+    }
+
+    /// Waits until an event containing `name` arrives or the deadline passes.
+    ///
+    /// Requires a prior `ATTACH` on this socket.
+    fn wait_event(&self, name: &str, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match self.receive(remaining.max(Duration::from_millis(1))) {
+                Ok(message) if message.starts_with('<') && message.contains(name) => return true,
+                Ok(_) => continue,
+                Err(_) => return false,
+            }
+        }
+        false
+    }
+}
+
+impl Drop for CtrlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.local_path);
+    }
+}
+
+/// Decodes wpa_supplicant's `printf_encode` escaping used for SSIDs.
+///
+/// Handles `\\`, `\"`, `\e`, `\n`, `\r`, `\t` and `\xNN`; invalid UTF-8
+/// is replaced like other SSID sources in this crate.
+fn unescape(encoded: &str) -> String {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut chars = encoded.bytes().peekable();
+
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match chars.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'e') => bytes.push(0x1b),
+            Some(b'x') => {
+                // Two hex digits follow
+                let hi = chars.next();
+                let lo = chars.next();
+                let hex = [hi.unwrap_or(b'0'), lo.unwrap_or(b'0')];
+                let value = std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .unwrap_or(b'?');
+                bytes.push(value);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Converts scan result flags (e.g., `[WPA2-PSK-CCMP][ESS]`) to an
+/// nmcli-style security string (e.g., `"WPA2"`).
+fn security_from_flags(flags: &str) -> String {
+    let (mut wep, mut wpa1, mut wpa2, mut wpa3, mut eap) = (false, false, false, false, false);
+
+    // Each bracketed group describes one protocol, e.g. "WPA2-PSK+SAE-CCMP"
+    for group in flags.split(']').map(|g| g.trim_start_matches('[')) {
+        if group.starts_with("WEP") {
+            wep = true;
+        } else if group.starts_with("WPA-") {
+            wpa1 = true;
+        } else if group.starts_with("WPA2-") || group.starts_with("RSN-") {
+            wpa2 |= group.contains("PSK") || group.contains("EAP");
+            wpa3 |= group.contains("SAE");
+        } else {
+            continue;
+        }
+        eap |= group.contains("EAP");
+    }
+
+    let labels = [(wep, "WEP"), (wpa1, "WPA1"), (wpa2, "WPA2"), (wpa3, "WPA3"), (eap, "802.1X")];
+    labels
+        .iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, label)| *label)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses `SCAN_RESULTS` output into scan entries.
+///
+/// Format: a header line, then one tab-separated row per BSS:
+/// `bssid  frequency  signal level  flags  ssid`. The advertised rate is
+/// not part of the scan results and is left unset.
+fn parse_scan_results(reply: &str) -> Vec<Network> {
+    reply
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(5, '\t').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            let signal = fields[2].parse().map(dbm_to_percent).unwrap_or(0);
+            let flags = fields[3];
+            Some(Network {
+                ssid: fields.get(4).map(|s| unescape(s)).unwrap_or_default(),
+                bssid: fields[0].to_string(),
+                signal,
+                security: Security::parse(&security_from_flags(flags)),
+                frequency_mhz: fields[1].parse().ok(),
+                mode: Some(if flags.contains("[IBSS]") {
+                    Mode::AdHoc
+                } else if flags.contains("[MESH]") {
+                    Mode::Mesh
+                } else {
+                    Mode::Infrastructure
+                }),
+                ..Network::default()
+            })
+        })
+        .collect()
+}
+
+/// Returns a short connected/connecting/disconnected label for a `wpa_state`.
+fn wpa_state_label(state: &str) -> &'static str {
+    match state {
+        "COMPLETED" => "connected",
+        "AUTHENTICATING" | "ASSOCIATING" | "ASSOCIATED" | "4WAY_HANDSHAKE"
+        | "GROUP_HANDSHAKE" => "connecting",
+        "INTERFACE_DISABLED" => "unavailable",
+        _ => "disconnected",
+    }
+}
+
+/// Looks up the IPv4 default gateway for an interface in `/proc/net/route`.
+///
+/// Returns None if the interface has no default route (e.g., DHCP still
+/// pending or the DHCP client does not install one).
+fn default_gateway(interface: &str) -> Option<String> {
+    let routes = fs::read_to_string("/proc/net/route").ok()?;
+
+    // Columns: Iface Destination Gateway Flags ... (addresses in little-endian hex)
+    routes.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[0] != interface || fields[1] != "00000000" {
+            return None;
+        }
+        let gateway = u32::from_str_radix(fields[2], 16).ok()?;
+        Some(std::net::Ipv4Addr::from(gateway.to_le_bytes()).to_string())
+    })
+}
+
+/// Encodes an SSID as the unquoted hex form accepted by `SET_NETWORK`.
+///
+/// Hex avoids any quoting issues with SSIDs containing quotes or spaces.
+fn hex_ssid(ssid: &str) -> String {
+    ssid.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl NetworkBackend for WpaSupplicantBackend {
+    fn name(&self) -> &'static str {
+        "wpa_supplicant"
+    }
+
+    fn list_interfaces(&self) -> Result<Vec<WifiInterface>> {
+        let entries = fs::read_dir(&self.ctrl_dir).map_err(|e| {
+            WifiProxyError::WpaSupplicant(format!("{}: {}", self.ctrl_dir.display(), e))
+        })?;
+
+        // Every socket in the control directory is an interface wpa_supplicant manages
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        names.sort();
+
+        let mut interfaces = Vec::new();
+        for name in names {
+            // Skip sockets we cannot talk to (e.g., leftovers from a dead daemon)
+            let Ok(ctrl) = self.open(&name) else {
+                continue;
+            };
+            let status = self.read_status(&ctrl).unwrap_or_default();
+            let state = status
+                .iter()
+                .find(|(k, _)| k == "wpa_state")
+                .map(|(_, v)| wpa_state_label(v))
+                .unwrap_or("unknown");
+
+            interfaces.push(WifiInterface {
+                is_usb: is_usb_interface(&name),
+                state: state.to_string(),
+                name,
+            });
+        }
+
+        Ok(interfaces)
+    }
+
+    fn scan(&self, interface: &str) -> Result<Vec<Network>> {
+        // Listen for events on a separate socket so the scan completion is not missed
+        let events = self.open(interface)?;
+        self.request_ok(&events, "ATTACH")?;
+
+        // FAIL-BUSY means a scan is already running; its results are just as good
+        let ctrl = self.open(interface)?;
+        let reply = ctrl.request("SCAN")?;
+        if reply.trim() != "OK" && reply.trim() != "FAIL-BUSY" {
+            return Err(WifiProxyError::WpaSupplicant(format!("SCAN returned {}", reply.trim())).into());
+        }
+        events.wait_event("CTRL-EVENT-SCAN-RESULTS", SCAN_TIMEOUT);
+        let _ = events.request("DETACH");
+
+        let results = ctrl.request("SCAN_RESULTS")?;
+        Ok(parse_scan_results(&results))
+    }
+
+    fn connect(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        self.select_network(interface, ssid, None, password, false)
+    }
+
+    fn connect_hidden(&self, interface: &str, ssid: &str, password: &str) -> Result<()> {
+        self.select_network(interface, ssid, None, password, true)
+    }
+
+    fn connect_bssid(&self, interface: &str, ssid: &str, bssid: &str, password: &str) -> Result<()> {
+        self.select_network(interface, ssid, Some(bssid), password, false)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        let ctrl = self.open(interface)?;
+        self.request_ok(&ctrl, "DISCONNECT")
+    }
+
+    fn status(&self, interface: &str) -> Result<ConnectionStatus> {
+        let ctrl = self.open(interface)?;
+        let fields = self.read_status(&ctrl)?;
+        let get = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+
+        let wpa_state = get("wpa_state").unwrap_or("UNKNOWN");
+        let connected = wpa_state == "COMPLETED";
+
+        Ok(ConnectionStatus {
+            interface: interface.to_string(),
+            state: format!("{} ({})", wpa_state, wpa_state_label(wpa_state)),
+            connection: get("ssid").filter(|_| connected).map(unescape),
+            ssid: get("ssid").filter(|_| connected).map(unescape),
+            ip_address: get("ip_address").map(String::from),
+            addresses: get("ip_address").map(String::from).into_iter().collect(),
+            dns: Vec::new(),
+            gateway: if connected {
+                default_gateway(interface)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn link_quality(&self, interface: &str) -> Result<Option<LinkQuality>> {
+        let ctrl = self.open(interface)?;
+
+        // Answered with FAIL while not associated
+        let reply = ctrl.request("SIGNAL_POLL")?;
+        if reply.trim() == "FAIL" {
+            return Ok(None);
+        }
+        let poll: Vec<(&str, &str)> = reply.lines().filter_map(|line| line.split_once('=')).collect();
+        let get = |key: &str| poll.iter().find(|(k, _)| *k == key).map(|(_, v)| v.trim());
+
+        // LINKSPEED is the current transmit rate in Mbit/s
+        let link = LinkQuality {
+            bssid: self
+                .read_status(&ctrl)?
+                .into_iter()
+                .find(|(k, _)| k == "bssid")
+                .map(|(_, v)| v),
+            rssi_dbm: get("RSSI").and_then(|v| v.parse().ok()),
+            tx_bitrate_mbps: get("LINKSPEED").and_then(|v| v.parse().ok()),
+            frequency_mhz: get("FREQUENCY").and_then(|v| v.parse().ok()),
+            ..LinkQuality::default()
+        };
+        Ok(Some(link.fill_from(iw::link(interface).unwrap_or_default())))
+    }
+
+    fn delete_profile(&self, name: &str) -> Result<()> {
+        // Network blocks are per interface; search every managed interface
+        for iface in self.list_interfaces()? {
+            let ctrl = self.open(&iface.name)?;
+            if let Some((id, _)) = self
+                .list_networks(&ctrl)?
+                .into_iter()
+                .find(|(_, ssid)| ssid == name)
+            {
+                self.request_ok(&ctrl, &format!("REMOVE_NETWORK {}", id))?;
+                let _ = ctrl.request("SAVE_CONFIG");
+                return Ok(());
+            }
+        }
+
+        Err(WifiProxyError::NetworkNotFound(name.to_string()).into())
+    }
+}
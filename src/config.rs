@@ -13,6 +13,11 @@
 //! ssid = "RoboDog-AP"
 //! password = "secret123"
 //! interface = "wlan1"  # Optional preferred interface
+//! hidden = true        # Optional; the network does not broadcast its SSID
+//!
+//! [discovery]  # Optional; how `discover` recognizes the robot
+//! ssid_patterns = ["^WAVESHARE", "^RoboDog"]
+//! oui_prefixes = ["24:0A:C4", "30:AE:A4"]
 //! ```
 
 use anyhow::{Context, Result};
@@ -20,6 +25,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::discover::{DEFAULT_SSID_PATTERNS, ESPRESSIF_OUIS};
+
 /// Main configuration structure containing all application settings.
 ///
 /// This struct is serialized to/from TOML format and contains:
@@ -41,6 +48,11 @@ pub struct Config {
     /// If None, the system will auto-detect a USB WiFi interface.
     #[serde(default)]
     pub default_interface: Option<String>,
+
+    /// How `discover` recognizes the robot's access point.
+    /// Only written to the file when changed from the defaults.
+    #[serde(default, skip_serializing_if = "DiscoveryConfig::is_default")]
+    pub discovery: DiscoveryConfig,
 }
 
 /// Configuration for a single saved WiFi network.
@@ -60,6 +72,70 @@ pub struct NetworkConfig {
     /// If None, the system will auto-detect or use the default interface.
     #[serde(default)]
     pub interface: Option<String>,
+
+    /// Whether the network hides its SSID, so connections must probe for it.
+    /// Omitted from the file (and defaulting to false) for visible networks.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+/// Signs by which `discover` recognizes the robot's access point.
+///
+/// Missing keys fall back to the defaults, so setting only `ssid_patterns`
+/// keeps the Espressif OUIs.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiscoveryConfig {
+    /// Regular expressions matched against SSIDs.
+    /// Defaults to [`DEFAULT_SSID_PATTERNS`].
+    #[serde(default = "default_ssid_patterns", skip_serializing_if = "is_default_ssid_patterns")]
+    pub ssid_patterns: Vec<String>,
+
+    /// BSSID prefixes of the robot's WiFi chip vendor (e.g., "24:0A:C4").
+    /// Defaults to [`ESPRESSIF_OUIS`].
+    #[serde(default = "default_oui_prefixes", skip_serializing_if = "is_default_oui_prefixes")]
+    pub oui_prefixes: Vec<String>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            ssid_patterns: default_ssid_patterns(),
+            oui_prefixes: default_oui_prefixes(),
+        }
+    }
+}
+
+impl DiscoveryConfig {
+    /// Returns whether nothing differs from the defaults.
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns whether the OUI prefixes are the built-in Espressif ones.
+    pub fn has_default_oui_prefixes(&self) -> bool {
+        is_default_oui_prefixes(&self.oui_prefixes)
+    }
+}
+
+/// Default for [`DiscoveryConfig::ssid_patterns`].
+fn default_ssid_patterns() -> Vec<String> {
+    DEFAULT_SSID_PATTERNS.iter().map(|p| p.to_string()).collect()
+}
+
+/// Default for [`DiscoveryConfig::oui_prefixes`].
+fn default_oui_prefixes() -> Vec<String> {
+    ESPRESSIF_OUIS.iter().map(|p| p.to_string()).collect()
+}
+
+/// Keeps unchanged SSID patterns out of the config file, so they follow
+/// the defaults of later releases.
+fn is_default_ssid_patterns(patterns: &[String]) -> bool {
+    patterns.iter().map(String::as_str).eq(DEFAULT_SSID_PATTERNS)
+}
+
+/// Keeps the (long) default OUI list out of the config file.
+fn is_default_oui_prefixes(prefixes: &[String]) -> bool {
+    prefixes.iter().map(String::as_str).eq(ESPRESSIF_OUIS)
 }
 
 impl Config {
@@ -115,6 +191,7 @@ impl Config {
     ///     ssid: "MyNetwork".to_string(),
     ///     password: "secret".to_string(),
     ///     interface: None,
+    ///     hidden: false,
     /// });
     /// cfg.save().expect("Failed to save config");
     /// ```
@@ -171,6 +248,79 @@ impl Config {
         // Add the new/updated network configuration
         self.networks.push(network);
     }
+
+    /// Returns a view of the configuration that is safe to print.
+    ///
+    /// Passwords are replaced by whether one is set, so the view can be
+    /// shown on screen or handed to scripts.
+    ///
+    /// # Returns
+    /// - `Ok(RedactedConfig)` including the config file path
+    /// - `Err` if the config directory cannot be determined
+    ///
+    /// # Example
+    /// ```no_run
+    /// use wifi_proxy::config::{Config, NetworkConfig};
+    ///
+    /// let mut cfg = Config::default();
+    /// cfg.add_network(NetworkConfig {
+    ///     ssid: "RoboDog-AP".to_string(),
+    ///     password: "secret123".to_string(),
+    ///     interface: None,
+    ///     hidden: false,
+    /// });
+    /// let view = cfg.redacted().expect("No config directory");
+    /// assert!(view.networks[0].password_set);
+    /// ```
+    pub fn redacted(&self) -> Result<RedactedConfig> {
+        Ok(RedactedConfig {
+            path: config_path()?,
+            default_interface: self.default_interface.clone(),
+            discovery: self.discovery.clone(),
+            networks: self
+                .networks
+                .iter()
+                .map(|n| RedactedNetwork {
+                    ssid: n.ssid.clone(),
+                    interface: n.interface.clone(),
+                    hidden: n.hidden,
+                    password_set: !n.password.is_empty(),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// The configuration without credentials, as printed by `show-config`.
+#[derive(Debug, Serialize)]
+pub struct RedactedConfig {
+    /// Path of the config file the configuration was loaded from.
+    pub path: PathBuf,
+
+    /// Default interface name, if configured.
+    pub default_interface: Option<String>,
+
+    /// How `discover` recognizes the robot (no secrets).
+    pub discovery: DiscoveryConfig,
+
+    /// Saved networks without their passwords.
+    pub networks: Vec<RedactedNetwork>,
+}
+
+/// A saved network without its password.
+#[derive(Debug, Serialize)]
+pub struct RedactedNetwork {
+    /// The SSID (network name) of the WiFi network.
+    pub ssid: String,
+
+    /// Preferred interface for this network, if any.
+    pub interface: Option<String>,
+
+    /// Whether the network hides its SSID.
+    pub hidden: bool,
+
+    /// Whether a password is saved (open networks have none).
+    pub password_set: bool,
 }
 
 /// Returns the path to the configuration file.
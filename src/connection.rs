@@ -1,15 +1,15 @@
 //! WiFi connection management module.
 //!
-//! This module provides functionality for managing WiFi connections using
-//! NetworkManager's `nmcli` command-line tool. It supports connecting to
-//! networks, disconnecting, querying connection status, and fetching content
-//! from the gateway.
+//! This module provides functionality for managing WiFi connections. It
+//! supports connecting to networks, disconnecting, querying connection status,
+//! and fetching content from the gateway.
 //!
-//! # Requirements
+//! # Backends
 //!
-//! - NetworkManager must be installed and running
-//! - The `nmcli` command must be available in PATH
-//! - User must have permission to manage network connections
+//! Connection management is delegated to the currently selected
+//! [`NetworkBackend`](crate::backend::NetworkBackend). By default this is the
+//! nmcli backend, which requires NetworkManager to be running and the `nmcli`
+//! command to be available in PATH.
 //!
 //! # Example
 //!
@@ -28,44 +28,121 @@
 //! ```
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::fs;
 use std::path::Path;
-use std::process::Command;
 
+use crate::backend::backend;
 use crate::error::WifiProxyError;
 
 /// Represents the current connection status of a WiFi interface.
 ///
-/// Contains information retrieved from NetworkManager about the interface's
+/// Contains information retrieved from the network backend about the interface's
 /// state, active connection, IP configuration, and gateway address.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ConnectionStatus {
     /// The name of the network interface (e.g., "wlan1").
     pub interface: String,
 
     /// The current state of the interface (e.g., "100 (connected)", "30 (disconnected)").
-    /// This is the raw state string from nmcli.
+    /// This is the raw state string reported by the backend.
     pub state: String,
 
     /// The name of the active connection profile, if connected.
     /// None if not connected to any network.
     pub connection: Option<String>,
 
+    /// The SSID of the network the interface is associated with.
+    /// Differs from `connection` when the profile was renamed (NetworkManager
+    /// names a second profile for the same network "WAVESHARE Robot 1").
+    /// None if not connected or the backend cannot tell.
+    pub ssid: Option<String>,
+
     /// The IPv4 address assigned to the interface (with CIDR notation, e.g., "192.168.4.2/24").
     /// None if no IP address is assigned.
     pub ip_address: Option<String>,
 
+    /// Every IPv4 address assigned to the interface, in the order reported.
+    /// The first entry is the same as `ip_address`.
+    pub addresses: Vec<String>,
+
+    /// IPv4 DNS servers configured for the connection, in priority order.
+    pub dns: Vec<String>,
+
     /// The IPv4 gateway address for the connection.
     /// This is typically the robot's IP address (e.g., "192.168.4.1").
     /// None if no gateway is configured.
     pub gateway: Option<String>,
 }
 
+/// Radio state of an interface's link to its access point.
+///
+/// Backends measure different subsets; whatever they cannot report is `None`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LinkQuality {
+    /// MAC address of the access point (e.g., "24:0a:c4:12:34:56").
+    pub bssid: Option<String>,
+
+    /// Signal strength as a percentage (0-100), as in scan results.
+    pub signal: Option<u8>,
+
+    /// Received signal strength in dBm (e.g., -64).
+    pub rssi_dbm: Option<i32>,
+
+    /// Bitrate of the last frames sent to the access point, in Mbit/s.
+    pub tx_bitrate_mbps: Option<f64>,
+
+    /// Bitrate of the last frames received from the access point, in Mbit/s.
+    pub rx_bitrate_mbps: Option<f64>,
+
+    /// Center frequency of the channel in MHz (e.g., 2437).
+    pub frequency_mhz: Option<u32>,
+
+    /// Channel number (e.g., 6).
+    pub channel: Option<u32>,
+}
+
+impl LinkQuality {
+    /// Fills the fields this measurement lacks from another one.
+    ///
+    /// A missing signal percentage is derived from the RSSI and a missing
+    /// channel from the frequency.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::connection::LinkQuality;
+    ///
+    /// let nmcli = LinkQuality { signal: Some(70), frequency_mhz: Some(2437), ..Default::default() };
+    /// let iw = LinkQuality { rssi_dbm: Some(-64), rx_bitrate_mbps: Some(65.0), ..Default::default() };
+    ///
+    /// let link = nmcli.fill_from(iw);
+    /// assert_eq!(link.signal, Some(70));
+    /// assert_eq!(link.rssi_dbm, Some(-64));
+    /// assert_eq!(link.channel, Some(6));
+    /// ```
+    pub fn fill_from(self, other: LinkQuality) -> LinkQuality {
+        let rssi_dbm = self.rssi_dbm.or(other.rssi_dbm);
+        let frequency_mhz = self.frequency_mhz.or(other.frequency_mhz);
+        LinkQuality {
+            bssid: self.bssid.or(other.bssid),
+            signal: self.signal.or(other.signal).or(rssi_dbm.map(dbm_to_percent)),
+            rssi_dbm,
+            tx_bitrate_mbps: self.tx_bitrate_mbps.or(other.tx_bitrate_mbps),
+            rx_bitrate_mbps: self.rx_bitrate_mbps.or(other.rx_bitrate_mbps),
+            frequency_mhz,
+            channel: self
+                .channel
+                .or(other.channel)
+                .or(frequency_mhz.and_then(channel_from_frequency)),
+        }
+    }
+}
+
 /// Connects to a WiFi network using the specified interface.
 ///
-/// Uses NetworkManager's `nmcli` to establish a WiFi connection. This command
-/// will create a new connection profile if one doesn't exist for the SSID,
-/// or update an existing one with the new credentials.
+/// Establishes a WiFi connection through the selected backend. The backend
+/// creates a new connection profile if one doesn't exist for the SSID, or
+/// updates an existing one with the new credentials.
 ///
 /// # Arguments
 /// * `interface` - The name of the WiFi interface to use (e.g., "wlan1")
@@ -76,11 +153,6 @@ pub struct ConnectionStatus {
 /// - `Ok(())` if the connection is established successfully
 /// - `Err(WifiProxyError::ConnectionFailed)` if the connection attempt fails
 ///
-/// # Command Executed
-/// ```bash
-/// nmcli device wifi connect <ssid> password <password> ifname <interface>
-/// ```
-///
 /// # Example
 /// ```no_run
 /// use wifi_proxy::connection::connect;
@@ -88,160 +160,148 @@ pub struct ConnectionStatus {
 /// connect("wlan1", "RoboDog-AP", "password123").expect("Failed to connect");
 /// ```
 pub fn connect(interface: &str, ssid: &str, password: &str) -> Result<()> {
-    // Execute nmcli command to connect to the WiFi network
-    let output = Command::new("nmcli")
-        .args([
-            "device",     // Device management command
-            "wifi",       // WiFi-specific operation
-            "connect",    // Connect action
-            ssid,         // Target network SSID
-            "password",   // Password keyword
-            password,     // Network password
-            "ifname",     // Interface name keyword
-            interface,    // Target interface
-        ])
-        .output()
-        .context("Failed to execute nmcli connect")?;
-
-    // Check if the command succeeded
-    if !output.status.success() {
-        // Extract error message from stderr (preferred) or stdout
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let error_msg = if stderr.is_empty() {
-            stdout.to_string()
-        } else {
-            stderr.to_string()
-        };
-        return Err(WifiProxyError::ConnectionFailed(error_msg).into());
-    }
+    backend().connect(interface, ssid, password)
+}
 
-    Ok(())
+/// Connects to a WiFi network that does not broadcast its SSID.
+///
+/// Like [`connect`], but the profile is marked hidden so the adapter probes
+/// for the SSID by name. Scans list such networks with an empty SSID, so the
+/// name must be known beforehand.
+///
+/// # Arguments
+/// * `interface` - The name of the WiFi interface to use (e.g., "wlan1")
+/// * `ssid` - The SSID (network name) the access point hides
+/// * `password` - The WPA/WPA2 password for the network
+///
+/// # Returns
+/// - `Ok(())` if the connection is established successfully
+/// - `Err(WifiProxyError::ConnectionFailed)` if the connection attempt fails
+///
+/// # Example
+/// ```no_run
+/// use wifi_proxy::connection::connect_hidden;
+///
+/// connect_hidden("wlan1", "RoboDog-AP", "password123").expect("Failed to connect");
+/// ```
+pub fn connect_hidden(interface: &str, ssid: &str, password: &str) -> Result<()> {
+    backend().connect_hidden(interface, ssid, password)
+}
+
+/// Connects to one access point of a WiFi network, chosen by its BSSID.
+///
+/// Like [`connect`], but when several access points broadcast the SSID
+/// (e.g., two robots with the factory name) the one with the given BSSID is
+/// joined, and the saved profile sticks to it.
+///
+/// # Arguments
+/// * `interface` - The name of the WiFi interface to use (e.g., "wlan1")
+/// * `ssid` - The SSID (network name) to connect to
+/// * `bssid` - MAC address of the access point (e.g., "24:0A:C4:12:34:56")
+/// * `password` - The WPA/WPA2 password for the network
+///
+/// # Returns
+/// - `Ok(())` if the connection is established successfully
+/// - `Err(WifiProxyError::NetworkNotFound)` if no access point has that SSID and BSSID
+/// - `Err(WifiProxyError::ConnectionFailed)` if the connection attempt fails
+///
+/// # Example
+/// ```no_run
+/// use wifi_proxy::connection::connect_bssid;
+///
+/// connect_bssid("wlan1", "WAVESHARE Robot", "24:0A:C4:12:34:56", "1234567890").expect("Failed to connect");
+/// ```
+pub fn connect_bssid(interface: &str, ssid: &str, bssid: &str, password: &str) -> Result<()> {
+    backend().connect_bssid(interface, ssid, bssid, password)
 }
 
 /// Disconnects the specified interface from its current network.
 ///
-/// Uses NetworkManager's `nmcli` to disconnect the interface. The connection
-/// profile is preserved and can be reconnected later.
+/// The connection profile is preserved and can be reconnected later.
 ///
 /// # Arguments
 /// * `interface` - The name of the WiFi interface to disconnect (e.g., "wlan1")
 ///
 /// # Returns
 /// - `Ok(())` if the disconnection is successful
-/// - `Err(WifiProxyError::NmcliExecution)` if the command fails
-///
-/// # Command Executed
-/// ```bash
-/// nmcli device disconnect <interface>
-/// ```
+/// - `Err` if the backend fails to disconnect the interface
 pub fn disconnect(interface: &str) -> Result<()> {
-    // Execute nmcli command to disconnect the interface
-    let output = Command::new("nmcli")
-        .args(["device", "disconnect", interface])
-        .output()
-        .context("Failed to execute nmcli disconnect")?;
-
-    // Check for command execution errors
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
-    }
-
-    Ok(())
+    backend().disconnect(interface)
 }
 
-/// Retrieves the connection status for the specified interface.
+/// Reads the radio state of the interface's current link.
 ///
-/// Queries NetworkManager for detailed information about the interface including
-/// its connection state, active network name, IP address, and gateway.
+/// Unlike [`crate::scan::scan_networks`] this never triggers a scan, so it
+/// is cheap enough to poll while the link is in use.
 ///
 /// # Arguments
 /// * `interface` - The name of the WiFi interface to query (e.g., "wlan1")
 ///
 /// # Returns
-/// - `Ok(ConnectionStatus)` containing the interface's current status
-/// - `Err(WifiProxyError::NmcliExecution)` if the command fails
+/// - `Ok(Some(LinkQuality))` while associated; fields the backend cannot
+///   measure are `None`
+/// - `Ok(None)` if the interface is not associated with an access point
+/// - `Err` if the backend cannot query the interface
+pub fn link_quality(interface: &str) -> Result<Option<LinkQuality>> {
+    backend().link_quality(interface)
+}
+
+/// Converts an RSSI in dBm to a 0-100 quality percentage.
 ///
-/// # Command Executed
-/// ```bash
-/// nmcli -t device show <interface>
-/// ```
+/// Uses NetworkManager's mapping (-100 dBm = 0%, -50 dBm = 100%) so the
+/// numbers are comparable between backends.
 ///
-/// The `-t` flag produces terse (machine-readable) output with colon-separated
-/// key:value pairs, one per line.
+/// # Example
+/// ```
+/// use wifi_proxy::connection::dbm_to_percent;
 ///
-/// # Parsed Fields
-/// - `GENERAL.STATE` - Interface state (e.g., "100 (connected)")
-/// - `GENERAL.CONNECTION` - Active connection profile name
-/// - `IP4.ADDRESS[1]` - Primary IPv4 address with CIDR
-/// - `IP4.GATEWAY` - IPv4 gateway address
-pub fn status(interface: &str) -> Result<ConnectionStatus> {
-    // Execute nmcli to get device information in terse format
-    let output = Command::new("nmcli")
-        .args(["-t", "device", "show", interface])
-        .output()
-        .context("Failed to execute nmcli device show")?;
-
-    // Check for command execution errors
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
-    }
-
-    // Parse the output and build the status struct
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    // Initialize status with default values
-    let mut status = ConnectionStatus {
-        interface: interface.to_string(),
-        state: "unknown".to_string(),
-        connection: None,
-        ip_address: None,
-        gateway: None,
-    };
-
-    // Parse each line of the terse output (format: KEY:VALUE)
-    for line in stdout.lines() {
-        // Split on first colon only (value might contain colons)
-        let parts: Vec<&str> = line.splitn(2, ':').collect();
-        if parts.len() != 2 {
-            continue; // Skip malformed lines
-        }
+/// assert_eq!(dbm_to_percent(-64), 72);
+/// assert_eq!(dbm_to_percent(-40), 100);
+/// assert_eq!(dbm_to_percent(-105), 0);
+/// ```
+pub fn dbm_to_percent(dbm: i32) -> u8 {
+    (2 * (dbm + 100)).clamp(0, 100) as u8
+}
 
-        let key = parts[0];
-        let value = parts[1].to_string();
-
-        // Extract relevant fields based on the key
-        match key {
-            // Connection state (e.g., "100 (connected)", "30 (disconnected)")
-            "GENERAL.STATE" => status.state = value,
-
-            // Active connection profile name (empty or "--" if not connected)
-            "GENERAL.CONNECTION" => {
-                if !value.is_empty() && value != "--" {
-                    status.connection = Some(value);
-                }
-            }
-
-            // Primary IPv4 address (e.g., "192.168.4.2/24")
-            "IP4.ADDRESS[1]" => {
-                status.ip_address = Some(value);
-            }
-
-            // IPv4 gateway address (e.g., "192.168.4.1")
-            "IP4.GATEWAY" => {
-                if !value.is_empty() && value != "--" {
-                    status.gateway = Some(value);
-                }
-            }
-
-            // Ignore other fields
-            _ => {}
-        }
+/// Returns the WiFi channel number of a center frequency in MHz.
+///
+/// # Returns
+/// - `Some(channel)` for frequencies in the 2.4, 5 and 6 GHz bands
+/// - `None` for anything else
+///
+/// # Example
+/// ```
+/// use wifi_proxy::connection::channel_from_frequency;
+///
+/// assert_eq!(channel_from_frequency(2437), Some(6));
+/// assert_eq!(channel_from_frequency(2484), Some(14));
+/// assert_eq!(channel_from_frequency(5180), Some(36));
+/// assert_eq!(channel_from_frequency(5955), Some(1));
+/// assert_eq!(channel_from_frequency(900), None);
+/// ```
+pub fn channel_from_frequency(mhz: u32) -> Option<u32> {
+    match mhz {
+        2484 => Some(14),
+        2412..=2472 => Some((mhz - 2407) / 5),
+        5160..=5885 => Some((mhz - 5000) / 5),
+        5955..=7115 => Some((mhz - 5950) / 5),
+        _ => None,
     }
+}
 
-    Ok(status)
+/// Retrieves the connection status for the specified interface.
+///
+/// Queries the selected backend for detailed information about the interface
+/// including its connection state, active network name, IP address, and gateway.
+///
+/// # Arguments
+/// * `interface` - The name of the WiFi interface to query (e.g., "wlan1")
+///
+/// # Returns
+/// - `Ok(ConnectionStatus)` containing the interface's current status
+/// - `Err` if the backend cannot query the interface
+pub fn status(interface: &str) -> Result<ConnectionStatus> {
+    backend().status(interface)
 }
 
 /// Displays connection status information in a human-readable format.
@@ -258,7 +318,9 @@ pub fn status(interface: &str) -> Result<ConnectionStatus> {
 /// State:     100 (connected)
 /// Connected: RoboDog-AP
 /// IP:        192.168.4.2/24
+///            10.42.0.5/16
 /// Gateway:   192.168.4.1
+/// DNS:       192.168.4.1, 8.8.8.8
 /// ```
 pub fn display_status(status: &ConnectionStatus) {
     // Print interface name
@@ -274,18 +336,35 @@ pub fn display_status(status: &ConnectionStatus) {
         println!("Connected: (none)");
     }
 
+    // Print the SSID when the profile is named differently
+    if let Some(ref ssid) = status.ssid
+        && status.connection.as_ref() != Some(ssid)
+    {
+        println!("SSID:      {}", ssid);
+    }
+
     // Print IP address if assigned
     if let Some(ref ip) = status.ip_address {
         println!("IP:        {}", ip);
     }
 
+    // Print any additional addresses aligned under the first one
+    for extra in status.addresses.iter().skip(1) {
+        println!("           {}", extra);
+    }
+
     // Print gateway address if available
     if let Some(ref gw) = status.gateway {
         println!("Gateway:   {}", gw);
     }
+
+    // Print DNS servers if any are configured
+    if !status.dns.is_empty() {
+        println!("DNS:       {}", status.dns.join(", "));
+    }
 }
 
-/// Deletes a saved connection profile from NetworkManager.
+/// Deletes a saved connection profile from the selected backend.
 ///
 /// Removes the connection profile by name. This does not disconnect an active
 /// connection but removes the saved credentials and settings.
@@ -295,30 +374,13 @@ pub fn display_status(status: &ConnectionStatus) {
 ///
 /// # Returns
 /// - `Ok(())` if the deletion is successful
-/// - `Err(WifiProxyError::NmcliExecution)` if the command fails
-///
-/// # Command Executed
-/// ```bash
-/// nmcli connection delete <name>
-/// ```
+/// - `Err` if the profile cannot be deleted
 ///
 /// # Note
 /// This function is currently not used by the CLI but is available for
 /// programmatic use.
 pub fn delete_connection(name: &str) -> Result<()> {
-    // Execute nmcli command to delete the connection profile
-    let output = Command::new("nmcli")
-        .args(["connection", "delete", name])
-        .output()
-        .context("Failed to execute nmcli connection delete")?;
-
-    // Check for command execution errors
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
-    }
-
-    Ok(())
+    backend().delete_profile(name)
 }
 
 /// Fetches content from a URL and saves it to a file.
@@ -0,0 +1,285 @@
+//! Discovery of the robot's access point.
+//!
+//! A fresh machine knows neither the robot's SSID nor which access point in
+//! range belongs to it. Discovery scans on the USB adapter and picks out the
+//! access points that look like a robot, by two independent signs:
+//!
+//! | Field        | Set when                                                            |
+//! |--------------|---------------------------------------------------------------------|
+//! | `ssid_match` | The SSID matches one of the configured patterns (`^WAVESHARE`)      |
+//! | `oui_match`  | The BSSID starts with an OUI of Espressif, who make the ESP32 chips |
+//!
+//! Both lists are configurable in the `[discovery]` table of the config file
+//! (see [`DiscoveryConfig`]), so robots renamed from the factory SSID or built
+//! on another chip can be found too. Candidates are ranked by how many signs
+//! they show, then whether credentials for their SSID are saved (so they can
+//! be joined without asking), then by signal.
+//!
+//! Robots configured not to broadcast their SSID still show up by their OUI,
+//! listed as `<hidden>`. They cannot be joined without their name, so
+//! [`best`] passes over them.
+//!
+//! # Example
+//!
+//! ```
+//! use wifi_proxy::config::{Config, DiscoveryConfig};
+//! use wifi_proxy::discover::RobotMatcher;
+//! use wifi_proxy::scan::Network;
+//!
+//! let matcher = RobotMatcher::from_config(&DiscoveryConfig::default()).unwrap();
+//! let networks = vec![
+//!     Network { ssid: "HomeNetwork".into(), bssid: "3C:84:6A:01:02:03".into(), signal: 90, ..Network::default() },
+//!     Network { ssid: "esp-cam".into(), bssid: "30:AE:A4:00:11:22".into(), signal: 80, ..Network::default() },
+//!     Network { ssid: "WAVESHARE Robot".into(), bssid: "24:0A:C4:12:34:56".into(), signal: 60, ..Network::default() },
+//! ];
+//!
+//! let candidates = matcher.candidates(&networks, &Config::default());
+//! assert_eq!(candidates.len(), 2);
+//! assert_eq!(candidates[0].network.ssid, "WAVESHARE Robot");
+//! assert!(candidates[0].ssid_match && candidates[0].oui_match);
+//! assert!(!candidates[1].ssid_match && candidates[1].oui_match);
+//! ```
+
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::cmp::Reverse;
+
+use crate::config::{Config, DiscoveryConfig};
+use crate::connection::ConnectionStatus;
+use crate::error::WifiProxyError;
+use crate::scan::{or_dash, signal_to_bar, truncate_ssid, Network};
+
+/// SSID patterns matched by default: the robot's factory SSID is "WAVESHARE Robot".
+pub const DEFAULT_SSID_PATTERNS: [&str; 1] = ["^WAVESHARE"];
+
+/// Common OUIs (first three BSSID octets) assigned to Espressif Systems.
+///
+/// The ESP32 on the robot's driver board opens the access point, so its
+/// BSSID starts with one of these. The list is not exhaustive; Espressif
+/// keeps registering new blocks.
+pub const ESPRESSIF_OUIS: [&str; 40] = [
+    "08:3A:F2", "10:52:1C", "18:FE:34", "24:0A:C4", "24:62:AB", "24:6F:28", "24:A1:60", "24:B2:DE",
+    "2C:3A:E8", "30:AE:A4", "30:C6:F7", "34:86:5D", "3C:61:05", "3C:71:BF", "40:22:D8", "48:3F:DA",
+    "4C:11:AE", "58:BF:25", "5C:CF:7F", "60:01:94", "68:C6:3A", "78:E3:6D", "7C:9E:BD", "80:7D:3A",
+    "84:0D:8E", "84:CC:A8", "84:F3:EB", "8C:AA:B5", "94:B9:7E", "98:F4:AB", "A0:20:A6", "A4:CF:12",
+    "AC:67:B2", "B4:E6:2D", "BC:DD:C2", "C4:4F:33", "C8:C9:A3", "CC:50:E3", "D8:A0:1D", "EC:FA:BC",
+];
+
+/// An access point that looks like a robot.
+#[derive(Debug, Clone, Serialize)]
+pub struct RobotCandidate {
+    /// The access point, as scanned.
+    #[serde(flatten)]
+    pub network: Network,
+
+    /// Whether the SSID matches one of the SSID patterns.
+    pub ssid_match: bool,
+
+    /// Whether the BSSID starts with one of the OUI prefixes.
+    pub oui_match: bool,
+
+    /// Whether credentials for the SSID are saved in the config.
+    pub saved: bool,
+}
+
+impl RobotCandidate {
+    /// Number of signs (SSID, OUI) pointing to a robot: 1 or 2.
+    pub fn evidence(&self) -> u8 {
+        u8::from(self.ssid_match) + u8::from(self.oui_match)
+    }
+}
+
+/// Result of `discover`, as printed in JSON mode.
+#[derive(Debug, Serialize)]
+pub struct DiscoveryReport {
+    /// Interface that was scanned.
+    pub interface: String,
+
+    /// Likely robots, best first.
+    pub candidates: Vec<RobotCandidate>,
+
+    /// Status after connecting to the best candidate, with `--connect`.
+    pub connection: Option<ConnectionStatus>,
+}
+
+/// Recognizes robot access points by SSID pattern and BSSID prefix.
+#[derive(Debug, Clone)]
+pub struct RobotMatcher {
+    /// Compiled SSID patterns.
+    ssid_patterns: Vec<Regex>,
+
+    /// OUI prefixes as uppercase hex digits without separators (e.g., "240AC4").
+    oui_prefixes: Vec<String>,
+}
+
+impl RobotMatcher {
+    /// Creates a matcher from SSID patterns and BSSID prefixes.
+    ///
+    /// # Arguments
+    /// * `ssid_patterns` - Regular expressions, any of which may match the SSID
+    /// * `oui_prefixes` - BSSID prefixes such as "24:0A:C4" (`-` or no
+    ///   separators are accepted too, in any case)
+    ///
+    /// # Returns
+    /// - `Ok(RobotMatcher)` ready to classify scan results
+    /// - `Err(WifiProxyError::InvalidDiscoveryPattern)` for a malformed
+    ///   pattern or prefix
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::discover::RobotMatcher;
+    ///
+    /// let matcher = RobotMatcher::new(&["^RoboDog".to_string()], &["ac-67-b2".to_string()]).unwrap();
+    /// assert!(matcher.ssid_matches("RoboDog-AP"));
+    /// assert!(matcher.oui_matches("AC:67:B2:01:02:03"));
+    ///
+    /// assert!(RobotMatcher::new(&[], &["24:0A:CX".to_string()]).is_err());
+    /// ```
+    pub fn new(ssid_patterns: &[String], oui_prefixes: &[String]) -> Result<Self> {
+        let ssid_patterns = ssid_patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    WifiProxyError::InvalidDiscoveryPattern(format!("SSID pattern '{}': {}", pattern, e))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        let oui_prefixes = oui_prefixes
+            .iter()
+            .map(|prefix| {
+                let digits: String = prefix
+                    .chars()
+                    .filter(|c| !matches!(c, ':' | '-'))
+                    .collect::<String>()
+                    .to_ascii_uppercase();
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(WifiProxyError::InvalidDiscoveryPattern(format!(
+                        "OUI prefix '{}' is not a hexadecimal MAC address prefix",
+                        prefix
+                    )));
+                }
+                Ok(digits)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            ssid_patterns,
+            oui_prefixes,
+        })
+    }
+
+    /// Creates a matcher from the `[discovery]` table of the config file.
+    pub fn from_config(config: &DiscoveryConfig) -> Result<Self> {
+        Self::new(&config.ssid_patterns, &config.oui_prefixes)
+    }
+
+    /// Returns whether the SSID matches any of the patterns.
+    pub fn ssid_matches(&self, ssid: &str) -> bool {
+        self.ssid_patterns.iter().any(|pattern| pattern.is_match(ssid))
+    }
+
+    /// Returns whether the BSSID starts with any of the OUI prefixes.
+    pub fn oui_matches(&self, bssid: &str) -> bool {
+        let digits: String = bssid
+            .chars()
+            .filter(|c| c.is_ascii_hexdigit())
+            .collect::<String>()
+            .to_ascii_uppercase();
+        self.oui_prefixes.iter().any(|prefix| digits.starts_with(prefix.as_str()))
+    }
+
+    /// Picks the likely robots out of a scan.
+    ///
+    /// # Arguments
+    /// * `networks` - Scan results
+    /// * `config` - Configuration holding the saved credentials
+    ///
+    /// # Returns
+    /// Access points showing at least one sign, best first: most signs,
+    /// then saved credentials, then strongest signal.
+    pub fn candidates(&self, networks: &[Network], config: &Config) -> Vec<RobotCandidate> {
+        let mut candidates: Vec<RobotCandidate> = networks
+            .iter()
+            .map(|network| RobotCandidate {
+                ssid_match: self.ssid_matches(&network.ssid),
+                oui_match: self.oui_matches(&network.bssid),
+                saved: config.find_network(&network.ssid).is_some(),
+                network: network.clone(),
+            })
+            .filter(|candidate| candidate.evidence() > 0)
+            .collect();
+
+        candidates.sort_by_key(|c| Reverse((c.evidence(), c.saved, c.network.signal)));
+        candidates
+    }
+}
+
+/// Returns the candidate `discover --connect` joins: the best one whose SSID
+/// is known.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::discover::{best, RobotCandidate};
+/// use wifi_proxy::scan::Network;
+///
+/// let candidate = |ssid: &str| RobotCandidate {
+///     network: Network { ssid: ssid.into(), ..Network::default() },
+///     ssid_match: false,
+///     oui_match: true,
+///     saved: false,
+/// };
+/// let candidates = [candidate(""), candidate("esp-cam")];
+/// assert_eq!(best(&candidates).unwrap().network.ssid, "esp-cam");
+/// ```
+pub fn best(candidates: &[RobotCandidate]) -> Option<&RobotCandidate> {
+    candidates.iter().find(|candidate| !candidate.network.is_hidden())
+}
+
+/// Displays likely robots in a formatted table, the [`best`] one marked with `*`.
+///
+/// # Output Format
+/// ```text
+///   SSID                     BSSID              CH  SIGNAL      MATCH      SAVED
+///   -----------------------------------------------------------------------------
+/// * WAVESHARE Robot          24:0A:C4:12:34:56   6   78% ███░  ssid+oui   yes
+///   <hidden>                 24:0A:C4:9A:BC:DE  11   52% ███░  oui        no
+///   esp-cam                  30:AE:A4:00:11:22  11   47% ██░░  oui        no
+/// ```
+pub fn display_candidates(candidates: &[RobotCandidate]) {
+    // Handle empty results
+    if candidates.is_empty() {
+        println!("No robot access points found.");
+        return;
+    }
+
+    // Print table header
+    println!(
+        "  {:<24} {:<17} {:>3}  {:<10}  {:<10} SAVED",
+        "SSID", "BSSID", "CH", "SIGNAL", "MATCH"
+    );
+    println!("  {}", "-".repeat(77));
+
+    // Print each candidate, marking the one `--connect` would choose
+    let chosen = best(candidates).map(|c| &c.network.bssid);
+    for candidate in candidates {
+        let network = &candidate.network;
+        let matched = match (candidate.ssid_match, candidate.oui_match) {
+            (true, true) => "ssid+oui",
+            (true, false) => "ssid",
+            _ => "oui",
+        };
+        println!(
+            "{} {:<24} {:<17} {:>3}  {:>3}% {}  {:<10} {}",
+            if Some(&network.bssid) == chosen { '*' } else { ' ' },
+            truncate_ssid(&network.ssid, 24),
+            or_dash(Some(&network.bssid).filter(|b| !b.is_empty())),
+            or_dash(network.channel),
+            network.signal,
+            signal_to_bar(network.signal),
+            matched,
+            if candidate.saved { "yes" } else { "no" }
+        );
+    }
+}
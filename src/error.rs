@@ -10,6 +10,19 @@
 //! - **nmcli Errors**: Failures executing or parsing NetworkManager commands
 //! - **Connection Errors**: Problems establishing WiFi connections
 //! - **Network Errors**: Issues with HTTP requests to the gateway
+//! - **Command Errors**: Malformed or unknown robot control commands
+//! - **Recording Errors**: Problems capturing the camera stream to disk
+//! - **Replay Errors**: Malformed command recordings or invalid replay settings
+//! - **Lockout Errors**: Commands refused while the emergency stop is engaged
+//! - **Rate Limit Errors**: Commands dropped to protect the robot's web server
+//! - **Queue Errors**: Commands refused because too many are waiting for the robot
+//! - **Monitor Errors**: Link quality samples that cannot be taken or logged
+//! - **Output Errors**: Unknown CLI output formats
+//! - **Scan Option Errors**: Invalid scan filters or sort keys
+//! - **Discovery Errors**: Malformed robot patterns, or no robot in range
+//!
+//! Every variant has a stable [`kind`](WifiProxyError::kind) for scripts
+//! consuming the CLI's JSON errors.
 
 use thiserror::Error;
 
@@ -90,4 +103,138 @@ pub enum WifiProxyError {
     /// timeout, or invalid URL.
     #[error("Failed to fetch URL: {0}")]
     FetchFailed(String),
+
+    /// A robot control command could not be decoded or is out of range.
+    ///
+    /// Contains a description of the offending parameter. Valid commands
+    /// are described in the [`crate::robot`] module.
+    #[error("Invalid robot command: {0}")]
+    InvalidCommand(String),
+
+    /// A D-Bus call to NetworkManager failed.
+    ///
+    /// Contains the error reported by the bus. This may indicate that
+    /// NetworkManager is not running, the system bus is unavailable,
+    /// or the caller lacks the required polkit authorization.
+    #[error("D-Bus request failed: {0}")]
+    Dbus(String),
+
+    /// A request to wpa_supplicant's control interface failed.
+    ///
+    /// Contains the command that failed or the socket error. This may
+    /// indicate wpa_supplicant is not running, was started without a
+    /// `ctrl_interface`, or the user lacks access to its socket.
+    #[error("wpa_supplicant request failed: {0}")]
+    WpaSupplicant(String),
+
+    /// The requested network backend name is not recognized.
+    ///
+    /// Contains the name that was requested. See [`crate::backend::BackendKind`]
+    /// for the list of built-in backends.
+    #[error("Unknown network backend '{0}'")]
+    UnknownBackend(String),
+
+    /// Recording the camera stream could not be started or completed.
+    ///
+    /// Contains a description such as an invalid output name, a recording
+    /// already in progress, or the camera delivering no frames.
+    #[error("Recording failed: {0}")]
+    Recording(String),
+
+    /// A recorded command sequence could not be loaded or replayed.
+    ///
+    /// Contains a description such as the malformed line of a JSONL file
+    /// or an invalid playback speed.
+    #[error("Replay failed: {0}")]
+    Replay(String),
+
+    /// Robot control is locked by the emergency stop.
+    ///
+    /// Contains a description of what holds the lock.
+    #[error("Control locked: {0}")]
+    Locked(String),
+
+    /// A command was dropped because its variable exceeded the rate limit.
+    ///
+    /// Contains the `var` of the dropped command (e.g., "move").
+    #[error("Rate limit exceeded for '{0}'")]
+    RateLimited(String),
+
+    /// A command was refused because the queue toward the robot is full.
+    ///
+    /// Contains the configured queue depth. Stops are never refused.
+    #[error("Command queue full ({0} commands waiting)")]
+    QueueFull(usize),
+
+    /// Link quality samples could not be taken or written.
+    ///
+    /// Contains a description such as an unknown log format.
+    #[error("Link monitor failed: {0}")]
+    Monitor(String),
+
+    /// The requested CLI output format is not recognized.
+    ///
+    /// Contains the name that was requested. See
+    /// [`crate::output::OutputFormat`] for the supported formats.
+    #[error("Unknown output format '{0}'")]
+    UnknownOutputFormat(String),
+
+    /// A scan filter or sort option is not valid.
+    ///
+    /// Contains a description such as an unknown band or sort key.
+    #[error("Invalid scan option: {0}")]
+    InvalidScanOption(String),
+
+    /// An SSID pattern or OUI prefix used to discover the robot is malformed.
+    ///
+    /// Contains the offending pattern and why it was rejected.
+    #[error("Invalid discovery pattern: {0}")]
+    InvalidDiscoveryPattern(String),
+
+    /// No access point in range looks like a robot.
+    ///
+    /// Contains the interface that was scanned. The robot may be switched
+    /// off, out of range, or renamed beyond the configured SSID patterns.
+    #[error("No robot access point found on '{0}'")]
+    NoRobotFound(String),
+}
+
+impl WifiProxyError {
+    /// Returns a stable, machine-readable name of the error's variant.
+    ///
+    /// Messages may be reworded between releases; kinds are not.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::WifiProxyError;
+    ///
+    /// let error = WifiProxyError::InterfaceNotFound("wlan9".into());
+    /// assert_eq!(error.kind(), "interface_not_found");
+    /// ```
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WifiProxyError::NoUsbInterfaceFound => "no_usb_interface",
+            WifiProxyError::InterfaceNotFound(_) => "interface_not_found",
+            WifiProxyError::NmcliExecution(_) => "nmcli_execution",
+            WifiProxyError::NmcliParse(_) => "nmcli_parse",
+            WifiProxyError::ConnectionFailed(_) => "connection_failed",
+            WifiProxyError::NetworkNotFound(_) => "network_not_found",
+            WifiProxyError::NotWifiInterface(_) => "not_wifi_interface",
+            WifiProxyError::FetchFailed(_) => "fetch_failed",
+            WifiProxyError::InvalidCommand(_) => "invalid_command",
+            WifiProxyError::Dbus(_) => "dbus",
+            WifiProxyError::WpaSupplicant(_) => "wpa_supplicant",
+            WifiProxyError::UnknownBackend(_) => "unknown_backend",
+            WifiProxyError::Recording(_) => "recording",
+            WifiProxyError::Replay(_) => "replay",
+            WifiProxyError::Locked(_) => "locked",
+            WifiProxyError::RateLimited(_) => "rate_limited",
+            WifiProxyError::QueueFull(_) => "queue_full",
+            WifiProxyError::Monitor(_) => "monitor",
+            WifiProxyError::UnknownOutputFormat(_) => "unknown_output_format",
+            WifiProxyError::InvalidScanOption(_) => "invalid_scan_option",
+            WifiProxyError::InvalidDiscoveryPattern(_) => "invalid_discovery_pattern",
+            WifiProxyError::NoRobotFound(_) => "no_robot_found",
+        }
+    }
 }
@@ -0,0 +1,303 @@
+//! Emergency stop with control lockout.
+//!
+//! Engaging the emergency stop locks the proxy, so every further control
+//! command is rejected until the stop is explicitly released, and
+//! immediately sends both stop moves and the steady posture to the robot.
+//! The lock is taken *before* the stop commands go out, so nothing queued
+//! behind the emergency stop can set the robot moving again.
+//!
+//! Every engagement and release is timestamped, kept in memory for status
+//! queries and, if configured, appended to a log file:
+//!
+//! ```text
+//! 1760000000.123 ENGAGED by 127.0.0.1 (reason: ran into the table)
+//! 1760000042.987 RELEASED by 127.0.0.1
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use wifi_proxy::estop::{EmergencyStop, STOP_SEQUENCE};
+//! use wifi_proxy::robot::{Action, RobotCommand};
+//!
+//! // The sequence ends in the steady posture
+//! assert_eq!(STOP_SEQUENCE[2], RobotCommand::Action(Action::Steady));
+//!
+//! let estop = EmergencyStop::new(None);
+//! assert!(!estop.is_engaged());
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::error::WifiProxyError;
+use crate::mjpeg::unix_timestamp;
+use crate::robot::{Action, CommandSink, MoveDirection, RobotCommand};
+
+/// Commands sent to the robot when the emergency stop is engaged, in order.
+pub const STOP_SEQUENCE: [RobotCommand; 3] = [
+    RobotCommand::Move(MoveDirection::StopLinear),
+    RobotCommand::Move(MoveDirection::StopTurn),
+    RobotCommand::Action(Action::Steady),
+];
+
+/// Number of past events kept for status queries.
+const EVENT_HISTORY: usize = 50;
+
+/// What happened to the lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstopAction {
+    /// The emergency stop was engaged (or re-engaged).
+    Engaged,
+    /// Control was handed back.
+    Released,
+}
+
+/// One timestamped engagement or release.
+#[derive(Debug, Clone, Serialize)]
+pub struct EstopEvent {
+    /// Engaged or released.
+    pub action: EstopAction,
+
+    /// Unix seconds (with milliseconds) of the event.
+    pub time: String,
+
+    /// Who triggered it (client id or address).
+    pub by: String,
+
+    /// Free-form reason given by the operator.
+    pub reason: Option<String>,
+
+    /// Stop commands that could not be delivered to the robot.
+    pub errors: Vec<String>,
+}
+
+/// Snapshot of the emergency stop for status endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct EstopStatus {
+    /// Whether control is locked.
+    pub engaged: bool,
+
+    /// The engagement holding the lock, if any.
+    pub since: Option<EstopEvent>,
+
+    /// Recent engagements and releases, oldest first.
+    pub events: Vec<EstopEvent>,
+}
+
+/// Emergency stop lock shared by all request handlers.
+pub struct EmergencyStop {
+    /// The engagement holding the lock; `None` while released.
+    engaged: Mutex<Option<EstopEvent>>,
+
+    /// Recent events, newest last.
+    history: Mutex<VecDeque<EstopEvent>>,
+
+    /// File every event is appended to, if any.
+    log: Option<PathBuf>,
+}
+
+impl EmergencyStop {
+    /// Creates a released emergency stop.
+    ///
+    /// # Arguments
+    /// * `log` - File to append timestamped events to; `None` keeps them in memory only
+    pub fn new(log: Option<PathBuf>) -> Self {
+        Self {
+            engaged: Mutex::new(None),
+            history: Mutex::new(VecDeque::new()),
+            log,
+        }
+    }
+
+    /// Returns true while control is locked.
+    pub fn is_engaged(&self) -> bool {
+        self.engaged.lock().unwrap().is_some()
+    }
+
+    /// Fails with [`WifiProxyError::Locked`] while control is locked.
+    pub fn check(&self) -> Result<()> {
+        if self.is_engaged() {
+            return Err(WifiProxyError::Locked("emergency stop engaged".into()).into());
+        }
+        Ok(())
+    }
+
+    /// Wraps `robot` so that only stop commands get through while control is
+    /// locked.
+    ///
+    /// For senders that do not go through the per-command [`check`](Self::check),
+    /// such as a macro replay.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn demo(queue: wifi_proxy::queue::CommandQueue) {
+    /// use wifi_proxy::estop::EmergencyStop;
+    /// use wifi_proxy::robot::CommandSink;
+    ///
+    /// let estop = EmergencyStop::new(None);
+    /// let guarded = estop.interlock(&queue);
+    /// // Refused once the stop is engaged; stops are always delivered
+    /// let _ = guarded.send_query("var=move&val=1&cmd=0").await;
+    /// # }
+    /// ```
+    pub fn interlock<'a, S: CommandSink>(&'a self, robot: &'a S) -> Interlocked<'a, S> {
+        Interlocked { robot, estop: self }
+    }
+
+    /// Locks control and stops the robot.
+    ///
+    /// Engaging an already engaged stop sends the stop sequence again and
+    /// records another event; the lock stays held either way. Commands that
+    /// `robot` accepted but has not delivered yet are discarded first.
+    ///
+    /// # Arguments
+    /// * `robot` - Client or queue used to send [`STOP_SEQUENCE`]
+    /// * `by` - Who triggered the stop
+    /// * `reason` - Optional reason recorded with the event
+    ///
+    /// # Returns
+    /// The recorded event; its `errors` list any stop commands that failed.
+    pub async fn engage(&self, robot: &impl CommandSink, by: &str, reason: Option<String>) -> EstopEvent {
+        // Lock first so no command slips in behind the stop
+        let mut event = EstopEvent {
+            action: EstopAction::Engaged,
+            time: unix_timestamp(SystemTime::now()),
+            by: by.to_string(),
+            reason,
+            errors: Vec::new(),
+        };
+        self.engaged.lock().unwrap().replace(event.clone());
+
+        // Nothing accepted before the stop may be sent after it
+        robot.cancel_pending();
+
+        // Attach the delivery errors, unless released in the meantime
+        event.errors = send_stop_sequence(robot).await;
+        if let Some(current) = self.engaged.lock().unwrap().as_mut() {
+            *current = event.clone();
+        }
+        self.record(&event);
+        event
+    }
+
+    /// Hands control back.
+    ///
+    /// # Returns
+    /// - `Some(EstopEvent)` with the recorded release
+    /// - `None` if the stop was not engaged
+    pub fn release(&self, by: &str) -> Option<EstopEvent> {
+        self.engaged.lock().unwrap().take()?;
+
+        let event = EstopEvent {
+            action: EstopAction::Released,
+            time: unix_timestamp(SystemTime::now()),
+            by: by.to_string(),
+            reason: None,
+            errors: Vec::new(),
+        };
+        self.record(&event);
+        Some(event)
+    }
+
+    /// Returns the lock state and recent events.
+    pub fn status(&self) -> EstopStatus {
+        let since = self.engaged.lock().unwrap().clone();
+        EstopStatus {
+            engaged: since.is_some(),
+            since,
+            events: self.history.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+
+    /// Prints, remembers and logs an event.
+    fn record(&self, event: &EstopEvent) {
+        let line = format_event(event);
+        println!("Emergency stop: {}", line);
+
+        let mut history = self.history.lock().unwrap();
+        if history.len() == EVENT_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+        drop(history);
+
+        if let Some(path) = &self.log
+            && let Err(e) = append_line(path, &line)
+        {
+            eprintln!("Failed to write emergency stop log: {:#}", e);
+        }
+    }
+}
+
+/// Sender returned by [`EmergencyStop::interlock`].
+pub struct Interlocked<'a, S> {
+    /// Client or queue the commands go to.
+    robot: &'a S,
+
+    /// Lock checked before every command that is not a stop.
+    estop: &'a EmergencyStop,
+}
+
+impl<S: CommandSink> CommandSink for Interlocked<'_, S> {
+    async fn send_query(&self, query: &str) -> Result<String> {
+        // Stops are what the lock is for, so they always go through
+        let stopping = RobotCommand::from_query(query).is_ok_and(|command| command.is_stop());
+        if !stopping {
+            self.estop.check()?;
+        }
+        self.robot.send_query(query).await
+    }
+
+    fn cancel_pending(&self) -> usize {
+        self.robot.cancel_pending()
+    }
+}
+
+/// Sends [`STOP_SEQUENCE`] to the robot, attempting every command.
+///
+/// # Returns
+/// A description of each command that failed; empty if all were delivered.
+pub async fn send_stop_sequence(robot: &impl CommandSink) -> Vec<String> {
+    let mut errors = Vec::new();
+    for command in STOP_SEQUENCE {
+        if let Err(e) = robot.send(&command).await {
+            errors.push(format!("{}: {}", command, e));
+        }
+    }
+    errors
+}
+
+/// Formats an event as one log line.
+fn format_event(event: &EstopEvent) -> String {
+    let mut line = match event.action {
+        EstopAction::Engaged => format!("{} ENGAGED by {}", event.time, event.by),
+        EstopAction::Released => format!("{} RELEASED by {}", event.time, event.by),
+    };
+    if let Some(reason) = &event.reason {
+        line.push_str(&format!(" (reason: {})", reason));
+    }
+    for error in &event.errors {
+        line.push_str(&format!("; FAILED {}", error));
+    }
+    line
+}
+
+/// Appends a line to a log file, creating it if needed.
+fn append_line(path: &std::path::Path, line: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
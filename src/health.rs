@@ -0,0 +1,398 @@
+//! Background health probing of the robot and the link to it.
+//!
+//! When control fails, a bare `502 Bad Gateway` does not say whether the
+//! adapter lost the robot's network, the signal is too weak, or the robot's
+//! web server hung. The [`HealthProber`] checks all of these at a fixed
+//! interval and keeps the latest results, so status requests are answered
+//! from memory and never wait for (or add load on) the robot.
+//!
+//! # Probes
+//!
+//! | Probe       | How                                                          |
+//! |-------------|--------------------------------------------------------------|
+//! | `interface` | Connection status, plus the latest [`LinkMonitor`] sample    |
+//! | `control`   | `GET http://<gateway>/` through the command queue; any HTTP  |
+//! |             | answer counts as reachable                                   |
+//! | `camera`    | TCP connection to the camera's host and port                 |
+//!
+//! The control probe fetches the gateway's root page rather than `/control`,
+//! so probing never reaches the robot's command parser. It goes through the
+//! [`CommandQueue`]'s idle lane, so it waits until no command is pending and
+//! never puts a second request in flight; when commands keep the queue busy
+//! for a whole round, the previous result is kept (the robot is evidently
+//! answering them).
+//!
+//! The camera probe only opens a connection: the ESP32-CAM serves one stream
+//! at a time, and a second stream request would disturb the viewers.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use wifi_proxy::health::HealthProber;
+//! use wifi_proxy::queue::CommandQueue;
+//!
+//! let queue = CommandQueue::new(reqwest::Client::new(), "192.168.4.1", Duration::from_secs(1), 16);
+//! let prober = HealthProber::new(
+//!     Arc::new(queue),
+//!     None,
+//!     "192.168.4.1",
+//!     "http://192.168.4.1:81/stream",
+//!     Duration::from_secs(2),
+//!     Duration::from_secs(1),
+//! );
+//!
+//! // Nothing is known until the first probe round completes
+//! let report = prober.report();
+//! assert!(report.checked.is_none());
+//! assert_eq!(report.control.reachable, None);
+//! assert!(report.problems().is_empty());
+//! ```
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
+
+use crate::connection::{self, ConnectionStatus, LinkQuality};
+use crate::monitor::{LinkMonitor, TREND_WINDOW};
+use crate::mjpeg::unix_timestamp;
+use crate::queue::{CommandQueue, DeliveryError};
+
+/// Signal strength (percent) below which the link is reported as weak.
+pub const WEAK_SIGNAL: u8 = 30;
+
+/// Signal loss (percentage points within [`TREND_WINDOW`]) reported as a
+/// degrading link.
+pub const FALLING_SIGNAL: i32 = 15;
+
+/// State of the USB adapter at the last probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct InterfaceHealth {
+    /// Interface name (e.g., "wlan1").
+    pub name: String,
+
+    /// Connection status, if it could be read.
+    pub status: Option<ConnectionStatus>,
+
+    /// Radio state of the current link, from the link monitor.
+    pub link: Option<LinkQuality>,
+
+    /// Change of the signal over the last [`TREND_WINDOW`], in percentage points.
+    pub signal_trend: Option<i32>,
+
+    /// Why the status could not be read.
+    pub error: Option<String>,
+}
+
+/// Reachability of one endpoint on the robot.
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealth {
+    /// Address probed (gateway or camera URL).
+    pub target: String,
+
+    /// Whether the last probe succeeded; `None` until it was first probed.
+    pub reachable: Option<bool>,
+
+    /// Round-trip time of the last successful probe, in milliseconds.
+    pub latency_ms: Option<f64>,
+
+    /// Unix seconds (with milliseconds) of the last successful probe.
+    pub last_ok: Option<String>,
+
+    /// Why the last probe failed.
+    pub error: Option<String>,
+}
+
+impl EndpointHealth {
+    /// Creates an entry for a target that was not probed yet.
+    fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+            reachable: None,
+            latency_ms: None,
+            last_ok: None,
+            error: None,
+        }
+    }
+
+    /// Records the outcome of one probe.
+    fn update(&mut self, outcome: Result<Duration, String>) {
+        match outcome {
+            Ok(latency) => {
+                self.reachable = Some(true);
+                self.latency_ms = Some(latency.as_secs_f64() * 1000.0);
+                self.last_ok = Some(unix_timestamp(SystemTime::now()));
+                self.error = None;
+            }
+            Err(e) => {
+                // The last measured latency is kept for comparison
+                self.reachable = Some(false);
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Latest results of every probe.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Unix seconds (with milliseconds) of the last completed probe round;
+    /// `None` before the first one.
+    pub checked: Option<String>,
+
+    /// USB adapter state; `None` when serving without an interface.
+    pub interface: Option<InterfaceHealth>,
+
+    /// The gateway's web server, which also serves `/control`.
+    pub control: EndpointHealth,
+
+    /// The camera stream's port.
+    pub camera: EndpointHealth,
+}
+
+impl HealthReport {
+    /// Lists what is wrong, in a form suitable for display.
+    ///
+    /// Endpoints not probed yet are not reported.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::health::{EndpointHealth, HealthReport};
+    ///
+    /// let endpoint = |reachable, error: Option<&str>| EndpointHealth {
+    ///     target: "192.168.4.1".into(),
+    ///     reachable,
+    ///     latency_ms: None,
+    ///     last_ok: None,
+    ///     error: error.map(String::from),
+    /// };
+    /// let report = HealthReport {
+    ///     checked: None,
+    ///     interface: None,
+    ///     control: endpoint(Some(false), Some("connection refused")),
+    ///     camera: endpoint(Some(true), None),
+    /// };
+    /// assert_eq!(report.problems(), ["Gateway 192.168.4.1 unreachable: connection refused"]);
+    /// ```
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(interface) = &self.interface {
+            match (&interface.error, &interface.status) {
+                (Some(e), _) => problems.push(format!("Interface {}: {}", interface.name, e)),
+                (None, Some(status)) if status.connection.is_none() => {
+                    problems.push(format!("Interface {} is {}", interface.name, status.state));
+                }
+                _ => {}
+            }
+            let signal = interface.link.as_ref().and_then(|l| l.signal);
+            if let Some(signal) = signal.filter(|s| *s < WEAK_SIGNAL) {
+                problems.push(format!("Weak signal ({}%)", signal));
+            }
+            if let Some(trend) = interface.signal_trend.filter(|t| *t <= -FALLING_SIGNAL) {
+                let window = TREND_WINDOW.as_secs();
+                problems.push(format!("Signal falling ({}% in {}s)", trend, window));
+            }
+        }
+
+        for (name, endpoint) in [("Gateway", &self.control), ("Camera", &self.camera)] {
+            if endpoint.reachable == Some(false) {
+                let error = endpoint.error.as_deref().unwrap_or("no answer");
+                problems.push(format!("{} {} unreachable: {}", name, endpoint.target, error));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Addresses the probes are aimed at.
+struct Targets {
+    /// Gateway address, optionally with a port.
+    gateway: String,
+
+    /// Camera stream URL.
+    stream_url: String,
+}
+
+/// Periodically probes the robot and keeps the latest results.
+pub struct HealthProber {
+    /// Queue the control probe waits its turn in.
+    queue: Arc<CommandQueue>,
+
+    /// Samples the USB interface's link, if there is one.
+    monitor: Option<Arc<LinkMonitor>>,
+
+    /// Time between two probe rounds.
+    interval: Duration,
+
+    /// Longest a single probe may take.
+    timeout: Duration,
+
+    /// Current gateway and camera.
+    targets: Mutex<Targets>,
+
+    /// Latest results.
+    report: Mutex<HealthReport>,
+
+    /// Wakes the prober early when the targets change.
+    wake: Notify,
+}
+
+impl HealthProber {
+    /// Creates a prober. Nothing is probed until [`run`](Self::run) is started.
+    ///
+    /// # Arguments
+    /// * `queue` - Command queue to the robot, used for the control probe
+    /// * `monitor` - Link monitor of the USB interface; `None` to skip the
+    ///   interface probe
+    /// * `gateway` - Gateway address, optionally with a port
+    /// * `stream_url` - Camera stream URL
+    /// * `interval` - Time between two probe rounds
+    /// * `timeout` - Longest a single probe may take
+    pub fn new(
+        queue: Arc<CommandQueue>,
+        monitor: Option<Arc<LinkMonitor>>,
+        gateway: &str,
+        stream_url: &str,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            queue,
+            monitor,
+            interval,
+            timeout,
+            targets: Mutex::new(Targets {
+                gateway: gateway.to_string(),
+                stream_url: stream_url.to_string(),
+            }),
+            report: Mutex::new(HealthReport {
+                checked: None,
+                interface: None,
+                control: EndpointHealth::new(gateway),
+                camera: EndpointHealth::new(stream_url),
+            }),
+            wake: Notify::new(),
+        }
+    }
+
+    /// Returns the latest results.
+    pub fn report(&self) -> HealthReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Aims the probes at another gateway and camera.
+    ///
+    /// Results for the old targets are discarded and a new probe round
+    /// starts right away.
+    pub fn set_targets(&self, gateway: &str, stream_url: &str) {
+        *self.targets.lock().unwrap() = Targets {
+            gateway: gateway.to_string(),
+            stream_url: stream_url.to_string(),
+        };
+        {
+            let mut report = self.report.lock().unwrap();
+            report.control = EndpointHealth::new(gateway);
+            report.camera = EndpointHealth::new(stream_url);
+        }
+        self.wake.notify_one();
+    }
+
+    /// Probes every `interval` until the task is dropped.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            self.probe().await;
+            tokio::select! {
+                _ = tokio::time::sleep(self.interval) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    /// Runs one round of all probes concurrently and stores the results.
+    async fn probe(&self) {
+        let (gateway, stream_url) = {
+            let targets = self.targets.lock().unwrap();
+            (targets.gateway.clone(), targets.stream_url.clone())
+        };
+
+        let (interface, control, camera) = tokio::join!(
+            self.probe_interface(),
+            self.probe_control(),
+            self.probe_camera(&stream_url),
+        );
+
+        // Results for targets replaced in the meantime are stale
+        let mut report = self.report.lock().unwrap();
+        report.checked = Some(unix_timestamp(SystemTime::now()));
+        report.interface = interface;
+        if report.control.target == gateway
+            && let Some(control) = control
+        {
+            report.control.update(control);
+        }
+        if report.camera.target == stream_url {
+            report.camera.update(camera);
+        }
+    }
+
+    /// Reads the adapter's connection status; the radio state comes from
+    /// the link monitor, which samples it anyway.
+    async fn probe_interface(&self) -> Option<InterfaceHealth> {
+        let monitor = self.monitor.as_ref()?;
+        let name = monitor.interface().to_string();
+
+        // Backends block (nmcli spawns a process), so keep them off the runtime
+        let query = name.clone();
+        let (status, error) = match tokio::task::spawn_blocking(move || connection::status(&query)).await {
+            Ok(Ok(status)) => (Some(status), None),
+            Ok(Err(e)) => (None, Some(e.to_string())),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        Some(InterfaceHealth {
+            name,
+            status,
+            link: monitor.latest().filter(|s| s.connected).map(|s| s.quality),
+            signal_trend: monitor.trend(),
+            error,
+        })
+    }
+
+    /// Fetches the gateway's root page through the command queue; any HTTP
+    /// answer counts.
+    ///
+    /// # Returns
+    /// - `Some(Ok(latency))` with the time the robot took to answer, not
+    ///   counting the wait for the queue
+    /// - `Some(Err(reason))` if the robot could not be reached
+    /// - `None` if the queue stayed busy with commands for the whole round
+    async fn probe_control(&self) -> Option<Result<Duration, String>> {
+        let started = Instant::now();
+        let ticket = self.queue.probe();
+        let outcome = tokio::time::timeout(self.interval + self.timeout, ticket.delivered()).await.ok()?;
+
+        Some(match outcome {
+            Ok(delivery) => Ok(started.elapsed().saturating_sub(delivery.waited)),
+            Err(DeliveryError::Upstream { message, .. }) => Err(message),
+            Err(e) => Err(e.to_string()),
+        })
+    }
+
+    /// Opens (and closes) a TCP connection to the camera's host and port.
+    async fn probe_camera(&self, stream_url: &str) -> Result<Duration, String> {
+        let url = reqwest::Url::parse(stream_url).map_err(|e| e.to_string())?;
+        let host = url.host_str().ok_or("stream URL has no host")?.to_string();
+        let port = url.port_or_known_default().ok_or("stream URL has no port")?;
+
+        let started = Instant::now();
+        match tokio::time::timeout(self.timeout, tokio::net::TcpStream::connect((host, port))).await {
+            Ok(Ok(_)) => Ok(started.elapsed()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("no connection within {}ms", self.timeout.as_millis())),
+        }
+    }
+}
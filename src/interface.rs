@@ -26,23 +26,24 @@
 //! println!("Using USB interface: {}", usb_iface.name);
 //! ```
 
-use anyhow::{Context, Result};
+use anyhow::Result;
+use serde::Serialize;
 use std::fs;
 use std::path::Path;
-use std::process::Command;
 
+use crate::backend::backend;
 use crate::error::WifiProxyError;
 
 /// Represents a WiFi network interface on the system.
 ///
 /// Contains information about the interface's name, current state,
 /// and whether it's connected via USB (as opposed to built-in/PCIe).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct WifiInterface {
     /// The interface name as shown by `ip link` (e.g., "wlan0", "wlan1").
     pub name: String,
 
-    /// The current state of the interface as reported by NetworkManager.
+    /// The current state of the interface as reported by the network backend.
     /// Examples: "connected", "disconnected", "unavailable".
     pub state: String,
 
@@ -54,21 +55,13 @@ pub struct WifiInterface {
 
 /// Lists all WiFi interfaces available on the system.
 ///
-/// Queries NetworkManager using nmcli to get a list of all network devices,
-/// then filters to only WiFi interfaces. For each interface, it also checks
+/// Queries the selected network backend for all network devices, keeping
+/// only WiFi interfaces. For each interface, the backend also reports
 /// whether it's a USB device.
 ///
 /// # Returns
 /// - `Ok(Vec<WifiInterface>)` containing all discovered WiFi interfaces
-/// - `Err(WifiProxyError::NmcliExecution)` if nmcli command fails
-///
-/// # Command Executed
-/// ```bash
-/// nmcli -t -f DEVICE,TYPE,STATE device
-/// ```
-///
-/// The `-t` flag produces terse output, and `-f` specifies the fields to display.
-/// Output format is `device:type:state` per line.
+/// - `Err` if the backend cannot enumerate devices
 ///
 /// # Example
 /// ```no_run
@@ -80,42 +73,7 @@ pub struct WifiInterface {
 /// }
 /// ```
 pub fn list_wifi_interfaces() -> Result<Vec<WifiInterface>> {
-    // Execute nmcli to list all network devices in terse format
-    let output = Command::new("nmcli")
-        .args(["-t", "-f", "DEVICE,TYPE,STATE", "device"])
-        .output()
-        .context("Failed to execute nmcli")?;
-
-    // Check for command execution errors
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
-    }
-
-    // Parse the output to extract WiFi interfaces
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut interfaces = Vec::new();
-
-    // Process each line (format: DEVICE:TYPE:STATE)
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split(':').collect();
-
-        // Only process lines with enough parts and type == "wifi"
-        if parts.len() >= 3 && parts[1] == "wifi" {
-            let name = parts[0].to_string();
-
-            // Check if this interface is USB-based
-            let is_usb = is_usb_interface(&name);
-
-            interfaces.push(WifiInterface {
-                name,
-                state: parts[2].to_string(),
-                is_usb,
-            });
-        }
-    }
-
-    Ok(interfaces)
+    backend().list_interfaces()
 }
 
 /// Checks if a network interface is USB-based by examining the Linux sysfs.
@@ -136,7 +94,7 @@ pub fn list_wifi_interfaces() -> Result<Vec<WifiInterface>> {
 /// On Linux, network interfaces have entries in `/sys/class/net/<interface>/`.
 /// The `device` symlink points to the actual hardware device in the sysfs tree.
 /// USB devices have paths containing "usb" (e.g., `/sys/devices/pci0000:00/.../usb1/...`).
-fn is_usb_interface(interface_name: &str) -> bool {
+pub(crate) fn is_usb_interface(interface_name: &str) -> bool {
     // Construct the path to the device symlink in sysfs
     let device_path = format!("/sys/class/net/{}/device", interface_name);
     let path = Path::new(&device_path);
@@ -148,20 +106,20 @@ fn is_usb_interface(interface_name: &str) -> bool {
 
     // Method 1: Resolve the symlink and check if path contains "usb"
     // This is the most reliable method for detecting USB devices
-    if let Ok(resolved) = fs::read_link(path) {
-        if let Some(resolved_str) = resolved.to_str() {
-            // USB devices have "usb" somewhere in their sysfs path
-            return resolved_str.contains("usb");
-        }
+    if let Ok(resolved) = fs::read_link(path)
+        && let Some(resolved_str) = resolved.to_str()
+    {
+        // USB devices have "usb" somewhere in their sysfs path
+        return resolved_str.contains("usb");
     }
 
     // Method 2: Check the uevent file for USB-related entries
     // This is a fallback in case symlink resolution fails
     let uevent_path = format!("{}/uevent", device_path);
-    if let Ok(content) = fs::read_to_string(&uevent_path) {
-        if content.contains("usb") {
-            return true;
-        }
+    if let Ok(content) = fs::read_to_string(&uevent_path)
+        && content.contains("usb")
+    {
+        return true;
     }
 
     // Default to false if neither method found USB indicators
@@ -6,12 +6,27 @@
 //!
 //! # Modules
 //!
+//! - [`backend`] - Pluggable network backends (nmcli, in-memory)
 //! - [`config`] - Configuration management for saved networks and settings
 //! - [`connection`] - WiFi connection management (connect, disconnect, status)
+//! - [`discover`] - Finding the robot's access point by SSID and vendor
 //! - [`error`] - Custom error types for the library
+//! - [`estop`] - Emergency stop locking out robot control
+//! - [`health`] - Background probing of the link, gateway and camera
 //! - [`interface`] - WiFi interface discovery and management
+//! - [`mjpeg`] - MJPEG stream parsing and fan-out to multiple viewers
+//! - [`mock_robot`] - Emulated robot gateway for development without hardware
+//! - [`monitor`] - Link quality sampling of the USB adapter
+//! - [`output`] - Table and JSON output of the command-line interface
+//! - [`queue`] - Ordered, serialized delivery of commands to the robot
+//! - [`recorder`] - Recording the camera stream to disk
+//! - [`replay`] - Recording and timed replay of robot commands
+//! - [`robot`] - Typed robot commands and async control client
 //! - [`scan`] - WiFi network scanning functionality
 //! - [`server`] - HTTP proxy server for robot control interface
+//! - [`supervisor`] - Link supervision and automatic reconnection while serving
+//! - [`throttle`] - Rate limiting and coalescing of commands to the robot
+//! - [`watchdog`] - Dead-man's-switch stopping movement of silent clients
 //!
 //! # Example Usage
 //!
@@ -29,6 +44,10 @@
 //! println!("Gateway: {:?}", conn_status.gateway);
 //! ```
 
+/// Backend module defining the `NetworkBackend` trait and its implementations.
+/// Connection, scan and interface functions dispatch through the selected backend.
+pub mod backend;
+
 /// Configuration module for managing saved networks and application settings.
 /// Handles reading/writing TOML config files and credential storage.
 pub mod config;
@@ -37,14 +56,58 @@ pub mod config;
 /// Provides functions to connect, disconnect, check status, and fetch gateway content.
 pub mod connection;
 
+/// Discover module recognizing robot access points by SSID pattern and Espressif OUI.
+/// Ranks them so a fresh machine can join the robot without knowing its SSID.
+pub mod discover;
+
 /// Error module defining custom error types for the library.
 /// Uses `thiserror` for ergonomic error handling.
 pub mod error;
 
+/// Emergency stop module halting the robot and locking out control commands.
+/// Logs every engagement and release with a timestamp.
+pub mod estop;
+
+/// Health module probing the USB link, the gateway and the camera in the background.
+/// Keeps the latest results so status requests never wait on the robot.
+pub mod health;
+
 /// Interface module for WiFi adapter discovery and management.
 /// Handles listing interfaces, detecting USB adapters, and interface resolution.
 pub mod interface;
 
+/// MJPEG module parsing camera streams and fanning frames out to viewers.
+/// Keeps a single upstream connection to the robot however many viewers there are.
+pub mod mjpeg;
+
+/// Mock robot module emulating the ESP32 gateway's control and stream endpoints.
+/// Used to develop the web interface and scripts without a physical robot.
+pub mod mock_robot;
+
+/// Monitor module sampling signal, bitrates and channel of the adapter's link.
+/// Keeps a short history to show the link degrading before control is lost.
+pub mod monitor;
+
+/// Output module selecting between human-readable tables and JSON for the CLI.
+/// Reports failures as structured errors with a stable kind.
+pub mod output;
+
+/// Queue module serializing command delivery to the robot in arrival order.
+/// Lets stops overtake waiting motion and bounds how many commands may wait.
+pub mod queue;
+
+/// Recorder module capturing the camera stream to AVI files or JPEG directories.
+/// Writes a CSV index of frame times beside every recording.
+pub mod recorder;
+
+/// Replay module recording forwarded robot commands to JSONL files.
+/// Plays recordings back with their original timing and always ends with a stop.
+pub mod replay;
+
+/// Robot module with the typed `/control` command model.
+/// Encodes/decodes commands and sends them to the gateway asynchronously.
+pub mod robot;
+
 /// Scan module for discovering available WiFi networks.
 /// Triggers rescans and parses network information from nmcli output.
 pub mod scan;
@@ -53,8 +116,23 @@ pub mod scan;
 /// Uses Axum to serve a web interface that proxies requests to the ESP32 gateway.
 pub mod server;
 
+/// Supervisor module watching the WiFi link to the robot while serving.
+/// Reconnects with saved credentials and exponential backoff, stopping the robot around outages.
+pub mod supervisor;
+
+/// Throttle module protecting the gateway from command floods.
+/// Coalesces repeated moves and rate-limits each variable, never holding back stops.
+pub mod throttle;
+
+/// Watchdog module tracking held movements per client.
+/// Sends the matching stop commands when a client's heartbeat lapses.
+pub mod watchdog;
+
+// Re-export the backend trait and selection helpers
+pub use backend::{select_backend, BackendKind, NetworkBackend};
+
 // Re-export commonly used items from connection module for convenient access
-pub use connection::{connect, disconnect, fetch_gateway, status, ConnectionStatus};
+pub use connection::{connect, connect_hidden, disconnect, fetch_gateway, status, ConnectionStatus};
 
 // Re-export the main error type for library users
 pub use error::WifiProxyError;
@@ -64,5 +142,8 @@ pub use interface::{
     find_usb_wifi_interface, get_interface, list_wifi_interfaces, resolve_interface, WifiInterface,
 };
 
+// Re-export the robot command model and client
+pub use robot::{RobotClient, RobotCommand};
+
 // Re-export scan-related items for network discovery
 pub use scan::{scan_networks, Network};
@@ -5,13 +5,31 @@
 //! network connection. It supports scanning for networks, connecting, and proxying
 //! requests to the robot's web interface.
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use regex::Regex;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 use wifi_proxy::{
+    backend::{self, BackendKind},
     config::{self, Config, NetworkConfig},
-    connection, interface, scan, server,
+    connection,
+    discover::{self, DiscoveryReport, RobotMatcher},
+    estop, interface, mjpeg, mock_robot,
+    monitor::{self, LinkMonitor, SampleFormat, SampleWriter},
+    output::{self, OutputFormat},
+    recorder::{self, RecordFormat},
+    replay,
+    scan::{self, Band, ScanFilter, SortKey},
+    server,
+    supervisor::ReconnectConfig,
+    RobotClient, WifiProxyError,
 };
 
 /// Command-line interface structure for the wifi-proxy application.
@@ -21,6 +39,16 @@ use wifi_proxy::{
 #[command(about = "Connect a secondary USB WiFi adapter to a different access point")]
 #[command(version)]
 struct Cli {
+    /// Network backend used to manage the WiFi adapter.
+    /// One of "nmcli" (default), "dbus", "wpa_supplicant" or "memory" (simulated adapter).
+    #[arg(long, global = true, default_value = "nmcli")]
+    backend: BackendKind,
+
+    /// Output format: "table" (default) for people, "json" for scripts.
+    /// Accepted before or after the command, e.g. `wifi-proxy scan --output json`.
+    #[arg(long, global = true, default_value = "table")]
+    output: OutputFormat,
+
     /// The subcommand to execute
     #[command(subcommand)]
     command: Commands,
@@ -35,12 +63,87 @@ enum Commands {
     ListInterfaces,
 
     /// Scan for available WiFi networks using the specified interface.
-    /// Shows SSID, signal strength, and security type for each network found.
+    /// Shows one row per access point: SSID, BSSID, channel, band, signal, rate and security.
     Scan {
         /// Network interface to use for scanning.
         /// If not specified, auto-detects the first USB WiFi interface.
         #[arg(short, long)]
         interface: Option<String>,
+
+        /// Hide access points with a weaker signal (percent).
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_signal: Option<u8>,
+
+        /// Only show access points on this band ("2.4", "5" or "6").
+        #[arg(long)]
+        band: Option<Band>,
+
+        /// Only show SSIDs matching this regular expression, e.g. "^WAVESHARE".
+        #[arg(long)]
+        ssid_regex: Option<Regex>,
+
+        /// Sort order: "signal" (default), "ssid" or "channel".
+        #[arg(long, default_value = "signal")]
+        sort: SortKey,
+
+        /// Show one row per SSID, with the number of access points and the strongest one.
+        #[arg(long)]
+        group: bool,
+
+        /// Keep scanning and print access points appearing, disappearing and
+        /// changing signal, until interrupted with Ctrl+C.
+        #[arg(short, long, conflicts_with = "group")]
+        watch: bool,
+
+        /// Milliseconds between the starts of two scans in watch mode.
+        /// Defaults to 5000 if not specified.
+        #[arg(long, default_value = "5000", requires = "watch", value_parser = clap::value_parser!(u64).range(1000..))]
+        interval_ms: u64,
+
+        /// Smallest signal change (percentage points) reported in watch mode.
+        #[arg(long, default_value = "5", requires = "watch", value_parser = clap::value_parser!(u8).range(1..=100))]
+        min_change: u8,
+
+        /// File to log every change to, as JSON lines (watch mode).
+        #[arg(short, long, requires = "watch")]
+        log: Option<PathBuf>,
+
+        /// Number of scans to run before exiting (watch mode).
+        /// If not specified, watches until interrupted with Ctrl+C.
+        #[arg(short, long, requires = "watch")]
+        count: Option<u64>,
+    },
+
+    /// Find the robot's access point by SSID pattern and Espressif BSSID prefix.
+    /// Marks access points with saved credentials and can connect to the best one.
+    Discover {
+        /// Network interface to scan with.
+        /// If not specified, auto-detects the first USB WiFi interface.
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Additional SSID pattern (regular expression), e.g. "^RoboDog".
+        /// May be repeated; adds to the patterns in the config file.
+        #[arg(long = "ssid-pattern", value_name = "REGEX")]
+        ssid_patterns: Vec<String>,
+
+        /// Additional BSSID prefix, e.g. "24:0A:C4".
+        /// May be repeated; adds to the prefixes in the config file.
+        #[arg(long = "oui", value_name = "PREFIX")]
+        oui_prefixes: Vec<String>,
+
+        /// Connect to the best candidate after listing them.
+        #[arg(short, long)]
+        connect: bool,
+
+        /// Password for the best candidate.
+        /// If not provided, attempts to use a previously saved password from config.
+        #[arg(short, long, requires = "connect")]
+        password: Option<String>,
+
+        /// Flag to save the credentials to the config file after connecting.
+        #[arg(short, long, requires = "connect")]
+        save: bool,
     },
 
     /// Connect to a WiFi network using the specified credentials.
@@ -64,6 +167,11 @@ enum Commands {
         /// Enables quick reconnection without re-entering the password.
         #[arg(short, long)]
         save: bool,
+
+        /// The network does not broadcast its SSID: create a profile that probes for it.
+        /// Saved with `--save`; networks saved as hidden are always joined this way.
+        #[arg(long)]
+        hidden: bool,
     },
 
     /// Display the current connection status for an interface.
@@ -90,7 +198,7 @@ enum Commands {
         /// File path where the fetched HTML content will be saved.
         /// Defaults to "gateway.html" in the current directory.
         #[arg(short, long, default_value = "gateway.html")]
-        output: PathBuf,
+        out: PathBuf,
 
         /// Network interface to use for determining the gateway.
         /// If not specified, auto-detects the first USB WiFi interface.
@@ -115,6 +223,243 @@ enum Commands {
         /// If not specified, auto-detects the first USB WiFi interface.
         #[arg(short, long)]
         interface: Option<String>,
+
+        /// Gateway address to proxy to instead of the interface's gateway.
+        /// May include a port, e.g. "127.0.0.1:8000" for `mock-robot`.
+        #[arg(short, long)]
+        gateway: Option<String>,
+
+        /// Port of the robot's camera stream on the gateway host.
+        /// Defaults to 81, the ESP32-CAM stream port.
+        #[arg(long, default_value = "81")]
+        stream_port: u16,
+
+        /// Milliseconds allowed to open a connection to the gateway.
+        /// Defaults to 2000 if not specified.
+        #[arg(long, default_value = "2000", value_parser = clap::value_parser!(u64).range(1..))]
+        connect_timeout_ms: u64,
+
+        /// Milliseconds allowed for a control request to complete.
+        /// Timed-out requests are answered with 504 Gateway Timeout.
+        #[arg(long, default_value = "5000", value_parser = clap::value_parser!(u64).range(1..))]
+        request_timeout_ms: u64,
+
+        /// Directory where recordings started from the web API are saved.
+        /// Defaults to "recordings" in the current directory.
+        #[arg(long, default_value = "recordings")]
+        recordings_dir: PathBuf,
+
+        /// Record every forwarded command to this JSONL file for later replay.
+        /// Recordings can also be started and stopped through `/macro`.
+        #[arg(long)]
+        record_commands: Option<PathBuf>,
+
+        /// Milliseconds a client may go without a heartbeat while holding a
+        /// movement before the robot is stopped for it. 0 disables the watchdog.
+        #[arg(long, default_value = "1500")]
+        watchdog_ms: u64,
+
+        /// File that emergency stop engagements and releases are appended to.
+        /// Without it, events are only kept in memory for `/api/estop`.
+        #[arg(long)]
+        estop_log: Option<PathBuf>,
+
+        /// Maximum commands per second forwarded for each control variable.
+        /// Stops are never limited. 0 disables rate limiting.
+        #[arg(long, default_value = "10", value_parser = parse_max_rate)]
+        max_rate: f64,
+
+        /// Milliseconds during which a repeated identical move is coalesced
+        /// instead of forwarded. 0 forwards every repeat.
+        #[arg(long, default_value = "1000")]
+        coalesce_ms: u64,
+
+        /// Maximum commands waiting to be sent to the robot; further commands
+        /// are refused until the robot catches up. Stops are never refused.
+        #[arg(long, default_value = "16", value_parser = clap::value_parser!(u16).range(1..))]
+        queue_depth: u16,
+
+        /// Do not reconnect when the adapter drops off the robot's network.
+        #[arg(long)]
+        no_reconnect: bool,
+
+        /// Reconnection attempts before giving up after the link drops.
+        /// 0 keeps trying forever.
+        #[arg(long, default_value = "0")]
+        reconnect_attempts: u32,
+
+        /// Longest wait in milliseconds between two reconnection attempts;
+        /// the wait doubles from one second up to this limit.
+        #[arg(long, default_value = "30000")]
+        reconnect_max_ms: u64,
+
+        /// Milliseconds between two health probes of the link, gateway and
+        /// camera, and between two link quality samples, as reported by
+        /// `/api/status` and `/api/link/quality`.
+        #[arg(long, default_value = "2000", value_parser = clap::value_parser!(u64).range(100..))]
+        status_interval_ms: u64,
+    },
+
+    /// Watch the USB adapter's link quality (signal, RSSI, bitrates, channel).
+    /// Prints one row per sample until interrupted with Ctrl+C.
+    Monitor {
+        /// Network interface to monitor.
+        /// If not specified, auto-detects the first USB WiFi interface.
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Milliseconds between two samples.
+        /// Defaults to 1000 if not specified.
+        #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(100..))]
+        interval_ms: u64,
+
+        /// File to log every sample to. Names ending in ".csv" are written
+        /// as CSV; anything else as JSON lines.
+        #[arg(short, long)]
+        log: Option<PathBuf>,
+
+        /// Log format ("csv" or "jsonl"), overriding the log file name.
+        #[arg(long, requires = "log")]
+        log_format: Option<SampleFormat>,
+
+        /// Number of samples to take before exiting.
+        /// If not specified, monitors until interrupted with Ctrl+C.
+        #[arg(short, long)]
+        count: Option<u64>,
+    },
+
+    /// Emergency stop: halt the robot and lock the running proxy's controls.
+    /// Falls back to stopping the robot directly if the server is not running.
+    Estop {
+        /// Release the lock instead of engaging it.
+        #[arg(long)]
+        release: bool,
+
+        /// Reason recorded in the emergency stop log.
+        #[arg(short, long)]
+        reason: Option<String>,
+
+        /// Base URL of the running proxy server.
+        #[arg(long, default_value = "http://localhost:8080")]
+        server: String,
+
+        /// Network interface used to find the robot if the server is down.
+        /// If not specified, auto-detects the first USB WiFi interface.
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Gateway address to stop directly if the server is down.
+        /// May include a port, e.g. "127.0.0.1:8000" for `mock-robot`.
+        #[arg(short, long)]
+        gateway: Option<String>,
+
+        /// Milliseconds to wait for the server to confirm the stop. The server
+        /// answers once all three stop commands were sent, each allowed its
+        /// `--request-timeout-ms` (5000 by default).
+        #[arg(long, default_value = "20000", value_parser = clap::value_parser!(u64).range(1000..))]
+        timeout_ms: u64,
+    },
+
+    /// Save the most recent frame of the robot's camera as a JPEG file.
+    /// Useful for bug reports and test evidence.
+    Snapshot {
+        /// File path where the JPEG will be saved.
+        /// Defaults to "snapshot.jpg" in the current directory.
+        #[arg(short, long, default_value = "snapshot.jpg")]
+        out: PathBuf,
+
+        /// Network interface used to determine the gateway.
+        /// If not specified, auto-detects the first USB WiFi interface.
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Gateway address to use instead of the interface's gateway.
+        /// May include a port, e.g. "127.0.0.1:8000" for `mock-robot`.
+        #[arg(short, long)]
+        gateway: Option<String>,
+
+        /// Port of the robot's camera stream on the gateway host.
+        /// Defaults to 81, the ESP32-CAM stream port.
+        #[arg(long, default_value = "81")]
+        stream_port: u16,
+
+        /// Milliseconds to wait for a frame.
+        /// Defaults to 5000 if not specified.
+        #[arg(long, default_value = "5000")]
+        timeout_ms: u64,
+    },
+
+    /// Record the robot's camera stream to an AVI file or a directory of JPEGs.
+    /// A CSV index of frame times is written alongside the recording.
+    Record {
+        /// Output path. Names ending in ".avi" record MJPEG-in-AVI;
+        /// anything else is created as a directory of timestamped JPEGs.
+        #[arg(short, long, default_value = "recording.avi")]
+        out: PathBuf,
+
+        /// Recording length in seconds (up to one day).
+        /// If not specified, records until interrupted with Ctrl+C.
+        #[arg(short, long, value_parser = parse_record_duration)]
+        duration: Option<Duration>,
+
+        /// Container format ("avi" or "jpeg"), overriding the output name.
+        #[arg(short, long)]
+        format: Option<RecordFormat>,
+
+        /// Network interface used to determine the gateway.
+        /// If not specified, auto-detects the first USB WiFi interface.
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Gateway address to use instead of the interface's gateway.
+        /// May include a port, e.g. "127.0.0.1:8000" for `mock-robot`.
+        #[arg(short, long)]
+        gateway: Option<String>,
+
+        /// Port of the robot's camera stream on the gateway host.
+        /// Defaults to 81, the ESP32-CAM stream port.
+        #[arg(long, default_value = "81")]
+        stream_port: u16,
+    },
+
+    /// Replay a JSONL command recording against the robot with its original timing.
+    /// Ctrl+C aborts the replay; the robot is always sent stop commands at the end.
+    Replay {
+        /// JSONL file written by `serve --record-commands` or `/macro/record/start`.
+        file: PathBuf,
+
+        /// Playback speed factor, e.g. 2.0 for twice as fast (0.01-100).
+        /// Defaults to 1.0 (original timing).
+        #[arg(short, long, default_value = "1.0", value_parser = parse_replay_speed)]
+        speed: f64,
+
+        /// Network interface used to determine the gateway.
+        /// If not specified, auto-detects the first USB WiFi interface.
+        #[arg(short, long)]
+        interface: Option<String>,
+
+        /// Gateway address to use instead of the interface's gateway.
+        /// May include a port, e.g. "127.0.0.1:8000" for `mock-robot`.
+        #[arg(short, long)]
+        gateway: Option<String>,
+    },
+
+    /// Run an emulated robot gateway for development without hardware.
+    /// Serves `/control` and an MJPEG `/stream` of synthetic frames.
+    MockRobot {
+        /// TCP port for the `/control` endpoint.
+        /// Defaults to 8000 if not specified.
+        #[arg(short, long, default_value = "8000")]
+        port: u16,
+
+        /// TCP port for the `/stream` endpoint.
+        /// Defaults to 8001 if not specified.
+        #[arg(long, default_value = "8001")]
+        stream_port: u16,
+
+        /// Frames per second of the synthetic camera stream (1-120).
+        #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..=120))]
+        fps: u32,
     },
 
     /// Save network credentials to the configuration file without connecting.
@@ -131,6 +476,10 @@ enum Commands {
         /// Optional - if not set, the system will auto-detect.
         #[arg(short, long)]
         interface: Option<String>,
+
+        /// The network does not broadcast its SSID; connections probe for it.
+        #[arg(long)]
+        hidden: bool,
     },
 
     /// Display the current saved configuration.
@@ -147,49 +496,238 @@ enum Commands {
 /// - `Ok(())` if the command completes successfully
 /// - `Err` with an error message if any operation fails
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     // Parse command-line arguments into the Cli struct
     let cli = Cli::parse();
+    let format = cli.output;
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // Scripts get the error as JSON on stdout, people on stderr
+            match format {
+                OutputFormat::Json => output::print_error(&e),
+                OutputFormat::Table => eprintln!("Error: {:?}", e),
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the command selected on the command line.
+///
+/// # Arguments
+/// * `cli` - Parsed command-line arguments
+///
+/// # Returns
+/// - `Ok(())` when the command succeeded
+/// - `Err` describing why it failed; printed by `main` in the output format
+async fn run(cli: Cli) -> Result<()> {
+    let format = cli.output;
+
+    // Install the requested network backend before any command runs
+    backend::select_backend(cli.backend)?;
 
     // Match on the subcommand and delegate to the appropriate handler
     match cli.command {
-        Commands::ListInterfaces => cmd_list_interfaces(),
-        Commands::Scan { interface } => cmd_scan(interface.as_deref()),
+        Commands::ListInterfaces => cmd_list_interfaces(format),
+        Commands::Scan {
+            interface,
+            min_signal,
+            band,
+            ssid_regex,
+            sort,
+            group,
+            watch,
+            interval_ms,
+            min_change,
+            log,
+            count,
+        } => {
+            let filter = ScanFilter {
+                min_signal,
+                band,
+                ssid_regex,
+            };
+            if watch {
+                let interval = Duration::from_millis(interval_ms);
+                let watch = WatchOptions {
+                    interval,
+                    min_change,
+                    log,
+                    count,
+                };
+                cmd_scan_watch(interface.as_deref(), &filter, watch, format).await
+            } else {
+                cmd_scan(interface.as_deref(), &filter, sort, group, format)
+            }
+        }
         Commands::Connect {
             ssid,
             password,
             interface,
             save,
-        } => cmd_connect(&ssid, password.as_deref(), interface.as_deref(), save),
-        Commands::Status { interface } => cmd_status(interface.as_deref()),
-        Commands::Disconnect { interface } => cmd_disconnect(interface.as_deref()),
+            hidden,
+        } => cmd_connect(&ssid, password.as_deref(), interface.as_deref(), save, hidden, format),
+        Commands::Discover {
+            interface,
+            ssid_patterns,
+            oui_prefixes,
+            connect,
+            password,
+            save,
+        } => cmd_discover(
+            interface.as_deref(),
+            &ssid_patterns,
+            &oui_prefixes,
+            connect,
+            password.as_deref(),
+            save,
+            format,
+        ),
+        Commands::Status { interface } => cmd_status(interface.as_deref(), format),
+        Commands::Disconnect { interface } => cmd_disconnect(interface.as_deref(), format),
         Commands::FetchGateway {
-            output,
+            out,
             interface,
             url,
-        } => cmd_fetch_gateway(&output, interface.as_deref(), url.as_deref()),
-        Commands::Serve { port, interface } => cmd_serve(port, interface.as_deref()).await,
+        } => cmd_fetch_gateway(&out, interface.as_deref(), url.as_deref(), format),
+        Commands::Serve {
+            port,
+            interface,
+            gateway,
+            stream_port,
+            connect_timeout_ms,
+            request_timeout_ms,
+            recordings_dir,
+            record_commands,
+            watchdog_ms,
+            estop_log,
+            max_rate,
+            coalesce_ms,
+            queue_depth,
+            no_reconnect,
+            reconnect_attempts,
+            reconnect_max_ms,
+            status_interval_ms,
+        } => {
+            let config = server::ServerConfig {
+                gateway: String::new(),
+                stream_port,
+                port,
+                connect_timeout: Duration::from_millis(connect_timeout_ms),
+                request_timeout: Duration::from_millis(request_timeout_ms),
+                recordings_dir,
+                record_commands,
+                watchdog_timeout: (watchdog_ms > 0).then(|| Duration::from_millis(watchdog_ms)),
+                estop_log,
+                // 0 turns rate limiting off; negative rates are rejected by clap
+                max_rate: (max_rate != 0.0).then_some(max_rate),
+                coalesce_window: (coalesce_ms > 0).then(|| Duration::from_millis(coalesce_ms)),
+                queue_depth: queue_depth.into(),
+                reconnect: None,
+                interface: None,
+                status_interval: Duration::from_millis(status_interval_ms),
+            };
+            let reconnect = (!no_reconnect).then(|| ReconnectOptions {
+                max_attempts: (reconnect_attempts > 0).then_some(reconnect_attempts),
+                max_backoff: Duration::from_millis(reconnect_max_ms),
+            });
+            cmd_serve(config, interface.as_deref(), gateway, reconnect).await
+        }
+        Commands::Snapshot {
+            out,
+            interface,
+            gateway,
+            stream_port,
+            timeout_ms,
+        } => {
+            let timeout = Duration::from_millis(timeout_ms);
+            cmd_snapshot(&out, interface.as_deref(), gateway, stream_port, timeout, format).await
+        }
+        Commands::Record {
+            out,
+            duration,
+            format: record_format,
+            interface,
+            gateway,
+            stream_port,
+        } => {
+            let gateway = resolve_gateway(interface.as_deref(), gateway)?;
+            let record_format = record_format.unwrap_or_else(|| RecordFormat::from_path(&out));
+            cmd_record(&out, record_format, duration, &gateway, stream_port, format).await
+        }
+        Commands::Monitor {
+            interface,
+            interval_ms,
+            log,
+            log_format,
+            count,
+        } => {
+            let interval = Duration::from_millis(interval_ms);
+            let log = log.map(|path| {
+                let log_format = log_format.unwrap_or_else(|| SampleFormat::from_path(&path));
+                (path, log_format)
+            });
+            cmd_monitor(interface.as_deref(), interval, log, count, format).await
+        }
+        Commands::Estop {
+            release,
+            reason,
+            server,
+            interface,
+            gateway,
+            timeout_ms,
+        } => {
+            let timeout = Duration::from_millis(timeout_ms);
+            if release {
+                cmd_estop_release(&server, timeout, format).await
+            } else {
+                cmd_estop(&server, reason, interface.as_deref(), gateway, timeout, format).await
+            }
+        }
+        Commands::Replay {
+            file,
+            speed,
+            interface,
+            gateway,
+        } => {
+            let gateway = resolve_gateway(interface.as_deref(), gateway)?;
+            cmd_replay(&file, speed, &gateway, format).await
+        }
+        Commands::MockRobot {
+            port,
+            stream_port,
+            fps,
+        } => cmd_mock_robot(port, stream_port, fps).await,
         Commands::SaveNetwork {
             ssid,
             password,
             interface,
-        } => cmd_save_network(&ssid, &password, interface.as_deref()),
-        Commands::ShowConfig => cmd_show_config(),
+            hidden,
+        } => cmd_save_network(&ssid, &password, interface.as_deref(), hidden, format),
+        Commands::ShowConfig => cmd_show_config(format),
     }
 }
 
 /// Handler for the `list-interfaces` command.
 ///
-/// Queries the system for all available WiFi interfaces using nmcli,
+/// Queries the system for all available WiFi interfaces using the selected backend,
 /// then displays them in a formatted table showing the interface name,
 /// current state (connected/disconnected), and whether it's a USB device.
 ///
+/// # Arguments
+/// * `format` - Table or JSON output (an array of interfaces)
+///
 /// # Returns
 /// - `Ok(())` on success
-/// - `Err` if nmcli command fails or output parsing fails
-fn cmd_list_interfaces() -> Result<()> {
+/// - `Err` if the backend fails to enumerate interfaces
+fn cmd_list_interfaces(format: OutputFormat) -> Result<()> {
     // Retrieve all WiFi interfaces from the system
     let interfaces = interface::list_wifi_interfaces()?;
+    if format == OutputFormat::Json {
+        return output::print_json(&interfaces);
+    }
 
     // Handle case where no WiFi interfaces are available
     if interfaces.is_empty() {
@@ -198,7 +736,7 @@ fn cmd_list_interfaces() -> Result<()> {
     }
 
     // Print table header with column alignment
-    println!("{:<16} {:<12} {}", "INTERFACE", "STATE", "TYPE");
+    println!("{:<16} {:<12} TYPE", "INTERFACE", "STATE");
     println!("{}", "-".repeat(40));
 
     // Iterate through each interface and display its details
@@ -215,27 +753,169 @@ fn cmd_list_interfaces() -> Result<()> {
 ///
 /// Scans for available WiFi networks using the specified interface
 /// (or auto-detected USB interface). Triggers a rescan and displays
-/// results sorted by signal strength.
+/// the access points that pass the filter in the requested order.
 ///
 /// # Arguments
 /// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `filter` - Criteria an access point must meet to be shown
+/// * `sort` - Order of the results
+/// * `group` - If true, shows one entry per SSID instead of per access point
+/// * `format` - Table or JSON output (an array of access points or groups)
 ///
 /// # Returns
 /// - `Ok(())` on success
 /// - `Err` if interface resolution or scanning fails
-fn cmd_scan(interface: Option<&str>) -> Result<()> {
+fn cmd_scan(
+    interface: Option<&str>,
+    filter: &ScanFilter,
+    sort: SortKey,
+    group: bool,
+    format: OutputFormat,
+) -> Result<()> {
     // Resolve the interface to use (specified or auto-detected USB)
     let iface = interface::resolve_interface(interface)?;
-    println!("Scanning on interface: {}", iface.name);
-    println!();
+    if format == OutputFormat::Table {
+        println!("Scanning on interface: {}", iface.name);
+        println!();
+    }
 
-    // Perform the network scan and display results in a formatted table
-    let networks = scan::scan_networks(&iface.name)?;
-    scan::display_networks(&networks);
+    // Perform the network scan, then filter and order the results
+    let mut networks = filter.apply(scan::scan_networks(&iface.name)?);
+    scan::sort_networks(&mut networks, sort);
+
+    // Display results in a formatted table or as JSON
+    match (group, format) {
+        (true, OutputFormat::Json) => output::print_json(&scan::group_by_ssid(&networks))?,
+        (true, OutputFormat::Table) => scan::display_groups(&scan::group_by_ssid(&networks)),
+        (false, OutputFormat::Json) => output::print_json(&networks)?,
+        (false, OutputFormat::Table) => scan::display_networks(&networks),
+    }
 
     Ok(())
 }
 
+/// Settings of `scan --watch`.
+struct WatchOptions {
+    /// Time between the starts of two scans.
+    interval: Duration,
+
+    /// Smallest signal change (percentage points) to report.
+    min_change: u8,
+
+    /// Optional file to log every change to, as JSON lines.
+    log: Option<PathBuf>,
+
+    /// Optional number of scans; watches until Ctrl+C if None.
+    count: Option<u64>,
+}
+
+/// Handler for the `scan --watch` command (async).
+///
+/// Rescans every interval and prints what changed since the previous scans,
+/// as tracked by a [`ScanWatcher`](scan::ScanWatcher). Only access points
+/// that pass the filter are followed. A failed scan is reported and the
+/// next one is tried as usual.
+///
+/// # Arguments
+/// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `filter` - Criteria an access point must meet to be followed
+/// * `watch` - Interval, threshold, log file and number of scans
+/// * `format` - Table or JSON output (one JSON line per change)
+///
+/// # Returns
+/// - `Ok(())` when watching ends
+/// - `Err` if no interface is found or the log cannot be written
+async fn cmd_scan_watch(
+    interface: Option<&str>,
+    filter: &ScanFilter,
+    watch: WatchOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    let table = format == OutputFormat::Table;
+    let iface = interface::resolve_interface(interface)?;
+    let mut log = match &watch.log {
+        Some(path) => Some(BufWriter::new(
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+        )),
+        None => None,
+    };
+
+    if table {
+        println!(
+            "Watching {} every {}ms (Ctrl+C to stop) ...",
+            iface.name,
+            watch.interval.as_millis()
+        );
+        if let Some(path) = &watch.log {
+            println!("Logging changes to {}", path.display());
+        }
+        println!();
+    }
+
+    // A scan can take longer than the interval; then the next one starts right after it
+    let mut ticks = tokio::time::interval(watch.interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut watcher = scan::ScanWatcher::new(watch.min_change, scan::DISAPPEAR_AFTER_SCANS);
+
+    let mut scans = 0;
+    let result = loop {
+        if watch.count.is_some_and(|count| scans >= count) {
+            break Ok(());
+        }
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+
+        // Backends block (nmcli spawns a process), so keep them off the runtime
+        let name = iface.name.clone();
+        let scanned = tokio::select! {
+            scanned = tokio::task::spawn_blocking(move || scan::scan_networks(&name)) => scanned,
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        };
+        scans += 1;
+        let networks = match scanned.map_err(anyhow::Error::from).and_then(|r| r) {
+            Ok(networks) => networks,
+            Err(e) => {
+                eprintln!("Scan failed: {:#}", e);
+                continue;
+            }
+        };
+
+        let changes = watcher.update(filter.apply(networks));
+        if let Err(e) = report_changes(&changes, format, log.as_mut()) {
+            break Err(e);
+        }
+    };
+
+    if table {
+        println!();
+        println!("Ran {} scan(s)", scans);
+    }
+    result
+}
+
+/// Prints scan changes and appends them to the log, if there is one.
+fn report_changes(
+    changes: &[scan::ScanChange],
+    format: OutputFormat,
+    mut log: Option<&mut BufWriter<File>>,
+) -> Result<()> {
+    for change in changes {
+        match format {
+            OutputFormat::Table => scan::display_change(change),
+            OutputFormat::Json => output::print_json_line(change)?,
+        }
+        if let Some(log) = log.as_mut() {
+            // Flush every line so the log can be followed with `tail -f`
+            serde_json::to_writer(&mut **log, change)?;
+            writeln!(log)?;
+            log.flush()?;
+        }
+    }
+    Ok(())
+}
+
 /// Handler for the `connect` command.
 ///
 /// Connects to a WiFi network using the provided or saved credentials.
@@ -246,13 +926,67 @@ fn cmd_scan(interface: Option<&str>) -> Result<()> {
 /// * `password` - Optional password; if None, looks up saved credentials
 /// * `interface` - Optional interface name; if None, auto-detects USB interface
 /// * `save` - If true, saves credentials to config after successful connection
+/// * `hidden` - If true, probes for a network that does not broadcast its SSID
+/// * `format` - Table or JSON output (the connection status); JSON omits
+///   progress messages
 ///
 /// # Returns
 /// - `Ok(())` on successful connection
 /// - `Err` if password is missing and not saved, or connection fails
-fn cmd_connect(ssid: &str, password: Option<&str>, interface: Option<&str>, save: bool) -> Result<()> {
+fn cmd_connect(
+    ssid: &str,
+    password: Option<&str>,
+    interface: Option<&str>,
+    save: bool,
+    hidden: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let table = format == OutputFormat::Table;
+
+    // Resolve the interface to use for connection
+    let iface = interface::resolve_interface(interface)?;
+
+    // Connect, then display the connection status
+    let status = connect_network(ssid, password, &iface.name, save, hidden, None, table)?;
+    if !table {
+        return output::print_json(&status);
+    }
+    println!();
+    connection::display_status(&status);
+
+    Ok(())
+}
+
+/// Connects an interface to a network with the provided or saved credentials.
+///
+/// Shared by `connect` and `discover --connect`.
+///
+/// # Arguments
+/// * `ssid` - The network name to connect to
+/// * `password` - Optional password; if None, looks up saved credentials
+/// * `interface` - Name of the interface to connect
+/// * `save` - If true, saves credentials to config after successful connection
+/// * `hidden` - If true, probes for a network that does not broadcast its
+///   SSID; networks saved as hidden are always probed for
+/// * `bssid` - Access point to join when several share the SSID; ignored
+///   for hidden networks
+/// * `table` - Whether to print progress messages
+///
+/// # Returns
+/// - `Ok(ConnectionStatus)` of the interface after connecting
+/// - `Err` if password is missing and not saved, or connection fails
+fn connect_network(
+    ssid: &str,
+    password: Option<&str>,
+    interface: &str,
+    save: bool,
+    hidden: bool,
+    bssid: Option<&str>,
+    table: bool,
+) -> Result<connection::ConnectionStatus> {
     // Load existing config or create a new default config
     let mut cfg = Config::load().unwrap_or_default();
+    let hidden = hidden || cfg.find_network(ssid).is_some_and(|n| n.hidden);
 
     // Resolve the password: use provided password, or look up saved credentials
     let password = match password {
@@ -260,7 +994,9 @@ fn cmd_connect(ssid: &str, password: Option<&str>, interface: Option<&str>, save
         None => {
             // Attempt to find saved credentials for this SSID
             if let Some(network) = cfg.find_network(ssid) {
-                println!("Using saved password for '{}'", ssid);
+                if table {
+                    println!("Using saved password for '{}'", ssid);
+                }
                 network.password.clone()
             } else {
                 // No password provided and no saved credentials - cannot proceed
@@ -269,29 +1005,119 @@ fn cmd_connect(ssid: &str, password: Option<&str>, interface: Option<&str>, save
         }
     };
 
-    // Resolve the interface to use for connection
-    let iface = interface::resolve_interface(interface)?;
-    println!("Connecting to '{}' on interface {}...", ssid, iface.name);
+    if table {
+        let kind = if hidden { "hidden network " } else { "" };
+        let via = bssid.filter(|_| !hidden).map(|b| format!(" ({})", b)).unwrap_or_default();
+        println!("Connecting to {}'{}'{} on interface {}...", kind, ssid, via, interface);
+    }
 
-    // Attempt to establish the WiFi connection using nmcli
-    connection::connect(&iface.name, ssid, &password)?;
-    println!("Connected successfully!");
+    // Attempt to establish the WiFi connection through the selected backend
+    match bssid {
+        _ if hidden => connection::connect_hidden(interface, ssid, &password)?,
+        Some(bssid) => connection::connect_bssid(interface, ssid, bssid, &password)?,
+        None => connection::connect(interface, ssid, &password)?,
+    }
+    if table {
+        println!("Connected successfully!");
+    }
 
     // Optionally save credentials for future quick connections
     if save {
         cfg.add_network(NetworkConfig {
             ssid: ssid.to_string(),
             password,
-            interface: Some(iface.name.clone()),
+            interface: Some(interface.to_string()),
+            hidden,
         });
         cfg.save()?;
-        println!("Credentials saved to config.");
+        if table {
+            println!("Credentials saved to config.");
+        }
     }
 
-    // Display the connection status after successful connection
-    println!();
-    let status = connection::status(&iface.name)?;
-    connection::display_status(&status);
+    connection::status(interface)
+}
+
+/// Handler for the `discover` command.
+///
+/// Scans on the USB adapter and lists the access points that look like a
+/// robot, by SSID pattern and Espressif BSSID prefix, best first. Optionally
+/// connects to the best one.
+///
+/// # Arguments
+/// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `ssid_patterns` - SSID patterns in addition to the configured ones
+/// * `oui_prefixes` - BSSID prefixes in addition to the configured ones
+/// * `connect` - If true, connects to the best candidate
+/// * `password` - Optional password for the best candidate; if None, looks
+///   up saved credentials
+/// * `save` - If true, saves credentials to config after connecting
+/// * `format` - Table or JSON output (a [`DiscoveryReport`]); JSON omits
+///   progress messages
+///
+/// # Returns
+/// - `Ok(())` on success, even if no robot was found without `connect`
+/// - `Err` if a pattern is invalid, scanning fails, or there is no robot
+///   to connect to
+fn cmd_discover(
+    interface: Option<&str>,
+    ssid_patterns: &[String],
+    oui_prefixes: &[String],
+    connect: bool,
+    password: Option<&str>,
+    save: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let table = format == OutputFormat::Table;
+
+    // Combine the configured signs with the ones given on the command line
+    let cfg = Config::load()?;
+    let mut discovery = cfg.discovery.clone();
+    discovery.ssid_patterns.extend_from_slice(ssid_patterns);
+    discovery.oui_prefixes.extend_from_slice(oui_prefixes);
+    let matcher = RobotMatcher::from_config(&discovery)?;
+
+    // Scan and pick out the likely robots
+    let iface = interface::resolve_interface(interface)?;
+    if table {
+        println!("Looking for robots on interface: {}", iface.name);
+        println!();
+    }
+    let networks = scan::scan_networks(&iface.name)?;
+    let candidates = matcher.candidates(&networks, &cfg);
+    if table {
+        discover::display_candidates(&candidates);
+    }
+
+    // Join the best candidate if requested
+    let connection = match (connect, discover::best(&candidates)) {
+        (false, _) => None,
+        (true, None) if !candidates.is_empty() => {
+            bail!("Only robots hiding their SSID were found; join one with `connect <ssid> --hidden`")
+        }
+        (true, None) => return Err(WifiProxyError::NoRobotFound(iface.name).into()),
+        (true, Some(best)) => {
+            if table {
+                println!();
+            }
+            // Join the very access point chosen; another may share its SSID
+            let network = &best.network;
+            let bssid = Some(network.bssid.as_str()).filter(|b| !b.is_empty());
+            Some(connect_network(&network.ssid, password, &iface.name, save, false, bssid, table)?)
+        }
+    };
+
+    if !table {
+        return output::print_json(&DiscoveryReport {
+            interface: iface.name,
+            candidates,
+            connection,
+        });
+    }
+    if let Some(status) = connection {
+        println!();
+        connection::display_status(&status);
+    }
 
     Ok(())
 }
@@ -303,15 +1129,19 @@ fn cmd_connect(ssid: &str, password: Option<&str>, interface: Option<&str>, save
 ///
 /// # Arguments
 /// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `format` - Table or JSON output
 ///
 /// # Returns
 /// - `Ok(())` on success
 /// - `Err` if interface resolution or status query fails
-fn cmd_status(interface: Option<&str>) -> Result<()> {
+fn cmd_status(interface: Option<&str>, format: OutputFormat) -> Result<()> {
     // Resolve the interface and query its current status
     let iface = interface::resolve_interface(interface)?;
     let status = connection::status(&iface.name)?;
-    connection::display_status(&status);
+    match format {
+        OutputFormat::Json => output::print_json(&status)?,
+        OutputFormat::Table => connection::display_status(&status),
+    }
 
     Ok(())
 }
@@ -322,13 +1152,18 @@ fn cmd_status(interface: Option<&str>) -> Result<()> {
 ///
 /// # Arguments
 /// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `format` - Table or JSON output (the status after disconnecting)
 ///
 /// # Returns
 /// - `Ok(())` on successful disconnection
 /// - `Err` if interface resolution or disconnection fails
-fn cmd_disconnect(interface: Option<&str>) -> Result<()> {
+fn cmd_disconnect(interface: Option<&str>, format: OutputFormat) -> Result<()> {
     // Resolve the interface and initiate disconnection
     let iface = interface::resolve_interface(interface)?;
+    if format == OutputFormat::Json {
+        connection::disconnect(&iface.name)?;
+        return output::print_json(&connection::status(&iface.name)?);
+    }
     println!("Disconnecting interface {}...", iface.name);
 
     connection::disconnect(&iface.name)?;
@@ -346,11 +1181,17 @@ fn cmd_disconnect(interface: Option<&str>) -> Result<()> {
 /// * `output` - Path where the fetched HTML will be saved
 /// * `interface` - Optional interface name; if None, auto-detects USB interface
 /// * `url` - Optional custom URL; if None, uses the gateway's root page
+/// * `format` - Table or JSON output (the URL fetched and file written)
 ///
 /// # Returns
 /// - `Ok(())` on successful fetch and save
 /// - `Err` if no gateway is found or HTTP request fails
-fn cmd_fetch_gateway(output: &PathBuf, interface: Option<&str>, url: Option<&str>) -> Result<()> {
+fn cmd_fetch_gateway(
+    output: &Path,
+    interface: Option<&str>,
+    url: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
     // Resolve interface and get its connection status to find the gateway
     let iface = interface::resolve_interface(interface)?;
     let status = connection::status(&iface.name)?;
@@ -367,6 +1208,10 @@ fn cmd_fetch_gateway(output: &PathBuf, interface: Option<&str>, url: Option<&str
     };
 
     // Perform the HTTP GET request and save the response to file
+    if format == OutputFormat::Json {
+        connection::fetch_gateway(&fetch_url, output)?;
+        return output::print_json(&serde_json::json!({ "url": fetch_url, "output": output }));
+    }
     println!("Fetching {} ...", fetch_url);
     connection::fetch_gateway(&fetch_url, output)?;
     println!("Saved to {}", output.display());
@@ -380,28 +1225,543 @@ fn cmd_fetch_gateway(output: &PathBuf, interface: Option<&str>, url: Option<&str
 /// This allows controlling the robot from localhost:port while the USB WiFi
 /// adapter maintains the connection to the robot's access point.
 ///
+/// When the gateway is taken from the interface, the link is supervised:
+/// if the adapter drops off the network it is connected to now, the server
+/// reconnects using the credentials saved for that network.
+///
 /// # Arguments
-/// * `port` - TCP port for the local server to listen on
+/// * `config` - Server settings from the command line; `gateway` and
+///   `reconnect` are resolved here
 /// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `gateway` - Optional gateway override; if set, the interface is not consulted
+/// * `reconnect` - Reconnection limits; None disables link supervision
 ///
 /// # Returns
 /// - `Ok(())` when server shuts down gracefully
 /// - `Err` if no gateway found or server fails to start
-async fn cmd_serve(port: u16, interface: Option<&str>) -> Result<()> {
-    // Resolve interface and get the gateway address for proxying
+async fn cmd_serve(
+    mut config: server::ServerConfig,
+    interface: Option<&str>,
+    gateway: Option<String>,
+    reconnect: Option<ReconnectOptions>,
+) -> Result<()> {
+    if let Some(gateway) = gateway {
+        // Explicit gateway (e.g., a mock robot) - no link to supervise
+        config.gateway = gateway;
+        return server::run_server(config).await;
+    }
+
+    // Resolve interface and get the gateway address from its status
     let iface = interface::resolve_interface(interface)?;
     let status = connection::status(&iface.name)?;
-
-    // Extract gateway IP - required for proxying requests
-    let gateway = status
+    config.gateway = status
         .gateway
         .ok_or_else(|| anyhow::anyhow!("No gateway found for interface {}", iface.name))?;
+    config.interface = Some(iface.name.clone());
+
+    // Supervise the current network with its saved credentials; the
+    // profile name may differ from the SSID the credentials are saved under
+    if status.connection.is_some() && status.ssid.is_none() {
+        eprintln!("Warning: cannot tell the SSID of {}; the link will not be supervised", iface.name);
+    }
+    if let (Some(options), Some(ssid)) = (reconnect, status.ssid) {
+        let saved = Config::load()?.find_network(&ssid).cloned();
+        if saved.is_none() {
+            eprintln!(
+                "Warning: no saved credentials for '{}'; the link can only be restored if the network is open",
+                ssid
+            );
+        }
+        config.reconnect = Some(ReconnectConfig {
+            interface: iface.name,
+            ssid,
+            password: saved.as_ref().map(|n| n.password.clone()),
+            hidden: saved.is_some_and(|n| n.hidden),
+            max_backoff: options.max_backoff,
+            max_attempts: options.max_attempts,
+        });
+    }
 
-    // Configure and start the proxy server
-    let config = server::ServerConfig { gateway, port };
+    // Start the proxy server
     server::run_server(config).await
 }
 
+/// Reconnection limits for `serve`, from the command line.
+struct ReconnectOptions {
+    /// Attempts before giving up; None retries forever.
+    max_attempts: Option<u32>,
+
+    /// Longest wait between two attempts.
+    max_backoff: Duration,
+}
+
+/// Handler for the `snapshot` command (async).
+///
+/// Connects to the robot's camera stream, waits for one complete frame and
+/// writes it to a JPEG file.
+///
+/// # Arguments
+/// * `output` - Path where the JPEG will be saved
+/// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `gateway` - Optional gateway override; if set, the interface is not consulted
+/// * `stream_port` - Port of the camera stream on the gateway host
+/// * `timeout` - Maximum time to wait for a frame
+/// * `format` - Table or JSON output (the file written and frame details)
+///
+/// # Returns
+/// - `Ok(())` when the frame was saved
+/// - `Err` if no gateway is found, no frame arrives, or the file cannot be written
+async fn cmd_snapshot(
+    output: &Path,
+    interface: Option<&str>,
+    gateway: Option<String>,
+    stream_port: u16,
+    timeout: Duration,
+    format: OutputFormat,
+) -> Result<()> {
+    let gateway = resolve_gateway(interface, gateway)?;
+    let url = server::stream_url(&gateway, stream_port);
+
+    if format == OutputFormat::Table {
+        println!("Capturing frame from {} ...", url);
+    }
+    let frame = mjpeg::fetch_snapshot(&url, timeout).await?;
+    std::fs::write(output, &frame.jpeg).context("Failed to write snapshot file")?;
+
+    if format == OutputFormat::Json {
+        return output::print_json(&serde_json::json!({
+            "url": url,
+            "output": output,
+            "bytes": frame.jpeg.len(),
+            "captured": mjpeg::unix_timestamp(frame.timestamp),
+        }));
+    }
+
+    println!(
+        "Saved {} bytes to {} (captured at {})",
+        frame.jpeg.len(),
+        output.display(),
+        mjpeg::unix_timestamp(frame.timestamp)
+    );
+
+    Ok(())
+}
+
+/// Handler for the `record` command (async).
+///
+/// Reads the camera stream through a [`StreamHub`](mjpeg::StreamHub), the
+/// same path the proxy server uses, and writes frames until the duration is
+/// reached or Ctrl+C is pressed.
+///
+/// # Arguments
+/// * `output` - AVI file or JPEG directory to create
+/// * `format` - Container format
+/// * `limit` - Optional recording length; records until Ctrl+C if None
+/// * `gateway` - Gateway address of the robot
+/// * `stream_port` - Port of the camera stream on the gateway host
+/// * `output_format` - Table or JSON output (the recording summary)
+///
+/// # Returns
+/// - `Ok(())` when the recording and its index are written
+/// - `Err` if the camera sends no frames or the output cannot be written
+async fn cmd_record(
+    output: &Path,
+    format: RecordFormat,
+    limit: Option<Duration>,
+    gateway: &str,
+    stream_port: u16,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let url = server::stream_url(gateway, stream_port);
+    let hub = Arc::new(mjpeg::StreamHub::new(reqwest::Client::new(), &url));
+
+    // Ctrl+C ends the recording cleanly so the file is still playable
+    let stop = CancellationToken::new();
+    tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                stop.cancel();
+            }
+        }
+    });
+
+    let table = output_format == OutputFormat::Table;
+    match limit {
+        Some(limit) if table => println!("Recording {} for {:.1}s ...", url, limit.as_secs_f64()),
+        None if table => println!("Recording {} (Ctrl+C to stop) ...", url),
+        _ => {}
+    }
+    let summary = recorder::record(hub.subscribe(), output, format, limit, stop).await?;
+    if !table {
+        return output::print_json(&summary);
+    }
+
+    println!(
+        "Saved {} frames ({:.1}s, {} dropped) to {}",
+        summary.frames,
+        summary.duration_secs,
+        summary.dropped,
+        summary.output.display()
+    );
+    println!("Frame index: {}", summary.index.display());
+
+    Ok(())
+}
+
+/// Handler for the `monitor` command (async).
+///
+/// Samples the interface's link with a [`LinkMonitor`], the same monitor the
+/// proxy server uses, and prints each sample with the signal trend.
+///
+/// # Arguments
+/// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `interval` - Time between two samples
+/// * `log` - Optional log file and its format
+/// * `count` - Optional number of samples; monitors until Ctrl+C if None
+/// * `format` - Table or JSON output (one JSON line per sample)
+///
+/// # Returns
+/// - `Ok(())` when monitoring ends
+/// - `Err` if no interface is found or the log cannot be written
+async fn cmd_monitor(
+    interface: Option<&str>,
+    interval: Duration,
+    log: Option<(PathBuf, SampleFormat)>,
+    count: Option<u64>,
+    format: OutputFormat,
+) -> Result<()> {
+    let table = format == OutputFormat::Table;
+    let iface = interface::resolve_interface(interface)?;
+    let mut writer = match &log {
+        Some((path, format)) => Some(SampleWriter::create(path, *format)?),
+        None => None,
+    };
+
+    // Subscribe before sampling starts so the first sample is not missed
+    let monitor = Arc::new(LinkMonitor::new(&iface.name, interval));
+    let mut samples = monitor.subscribe();
+    let sampler = tokio::spawn(monitor.clone().run());
+
+    if table {
+        println!(
+            "Monitoring {} every {}ms (Ctrl+C to stop) ...",
+            iface.name,
+            interval.as_millis()
+        );
+        if let Some((path, log_format)) = &log {
+            println!("Logging to {} ({})", path.display(), log_format);
+        }
+        println!();
+        monitor::display_header();
+    }
+
+    let mut taken = 0;
+    let result = loop {
+        if count.is_some_and(|count| taken >= count) {
+            break Ok(());
+        }
+        let sample = tokio::select! {
+            sample = samples.recv() => match sample {
+                Ok(sample) => sample,
+                // A slow terminal only skips rows
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(e) => break Err(e.into()),
+            },
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        };
+        taken += 1;
+
+        if table {
+            monitor::display_sample(&sample, monitor.trend());
+        } else if let Err(e) = output::print_json_line(&sample) {
+            break Err(e);
+        }
+        if let Some(writer) = writer.as_mut()
+            && let Err(e) = writer.write(&sample)
+        {
+            break Err(e);
+        }
+    };
+
+    sampler.abort();
+    if table {
+        println!();
+        println!("Took {} sample(s)", taken);
+    }
+    result
+}
+
+/// Handler for the `estop` command (async).
+///
+/// Asks the running proxy server to engage its emergency stop, which halts
+/// the robot and locks out further control. If no server is listening the
+/// stop sequence is sent to the robot directly; there is then no lock.
+///
+/// A server that accepted the request but did not answer in time is not
+/// bypassed: it has locked control and is still sending the stops, and
+/// commands sent alongside its queue would only compete with them.
+///
+/// # Arguments
+/// * `server` - Base URL of the proxy server
+/// * `reason` - Optional reason recorded in the server's log
+/// * `interface` - Interface used to find the robot if the server is down
+/// * `gateway` - Gateway override used if the server is down
+/// * `timeout` - How long to wait for the server's answer
+/// * `format` - Table or JSON output (the server's stop event, or the robot
+///   stopped directly)
+///
+/// # Returns
+/// - `Ok(())` when every stop command was delivered
+/// - `Err` if a stop command failed, the server did not answer in time, or
+///   the robot could not be found
+async fn cmd_estop(
+    server: &str,
+    reason: Option<String>,
+    interface: Option<&str>,
+    gateway: Option<String>,
+    timeout: Duration,
+    format: OutputFormat,
+) -> Result<()> {
+    let body = serde_json::json!({ "reason": reason });
+    let result = estop_request(server, "/api/estop", Some(body), timeout).await;
+
+    let (status, event) = match result {
+        Ok(answer) => answer,
+        Err(e) if !is_connect_error(&e) => {
+            return Err(e.context(format!(
+                "The proxy server did not confirm the emergency stop; check `GET {}/api/estop`",
+                server.trim_end_matches('/')
+            )));
+        }
+        Err(e) => {
+            // No server to lock: stop the robot ourselves
+            eprintln!("Proxy server not reachable ({:#}); stopping the robot directly", e);
+            let gateway = resolve_gateway(interface, gateway)?;
+            let robot = RobotClient::new(&gateway).with_timeout(Duration::from_secs(2));
+            let errors = estop::send_stop_sequence(&robot).await;
+            if !errors.is_empty() {
+                bail!("Emergency stop failed: {}", errors.join("; "));
+            }
+            if format == OutputFormat::Json {
+                return output::print_json(&serde_json::json!({ "gateway": gateway, "locked": false }));
+            }
+            println!("Robot at {} stopped (no server lock in place)", gateway);
+            return Ok(());
+        }
+    };
+
+    if format == OutputFormat::Table {
+        println!("EMERGENCY STOP ENGAGED at {} - controls locked", event["time"].as_str().unwrap_or("?"));
+    }
+    if !status.is_success() {
+        let errors: Vec<&str> = event["errors"]
+            .as_array()
+            .map(|errors| errors.iter().filter_map(|e| e.as_str()).collect())
+            .unwrap_or_default();
+        bail!("Stop commands failed: {}", errors.join("; "));
+    }
+    if format == OutputFormat::Json {
+        return output::print_json(&event);
+    }
+    println!("Release with: wifi-proxy estop --release");
+
+    Ok(())
+}
+
+/// Handler for `estop --release` (async).
+///
+/// # Arguments
+/// * `server` - Base URL of the proxy server
+/// * `timeout` - How long to wait for the server's answer
+/// * `format` - Table or JSON output (the server's release event)
+///
+/// # Returns
+/// - `Ok(())` when control was handed back
+/// - `Err` if the server cannot be reached or the stop was not engaged
+async fn cmd_estop_release(server: &str, timeout: Duration, format: OutputFormat) -> Result<()> {
+    let (status, event) = estop_request(server, "/api/estop/release", None, timeout).await?;
+    if !status.is_success() {
+        bail!("{}", event.as_str().unwrap_or("Release failed"));
+    }
+    if format == OutputFormat::Json {
+        return output::print_json(&event);
+    }
+    println!("Emergency stop released at {}", event["time"].as_str().unwrap_or("?"));
+    Ok(())
+}
+
+/// Posts to one of the server's emergency stop endpoints.
+///
+/// Connecting is given 3 seconds, so a server that is not running is
+/// detected quickly; the answer may take up to `timeout`.
+///
+/// # Returns
+/// - `Ok((status, body))` with the JSON body, or the plain-text body as a JSON string
+/// - `Err` if the server cannot be reached or does not answer in time
+async fn estop_request(
+    server: &str,
+    path: &str,
+    body: Option<serde_json::Value>,
+    timeout: Duration,
+) -> Result<(reqwest::StatusCode, serde_json::Value)> {
+    let url = format!("{}{}", server.trim_end_matches('/'), path);
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(3))
+        .build()?;
+    let mut request = client
+        .post(&url)
+        .header("x-client-id", "cli")
+        .timeout(timeout);
+    if let Some(body) = body {
+        request = request
+            .header("content-type", "application/json")
+            .body(body.to_string());
+    }
+
+    let response = request.send().await.with_context(|| format!("POST {}", url))?;
+    let status = response.status();
+    let text = response.text().await?;
+    let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+    Ok((status, body))
+}
+
+/// Returns whether an error means no server accepted the connection (as
+/// opposed to a server that was reached but failed or answered too late).
+fn is_connect_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect())
+}
+
+/// Handler for the `replay` command (async).
+///
+/// Loads a command recording and sends it to the robot with the recorded
+/// spacing between commands. Ctrl+C aborts the replay; either way the robot
+/// is sent both stop commands before the command returns.
+///
+/// # Arguments
+/// * `file` - JSONL command recording to play
+/// * `speed` - Playback speed factor (1.0 = original timing)
+/// * `gateway` - Gateway address of the robot
+/// * `format` - Table or JSON output (the replay summary)
+///
+/// # Returns
+/// - `Ok(())` when the replay completed or was aborted, and the robot stopped
+/// - `Err` if the file is malformed or the robot could not be reached
+async fn cmd_replay(file: &Path, speed: f64, gateway: &str, format: OutputFormat) -> Result<()> {
+    let commands = replay::load(file)?;
+    let robot = RobotClient::new(gateway).with_timeout(Duration::from_secs(5));
+
+    // Ctrl+C aborts the replay, which then stops the robot
+    let stop = CancellationToken::new();
+    tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                stop.cancel();
+            }
+        }
+    });
+
+    let length = commands.last().map_or(0.0, |last| last.t - commands[0].t);
+    if format == OutputFormat::Table {
+        println!(
+            "Replaying {} commands ({:.1}s at {}x) to {} (Ctrl+C to abort) ...",
+            commands.len(),
+            length / speed,
+            speed,
+            gateway
+        );
+    }
+    let summary = replay::replay(&robot, &commands, speed, stop).await?;
+    if format == OutputFormat::Json {
+        return output::print_json(&summary);
+    }
+
+    if summary.aborted {
+        println!("Aborted after {} of {} commands; robot stopped", summary.sent, summary.commands);
+    } else {
+        println!("Replayed {} commands in {:.1}s; robot stopped", summary.sent, summary.duration_secs);
+    }
+
+    Ok(())
+}
+
+/// Determines the gateway address to talk to.
+///
+/// # Arguments
+/// * `interface` - Optional interface name; if None, auto-detects USB interface
+/// * `gateway` - Optional explicit gateway; returned as-is when set
+///
+/// # Returns
+/// - `Ok(String)` with the explicit gateway or the interface's gateway
+/// - `Err` if the interface cannot be resolved or has no gateway
+fn resolve_gateway(interface: Option<&str>, gateway: Option<String>) -> Result<String> {
+    match gateway {
+        // Explicit gateway (e.g., a mock robot) - no WiFi interface needed
+        Some(gateway) => Ok(gateway),
+        None => {
+            // Resolve interface and get the gateway address from its status
+            let iface = interface::resolve_interface(interface)?;
+            let status = connection::status(&iface.name)?;
+
+            // Extract gateway IP - required to reach the robot
+            status
+                .gateway
+                .ok_or_else(|| anyhow::anyhow!("No gateway found for interface {}", iface.name))
+        }
+    }
+}
+
+/// Parses `record --duration`: positive seconds, up to one day.
+///
+/// Used as a clap value parser so out-of-range values are usage errors.
+fn parse_record_duration(value: &str) -> Result<Duration, WifiProxyError> {
+    let secs: f64 = value
+        .parse()
+        .map_err(|_| WifiProxyError::Recording(format!("'{}' is not a number of seconds", value)))?;
+    recorder::duration_from_secs(secs)
+}
+
+/// Parses `replay --speed`: a factor between 0.01 and 100.
+fn parse_replay_speed(value: &str) -> Result<f64, WifiProxyError> {
+    let speed: f64 = value
+        .parse()
+        .map_err(|_| WifiProxyError::Replay(format!("'{}' is not a number", value)))?;
+    replay::check_speed(speed)
+}
+
+/// Parses `serve --max-rate`: commands per second, 0 for no limit.
+fn parse_max_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(rate),
+        _ => Err(format!("'{}' is not a rate (commands per second, 0 for no limit)", value)),
+    }
+}
+
+/// Handler for the `mock-robot` command (async).
+///
+/// Runs an emulated robot gateway so the web interface and proxy can be
+/// used without a physical robot.
+///
+/// # Arguments
+/// * `port` - TCP port for the `/control` endpoint
+/// * `stream_port` - TCP port for the `/stream` endpoint
+/// * `fps` - Frames per second of the synthetic stream
+///
+/// # Returns
+/// - `Ok(())` when the mock robot shuts down
+/// - `Err` if either port cannot be bound
+async fn cmd_mock_robot(port: u16, stream_port: u16, fps: u32) -> Result<()> {
+    let config = mock_robot::MockRobotConfig {
+        control_port: port,
+        stream_port,
+        fps,
+    };
+    mock_robot::run_mock_robot(config).await
+}
+
 /// Handler for the `save-network` command.
 ///
 /// Saves network credentials to the configuration file without attempting
@@ -411,11 +1771,19 @@ async fn cmd_serve(port: u16, interface: Option<&str>) -> Result<()> {
 /// * `ssid` - Network name to save
 /// * `password` - Password for the network
 /// * `interface` - Optional preferred interface for this network
+/// * `hidden` - If true, the network does not broadcast its SSID
+/// * `format` - Table or JSON output (the redacted configuration after saving)
 ///
 /// # Returns
 /// - `Ok(())` on successful save
 /// - `Err` if config file cannot be written
-fn cmd_save_network(ssid: &str, password: &str, interface: Option<&str>) -> Result<()> {
+fn cmd_save_network(
+    ssid: &str,
+    password: &str,
+    interface: Option<&str>,
+    hidden: bool,
+    format: OutputFormat,
+) -> Result<()> {
     // Load existing config or create default
     let mut cfg = Config::load().unwrap_or_default();
 
@@ -424,10 +1792,14 @@ fn cmd_save_network(ssid: &str, password: &str, interface: Option<&str>) -> Resu
         ssid: ssid.to_string(),
         password: password.to_string(),
         interface: interface.map(String::from),
+        hidden,
     });
 
     // Persist the updated configuration to disk
     cfg.save()?;
+    if format == OutputFormat::Json {
+        return output::print_json(&cfg.redacted()?);
+    }
 
     // Confirm the save location to the user
     let path = config::config_path()?;
@@ -441,10 +1813,17 @@ fn cmd_save_network(ssid: &str, password: &str, interface: Option<&str>) -> Resu
 /// Displays the current configuration including all saved networks.
 /// Passwords are masked for security when displayed.
 ///
+/// # Arguments
+/// * `format` - Table or JSON output; JSON only tells whether a password is set
+///
 /// # Returns
 /// - `Ok(())` on success
 /// - `Err` if config file cannot be read
-fn cmd_show_config() -> Result<()> {
+fn cmd_show_config(format: OutputFormat) -> Result<()> {
+    if format == OutputFormat::Json {
+        return output::print_json(&Config::load()?.redacted()?);
+    }
+
     // Get and display the config file path
     let path = config::config_path()?;
     println!("Config file: {}", path.display());
@@ -458,17 +1837,29 @@ fn cmd_show_config() -> Result<()> {
         println!("No saved networks.");
     } else {
         // Print table header
-        println!("{:<24} {:<20} {}", "SSID", "INTERFACE", "PASSWORD");
-        println!("{}", "-".repeat(60));
+        println!("{:<24} {:<20} {:<12} HIDDEN", "SSID", "INTERFACE", "PASSWORD");
+        println!("{}", "-".repeat(66));
 
         // Print each saved network with masked password
         for network in &cfg.networks {
             let iface = network.interface.as_deref().unwrap_or("-");
             // Mask password with asterisks (max 12 chars for display)
             let masked_pw = "*".repeat(network.password.len().min(12));
-            println!("{:<24} {:<20} {}", network.ssid, iface, masked_pw);
+            let hidden = if network.hidden { "yes" } else { "no" };
+            println!("{:<24} {:<20} {:<12} {}", network.ssid, iface, masked_pw, hidden);
         }
     }
 
+    // Display how `discover` recognizes the robot
+    let discovery = &cfg.discovery;
+    println!();
+    println!("Robot discovery:");
+    println!("  SSID patterns: {}", discovery.ssid_patterns.join(", "));
+    if discovery.has_default_oui_prefixes() {
+        println!("  OUI prefixes:  Espressif ({} prefixes)", discovery.oui_prefixes.len());
+    } else {
+        println!("  OUI prefixes:  {}", discovery.oui_prefixes.join(", "));
+    }
+
     Ok(())
 }
@@ -0,0 +1,637 @@
+//! MJPEG stream parsing and fan-out to many viewers.
+//!
+//! The ESP32-CAM serves its camera as a `multipart/x-mixed-replace` stream
+//! on port 81 and copes badly with more than one client. [`StreamHub`] keeps
+//! a single upstream connection per gateway, splits it into JPEG frames with
+//! [`MjpegParser`], and broadcasts each frame to every subscriber.
+//!
+//! # Lifecycle
+//!
+//! - The upstream connection is opened when the first subscriber arrives
+//! - It is reopened after errors or stalls while anyone is still watching
+//! - It is closed as soon as the last subscriber leaves
+//!
+//! # Slow Subscribers
+//!
+//! Frames are delivered through a small broadcast buffer. A subscriber that
+//! falls behind skips the frames it missed and continues with newer ones;
+//! the upstream reader never waits for it.
+
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::error::WifiProxyError;
+
+/// Multipart boundary used when re-serving frames to viewers.
+pub const BOUNDARY: &str = "frame";
+
+/// Frames buffered per subscriber before older ones are dropped.
+const SUBSCRIBER_BUFFER: usize = 4;
+
+/// Delay before reopening the upstream after it failed or ended.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Upstream connections delivering no data for this long are reopened.
+const STALL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Buffered bytes without a complete frame after which the buffer is discarded.
+const MAX_BUFFER: usize = 4 * 1024 * 1024;
+
+/// A single JPEG frame received from the camera.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// Position of the frame in the stream, starting at 1 for each hub.
+    pub sequence: u64,
+
+    /// Time the frame was received from the robot.
+    pub timestamp: SystemTime,
+
+    /// JPEG-encoded image data.
+    pub jpeg: Bytes,
+}
+
+/// Incremental parser splitting a multipart MJPEG body into JPEG frames.
+///
+/// Bytes are pushed in whatever chunks the network delivers; complete frames
+/// are taken out with [`MjpegParser::next_frame`]. Parts with a
+/// `Content-Length` header are cut by length, others at the next boundary.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::mjpeg::MjpegParser;
+///
+/// let mut parser = MjpegParser::new(Some("frame"));
+/// parser.push(b"--frame\r\nContent-Type: image/jpeg\r\nContent-Length: 4\r\n\r\n\xFF\xD8\xFF");
+/// assert!(parser.next_frame().is_none()); // Incomplete so far
+///
+/// parser.push(b"\xD9\r\n--frame\r\nContent-Type: image/jpeg\r\n\r\nabc\r\n--frame\r\n");
+/// assert_eq!(&parser.next_frame().unwrap()[..], b"\xFF\xD8\xFF\xD9");
+/// assert_eq!(&parser.next_frame().unwrap()[..], b"abc");
+/// assert!(parser.next_frame().is_none());
+/// ```
+#[derive(Debug, Default)]
+pub struct MjpegParser {
+    /// Boundary marker including the leading dashes (e.g., `--frame`).
+    /// Learned from the first `--` line if not known up front.
+    marker: Option<Vec<u8>>,
+
+    /// Bytes received but not yet consumed.
+    buffer: BytesMut,
+}
+
+impl MjpegParser {
+    /// Creates a parser for the given multipart boundary.
+    ///
+    /// # Arguments
+    /// * `boundary` - Boundary from the `Content-Type` header, or `None` to
+    ///   detect it from the first part
+    pub fn new(boundary: Option<&str>) -> Self {
+        Self {
+            marker: boundary.map(|b| format!("--{}", b.trim_start_matches("--")).into_bytes()),
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Appends received bytes to the parser's buffer.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Returns the next complete frame, if one has been received.
+    pub fn next_frame(&mut self) -> Option<Bytes> {
+        loop {
+            // Learn the boundary from the first line starting with "--"
+            if self.marker.is_none() {
+                let line_end = find(&self.buffer, b"\r\n", 0)?;
+                let line = &self.buffer[..line_end];
+                if line.starts_with(b"--") {
+                    self.marker = Some(line.to_vec());
+                } else {
+                    let _ = self.buffer.split_to(line_end + 2);
+                    continue;
+                }
+            }
+            let marker = self.marker.as_deref()?;
+
+            // Locate the start of the next part and the end of its headers
+            let Some(start) = find(&self.buffer, marker, 0) else {
+                self.discard_if_oversized();
+                return None;
+            };
+            let Some(headers_end) = find(&self.buffer, b"\r\n\r\n", start) else {
+                self.discard_if_oversized();
+                return None;
+            };
+            let body_start = headers_end + 4;
+            let headers = String::from_utf8_lossy(&self.buffer[start..headers_end]);
+
+            // Cut the body by Content-Length, or at the following boundary
+            let body_end = match content_length(&headers) {
+                Some(length) if self.buffer.len() >= body_start + length => body_start + length,
+                Some(_) => {
+                    self.discard_if_oversized();
+                    return None;
+                }
+                None => match find(&self.buffer, marker, body_start) {
+                    Some(next) => trim_crlf(&self.buffer, body_start, next),
+                    None => {
+                        self.discard_if_oversized();
+                        return None;
+                    }
+                },
+            };
+
+            // Consume the part; empty bodies (e.g., a closing part) are skipped
+            let mut part = self.buffer.split_to(body_end);
+            let jpeg = part.split_off(body_start).freeze();
+            if !jpeg.is_empty() {
+                return Some(jpeg);
+            }
+        }
+    }
+
+    /// Drops buffered data that has grown too large to hold a valid frame.
+    fn discard_if_oversized(&mut self) {
+        if self.buffer.len() > MAX_BUFFER {
+            self.buffer.clear();
+        }
+    }
+}
+
+/// Returns the position of `needle` in `haystack` at or after `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|pos| pos + from)
+}
+
+/// Returns `end` moved back over a single `\r\n` preceding it.
+fn trim_crlf(buffer: &[u8], start: usize, end: usize) -> usize {
+    if end >= start + 2 && &buffer[end - 2..end] == b"\r\n" {
+        end - 2
+    } else {
+        end
+    }
+}
+
+/// Extracts the `Content-Length` value from a block of part headers.
+fn content_length(headers: &str) -> Option<usize> {
+    headers.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Encodes a JPEG as one part of a multipart stream using [`BOUNDARY`].
+///
+/// Each part carries `Content-Type` and `Content-Length` headers, like the
+/// ESP32-CAM firmware sends them.
+pub fn encode_part(jpeg: &[u8]) -> Bytes {
+    let mut part = format!(
+        "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+        BOUNDARY,
+        jpeg.len()
+    )
+    .into_bytes();
+    part.extend_from_slice(jpeg);
+    part.extend_from_slice(b"\r\n");
+    Bytes::from(part)
+}
+
+/// Returns the `Content-Type` header value for a stream of [`encode_part`] parts.
+pub fn content_type() -> String {
+    format!("multipart/x-mixed-replace; boundary={}", BOUNDARY)
+}
+
+/// Formats a capture time as Unix seconds with millisecond precision.
+///
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use wifi_proxy::mjpeg::unix_timestamp;
+///
+/// let t = UNIX_EPOCH + Duration::from_millis(1_700_000_000_042);
+/// assert_eq!(unix_timestamp(t), "1700000000.042");
+/// ```
+pub fn unix_timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{}.{:03}", since.as_secs(), since.subsec_millis())
+}
+
+/// Extracts the multipart boundary from a `Content-Type` header value.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::mjpeg::boundary_from_content_type;
+///
+/// let ct = "multipart/x-mixed-replace;boundary=123456789000000000000987654321";
+/// assert_eq!(boundary_from_content_type(ct).as_deref(), Some("123456789000000000000987654321"));
+/// assert_eq!(boundary_from_content_type("image/jpeg"), None);
+/// ```
+pub fn boundary_from_content_type(content_type: &str) -> Option<String> {
+    content_type.split(';').find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("boundary") {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Last problem the upstream reader ran into.
+#[derive(Debug, Clone)]
+pub struct UpstreamError {
+    /// Whether the failure was a timeout (connect timeout or stall).
+    pub timeout: bool,
+
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+/// Mutable state behind the hub's lock.
+#[derive(Default)]
+struct HubInner {
+    /// Full URL of the robot's stream endpoint.
+    url: String,
+
+    /// Broadcast channel of the running upstream, if any.
+    sender: Option<broadcast::Sender<Frame>>,
+
+    /// Upstream reader task, running while there are subscribers.
+    task: Option<JoinHandle<()>>,
+
+    /// Number of live [`Subscription`]s.
+    subscribers: usize,
+
+    /// Most recent upstream failure, cleared when a frame arrives.
+    last_error: Option<UpstreamError>,
+
+    /// Most recent frame of the running upstream, cleared when it stops.
+    latest: Option<Frame>,
+
+    /// Identifies the current upstream task; bumped whenever it is replaced
+    /// or stopped.
+    generation: u64,
+}
+
+impl HubInner {
+    /// Stops the upstream task and clears what it left behind.
+    ///
+    /// `abort` only requests cancellation: the task keeps running until its
+    /// next await point and may still be about to store a frame or error.
+    /// Bumping the generation makes [`update_if_current`] drop those writes.
+    fn stop_upstream(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        self.generation += 1;
+        self.sender = None;
+        self.latest = None;
+    }
+}
+
+/// Applies an upstream task's update to the hub, unless the task has been
+/// replaced or stopped since it was started for `generation`.
+fn update_if_current(inner: &Mutex<HubInner>, generation: u64, update: impl FnOnce(&mut HubInner)) {
+    let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+    if inner.generation == generation {
+        update(&mut inner);
+    }
+}
+
+/// Shares one upstream MJPEG connection among any number of subscribers.
+///
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+/// use wifi_proxy::mjpeg::StreamHub;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let hub = Arc::new(StreamHub::new(reqwest::Client::new(), "http://192.168.4.1:81/stream"));
+///
+///     // The first subscriber opens the camera connection
+///     let mut viewer = hub.subscribe();
+///     while let Some(frame) = viewer.recv().await {
+///         println!("frame {} ({} bytes)", frame.sequence, frame.jpeg.len());
+///     }
+/// }
+/// ```
+pub struct StreamHub {
+    /// HTTP client used for the upstream connection.
+    http: reqwest::Client,
+
+    /// Shared state, also updated by the upstream task.
+    inner: Arc<Mutex<HubInner>>,
+}
+
+impl StreamHub {
+    /// Creates a hub for the given stream URL. Nothing is opened yet.
+    pub fn new(http: reqwest::Client, url: &str) -> Self {
+        Self {
+            http,
+            inner: Arc::new(Mutex::new(HubInner {
+                url: url.to_string(),
+                ..HubInner::default()
+            })),
+        }
+    }
+
+    /// Returns the upstream stream URL.
+    pub fn url(&self) -> String {
+        self.lock().url.clone()
+    }
+
+    /// Points the hub at a new stream URL.
+    ///
+    /// The current upstream is closed, which ends every subscription: their
+    /// [`Subscription::recv`] returns `None`, so viewers see their stream end
+    /// cleanly rather than freeze. The next subscriber opens the new URL.
+    ///
+    /// # Returns
+    /// The number of subscriptions that were closed.
+    pub fn retarget(&self, url: &str) -> usize {
+        let mut inner = self.lock();
+        if inner.url == url {
+            return 0;
+        }
+        inner.url = url.to_string();
+
+        // Dropping every sender closes the channel for all receivers
+        let closed = if inner.sender.is_some() { inner.subscribers } else { 0 };
+        inner.stop_upstream();
+        inner.last_error = None;
+        closed
+    }
+
+    /// Returns the number of current subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        self.lock().subscribers
+    }
+
+    /// Returns the most recent upstream failure, if the stream is not healthy.
+    pub fn last_error(&self) -> Option<UpstreamError> {
+        self.lock().last_error.clone()
+    }
+
+    /// Returns the most recent frame, opening the upstream if needed.
+    ///
+    /// While viewers are watching, the latest frame they received is returned
+    /// immediately. Otherwise the upstream is opened just long enough to
+    /// receive one frame.
+    ///
+    /// # Arguments
+    /// * `timeout` - How long to wait for a frame if none is available yet
+    ///
+    /// # Returns
+    /// - `Ok(Frame)` with the most recent frame
+    /// - `Err(UpstreamError)` if no frame arrived within `timeout`
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use wifi_proxy::mjpeg::StreamHub;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let hub = Arc::new(StreamHub::new(reqwest::Client::new(), "http://192.168.4.1:81/stream"));
+    ///     let frame = hub.snapshot(Duration::from_secs(5)).await.expect("No frame");
+    ///     std::fs::write("snapshot.jpg", &frame.jpeg).unwrap();
+    /// }
+    /// ```
+    pub async fn snapshot(self: &Arc<Self>, timeout: Duration) -> Result<Frame, UpstreamError> {
+        // Subscribe first so the upstream stays open while we look and wait
+        let mut subscription = self.subscribe();
+        if let Some(frame) = self.lock().latest.clone() {
+            return Ok(frame);
+        }
+        subscription.recv_timeout(timeout).await
+    }
+
+    /// Subscribes to frames, opening the upstream if this is the first subscriber.
+    pub fn subscribe(self: &Arc<Self>) -> Subscription {
+        let mut inner = self.lock();
+
+        // Start the upstream reader on the first subscriber
+        let sender = match &inner.sender {
+            Some(sender) => sender.clone(),
+            None => {
+                let (sender, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+                inner.last_error = None;
+                inner.generation += 1;
+                inner.task = Some(tokio::spawn(run_upstream(
+                    self.http.clone(),
+                    inner.url.clone(),
+                    sender.clone(),
+                    self.inner.clone(),
+                    inner.generation,
+                )));
+                inner.sender = Some(sender.clone());
+                sender
+            }
+        };
+        inner.subscribers += 1;
+
+        Subscription {
+            receiver: sender.subscribe(),
+            hub: self.clone(),
+        }
+    }
+
+    /// Locks the state, recovering from a poisoned lock.
+    fn lock(&self) -> std::sync::MutexGuard<'_, HubInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Called when a subscription is dropped; stops the upstream after the last one.
+    fn unsubscribe(&self) {
+        let mut inner = self.lock();
+        inner.subscribers = inner.subscribers.saturating_sub(1);
+        if inner.subscribers == 0 {
+            inner.stop_upstream();
+        }
+    }
+}
+
+/// Fetches a single frame from a camera stream URL.
+///
+/// Convenience wrapper for one-off captures (e.g., the `snapshot` command):
+/// opens a private [`StreamHub`], takes its first frame and closes it again.
+///
+/// # Arguments
+/// * `url` - Full stream URL (see [`crate::server::stream_url`])
+/// * `timeout` - Maximum time to connect and receive a frame
+///
+/// # Returns
+/// - `Ok(Frame)` with the first complete frame
+/// - `Err` if the camera could not be reached or sent no frame in time
+pub async fn fetch_snapshot(url: &str, timeout: Duration) -> anyhow::Result<Frame> {
+    let http = reqwest::Client::builder().connect_timeout(timeout).build()?;
+    let hub = Arc::new(StreamHub::new(http, url));
+    let frame = hub
+        .snapshot(timeout)
+        .await
+        .map_err(|e| WifiProxyError::FetchFailed(e.to_string()))?;
+    Ok(frame)
+}
+
+/// A viewer's handle on a [`StreamHub`]. Dropping it unsubscribes.
+pub struct Subscription {
+    /// Receiving end of the hub's broadcast channel.
+    receiver: broadcast::Receiver<Frame>,
+
+    /// Hub to notify when the subscription ends.
+    hub: Arc<StreamHub>,
+}
+
+impl Subscription {
+    /// Waits for the next frame, skipping any this subscriber fell behind on.
+    ///
+    /// # Returns
+    /// - `Some(Frame)` with the next available frame
+    /// - `None` if the hub has shut the upstream down
+    pub async fn recv(&mut self) -> Option<Frame> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(frame) => return Some(frame),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for the next frame.
+    ///
+    /// # Returns
+    /// - `Ok(Frame)` with the next available frame
+    /// - `Err(UpstreamError)` with the upstream's last failure, or a timeout
+    ///   error if the upstream simply has not produced a frame yet
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<Frame, UpstreamError> {
+        match tokio::time::timeout(timeout, self.recv()).await {
+            Ok(Some(frame)) => Ok(frame),
+            _ => Err(self.hub.last_error().unwrap_or_else(|| UpstreamError {
+                timeout: true,
+                message: format!("no frame received within {} ms", timeout.as_millis()),
+            })),
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.hub.unsubscribe();
+    }
+}
+
+/// Reads the upstream stream and broadcasts frames until aborted.
+///
+/// Reconnects after errors, stalls or the stream ending, recording the
+/// failure in the hub so handlers can report it. `generation` identifies
+/// this task in the hub; once superseded, its updates are ignored.
+async fn run_upstream(
+    http: reqwest::Client,
+    url: String,
+    sender: broadcast::Sender<Frame>,
+    inner: Arc<Mutex<HubInner>>,
+    generation: u64,
+) {
+    let mut sequence = 0;
+    let record = |error: UpstreamError| {
+        update_if_current(&inner, generation, |inner| inner.last_error = Some(error));
+    };
+
+    loop {
+        match read_upstream(&http, &url, &sender, &mut sequence, &inner, generation).await {
+            Ok(()) => record(UpstreamError {
+                timeout: false,
+                message: "stream ended".to_string(),
+            }),
+            Err(error) => record(error),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Opens the upstream once and broadcasts frames until it ends or fails.
+async fn read_upstream(
+    http: &reqwest::Client,
+    url: &str,
+    sender: &broadcast::Sender<Frame>,
+    sequence: &mut u64,
+    inner: &Mutex<HubInner>,
+    generation: u64,
+) -> Result<(), UpstreamError> {
+    let from_reqwest = |e: reqwest::Error| UpstreamError {
+        timeout: e.is_timeout(),
+        message: e.to_string(),
+    };
+
+    let response = http
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(from_reqwest)?;
+
+    // The boundary is announced in the Content-Type header
+    let boundary = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .and_then(boundary_from_content_type);
+    let mut parser = MjpegParser::new(boundary.as_deref());
+    let mut body = response.bytes_stream();
+
+    loop {
+        let chunk = match tokio::time::timeout(STALL_TIMEOUT, body.next()).await {
+            Ok(Some(chunk)) => chunk.map_err(from_reqwest)?,
+            Ok(None) => return Ok(()),
+            Err(_) => {
+                return Err(UpstreamError {
+                    timeout: true,
+                    message: format!("no data for {} seconds", STALL_TIMEOUT.as_secs()),
+                })
+            }
+        };
+
+        parser.push(&chunk);
+        while let Some(jpeg) = parser.next_frame() {
+            *sequence += 1;
+            let frame = Frame {
+                sequence: *sequence,
+                timestamp: SystemTime::now(),
+                jpeg,
+            };
+
+            // Remember it for snapshots; the stream is healthy again
+            update_if_current(inner, generation, |inner| {
+                inner.last_error = None;
+                inner.latest = Some(frame.clone());
+            });
+
+            // Sending only fails when nobody is subscribed right now
+            let _ = sender.send(frame);
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Built-in emulator of the ESP32 robot gateway.
+//!
+//! Lets the web interface, scripts and the proxy server be developed without
+//! a physical robot. The emulator listens on two ports, mirroring the real
+//! firmware where control is served on port 80 and the camera on port 81.
+//!
+//! # Endpoints
+//!
+//! Control port:
+//! - `GET /control` - Accepts `var`/`val`/`cmd` like the firmware, logs and records them
+//! - `GET /commands` - Returns every recorded command as JSON
+//!
+//! Stream port:
+//! - `GET /stream` - MJPEG stream of synthetic, numbered frames
+//!
+//! # Example
+//!
+//! ```bash
+//! # Terminal 1: start the emulator
+//! wifi-proxy mock-robot --port 8000 --stream-port 8001
+//!
+//! # Terminal 2: point the proxy server at it
+//! wifi-proxy serve --gateway 127.0.0.1:8000 --stream-port 8001
+//! ```
+
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use futures_util::stream;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::mjpeg;
+use crate::robot::RobotCommand;
+
+/// Width of synthetic camera frames in pixels.
+const FRAME_WIDTH: u16 = 320;
+
+/// Height of synthetic camera frames in pixels.
+const FRAME_HEIGHT: u16 = 240;
+
+/// Highest frame rate of the synthetic stream; keeps the frame period well
+/// above zero.
+pub const MAX_FPS: u32 = 120;
+
+/// Configuration for the mock robot.
+pub struct MockRobotConfig {
+    /// Port serving `/control` (the real robot uses 80).
+    pub control_port: u16,
+
+    /// Port serving `/stream` (the real robot uses 81).
+    pub stream_port: u16,
+
+    /// Frames per second produced by the synthetic camera (clamped to
+    /// 1..=[`MAX_FPS`]).
+    pub fps: u32,
+}
+
+/// A control request received by the mock robot.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordedCommand {
+    /// Milliseconds since the mock robot started.
+    pub elapsed_ms: u64,
+
+    /// Raw `var` parameter.
+    pub var: String,
+
+    /// Raw `val` parameter.
+    pub val: String,
+
+    /// Raw `cmd` parameter, if present.
+    pub cmd: Option<String>,
+
+    /// Whether the parameters decoded to a known [`RobotCommand`].
+    pub valid: bool,
+}
+
+/// Shared state of the running mock robot.
+struct MockState {
+    /// Time the mock robot started (for relative timestamps).
+    started: Instant,
+
+    /// Every control request received, in arrival order.
+    commands: Mutex<Vec<RecordedCommand>>,
+
+    /// Frames per second for the stream.
+    fps: u32,
+}
+
+/// Starts the mock robot and serves until an error occurs.
+///
+/// # Arguments
+/// * `config` - Ports and frame rate to use
+///
+/// # Returns
+/// - `Ok(())` when both listeners shut down
+/// - `Err` if either port cannot be bound
+pub async fn run_mock_robot(config: MockRobotConfig) -> anyhow::Result<()> {
+    let state = Arc::new(MockState {
+        started: Instant::now(),
+        commands: Mutex::new(Vec::new()),
+        fps: config.fps.clamp(1, MAX_FPS),
+    });
+
+    // Control endpoints, like the firmware's port 80 server
+    let control_app = Router::new()
+        .route("/control", get(control_handler))
+        .route("/commands", get(commands_handler))
+        .with_state(state.clone());
+
+    // Camera endpoint, like the firmware's port 81 server
+    let stream_app = Router::new()
+        .route("/stream", get(stream_handler))
+        .with_state(state);
+
+    let control_listener =
+        tokio::net::TcpListener::bind(("0.0.0.0", config.control_port)).await?;
+    let stream_listener = tokio::net::TcpListener::bind(("0.0.0.0", config.stream_port)).await?;
+
+    println!("Mock robot control at http://localhost:{}/control", config.control_port);
+    println!("Mock robot stream at  http://localhost:{}/stream", config.stream_port);
+
+    tokio::try_join!(
+        axum::serve(control_listener, control_app),
+        axum::serve(stream_listener, stream_app),
+    )?;
+
+    Ok(())
+}
+
+/// Handler for `GET /control`.
+///
+/// Records the request, logs it with its decoded meaning, and answers with
+/// an empty `200 OK` like the firmware. Unknown commands are still recorded
+/// but answered with `400 Bad Request`.
+async fn control_handler(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let elapsed = state.started.elapsed();
+    let decoded = RobotCommand::from_map(&params);
+
+    let record = RecordedCommand {
+        elapsed_ms: elapsed.as_millis() as u64,
+        var: params.get("var").cloned().unwrap_or_default(),
+        val: params.get("val").cloned().unwrap_or_default(),
+        cmd: params.get("cmd").cloned(),
+        valid: decoded.is_ok(),
+    };
+
+    // Log in a compact, greppable form
+    let meaning = match &decoded {
+        Ok(command) => format!("{:?}", command),
+        Err(e) => e.to_string(),
+    };
+    println!(
+        "[mock-robot] +{:>8.3}s var={} val={} cmd={} -> {}",
+        elapsed.as_secs_f64(),
+        record.var,
+        record.val,
+        record.cmd.as_deref().unwrap_or("-"),
+        meaning
+    );
+
+    state
+        .commands
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(record);
+
+    match decoded {
+        Ok(_) => (StatusCode::OK, String::new()),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// Handler for `GET /commands`: returns all recorded commands as JSON.
+async fn commands_handler(State(state): State<Arc<MockState>>) -> Json<Vec<RecordedCommand>> {
+    let commands = state.commands.lock().unwrap_or_else(|e| e.into_inner());
+    Json(commands.clone())
+}
+
+/// Handler for `GET /stream`: an endless MJPEG stream of numbered frames.
+///
+/// Each connected client gets its own frame counter starting at zero.
+async fn stream_handler(State(state): State<Arc<MockState>>) -> Response {
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / state.fps);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    // Produce one multipart part per tick
+    let frames = stream::unfold((interval, 0u64), |(mut interval, n)| async move {
+        interval.tick().await;
+        let part = mjpeg::encode_part(&synthetic_frame(n));
+        Some((Ok::<_, Infallible>(part), (interval, n + 1)))
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", mjpeg::content_type())
+        .body(Body::from_stream(frames))
+        .unwrap()
+}
+
+/// Segments lit for each digit 0-9 on a seven-segment display.
+///
+/// Bit order: top, top-right, bottom-right, bottom, bottom-left, top-left, middle.
+const SEGMENTS: [u8; 10] = [
+    0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101, 0b1111101, 0b0000111,
+    0b1111111, 0b1101111,
+];
+
+/// Renders a grayscale JPEG showing the frame number and a moving bar.
+///
+/// The number makes dropped or repeated frames visible downstream; the bar
+/// makes motion obvious when watching the stream.
+fn synthetic_frame(number: u64) -> Vec<u8> {
+    let (w, h) = (FRAME_WIDTH as usize, FRAME_HEIGHT as usize);
+    let mut pixels = vec![40u8; w * h];
+
+    // Moving bar along the bottom edge
+    let bar_x = (number as usize * 8) % w;
+    for y in h - 20..h - 8 {
+        for x in bar_x..(bar_x + 24).min(w) {
+            pixels[y * w + x] = 220;
+        }
+    }
+
+    // Frame number as seven-segment digits, centered
+    let digits = number.to_string();
+    let (digit_w, digit_h, thickness, gap) = (36, 72, 8, 12);
+    let total_w = digits.len() * (digit_w + gap) - gap;
+    let mut left = w.saturating_sub(total_w) / 2;
+    let top = (h - digit_h) / 2 - 10;
+
+    for digit in digits.bytes().map(|b| (b - b'0') as usize) {
+        let segments = SEGMENTS[digit];
+        let mid = top + digit_h / 2 - thickness / 2;
+        let rects = [
+            (left, top, digit_w, thickness),
+            (left + digit_w - thickness, top, thickness, digit_h / 2),
+            (left + digit_w - thickness, top + digit_h / 2, thickness, digit_h / 2),
+            (left, top + digit_h - thickness, digit_w, thickness),
+            (left, top + digit_h / 2, thickness, digit_h / 2),
+            (left, top, thickness, digit_h / 2),
+            (left, mid, digit_w, thickness),
+        ];
+        for (i, (x0, y0, rw, rh)) in rects.iter().enumerate() {
+            if segments & (1 << i) == 0 {
+                continue;
+            }
+            for y in *y0..(y0 + rh).min(h) {
+                for x in *x0..(x0 + rw).min(w) {
+                    pixels[y * w + x] = 255;
+                }
+            }
+        }
+        left += digit_w + gap;
+    }
+
+    let mut jpeg = Vec::new();
+    jpeg_encoder::Encoder::new(&mut jpeg, 75)
+        .encode(&pixels, FRAME_WIDTH, FRAME_HEIGHT, jpeg_encoder::ColorType::Luma)
+        .expect("encoding an in-memory grayscale frame cannot fail");
+    jpeg
+}
@@ -0,0 +1,392 @@
+//! Link quality monitoring of the USB WiFi adapter.
+//!
+//! The robot's access point is weak, and control is lost abruptly once the
+//! robot walks out of range. The [`LinkMonitor`] samples the radio state of
+//! the adapter's link ([`LinkQuality`]: signal, RSSI, bitrates, channel and
+//! frequency) at a fixed interval and keeps a short history, so a falling
+//! signal shows up as a negative [`trend`](LinkMonitor::trend) well before
+//! commands start to fail.
+//!
+//! The same monitor drives the `monitor` command's live view and log, and
+//! feeds `/api/status` and `/api/link/quality` while serving.
+//!
+//! # Log Formats
+//!
+//! | Format  | Content                                              |
+//! |---------|------------------------------------------------------|
+//! | `csv`   | Header, then one row per sample (see [`CSV_HEADER`]) |
+//! | `jsonl` | One [`LinkSample`] JSON object per line              |
+//!
+//! Empty CSV cells and JSON `null`s are values the backend cannot measure,
+//! or every radio value while the adapter is not connected.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use wifi_proxy::backend::memory::MemoryBackend;
+//! use wifi_proxy::backend::{set_backend, NetworkBackend};
+//! use wifi_proxy::monitor::{sample, LinkMonitor};
+//!
+//! let backend = Arc::new(MemoryBackend::simulated());
+//! backend.connect("wlan1", "WAVESHARE Robot", "1234567890").unwrap();
+//! set_backend(backend.clone());
+//!
+//! let first = sample("wlan1");
+//! assert!(first.connected);
+//! assert_eq!(first.quality.signal, Some(78));
+//!
+//! // The robot walks away
+//! backend.set_signal("WAVESHARE Robot", 40).unwrap();
+//! let monitor = LinkMonitor::new("wlan1", Duration::from_secs(1));
+//! monitor.record(first);
+//! monitor.record(sample("wlan1"));
+//! assert_eq!(monitor.trend(), Some(-38));
+//! ```
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+use tokio::time::MissedTickBehavior;
+
+use crate::connection::{self, LinkQuality};
+use crate::error::WifiProxyError;
+use crate::health::FALLING_SIGNAL;
+use crate::mjpeg::unix_timestamp;
+use crate::scan::signal_to_bar;
+
+/// Header of CSV logs.
+pub const CSV_HEADER: &str = "time,interface,connected,bssid,signal,rssi_dbm,\
+tx_bitrate_mbps,rx_bitrate_mbps,frequency_mhz,channel,error";
+
+/// Time span over which the signal trend is measured.
+pub const TREND_WINDOW: Duration = Duration::from_secs(10);
+
+/// Samples kept for status queries.
+const HISTORY: usize = 300;
+
+/// One measurement of the adapter's link.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkSample {
+    /// Unix seconds (with milliseconds) the sample was taken at.
+    pub time: String,
+
+    /// Interface sampled.
+    pub interface: String,
+
+    /// Whether the interface was associated with an access point.
+    pub connected: bool,
+
+    /// Radio state; all `None` while not connected.
+    #[serde(flatten)]
+    pub quality: LinkQuality,
+
+    /// Why the backend could not be queried.
+    pub error: Option<String>,
+}
+
+/// Takes one sample of the interface's link.
+///
+/// Blocks while the backend is queried; run it off the async runtime.
+pub fn sample(interface: &str) -> LinkSample {
+    let time = unix_timestamp(SystemTime::now());
+    let (connected, quality, error) = match connection::link_quality(interface) {
+        Ok(Some(quality)) => (true, quality, None),
+        Ok(None) => (false, LinkQuality::default(), None),
+        Err(e) => (false, LinkQuality::default(), Some(e.to_string())),
+    };
+    LinkSample {
+        time,
+        interface: interface.to_string(),
+        connected,
+        quality,
+        error,
+    }
+}
+
+/// Format of a sample log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Comma-separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+impl SampleFormat {
+    /// Picks the format from a log path: `.csv` files get CSV, anything
+    /// else JSONL.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => SampleFormat::Csv,
+            _ => SampleFormat::Jsonl,
+        }
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SampleFormat::Csv => "csv",
+            SampleFormat::Jsonl => "jsonl",
+        })
+    }
+}
+
+impl FromStr for SampleFormat {
+    type Err = WifiProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(SampleFormat::Csv),
+            "jsonl" | "json" => Ok(SampleFormat::Jsonl),
+            _ => Err(WifiProxyError::Monitor(format!("unknown log format '{}'", s))),
+        }
+    }
+}
+
+/// Appends samples to a CSV or JSONL log.
+///
+/// Every sample is flushed at once, so the log can be followed with
+/// `tail -f` and nothing is lost when the process is interrupted.
+pub struct SampleWriter {
+    /// Log format.
+    format: SampleFormat,
+
+    /// Open log file.
+    file: BufWriter<File>,
+}
+
+impl SampleWriter {
+    /// Creates (or truncates) a log, writing the CSV header if needed.
+    pub fn create(path: &Path, format: SampleFormat) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = Self {
+            format,
+            file: BufWriter::new(file),
+        };
+        if format == SampleFormat::Csv {
+            writeln!(writer.file, "{}", CSV_HEADER)?;
+            writer.file.flush()?;
+        }
+        Ok(writer)
+    }
+
+    /// Appends one sample.
+    pub fn write(&mut self, sample: &LinkSample) -> Result<()> {
+        match self.format {
+            SampleFormat::Jsonl => {
+                serde_json::to_writer(&mut self.file, sample)?;
+                writeln!(self.file)?;
+            }
+            SampleFormat::Csv => {
+                let q = &sample.quality;
+                writeln!(
+                    self.file,
+                    "{},{},{},{},{},{},{},{},{},{},{}",
+                    sample.time,
+                    sample.interface,
+                    sample.connected,
+                    cell(&q.bssid),
+                    cell(&q.signal),
+                    cell(&q.rssi_dbm),
+                    cell(&q.tx_bitrate_mbps),
+                    cell(&q.rx_bitrate_mbps),
+                    cell(&q.frequency_mhz),
+                    cell(&q.channel),
+                    quote(sample.error.as_deref().unwrap_or_default()),
+                )?;
+            }
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Formats an optional value as a CSV cell, empty when missing.
+fn cell<T: fmt::Display>(value: &Option<T>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Quotes a free-text CSV cell if it contains separators or quotes.
+fn quote(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Prints the column header of the live view.
+///
+/// # Output Format
+/// ```text
+/// TIME           SIGNAL         RSSI     TX Mbit/s RX Mbit/s  CH  FREQ TREND
+/// ----------------------------------------------------------------------------
+/// 1760000000.123  78% ████   -61 dBm      72.2      65.0   6  2437    -4
+/// 1760000001.125 not connected
+/// ```
+pub fn display_header() {
+    println!(
+        "{:<14} {:<14} {:>7} {:>9} {:>9} {:>3} {:>5} {:>5}",
+        "TIME", "SIGNAL", "RSSI", "TX Mbit/s", "RX Mbit/s", "CH", "FREQ", "TREND"
+    );
+    println!("{}", "-".repeat(76));
+}
+
+/// Prints one sample as a row of the live view.
+///
+/// Values the backend cannot measure are shown as `-`. A trend at or below
+/// [`FALLING_SIGNAL`](crate::health::FALLING_SIGNAL) is flagged, since the
+/// link is about to be lost.
+///
+/// # Arguments
+/// * `sample` - The sample to print
+/// * `trend` - Signal change over the trend window, if known
+pub fn display_sample(sample: &LinkSample, trend: Option<i32>) {
+    // Disconnected samples carry no radio values
+    if !sample.connected {
+        match &sample.error {
+            Some(e) => println!("{:<14} error: {}", sample.time, e),
+            None => println!("{:<14} not connected", sample.time),
+        }
+        return;
+    }
+
+    let q = &sample.quality;
+    let dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let signal = match q.signal {
+        Some(signal) => format!("{:>3}% {}", signal, signal_to_bar(signal)),
+        None => "-".to_string(),
+    };
+    let falling = trend.is_some_and(|t| t <= -FALLING_SIGNAL);
+
+    println!(
+        "{:<14} {:<14} {:>7} {:>9} {:>9} {:>3} {:>5} {:>5}{}",
+        sample.time,
+        signal,
+        dash(q.rssi_dbm.map(|r| format!("{} dBm", r))),
+        dash(q.tx_bitrate_mbps.map(|r| format!("{:.1}", r))),
+        dash(q.rx_bitrate_mbps.map(|r| format!("{:.1}", r))),
+        dash(q.channel.map(|c| c.to_string())),
+        dash(q.frequency_mhz.map(|f| f.to_string())),
+        dash(trend.map(|t| format!("{:+}", t))),
+        if falling { "  SIGNAL FALLING" } else { "" },
+    );
+}
+
+/// Samples the adapter's link periodically and keeps recent samples.
+pub struct LinkMonitor {
+    /// Interface sampled.
+    interface: String,
+
+    /// Time between two samples.
+    interval: Duration,
+
+    /// Recent samples, oldest first.
+    history: Mutex<VecDeque<LinkSample>>,
+
+    /// Notifies live viewers of every sample.
+    samples: broadcast::Sender<LinkSample>,
+}
+
+impl LinkMonitor {
+    /// Creates a monitor. Nothing is sampled until [`run`](Self::run) is started.
+    ///
+    /// # Arguments
+    /// * `interface` - WiFi interface to sample (e.g., "wlan1")
+    /// * `interval` - Time between two samples
+    pub fn new(interface: &str, interval: Duration) -> Self {
+        let (samples, _) = broadcast::channel(16);
+        Self {
+            interface: interface.to_string(),
+            interval,
+            history: Mutex::new(VecDeque::new()),
+            samples,
+        }
+    }
+
+    /// Returns the interface sampled.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// Returns the time between two samples.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the most recent sample, if any.
+    pub fn latest(&self) -> Option<LinkSample> {
+        self.history.lock().unwrap().back().cloned()
+    }
+
+    /// Returns the recent samples, oldest first.
+    pub fn history(&self) -> Vec<LinkSample> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Subscribes to samples as they are taken.
+    pub fn subscribe(&self) -> broadcast::Receiver<LinkSample> {
+        self.samples.subscribe()
+    }
+
+    /// Change of the signal percentage over the last [`TREND_WINDOW`].
+    ///
+    /// # Returns
+    /// - `Some(change)` in percentage points; negative when the link degrades
+    /// - `None` with fewer than two connected samples in the window
+    pub fn trend(&self) -> Option<i32> {
+        let window = (TREND_WINDOW.as_millis() / self.interval.as_millis().max(1)) as usize;
+        let history = self.history.lock().unwrap();
+
+        // Compare the newest sample to the oldest one inside the window
+        let mut signals = history
+            .iter()
+            .rev()
+            .take(window + 1)
+            .map(|s| s.quality.signal.filter(|_| s.connected));
+        let newest = signals.next()??;
+        let oldest = signals.flatten().last()?;
+        Some(i32::from(newest) - i32::from(oldest))
+    }
+
+    /// Adds a sample to the history and passes it to subscribers.
+    pub fn record(&self, sample: LinkSample) {
+        {
+            let mut history = self.history.lock().unwrap();
+            if history.len() == HISTORY {
+                history.pop_front();
+            }
+            history.push_back(sample.clone());
+        }
+        // Nobody listening is fine
+        let _ = self.samples.send(sample);
+    }
+
+    /// Samples every `interval` until the task is dropped.
+    pub async fn run(self: Arc<Self>) {
+        let mut ticks = tokio::time::interval(self.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+
+            // Backends block (nmcli spawns a process), so keep them off the runtime
+            let interface = self.interface.clone();
+            match tokio::task::spawn_blocking(move || sample(&interface)).await {
+                Ok(sample) => self.record(sample),
+                Err(e) => eprintln!("Link monitor: sampling failed: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Output formats of the command-line interface.
+//!
+//! By default the CLI prints aligned tables meant for people. With
+//! `--output json` every command prints JSON instead, so scripts can consume
+//! it with `jq` rather than scraping columns:
+//!
+//! | Output    | Content                                                         |
+//! |-----------|-----------------------------------------------------------------|
+//! | Result    | One JSON document on stdout (array or object per command)       |
+//! | Streaming | One JSON object per line (`monitor`)                            |
+//! | Failure   | `{"error": {...}}` on stdout (see [`ErrorReport`]), exit code 1 |
+//!
+//! Progress messages are not printed in JSON mode.
+//!
+//! # Example
+//!
+//! ```
+//! use wifi_proxy::output::{ErrorReport, OutputFormat};
+//! use wifi_proxy::WifiProxyError;
+//!
+//! let format: OutputFormat = "json".parse().unwrap();
+//! assert_eq!(format, OutputFormat::Json);
+//!
+//! let error = anyhow::Error::from(WifiProxyError::NoUsbInterfaceFound);
+//! let report = ErrorReport::from_error(&error.context("Scan failed"));
+//! assert_eq!(report.kind, "no_usb_interface");
+//! assert_eq!(report.message, "Scan failed");
+//! assert_eq!(report.causes, ["No USB WiFi interface found"]);
+//! ```
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::WifiProxyError;
+
+/// How the CLI prints results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned, human-readable tables (default).
+    #[default]
+    Table,
+    /// JSON documents for scripts.
+    Json,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Json => "json",
+        })
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = WifiProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(WifiProxyError::UnknownOutputFormat(s.to_string())),
+        }
+    }
+}
+
+/// A failed command, as printed in JSON mode.
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    /// Stable error category, e.g. "interface_not_found" (see
+    /// [`WifiProxyError::kind`]), "io", "http", "config" or "other".
+    pub kind: &'static str,
+
+    /// The outermost error message.
+    pub message: String,
+
+    /// Underlying causes, outermost first.
+    pub causes: Vec<String>,
+}
+
+impl ErrorReport {
+    /// Describes an error and its causes.
+    ///
+    /// The kind is taken from the first [`WifiProxyError`] in the chain, so
+    /// context added on the way up does not hide it. Errors from other
+    /// sources are categorized by their type.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let kind = error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<WifiProxyError>() {
+                    Some(e.kind())
+                } else if cause.is::<std::io::Error>() {
+                    Some("io")
+                } else if cause.is::<reqwest::Error>() {
+                    Some("http")
+                } else if cause.is::<toml::de::Error>() || cause.is::<toml::ser::Error>() {
+                    Some("config")
+                } else if cause.is::<serde_json::Error>() {
+                    Some("json")
+                } else {
+                    None
+                }
+            })
+            .unwrap_or("other");
+
+        Self {
+            kind,
+            message: error.to_string(),
+            causes: error.chain().skip(1).map(|cause| cause.to_string()).collect(),
+        }
+    }
+}
+
+/// Prints a value as pretty-printed JSON on stdout.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Prints a value as a single line of JSON on stdout, for streamed output.
+pub fn print_json_line<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+/// Prints a failed command as `{"error": {...}}` on stdout.
+pub fn print_error(error: &anyhow::Error) {
+    #[derive(Serialize)]
+    struct Failure {
+        error: ErrorReport,
+    }
+
+    let failure = Failure {
+        error: ErrorReport::from_error(error),
+    };
+    // Serializing plain strings cannot fail
+    if let Ok(json) = serde_json::to_string_pretty(&failure) {
+        println!("{}", json);
+    }
+}
@@ -0,0 +1,543 @@
+//! Ordered, serialized delivery of commands to the robot.
+//!
+//! The ESP32 answers `/control` requests in whatever order they happen to
+//! finish. When every client request is proxied on its own, a quick press
+//! and release can overtake each other: `move val=3` arrives first, then
+//! `move val=1`, and the robot walks off. The [`CommandQueue`] puts a single
+//! worker in front of the robot that sends one command at a time, in arrival
+//! order, for every client of the proxy.
+//!
+//! # Lanes
+//!
+//! Commands wait in one of three lanes:
+//!
+//! - **Stop lane**: stop moves. Always served before the normal lane and
+//!   never refused. A stop also *supersedes* every move on its axis that is
+//!   still waiting in the normal lane: those moves were issued before the
+//!   stop, so sending them after it would set the robot moving again.
+//! - **Normal lane**: everything else, bounded to a configured depth.
+//! - **Idle lane**: health probes of the robot's root page (see
+//!   [`probe`](CommandQueue::probe)), sent only when both other lanes are
+//!   empty. Probing thus never delays a command nor puts a second request
+//!   in flight.
+//!
+//! # Overflow
+//!
+//! When the normal lane is full, a new command is refused at once with
+//! [`DeliveryError::QueueFull`] instead of waiting: a movement that starts
+//! seconds late is worse than one that never starts. Commands already
+//! waiting are never dropped to make room.
+//!
+//! # Sequence Numbers
+//!
+//! Every submitted command receives a sequence number, increasing in
+//! submission order, which tells clients the order the robot sees commands
+//! in and lets them match answers to commands.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use wifi_proxy::queue::{CommandQueue, DeliveryError};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let queue = CommandQueue::new(reqwest::Client::new(), "192.168.4.1", Duration::from_secs(5), 16);
+//!
+//!     // Without a running worker both commands stay queued
+//!     let forward = queue.submit("var=move&val=1&cmd=0");
+//!     let stop = queue.submit("var=move&val=3&cmd=0");
+//!     assert!(stop.seq() > forward.seq());
+//!
+//!     // The stop made the waiting forward move obsolete
+//!     assert!(matches!(forward.delivered().await, Err(DeliveryError::Superseded { .. })));
+//!     assert_eq!(queue.stats().waiting_stops, 1);
+//! }
+//! ```
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Notify};
+
+use crate::error::WifiProxyError;
+use crate::robot::{CommandSink, RobotCommand};
+
+/// The robot's answer to a delivered command.
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    /// Sequence number of the command; 0 for a probe.
+    pub seq: u64,
+
+    /// HTTP status code the robot answered with.
+    pub status: u16,
+
+    /// Response body.
+    pub body: String,
+
+    /// Time the command spent waiting for its turn.
+    pub waited: Duration,
+}
+
+/// Why a queued command did not produce an answer from the robot.
+#[derive(Debug, Clone)]
+pub enum DeliveryError {
+    /// The normal lane was full; the command was not queued.
+    QueueFull(usize),
+
+    /// A later stop on the same axis made the command obsolete before it
+    /// was sent.
+    Superseded {
+        /// Sequence number of the stop.
+        by: u64,
+    },
+
+    /// Pending commands were discarded, by the emergency stop or because
+    /// the link to the robot was lost.
+    Cancelled,
+
+    /// The robot could not be reached or did not answer in time.
+    Upstream {
+        /// Whether the failure was a timeout.
+        timeout: bool,
+
+        /// Human-readable description.
+        message: String,
+    },
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::QueueFull(depth) => write!(f, "{}", WifiProxyError::QueueFull(*depth)),
+            DeliveryError::Superseded { by } => write!(f, "Superseded by stop #{}", by),
+            DeliveryError::Cancelled => f.write_str("Cancelled before it was sent"),
+            DeliveryError::Upstream { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for DeliveryError {}
+
+/// Called after each command the worker sent, with the decoded command and
+/// whether the robot accepted it (answered with a 2xx status).
+pub type SentHook = Box<dyn Fn(&RobotCommand, bool) + Send + Sync>;
+
+/// Snapshot of the queue for status endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStats {
+    /// Commands that may wait in the normal lane.
+    pub depth: usize,
+
+    /// Commands waiting in the normal lane.
+    pub waiting: usize,
+
+    /// Stops waiting in the stop lane.
+    pub waiting_stops: usize,
+
+    /// Sequence number of the command being sent, if any.
+    pub in_flight: Option<u64>,
+
+    /// Highest sequence number handed out so far (0 before the first command).
+    pub last_seq: u64,
+
+    /// Commands the robot answered.
+    pub delivered: u64,
+
+    /// Commands that could not be delivered.
+    pub failed: u64,
+
+    /// Moves made obsolete by a later stop.
+    pub superseded: u64,
+
+    /// Commands discarded by the emergency stop or on link loss.
+    pub cancelled: u64,
+
+    /// Commands refused because the normal lane was full.
+    pub rejected: u64,
+}
+
+/// A command waiting for its turn.
+struct Job {
+    /// Sequence number.
+    seq: u64,
+
+    /// Query string to send verbatim.
+    query: String,
+
+    /// Decoded form of `query`, used to find superseded moves.
+    command: Option<RobotCommand>,
+
+    /// Whether this is a probe of the root page rather than a command.
+    probe: bool,
+
+    /// Time the command was submitted.
+    submitted: Instant,
+
+    /// Receives the outcome.
+    reply: oneshot::Sender<Result<Delivery, DeliveryError>>,
+}
+
+impl Job {
+    /// Resolves the job without sending it.
+    fn resolve(self, error: DeliveryError) {
+        // The submitter may have gone away; nothing to tell then
+        let _ = self.reply.send(Err(error));
+    }
+}
+
+/// Mutable state behind the queue's lock.
+#[derive(Default)]
+struct Lanes {
+    /// Stops, served first.
+    stop: VecDeque<Job>,
+
+    /// Everything else, in arrival order.
+    normal: VecDeque<Job>,
+
+    /// Probes, served when no command waits.
+    idle: VecDeque<Job>,
+
+    /// Sequence number of the command being sent.
+    in_flight: Option<u64>,
+
+    /// Commands the robot answered.
+    delivered: u64,
+
+    /// Commands that could not be delivered.
+    failed: u64,
+
+    /// Moves made obsolete by a later stop.
+    superseded: u64,
+
+    /// Commands discarded by the emergency stop or on link loss.
+    cancelled: u64,
+
+    /// Commands refused because the normal lane was full.
+    rejected: u64,
+}
+
+/// A submitted command, resolving to the robot's answer.
+pub struct Ticket {
+    /// Sequence number of the command.
+    seq: u64,
+
+    /// Receives the outcome from the worker.
+    reply: oneshot::Receiver<Result<Delivery, DeliveryError>>,
+}
+
+impl Ticket {
+    /// Returns the command's sequence number.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Waits until the command was sent and answered, or dropped.
+    pub async fn delivered(self) -> Result<Delivery, DeliveryError> {
+        self.reply.await.unwrap_or_else(|_| {
+            Err(DeliveryError::Upstream {
+                timeout: false,
+                message: "command queue stopped".into(),
+            })
+        })
+    }
+}
+
+/// Serializes command delivery to one robot.
+///
+/// Commands are accepted by [`submit`](Self::submit) and sent by a single
+/// worker started with [`run`](Self::run), so at most one request to the
+/// robot is in flight at any time.
+pub struct CommandQueue {
+    /// Pooled HTTP client used for every command.
+    http: reqwest::Client,
+
+    /// Gateway address, optionally with a port; swapped by [`set_gateway`](Self::set_gateway).
+    gateway: Mutex<String>,
+
+    /// Deadline for each command, from sending to reading the body.
+    timeout: Duration,
+
+    /// Commands that may wait in the normal lane.
+    depth: usize,
+
+    /// Last sequence number handed out.
+    seq: AtomicU64,
+
+    /// Waiting commands and counters.
+    lanes: Mutex<Lanes>,
+
+    /// Wakes the worker when a command is queued.
+    wake: Notify,
+
+    /// Told about every command sent; see [`on_sent`](Self::on_sent).
+    on_sent: Option<SentHook>,
+}
+
+impl CommandQueue {
+    /// Creates an empty queue; commands are sent once [`run`](Self::run) is spawned.
+    ///
+    /// # Arguments
+    /// * `http` - Pooled client shared with the rest of the server
+    /// * `gateway` - Host or `host:port` of the robot (e.g., "192.168.4.1")
+    /// * `timeout` - Deadline for each command
+    /// * `depth` - Commands that may wait in the normal lane (at least 1)
+    pub fn new(http: reqwest::Client, gateway: &str, timeout: Duration, depth: usize) -> Self {
+        Self {
+            http,
+            gateway: Mutex::new(gateway.to_string()),
+            timeout,
+            depth: depth.max(1),
+            seq: AtomicU64::new(0),
+            lanes: Mutex::new(Lanes::default()),
+            wake: Notify::new(),
+            on_sent: None,
+        }
+    }
+
+    /// Registers a hook called after every command the worker sent.
+    ///
+    /// The hook runs before the submitter learns the outcome, so state it
+    /// updates is current by the time the answer is relayed. Commands that
+    /// were never sent (refused, superseded or cancelled) do not reach it.
+    ///
+    /// # Arguments
+    /// * `hook` - Receives the decoded command and whether the robot accepted it
+    pub fn on_sent(mut self, hook: impl Fn(&RobotCommand, bool) + Send + Sync + 'static) -> Self {
+        self.on_sent = Some(Box::new(hook));
+        self
+    }
+
+    /// Returns the gateway commands are sent to.
+    pub fn gateway(&self) -> String {
+        self.gateway.lock().unwrap().clone()
+    }
+
+    /// Sends all further commands to a new gateway.
+    ///
+    /// Waiting commands stay queued and go to the new gateway; a command
+    /// already in flight completes against the old one.
+    pub fn set_gateway(&self, gateway: &str) {
+        *self.gateway.lock().unwrap() = gateway.to_string();
+    }
+
+    /// Queues a `/control` query string for delivery.
+    ///
+    /// Stop moves enter the stop lane and supersede waiting moves on their
+    /// axis. Other commands enter the normal lane, or are refused at once if
+    /// it is full; the returned ticket then resolves to
+    /// [`DeliveryError::QueueFull`].
+    pub fn submit(&self, query: &str) -> Ticket {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst) + 1;
+        let (reply, receiver) = oneshot::channel();
+        let command = RobotCommand::from_query(query).ok();
+        let job = Job {
+            seq,
+            query: query.to_string(),
+            command,
+            probe: false,
+            submitted: Instant::now(),
+            reply,
+        };
+
+        let mut lanes = self.lanes.lock().unwrap();
+        match command {
+            Some(RobotCommand::Move(stop)) if stop.is_stop() => {
+                // Drop waiting moves of the axis: they would undo the stop
+                let (obsolete, keep): (VecDeque<Job>, VecDeque<Job>) = std::mem::take(&mut lanes.normal)
+                    .into_iter()
+                    .partition(|job| {
+                        matches!(job.command, Some(RobotCommand::Move(dir)) if dir.stop() == stop)
+                    });
+                lanes.normal = keep;
+                lanes.superseded += obsolete.len() as u64;
+                for job in obsolete {
+                    job.resolve(DeliveryError::Superseded { by: seq });
+                }
+                lanes.stop.push_back(job);
+            }
+            _ if lanes.normal.len() >= self.depth => {
+                lanes.rejected += 1;
+                job.resolve(DeliveryError::QueueFull(self.depth));
+            }
+            _ => lanes.normal.push_back(job),
+        }
+        drop(lanes);
+
+        self.wake.notify_one();
+        Ticket { seq, reply: receiver }
+    }
+
+    /// Queues a request for the robot's root page in the idle lane.
+    ///
+    /// Used by the health prober to check that the robot's web server
+    /// answers. The probe is sent once no command is waiting, and is not
+    /// counted in the delivery statistics. A probe whose ticket was dropped
+    /// before its turn is not sent.
+    ///
+    /// # Returns
+    /// A ticket with sequence number 0, resolving to the robot's answer.
+    pub fn probe(&self) -> Ticket {
+        let (reply, receiver) = oneshot::channel();
+        self.lanes.lock().unwrap().idle.push_back(Job {
+            seq: 0,
+            query: String::new(),
+            command: None,
+            probe: true,
+            submitted: Instant::now(),
+            reply,
+        });
+
+        self.wake.notify_one();
+        Ticket { seq: 0, reply: receiver }
+    }
+
+    /// Discards every command waiting in the normal lane.
+    ///
+    /// Stops already queued are kept.
+    ///
+    /// # Returns
+    /// The number of commands discarded.
+    pub fn cancel_waiting(&self) -> usize {
+        let mut lanes = self.lanes.lock().unwrap();
+        let cancelled = std::mem::take(&mut lanes.normal);
+        lanes.cancelled += cancelled.len() as u64;
+        drop(lanes);
+
+        let count = cancelled.len();
+        for job in cancelled {
+            job.resolve(DeliveryError::Cancelled);
+        }
+        count
+    }
+
+    /// Returns the lane lengths and counters.
+    pub fn stats(&self) -> QueueStats {
+        let lanes = self.lanes.lock().unwrap();
+        QueueStats {
+            depth: self.depth,
+            waiting: lanes.normal.len(),
+            waiting_stops: lanes.stop.len(),
+            in_flight: lanes.in_flight,
+            last_seq: self.seq.load(Ordering::SeqCst),
+            delivered: lanes.delivered,
+            failed: lanes.failed,
+            superseded: lanes.superseded,
+            cancelled: lanes.cancelled,
+            rejected: lanes.rejected,
+        }
+    }
+
+    /// Sends queued commands one at a time until the task is dropped.
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            // Register for wake-ups before looking, so none is missed
+            let woken = self.wake.notified();
+            let Some(job) = self.next_job() else {
+                woken.await;
+                continue;
+            };
+
+            let waited = job.submitted.elapsed();
+            let result = self.deliver(&job).await.map(|(status, body)| Delivery {
+                seq: job.seq,
+                status,
+                body,
+                waited,
+            });
+
+            let mut lanes = self.lanes.lock().unwrap();
+            lanes.in_flight = None;
+            match &result {
+                _ if job.probe => {}
+                Ok(_) => lanes.delivered += 1,
+                Err(_) => lanes.failed += 1,
+            }
+            drop(lanes);
+
+            if let (Some(hook), Some(command)) = (&self.on_sent, &job.command) {
+                let accepted = matches!(&result, Ok(delivery) if (200..300).contains(&delivery.status));
+                hook(command, accepted);
+            }
+            let _ = job.reply.send(result);
+        }
+    }
+
+    /// Takes the next command to send, stops first and probes last.
+    fn next_job(&self) -> Option<Job> {
+        let mut lanes = self.lanes.lock().unwrap();
+        if let Some(job) = lanes.stop.pop_front().or_else(|| lanes.normal.pop_front()) {
+            lanes.in_flight = Some(job.seq);
+            return Some(job);
+        }
+
+        // Probes nobody waits for any more are not worth a request
+        while let Some(job) = lanes.idle.pop_front() {
+            if !job.reply.is_closed() {
+                return Some(job);
+            }
+        }
+        None
+    }
+
+    /// Sends one command (or probe) to the robot and reads its answer.
+    async fn deliver(&self, job: &Job) -> Result<(u16, String), DeliveryError> {
+        let url = if job.probe {
+            format!("http://{}/", self.gateway())
+        } else {
+            format!("http://{}/control?{}", self.gateway(), job.query)
+        };
+        // Include the cause (e.g., "Connection refused"), not just "error sending request"
+        let upstream = |e: reqwest::Error| DeliveryError::Upstream {
+            timeout: e.is_timeout(),
+            message: format!("{:#}", anyhow::Error::new(e)),
+        };
+
+        let response = self
+            .http
+            .get(url)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(upstream)?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(upstream)?;
+        Ok((status, body))
+    }
+}
+
+impl CommandSink for CommandQueue {
+    /// Queues the query and waits for the robot's answer.
+    ///
+    /// A superseded command counts as handled: the stop that replaced it
+    /// has the final word. Cancelled commands fail with
+    /// [`WifiProxyError::FetchFailed`], refused ones with
+    /// [`WifiProxyError::QueueFull`].
+    fn send_query(&self, query: &str) -> impl Future<Output = Result<String>> + Send {
+        let ticket = self.submit(query);
+        async move {
+            match ticket.delivered().await {
+                Ok(delivery) if (200..300).contains(&delivery.status) => Ok(delivery.body),
+                Ok(delivery) => Err(WifiProxyError::FetchFailed(format!(
+                    "robot answered {} to command #{}",
+                    delivery.status, delivery.seq
+                ))
+                .into()),
+                Err(DeliveryError::Superseded { .. }) => Ok(String::new()),
+                Err(DeliveryError::QueueFull(depth)) => Err(WifiProxyError::QueueFull(depth).into()),
+                Err(e @ (DeliveryError::Cancelled | DeliveryError::Upstream { .. })) => {
+                    Err(WifiProxyError::FetchFailed(e.to_string()).into())
+                }
+            }
+        }
+    }
+
+    fn cancel_pending(&self) -> usize {
+        self.cancel_waiting()
+    }
+}
@@ -0,0 +1,677 @@
+//! Recording of the robot's camera stream to disk.
+//!
+//! Frames are taken from a [`StreamHub`](crate::mjpeg::StreamHub)
+//! subscription, so a recording shares the upstream connection with anyone
+//! watching `/stream` at the same time.
+//!
+//! # Formats
+//!
+//! | Format | Output                                        | Index                    |
+//! |--------|-----------------------------------------------|--------------------------|
+//! | `avi`  | MJPEG-in-AVI file playable by common players  | `<name>.index.csv` beside it |
+//! | `jpeg` | Directory of `<frame>_<timestamp>.jpg` files  | `index.csv` inside it    |
+//!
+//! The CSV index has one row per frame with the columns `frame`, `sequence`,
+//! `timestamp` (Unix seconds), `offset_ms` (since the first frame) and
+//! `bytes`. Gaps in `sequence` show frames that were dropped because the disk
+//! could not keep up.
+//!
+//! Recordings never overwrite: an output or index that already exists is an
+//! error.
+//!
+//! # AVI Limits
+//!
+//! AVI 1.0 stores sizes and offsets as 32-bit numbers, and many players stop
+//! reading a RIFF file past 1 GiB. A recording that reaches [`AVI_MAX_LEN`]
+//! is finished at the last frame that fits and ends with an error; start a
+//! new one to keep recording.
+//!
+//! The header is rewritten every [`AVI_HEADER_REFRESH`] frames. If the
+//! process is killed mid-recording, the file lacks its `idx1` index and the
+//! frames since the last refresh, but the header describes everything before
+//! them, so players that read unindexed AVI (ffmpeg, VLC) can still play it.
+//! The CSV index is flushed along with the header.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use tokio_util::sync::CancellationToken;
+//! use wifi_proxy::mjpeg::StreamHub;
+//! use wifi_proxy::recorder::{record, RecordFormat};
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let hub = Arc::new(StreamHub::new(reqwest::Client::new(), "http://192.168.4.1:81/stream"));
+//!     let output = Path::new("run.avi");
+//!
+//!     // Record ten seconds of video
+//!     let summary = record(
+//!         hub.subscribe(),
+//!         output,
+//!         RecordFormat::from_path(output),
+//!         Some(Duration::from_secs(10)),
+//!         CancellationToken::new(),
+//!     )
+//!     .await?;
+//!     println!("{} frames", summary.frames);
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::WifiProxyError;
+use crate::mjpeg::{unix_timestamp, Frame, Subscription};
+
+/// Time to wait for the first frame before giving up on a recording.
+const FIRST_FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Header written at the top of every CSV index.
+const INDEX_HEADER: &str = "frame,sequence,timestamp,offset_ms,bytes";
+
+/// Longest recording length accepted, in seconds (one day).
+pub const MAX_DURATION_SECS: f64 = 86_400.0;
+
+/// Largest AVI file written, in bytes (1 GiB, the AVI 1.0 compatibility limit).
+pub const AVI_MAX_LEN: u64 = 1 << 30;
+
+/// Number of frames between two rewrites of the AVI header.
+pub const AVI_HEADER_REFRESH: usize = 50;
+
+/// Container to record into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    /// A single MJPEG-in-AVI file.
+    Avi,
+    /// A directory of individual JPEG files.
+    Jpeg,
+}
+
+impl RecordFormat {
+    /// Picks the format from an output path: `.avi` files record to AVI,
+    /// anything else is treated as a directory of JPEGs.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("avi") => RecordFormat::Avi,
+            _ => RecordFormat::Jpeg,
+        }
+    }
+}
+
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RecordFormat::Avi => "avi",
+            RecordFormat::Jpeg => "jpeg",
+        })
+    }
+}
+
+impl FromStr for RecordFormat {
+    type Err = WifiProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "avi" => Ok(RecordFormat::Avi),
+            "jpeg" | "jpg" => Ok(RecordFormat::Jpeg),
+            _ => Err(WifiProxyError::Recording(format!("unknown format '{}'", s))),
+        }
+    }
+}
+
+/// Validates a recording length given in seconds.
+///
+/// # Returns
+/// - `Ok(Duration)` for a length above zero and at most [`MAX_DURATION_SECS`]
+/// - `Err(WifiProxyError::Recording)` for zero, negative, NaN or longer lengths
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// use wifi_proxy::recorder::duration_from_secs;
+///
+/// assert_eq!(duration_from_secs(1.5).unwrap(), Duration::from_millis(1500));
+/// assert!(duration_from_secs(-1.0).is_err());
+/// assert!(duration_from_secs(1e300).is_err());
+/// assert!(duration_from_secs(f64::NAN).is_err());
+/// ```
+pub fn duration_from_secs(secs: f64) -> Result<Duration, WifiProxyError> {
+    if !(secs > 0.0 && secs <= MAX_DURATION_SECS) {
+        return Err(WifiProxyError::Recording(format!(
+            "duration must be above 0 and at most {} seconds",
+            MAX_DURATION_SECS
+        )));
+    }
+    Duration::try_from_secs_f64(secs).map_err(|e| WifiProxyError::Recording(e.to_string()))
+}
+
+/// Outcome of a finished recording.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    /// AVI file or JPEG directory that was written.
+    pub output: PathBuf,
+
+    /// CSV index of frame times.
+    pub index: PathBuf,
+
+    /// Container format used.
+    pub format: RecordFormat,
+
+    /// Number of frames written.
+    pub frames: u64,
+
+    /// Number of frames skipped because the recorder fell behind.
+    pub dropped: u64,
+
+    /// Time between the first and the last recorded frame, in seconds.
+    pub duration_secs: f64,
+}
+
+/// Records frames from a subscription until stopped or the limit is reached.
+///
+/// # Arguments
+/// * `subscription` - Source of frames (keeps the upstream open while recording)
+/// * `output` - AVI file or JPEG directory to create
+/// * `format` - Container to write
+/// * `limit` - Maximum recording length; `None` records until `stop` is cancelled
+/// * `stop` - Cancel to end the recording early
+///
+/// # Returns
+/// - `Ok(RecordingSummary)` once the output and index are complete
+/// - `Err(WifiProxyError::Recording)` if no frame arrives from the camera,
+///   or an AVI file reaches [`AVI_MAX_LEN`] (the file is still finished)
+/// - `Err` if the output already exists or cannot be written
+pub async fn record(
+    mut subscription: Subscription,
+    output: &Path,
+    format: RecordFormat,
+    limit: Option<Duration>,
+    stop: CancellationToken,
+) -> Result<RecordingSummary> {
+    // Wait for the camera before creating any files
+    let first = tokio::select! {
+        _ = stop.cancelled() => {
+            return Err(WifiProxyError::Recording("stopped before the first frame".into()).into())
+        }
+        first = subscription.recv_timeout(FIRST_FRAME_TIMEOUT) => {
+            first.map_err(|e| WifiProxyError::Recording(e.to_string()))?
+        }
+    };
+
+    let mut sink: Box<dyn FrameSink> = match format {
+        RecordFormat::Avi => Box::new(AviWriter::create(output)?),
+        RecordFormat::Jpeg => Box::new(JpegDirWriter::create(output)?),
+    };
+    let mut index = IndexWriter::create(&sink.index_path())?;
+
+    // The limit counts from the first frame
+    let deadline = limit.map(|limit| tokio::time::Instant::now() + limit);
+    let until_deadline = async {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(until_deadline);
+
+    let mut frame = Some(first);
+    while let Some(current) = frame.take() {
+        // Keep what was recorded so far playable, then report why it ended
+        if let Err(e) = sink.write_frame(&current) {
+            let _ = index.finish();
+            let _ = sink.finish();
+            return Err(e);
+        }
+        index.write(&current)?;
+        if index.frames.is_multiple_of(AVI_HEADER_REFRESH as u64) {
+            index.flush()?;
+        }
+
+        frame = tokio::select! {
+            _ = stop.cancelled() => None,
+            _ = &mut until_deadline => None,
+            next = subscription.recv() => next,
+        };
+    }
+
+    let index = index.finish()?;
+    sink.finish()?;
+
+    Ok(RecordingSummary {
+        output: output.to_path_buf(),
+        index: index.path,
+        format,
+        frames: index.frames,
+        dropped: index.dropped,
+        duration_secs: index.duration.as_secs_f64(),
+    })
+}
+
+/// Destination for recorded frames.
+trait FrameSink: Send {
+    /// Writes one frame.
+    fn write_frame(&mut self, frame: &Frame) -> Result<()>;
+
+    /// Returns where the CSV index for this output belongs.
+    fn index_path(&self) -> PathBuf;
+
+    /// Completes the output (e.g., writes headers that depend on the frame count).
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Writes the CSV index of frame times and tracks dropped frames.
+struct IndexWriter {
+    /// Buffered CSV file.
+    file: BufWriter<File>,
+
+    /// Path of the CSV file.
+    path: PathBuf,
+
+    /// Frames written so far.
+    frames: u64,
+
+    /// Frames missing between consecutive sequence numbers.
+    dropped: u64,
+
+    /// Sequence number and time of the first frame.
+    first: Option<(u64, SystemTime)>,
+
+    /// Sequence number of the previous frame.
+    last_sequence: u64,
+
+    /// Offset of the latest frame from the first one.
+    duration: Duration,
+}
+
+impl IndexWriter {
+    /// Creates the index file and writes the header row.
+    fn create(path: &Path) -> Result<Self> {
+        let mut file = BufWriter::new(create_new(path)?);
+        writeln!(file, "{}", INDEX_HEADER)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            frames: 0,
+            dropped: 0,
+            first: None,
+            last_sequence: 0,
+            duration: Duration::ZERO,
+        })
+    }
+
+    /// Appends a row for the frame.
+    fn write(&mut self, frame: &Frame) -> Result<()> {
+        let (_, started) = *self.first.get_or_insert((frame.sequence, frame.timestamp));
+        if self.frames > 0 && frame.sequence > self.last_sequence + 1 {
+            self.dropped += frame.sequence - self.last_sequence - 1;
+        }
+        self.duration = frame.timestamp.duration_since(started).unwrap_or_default();
+
+        self.frames += 1;
+        self.last_sequence = frame.sequence;
+        writeln!(
+            self.file,
+            "{},{},{},{},{}",
+            self.frames,
+            frame.sequence,
+            unix_timestamp(frame.timestamp),
+            self.duration.as_millis(),
+            frame.jpeg.len()
+        )?;
+        Ok(())
+    }
+
+    /// Writes buffered rows to disk.
+    fn flush(&mut self) -> Result<()> {
+        self.file.flush()?;
+        Ok(())
+    }
+
+    /// Flushes the index to disk.
+    fn finish(mut self) -> Result<Self> {
+        self.file.flush()?;
+        Ok(self)
+    }
+}
+
+/// Writes frames as numbered, timestamped JPEG files in a directory.
+struct JpegDirWriter {
+    /// Output directory.
+    dir: PathBuf,
+
+    /// Frames written so far.
+    count: u64,
+}
+
+impl JpegDirWriter {
+    /// Creates the output directory (and any missing parents).
+    ///
+    /// The directory itself must not exist yet, so an earlier recording's
+    /// frames and index are never mixed with or overwritten by this one.
+    fn create(dir: &Path) -> Result<Self> {
+        if let Some(parent) = dir.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::create_dir(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            count: 0,
+        })
+    }
+}
+
+impl FrameSink for JpegDirWriter {
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        self.count += 1;
+        let name = format!("{:06}_{}.jpg", self.count, unix_timestamp(frame.timestamp));
+        fs::write(self.dir.join(name), &frame.jpeg).context("Failed to write frame")?;
+        Ok(())
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.csv")
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Size of the fixed AVI header written before the `movi` list.
+///
+/// RIFF header (12) + `hdrl` list (12) + `avih` (8 + 56) + `strl` list (12)
+/// + `strh` (8 + 56) + `strf` (8 + 40) + `movi` list header (12).
+const AVI_HEADER_LEN: u64 = 12 + 12 + 64 + 12 + 64 + 48 + 12;
+
+/// One entry of the AVI `idx1` chunk.
+struct AviIndexEntry {
+    /// Offset of the chunk from the `movi` fourcc.
+    offset: u32,
+    /// Size of the JPEG data.
+    size: u32,
+}
+
+/// Writes frames into an MJPEG-in-AVI (RIFF) file.
+///
+/// The header is written up front, refreshed every [`AVI_HEADER_REFRESH`]
+/// frames and patched by [`FrameSink::finish`] once the final frame count,
+/// dimensions and average frame interval are known. The file is capped at
+/// [`AVI_MAX_LEN`], so every size and offset fits the format's 32 bits.
+struct AviWriter {
+    /// Buffered output file.
+    file: BufWriter<File>,
+
+    /// Path of the AVI file (used to place the index beside it).
+    path: PathBuf,
+
+    /// One entry per written frame, for the `idx1` chunk.
+    index: Vec<AviIndexEntry>,
+
+    /// Bytes written inside the `movi` list after its fourcc.
+    movi_len: u64,
+
+    /// Largest frame written, in bytes.
+    max_frame: u32,
+
+    /// Image size, taken from the first frame that declares it.
+    dimensions: Option<(u16, u16)>,
+
+    /// Times of the first and latest frame.
+    span: Option<(SystemTime, SystemTime)>,
+}
+
+impl AviWriter {
+    /// Creates the file and reserves space for the header.
+    fn create(path: &Path) -> Result<Self> {
+        let mut writer = Self {
+            file: BufWriter::new(create_new(path)?),
+            path: path.to_path_buf(),
+            index: Vec::new(),
+            movi_len: 0,
+            max_frame: 0,
+            dimensions: None,
+            span: None,
+        };
+
+        // A valid (empty) header from the start, in case the process dies
+        writer.write_header(false)?;
+        Ok(writer)
+    }
+
+    /// Returns the file length once the frame and its index entry are added.
+    fn len_with(&self, frame_len: u64) -> u64 {
+        let chunk = 8 + frame_len + frame_len % 2;
+        let idx1 = 8 + 16 * (self.index.len() as u64 + 1);
+        AVI_HEADER_LEN + self.movi_len + chunk + idx1
+    }
+
+    /// Writes the RIFF header and `movi` list header at the start of the file,
+    /// then returns to the end.
+    ///
+    /// # Arguments
+    /// * `indexed` - Whether the `idx1` chunk has been written after `movi`
+    ///   (only when finishing; provisional headers describe `movi` alone)
+    fn write_header(&mut self, indexed: bool) -> Result<()> {
+        // All sizes fit in 32 bits: write_frame keeps the file below AVI_MAX_LEN
+        let idx_len = if indexed { 8 + self.index.len() as u64 * 16 } else { 0 };
+        let riff_len = (AVI_HEADER_LEN - 8 + self.movi_len + idx_len) as u32;
+        let frames = self.index.len() as u32;
+        let (width, height) = self.dimensions.unwrap_or((0, 0));
+
+        // Average frame interval over the recording (10 fps if unknown)
+        let micros_per_frame = match self.span {
+            Some((first, last)) if frames > 1 => {
+                let total = last.duration_since(first).unwrap_or_default();
+                (total.as_micros() / (frames as u128 - 1)).max(1) as u32
+            }
+            _ => 100_000,
+        };
+
+        let mut h = Vec::with_capacity(AVI_HEADER_LEN as usize);
+        h.extend_from_slice(b"RIFF");
+        put_u32(&mut h, riff_len);
+        h.extend_from_slice(b"AVI ");
+
+        // hdrl list: main header and one video stream
+        h.extend_from_slice(b"LIST");
+        put_u32(&mut h, 4 + 64 + 12 + 64 + 48);
+        h.extend_from_slice(b"hdrl");
+
+        h.extend_from_slice(b"avih");
+        put_u32(&mut h, 56);
+        put_u32(&mut h, micros_per_frame); // dwMicroSecPerFrame
+        put_u32(&mut h, 0); // dwMaxBytesPerSec
+        put_u32(&mut h, 0); // dwPaddingGranularity
+        put_u32(&mut h, if indexed { 0x10 } else { 0 }); // dwFlags: AVIF_HASINDEX
+        put_u32(&mut h, frames); // dwTotalFrames
+        put_u32(&mut h, 0); // dwInitialFrames
+        put_u32(&mut h, 1); // dwStreams
+        put_u32(&mut h, self.max_frame); // dwSuggestedBufferSize
+        put_u32(&mut h, width as u32); // dwWidth
+        put_u32(&mut h, height as u32); // dwHeight
+        h.extend_from_slice(&[0; 16]); // dwReserved
+
+        h.extend_from_slice(b"LIST");
+        put_u32(&mut h, 4 + 64 + 48);
+        h.extend_from_slice(b"strl");
+
+        h.extend_from_slice(b"strh");
+        put_u32(&mut h, 56);
+        h.extend_from_slice(b"vids"); // fccType
+        h.extend_from_slice(b"MJPG"); // fccHandler
+        put_u32(&mut h, 0); // dwFlags
+        put_u32(&mut h, 0); // wPriority + wLanguage
+        put_u32(&mut h, 0); // dwInitialFrames
+        put_u32(&mut h, micros_per_frame); // dwScale
+        put_u32(&mut h, 1_000_000); // dwRate (rate / scale = fps)
+        put_u32(&mut h, 0); // dwStart
+        put_u32(&mut h, frames); // dwLength
+        put_u32(&mut h, self.max_frame); // dwSuggestedBufferSize
+        put_u32(&mut h, u32::MAX); // dwQuality: default
+        put_u32(&mut h, 0); // dwSampleSize
+        put_u16(&mut h, 0); // rcFrame.left
+        put_u16(&mut h, 0); // rcFrame.top
+        put_u16(&mut h, width); // rcFrame.right
+        put_u16(&mut h, height); // rcFrame.bottom
+
+        h.extend_from_slice(b"strf");
+        put_u32(&mut h, 40);
+        put_u32(&mut h, 40); // biSize
+        put_u32(&mut h, width as u32); // biWidth
+        put_u32(&mut h, height as u32); // biHeight
+        put_u16(&mut h, 1); // biPlanes
+        put_u16(&mut h, 24); // biBitCount
+        h.extend_from_slice(b"MJPG"); // biCompression
+        put_u32(&mut h, width as u32 * height as u32 * 3); // biSizeImage
+        h.extend_from_slice(&[0; 16]); // resolution and palette fields
+
+        // movi list header; frames follow it directly
+        h.extend_from_slice(b"LIST");
+        put_u32(&mut h, (4 + self.movi_len) as u32);
+        h.extend_from_slice(b"movi");
+
+        debug_assert_eq!(h.len() as u64, AVI_HEADER_LEN);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&h)?;
+        self.file.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+}
+
+impl FrameSink for AviWriter {
+    fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        if self.len_with(frame.jpeg.len() as u64) > AVI_MAX_LEN {
+            return Err(WifiProxyError::Recording(format!(
+                "{} reached the AVI size limit of {} MiB after {} frames",
+                self.path.display(),
+                AVI_MAX_LEN >> 20,
+                self.index.len()
+            ))
+            .into());
+        }
+
+        let size = frame.jpeg.len() as u32;
+        let padding = (size % 2) as usize;
+
+        // Offsets in idx1 are relative to the "movi" fourcc
+        self.index.push(AviIndexEntry {
+            offset: (4 + self.movi_len) as u32,
+            size,
+        });
+
+        self.file.write_all(b"00dc")?;
+        self.file.write_all(&size.to_le_bytes())?;
+        self.file.write_all(&frame.jpeg)?;
+        self.file.write_all(&[0; 1][..padding])?;
+        self.movi_len += 8 + size as u64 + padding as u64;
+
+        self.max_frame = self.max_frame.max(size);
+        if self.dimensions.is_none() {
+            self.dimensions = jpeg_dimensions(&frame.jpeg);
+        }
+        self.span = Some(match self.span {
+            Some((first, _)) => (first, frame.timestamp),
+            None => (frame.timestamp, frame.timestamp),
+        });
+
+        // Keep the header current so a killed recording stays playable
+        if self.index.len().is_multiple_of(AVI_HEADER_REFRESH) {
+            self.write_header(false)?;
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.path.with_extension("index.csv")
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        // idx1 chunk: one keyframe entry per frame
+        let idx_len = self.index.len() as u64 * 16;
+        self.file.write_all(b"idx1")?;
+        self.file.write_all(&(idx_len as u32).to_le_bytes())?;
+        for entry in &self.index {
+            let mut e = Vec::with_capacity(16);
+            e.extend_from_slice(b"00dc");
+            put_u32(&mut e, 0x10); // AVIIF_KEYFRAME
+            put_u32(&mut e, entry.offset);
+            put_u32(&mut e, entry.size);
+            self.file.write_all(&e)?;
+        }
+
+        self.write_header(true)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Creates a file that must not exist yet, and any missing parent directories.
+fn create_new(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))
+}
+
+/// Appends a little-endian `u32`.
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Appends a little-endian `u16`.
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Reads the width and height from a JPEG's start-of-frame marker.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::recorder::jpeg_dimensions;
+///
+/// // SOI, then a baseline SOF0 segment for a 320x240 image
+/// let jpeg = [0xFF, 0xD8, 0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00, 0xF0, 0x01, 0x40, 0x01, 0x01, 0x11, 0x00];
+/// assert_eq!(jpeg_dimensions(&jpeg), Some((320, 240)));
+/// ```
+pub fn jpeg_dimensions(jpeg: &[u8]) -> Option<(u16, u16)> {
+    if jpeg.get(..2)? != [0xFF, 0xD8] {
+        return None;
+    }
+
+    // Walk the marker segments until a start-of-frame marker
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xFF {
+            return None;
+        }
+        let marker = jpeg[pos + 1];
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+
+        // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let sof = jpeg.get(pos + 5..pos + 9)?;
+            let height = u16::from_be_bytes([sof[0], sof[1]]);
+            let width = u16::from_be_bytes([sof[2], sof[3]]);
+            return Some((width, height));
+        }
+        pos += 2 + len;
+    }
+    None
+}
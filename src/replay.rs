@@ -0,0 +1,347 @@
+//! Recording of robot commands and timed replay (macros).
+//!
+//! While recording, every command forwarded by the proxy server is appended
+//! to a JSONL file with its time. Replaying the file sends the same commands
+//! to a robot with the original spacing, optionally faster or slower, which
+//! lets a routine driven once from the gamepad be reproduced exactly.
+//!
+//! # File Format
+//!
+//! One JSON object per line:
+//!
+//! ```text
+//! {"t":0.0,"time":"1760000000.120","query":"var=move&val=1&cmd=0","command":{"move":"forward"}}
+//! {"t":1.532,"time":"1760000001.652","query":"var=move&val=3&cmd=0","command":{"move":"stop_linear"}}
+//! ```
+//!
+//! - `t` - Seconds since the recording started
+//! - `time` - Unix seconds with milliseconds when the command was sent
+//! - `query` - The `/control` query string exactly as forwarded
+//! - `command` - The decoded [`RobotCommand`], omitted when the query is not
+//!   one this crate models (it is still replayed verbatim)
+//!
+//! # Safety
+//!
+//! A replay always ends by sending both stop commands, whether it completed,
+//! was aborted, or failed part way, so the robot is never left walking.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use tokio_util::sync::CancellationToken;
+//! use wifi_proxy::replay::{load, replay};
+//! use wifi_proxy::RobotClient;
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let robot = RobotClient::new("192.168.4.1");
+//!     let commands = load(Path::new("demo.jsonl"))?;
+//!
+//!     // Play the routine back at double speed
+//!     let summary = replay(&robot, &commands, 2.0, CancellationToken::new()).await?;
+//!     println!("sent {} of {} commands", summary.sent, summary.commands);
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::WifiProxyError;
+use crate::mjpeg::unix_timestamp;
+use crate::robot::{CommandSink, RobotCommand};
+
+/// Latest command time accepted in a recording, in seconds (one week).
+pub const MAX_TIME_SECS: f64 = 7.0 * 86_400.0;
+
+/// Slowest playback speed accepted.
+pub const MIN_SPEED: f64 = 0.01;
+
+/// Fastest playback speed accepted.
+pub const MAX_SPEED: f64 = 100.0;
+
+/// One line of a command recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCommand {
+    /// Seconds since the recording started.
+    pub t: f64,
+
+    /// Unix seconds (with milliseconds) when the command was sent.
+    #[serde(default)]
+    pub time: String,
+
+    /// The `/control` query string, replayed verbatim.
+    pub query: String,
+
+    /// Decoded form of `query`, for readability; not used when replaying.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<RobotCommand>,
+}
+
+/// Outcome of a finished command recording.
+#[derive(Debug, Clone, Serialize)]
+pub struct MacroSummary {
+    /// JSONL file that was written.
+    pub output: PathBuf,
+
+    /// Number of commands recorded.
+    pub commands: u64,
+
+    /// Time from starting to finishing the recording, in seconds.
+    pub duration_secs: f64,
+}
+
+/// Outcome of a replay.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaySummary {
+    /// Number of commands in the recording.
+    pub commands: usize,
+
+    /// Number of commands sent before the replay ended.
+    pub sent: usize,
+
+    /// Whether the replay was stopped before the last command.
+    pub aborted: bool,
+
+    /// Time the replay took, including the final stop commands, in seconds.
+    pub duration_secs: f64,
+}
+
+/// Appends commands to a JSONL file as they are sent.
+///
+/// Shared between request handlers; every line is flushed immediately so a
+/// crash loses at most the command being written.
+pub struct CommandRecorder {
+    /// File being written.
+    output: PathBuf,
+
+    /// Time the recording started; `t` counts from here.
+    started: Instant,
+
+    /// Buffered file and the number of commands written so far.
+    file: Mutex<(BufWriter<File>, u64)>,
+}
+
+impl CommandRecorder {
+    /// Creates the JSONL file, creating missing parent directories.
+    ///
+    /// Like video recordings, command recordings never overwrite: the file
+    /// must not exist yet.
+    ///
+    /// # Arguments
+    /// * `output` - Path of the JSONL file to write
+    ///
+    /// # Returns
+    /// - `Ok(CommandRecorder)` ready to record
+    /// - `Err` if the file exists (an [`std::io::ErrorKind::AlreadyExists`]
+    ///   I/O error) or cannot be created
+    pub fn create(output: &Path) -> Result<Self> {
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(output)
+            .with_context(|| format!("Failed to create {}", output.display()))?;
+
+        Ok(Self {
+            output: output.to_path_buf(),
+            started: Instant::now(),
+            file: Mutex::new((BufWriter::new(file), 0)),
+        })
+    }
+
+    /// Returns the file being written.
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    /// Returns the number of commands recorded so far.
+    pub fn count(&self) -> u64 {
+        self.file.lock().unwrap().1
+    }
+
+    /// Appends one command.
+    ///
+    /// # Arguments
+    /// * `query` - The `/control` query string as it is forwarded to the robot
+    pub fn record(&self, query: &str) -> Result<()> {
+        let line = RecordedCommand {
+            t: self.started.elapsed().as_secs_f64(),
+            time: unix_timestamp(SystemTime::now()),
+            query: query.to_string(),
+            command: RobotCommand::from_query(query).ok(),
+        };
+
+        let mut guard = self.file.lock().unwrap();
+        let (file, count) = &mut *guard;
+        serde_json::to_writer(&mut *file, &line)?;
+        writeln!(file)?;
+        file.flush()?;
+        *count += 1;
+        Ok(())
+    }
+
+    /// Flushes the file and returns a summary of the recording.
+    pub fn finish(self) -> Result<MacroSummary> {
+        let (mut file, commands) = self.file.into_inner().unwrap();
+        file.flush()?;
+        Ok(MacroSummary {
+            output: self.output,
+            commands,
+            duration_secs: self.started.elapsed().as_secs_f64(),
+        })
+    }
+}
+
+/// Loads a command recording.
+///
+/// Blank lines are ignored. Commands are returned in file order, which must
+/// also be time order. Times must lie between 0 and [`MAX_TIME_SECS`].
+///
+/// # Returns
+/// - `Ok(Vec<RecordedCommand>)` with every recorded command
+/// - `Err(WifiProxyError::Replay)` naming the first malformed line
+/// - `Err` if the file cannot be read
+pub fn load(path: &Path) -> Result<Vec<RecordedCommand>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut commands: Vec<RecordedCommand> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let malformed = |reason: String| {
+            WifiProxyError::Replay(format!("{} line {}: {}", path.display(), number + 1, reason))
+        };
+        let command: RecordedCommand =
+            serde_json::from_str(line).map_err(|e| malformed(e.to_string()))?;
+        if !(0.0..=MAX_TIME_SECS).contains(&command.t) {
+            return Err(malformed(format!("time is not between 0 and {} seconds", MAX_TIME_SECS)).into());
+        }
+        if commands.last().is_some_and(|prev| command.t < prev.t) {
+            return Err(malformed(format!("time {} is out of order", command.t)).into());
+        }
+        commands.push(command);
+    }
+
+    Ok(commands)
+}
+
+/// Validates a playback speed factor.
+///
+/// # Returns
+/// - `Ok(speed)` for a speed between [`MIN_SPEED`] and [`MAX_SPEED`]
+/// - `Err(WifiProxyError::Replay)` for anything else, including NaN
+///
+/// # Example
+/// ```
+/// use wifi_proxy::replay::check_speed;
+///
+/// assert_eq!(check_speed(2.0).unwrap(), 2.0);
+/// assert!(check_speed(0.0).is_err());
+/// assert!(check_speed(1e-300).is_err());
+/// assert!(check_speed(f64::INFINITY).is_err());
+/// ```
+pub fn check_speed(speed: f64) -> Result<f64, WifiProxyError> {
+    if !(MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        return Err(WifiProxyError::Replay(format!(
+            "speed must be between {} and {}",
+            MIN_SPEED, MAX_SPEED
+        )));
+    }
+    Ok(speed)
+}
+
+/// Replays recorded commands against a robot with their original timing.
+///
+/// The first command is sent immediately; each later one is sent when its
+/// offset from the first, divided by `speed`, has elapsed. Commands are
+/// scheduled against the start of the replay, so slow answers from the
+/// robot do not accumulate into drift.
+///
+/// Once `stop` is cancelled no further recorded command is sent, even one
+/// whose slot has already come. Whatever the outcome, the replay finishes by
+/// sending both stop commands.
+///
+/// # Arguments
+/// * `robot` - Client or queue for the robot to drive
+/// * `commands` - Recording to play, as returned by [`load`]
+/// * `speed` - Playback speed factor (1.0 = original timing, 2.0 = twice as fast)
+/// * `stop` - Cancel to abort the replay
+///
+/// # Returns
+/// - `Ok(ReplaySummary)` when the replay completed or was aborted
+/// - `Err(WifiProxyError::Replay)` if `speed` fails [`check_speed`] or a
+///   command's time is out of range (see [`load`])
+/// - `Err` if a command or the final stop could not be sent
+pub async fn replay(
+    robot: &impl CommandSink,
+    commands: &[RecordedCommand],
+    speed: f64,
+    stop: CancellationToken,
+) -> Result<ReplaySummary> {
+    check_speed(speed)?;
+
+    let started = tokio::time::Instant::now();
+    let origin = commands.first().map_or(0.0, |c| c.t);
+    let mut sent = 0;
+    let mut aborted = false;
+    let mut failure = None;
+
+    for command in commands {
+        // Wait for the command's slot, unless the replay is stopped first
+        let offset = match Duration::try_from_secs_f64((command.t - origin).max(0.0) / speed) {
+            Ok(offset) => offset,
+            Err(e) => {
+                failure = Some(WifiProxyError::Replay(format!("time {}: {}", command.t, e)).into());
+                break;
+            }
+        };
+        tokio::select! {
+            biased;
+            _ = stop.cancelled() => {
+                aborted = true;
+                break;
+            }
+            _ = tokio::time::sleep_until(started + offset) => {}
+        }
+
+        // The stop may have come while the slot was being taken
+        if stop.is_cancelled() {
+            aborted = true;
+            break;
+        }
+        if let Err(e) = robot.send_query(&command.query).await {
+            failure = Some(e.context(format!("command {} ({}) failed", sent + 1, command.query)));
+            break;
+        }
+        sent += 1;
+    }
+
+    // Never leave the robot moving, however the replay ended
+    let stopped = robot.stop().await.context("Failed to stop the robot after replay");
+
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    stopped?;
+
+    Ok(ReplaySummary {
+        commands: commands.len(),
+        sent,
+        aborted,
+        duration_secs: started.elapsed().as_secs_f64(),
+    })
+}
@@ -0,0 +1,528 @@
+//! Typed robot command model and async client for the `/control` protocol.
+//!
+//! The ESP32 firmware exposes a single `GET /control` endpoint that takes
+//! three query parameters: `var` (the command family), `val` (the command
+//! value) and `cmd` (an extra argument). This module gives those commands a
+//! typed representation so Rust tooling can drive the robot without building
+//! URLs by hand.
+//!
+//! # Wire Protocol
+//!
+//! | Command                      | `var`      | `val`       | `cmd`   |
+//! |------------------------------|------------|-------------|---------|
+//! | Move forward                 | `move`     | `1`         | `0`     |
+//! | Turn left                    | `move`     | `2`         | `0`     |
+//! | Stop forward/backward motion | `move`     | `3`         | `0`     |
+//! | Turn right                   | `move`     | `4`         | `0`     |
+//! | Move backward                | `move`     | `5`         | `0`     |
+//! | Stop turning                 | `move`     | `6`         | `0`     |
+//! | Action (steady, jump, ...)   | `funcMode` | `1`..`9`    | `0`     |
+//! | Adjust servo calibration     | `sconfig`  | servo index | delta   |
+//! | Store servo calibration      | `sset`     | servo index | `1`     |
+//!
+//! # Example
+//!
+//! ```no_run
+//! use wifi_proxy::robot::{Action, MoveDirection, RobotClient, RobotCommand};
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     let robot = RobotClient::new("192.168.4.1");
+//!
+//!     // Walk forward, then stop and sit steady
+//!     robot.send(&RobotCommand::Move(MoveDirection::Forward)).await?;
+//!     robot.stop().await?;
+//!     robot.send(&RobotCommand::Action(Action::Steady)).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::WifiProxyError;
+
+/// Number of servos addressable through `sconfig`/`sset` (indices 0-15).
+pub const SERVO_COUNT: u8 = 16;
+
+/// Direction values for the `move` command family.
+///
+/// Movement is "press and release": a direction starts continuous motion and
+/// the matching stop value ends it. Forward/backward are stopped with
+/// [`MoveDirection::StopLinear`], left/right with [`MoveDirection::StopTurn`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveDirection {
+    /// Start walking forward (`val=1`).
+    Forward,
+    /// Start walking backward (`val=5`).
+    Backward,
+    /// Start turning left (`val=2`).
+    Left,
+    /// Start turning right (`val=4`).
+    Right,
+    /// Stop forward/backward motion (`val=3`).
+    StopLinear,
+    /// Stop turning (`val=6`).
+    StopTurn,
+}
+
+impl MoveDirection {
+    /// Returns the wire value sent as `val` for this direction.
+    pub fn code(self) -> u8 {
+        match self {
+            MoveDirection::Forward => 1,
+            MoveDirection::Left => 2,
+            MoveDirection::StopLinear => 3,
+            MoveDirection::Right => 4,
+            MoveDirection::Backward => 5,
+            MoveDirection::StopTurn => 6,
+        }
+    }
+
+    /// Looks up a direction by its wire value.
+    ///
+    /// # Returns
+    /// - `Some(MoveDirection)` for values 1-6
+    /// - `None` for any other value
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(MoveDirection::Forward),
+            2 => Some(MoveDirection::Left),
+            3 => Some(MoveDirection::StopLinear),
+            4 => Some(MoveDirection::Right),
+            5 => Some(MoveDirection::Backward),
+            6 => Some(MoveDirection::StopTurn),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this direction ends motion rather than starting it.
+    pub fn is_stop(self) -> bool {
+        matches!(self, MoveDirection::StopLinear | MoveDirection::StopTurn)
+    }
+
+    /// Returns the stop direction that ends motion started by this one.
+    ///
+    /// Stop directions return themselves.
+    pub fn stop(self) -> Self {
+        match self {
+            MoveDirection::Forward | MoveDirection::Backward | MoveDirection::StopLinear => {
+                MoveDirection::StopLinear
+            }
+            MoveDirection::Left | MoveDirection::Right | MoveDirection::StopTurn => {
+                MoveDirection::StopTurn
+            }
+        }
+    }
+}
+
+/// Predefined actions and postures for the `funcMode` command family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Stand steady (`val=1`). Also used as the "halt" posture.
+    Steady,
+    /// Crouch down (`val=2`).
+    StayLow,
+    /// Raise a paw for a handshake (`val=3`).
+    HandShake,
+    /// Jump (`val=4`).
+    Jump,
+    /// Firmware-defined action A (`val=5`).
+    ActionA,
+    /// Firmware-defined action B (`val=6`).
+    ActionB,
+    /// Firmware-defined action C (`val=7`).
+    ActionC,
+    /// Initial (calibration) position (`val=8`).
+    InitPos,
+    /// Middle position (`val=9`).
+    MiddlePos,
+}
+
+impl Action {
+    /// Returns the wire value sent as `val` for this action.
+    pub fn code(self) -> u8 {
+        match self {
+            Action::Steady => 1,
+            Action::StayLow => 2,
+            Action::HandShake => 3,
+            Action::Jump => 4,
+            Action::ActionA => 5,
+            Action::ActionB => 6,
+            Action::ActionC => 7,
+            Action::InitPos => 8,
+            Action::MiddlePos => 9,
+        }
+    }
+
+    /// Looks up an action by its wire value.
+    ///
+    /// # Returns
+    /// - `Some(Action)` for values 1-9
+    /// - `None` for any other value
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(Action::Steady),
+            2 => Some(Action::StayLow),
+            3 => Some(Action::HandShake),
+            4 => Some(Action::Jump),
+            5 => Some(Action::ActionA),
+            6 => Some(Action::ActionB),
+            7 => Some(Action::ActionC),
+            8 => Some(Action::InitPos),
+            9 => Some(Action::MiddlePos),
+            _ => None,
+        }
+    }
+}
+
+/// A single command understood by the robot's `/control` endpoint.
+///
+/// Serializes to JSON as a single-key object, e.g. `{"move": "forward"}`,
+/// `{"action": "jump"}` or `{"servo_adjust": {"servo": 3, "delta": -1}}`.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::robot::{MoveDirection, RobotCommand};
+///
+/// let cmd: RobotCommand = serde_json::from_str(r#"{"move": "stop_turn"}"#).unwrap();
+/// assert_eq!(cmd, RobotCommand::Move(MoveDirection::StopTurn));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RobotCommand {
+    /// Start or stop movement (`var=move`).
+    Move(MoveDirection),
+
+    /// Perform a predefined action or posture (`var=funcMode`).
+    Action(Action),
+
+    /// Nudge a servo's calibration offset (`var=sconfig`).
+    ServoAdjust {
+        /// Servo index (0-15).
+        servo: u8,
+        /// Offset change; the web interface sends `1` or `-1`.
+        delta: i32,
+    },
+
+    /// Store the current calibration of a servo (`var=sset`).
+    ServoSet {
+        /// Servo index (0-15).
+        servo: u8,
+    },
+}
+
+impl RobotCommand {
+    /// Returns the `var`, `val` and `cmd` query parameters for this command.
+    pub fn params(&self) -> (&'static str, String, String) {
+        match *self {
+            RobotCommand::Move(dir) => ("move", dir.code().to_string(), "0".to_string()),
+            RobotCommand::Action(action) => {
+                ("funcMode", action.code().to_string(), "0".to_string())
+            }
+            RobotCommand::ServoAdjust { servo, delta } => {
+                ("sconfig", servo.to_string(), delta.to_string())
+            }
+            RobotCommand::ServoSet { servo } => ("sset", servo.to_string(), "1".to_string()),
+        }
+    }
+
+    /// Encodes the command as a `/control` query string.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::robot::{MoveDirection, RobotCommand};
+    ///
+    /// let cmd = RobotCommand::Move(MoveDirection::Forward);
+    /// assert_eq!(cmd.to_query(), "var=move&val=1&cmd=0");
+    /// ```
+    pub fn to_query(&self) -> String {
+        let (var, val, cmd) = self.params();
+        format!("var={}&val={}&cmd={}", var, val, cmd)
+    }
+
+    /// Decodes a command from its `var`, `val` and `cmd` parameters.
+    ///
+    /// `cmd` is only required for `sconfig`, where it carries the servo delta;
+    /// the other command families ignore it just like the firmware does.
+    ///
+    /// # Returns
+    /// - `Ok(RobotCommand)` if the parameters describe a known command
+    /// - `Err(WifiProxyError::InvalidCommand)` for unknown families or out-of-range values
+    pub fn from_params(var: &str, val: &str, cmd: Option<&str>) -> Result<Self> {
+        // Every command family carries a small unsigned integer in `val`
+        let code: u8 = val
+            .parse()
+            .map_err(|_| invalid(format!("invalid val '{}' for var '{}'", val, var)))?;
+
+        match var {
+            "move" => MoveDirection::from_code(code)
+                .map(RobotCommand::Move)
+                .ok_or_else(|| invalid(format!("unknown move value {}", code))),
+
+            "funcMode" => Action::from_code(code)
+                .map(RobotCommand::Action)
+                .ok_or_else(|| invalid(format!("unknown funcMode value {}", code))),
+
+            "sconfig" => {
+                let servo = check_servo(code)?;
+                let cmd = cmd.ok_or_else(|| invalid("sconfig requires cmd".to_string()))?;
+                let delta = cmd
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid servo delta '{}'", cmd)))?;
+                Ok(RobotCommand::ServoAdjust { servo, delta })
+            }
+
+            "sset" => Ok(RobotCommand::ServoSet {
+                servo: check_servo(code)?,
+            }),
+
+            _ => Err(invalid(format!("unknown var '{}'", var))),
+        }
+    }
+
+    /// Decodes a command from already-parsed query parameters.
+    ///
+    /// This is the form Axum's `Query<HashMap<String, String>>` extractor produces.
+    pub fn from_map(params: &HashMap<String, String>) -> Result<Self> {
+        let var = params
+            .get("var")
+            .ok_or_else(|| invalid("missing var".to_string()))?;
+        let val = params
+            .get("val")
+            .ok_or_else(|| invalid("missing val".to_string()))?;
+        Self::from_params(var, val, params.get("cmd").map(String::as_str))
+    }
+
+    /// Decodes a command from a `/control` query string.
+    ///
+    /// A leading `?` is accepted, and parameter order does not matter.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::robot::{Action, RobotCommand};
+    ///
+    /// let cmd = RobotCommand::from_query("var=funcMode&val=4&cmd=0").unwrap();
+    /// assert_eq!(cmd, RobotCommand::Action(Action::Jump));
+    /// ```
+    pub fn from_query(query: &str) -> Result<Self> {
+        let params: HashMap<String, String> = query
+            .trim_start_matches('?')
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Self::from_map(&params)
+    }
+
+    /// Returns true if this command stops movement.
+    pub fn is_stop(&self) -> bool {
+        matches!(self, RobotCommand::Move(dir) if dir.is_stop())
+    }
+
+    /// Checks values that the type system cannot, such as the servo index.
+    ///
+    /// Commands decoded from query parameters are already validated; this is
+    /// for commands built directly or deserialized from JSON.
+    ///
+    /// # Returns
+    /// - `Ok(())` if the robot would accept the command
+    /// - `Err(WifiProxyError::InvalidCommand)` for out-of-range servo indices
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            RobotCommand::ServoAdjust { servo, .. } | RobotCommand::ServoSet { servo } => {
+                check_servo(servo).map(|_| ())
+            }
+            RobotCommand::Move(_) | RobotCommand::Action(_) => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for RobotCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_query())
+    }
+}
+
+/// Builds an `InvalidCommand` error with the given message.
+fn invalid(msg: String) -> anyhow::Error {
+    WifiProxyError::InvalidCommand(msg).into()
+}
+
+/// Validates a servo index against [`SERVO_COUNT`].
+fn check_servo(servo: u8) -> Result<u8> {
+    if servo < SERVO_COUNT {
+        Ok(servo)
+    } else {
+        Err(invalid(format!("servo index {} out of range 0-{}", servo, SERVO_COUNT - 1)))
+    }
+}
+
+/// Async HTTP client that sends [`RobotCommand`]s to a robot gateway.
+///
+/// Wraps a `reqwest::Client`, so cloning is cheap and clones share the same
+/// connection pool.
+#[derive(Debug, Clone)]
+pub struct RobotClient {
+    /// Underlying HTTP client (shared connection pool).
+    client: reqwest::Client,
+
+    /// Gateway address, optionally with a port (e.g., "192.168.4.1").
+    gateway: String,
+
+    /// Deadline for each command, from sending to reading the body.
+    timeout: Option<Duration>,
+}
+
+impl RobotClient {
+    /// Creates a client for the robot at the given gateway address.
+    ///
+    /// # Arguments
+    /// * `gateway` - Host or `host:port` of the robot (e.g., "192.168.4.1")
+    pub fn new(gateway: &str) -> Self {
+        Self::with_client(gateway, reqwest::Client::new())
+    }
+
+    /// Creates a client that reuses an existing `reqwest::Client`.
+    ///
+    /// Useful when the caller has already configured timeouts or pooling.
+    pub fn with_client(gateway: &str, client: reqwest::Client) -> Self {
+        Self {
+            client,
+            gateway: gateway.to_string(),
+            timeout: None,
+        }
+    }
+
+    /// Sets a deadline for each command sent by this client.
+    ///
+    /// Without one, a command waits as long as the underlying client allows.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the gateway address this client sends commands to.
+    pub fn gateway(&self) -> &str {
+        &self.gateway
+    }
+
+    /// Returns the full `/control` URL for a command.
+    pub fn control_url(&self, command: &RobotCommand) -> String {
+        format!("http://{}/control?{}", self.gateway, command.to_query())
+    }
+
+    /// Sends a command to the robot and returns the response body.
+    ///
+    /// # Returns
+    /// - `Ok(String)` with the robot's response body
+    /// - `Err(WifiProxyError::FetchFailed)` if the request fails or the robot
+    ///   answers with a non-success status
+    pub async fn send(&self, command: &RobotCommand) -> Result<String> {
+        self.send_query(&command.to_query()).await
+    }
+
+    /// Sends a raw `/control` query string to the robot and returns the
+    /// response body.
+    ///
+    /// Used to replay recorded traffic verbatim, including parameters that
+    /// [`RobotCommand`] does not model.
+    ///
+    /// # Returns
+    /// - `Ok(String)` with the robot's response body
+    /// - `Err(WifiProxyError::FetchFailed)` if the request fails or the robot
+    ///   answers with a non-success status
+    pub async fn send_query(&self, query: &str) -> Result<String> {
+        let url = format!("http://{}/control?{}", self.gateway, query);
+        let mut request = self.client.get(url);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+
+        let response = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| WifiProxyError::FetchFailed(e.to_string()))?;
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| WifiProxyError::FetchFailed(e.to_string()))?;
+
+        Ok(body)
+    }
+
+    /// Stops all movement by sending both stop commands.
+    ///
+    /// Both stops are attempted even if the first one fails; the first error
+    /// encountered is returned.
+    pub async fn stop(&self) -> Result<()> {
+        let linear = self.send(&RobotCommand::Move(MoveDirection::StopLinear)).await;
+        let turn = self.send(&RobotCommand::Move(MoveDirection::StopTurn)).await;
+        linear?;
+        turn?;
+        Ok(())
+    }
+}
+
+/// Anything that delivers `/control` queries to a robot.
+///
+/// Implemented by [`RobotClient`], which sends each command directly, and by
+/// the proxy server's [`CommandQueue`](crate::queue::CommandQueue), which
+/// serializes the commands of all clients. Code that stops or drives the
+/// robot on its own (the watchdog, the emergency stop, replays) is generic
+/// over this trait so it works with either.
+pub trait CommandSink: Send + Sync {
+    /// Delivers a raw `/control` query string and returns the response body.
+    fn send_query(&self, query: &str) -> impl Future<Output = Result<String>> + Send;
+
+    /// Delivers a command and returns the response body.
+    fn send(&self, command: &RobotCommand) -> impl Future<Output = Result<String>> + Send {
+        let query = command.to_query();
+        async move { self.send_query(&query).await }
+    }
+
+    /// Stops all movement, attempting both stops and returning the first error.
+    fn stop(&self) -> impl Future<Output = Result<()>> + Send {
+        async move {
+            let linear = self.send(&RobotCommand::Move(MoveDirection::StopLinear)).await;
+            let turn = self.send(&RobotCommand::Move(MoveDirection::StopTurn)).await;
+            linear?;
+            turn?;
+            Ok(())
+        }
+    }
+
+    /// Discards commands accepted but not yet delivered, so that nothing
+    /// sent before an emergency stop reaches the robot after it.
+    ///
+    /// # Returns
+    /// The number of commands discarded; always 0 for direct senders.
+    fn cancel_pending(&self) -> usize {
+        0
+    }
+}
+
+impl CommandSink for RobotClient {
+    fn send_query(&self, query: &str) -> impl Future<Output = Result<String>> + Send {
+        RobotClient::send_query(self, query)
+    }
+}
+
+impl<S: CommandSink> CommandSink for Arc<S> {
+    fn send_query(&self, query: &str) -> impl Future<Output = Result<String>> + Send {
+        (**self).send_query(query)
+    }
+
+    fn cancel_pending(&self) -> usize {
+        (**self).cancel_pending()
+    }
+}
@@ -1,168 +1,694 @@
 //! WiFi network scanning module.
 //!
 //! This module provides functionality for scanning nearby WiFi networks
-//! and displaying the results. It uses NetworkManager's nmcli tool to
-//! trigger scans and retrieve network information.
+//! and displaying the results. Scans are performed by the currently selected
+//! [`NetworkBackend`](crate::backend::NetworkBackend).
 //!
 //! # Scanning Process
 //!
-//! 1. Triggers a rescan on the specified interface using `nmcli device wifi rescan`
-//! 2. Waits briefly for the scan to complete (500ms)
-//! 3. Retrieves the list of discovered networks using `nmcli device wifi list`
-//! 4. Parses and deduplicates the results
-//! 5. Sorts networks by signal strength (strongest first)
+//! 1. Triggers a rescan on the specified interface
+//! 2. Waits for the scan to complete
+//! 3. Retrieves the list of discovered access points
+//! 4. Keeps one entry per BSSID, dropping entries with neither SSID nor BSSID
+//! 5. Sorts access points by signal strength (strongest first)
+//!
+//! Every access point is listed on its own, so two robots sharing the
+//! factory SSID show up as two entries with different BSSIDs. Use
+//! [`group_by_ssid`] for one row per network name, and a [`ScanWatcher`]
+//! to follow access points across repeated scans.
+//!
+//! Hidden networks, which leave their SSID out of beacons, are listed with an
+//! empty SSID (shown as `<hidden>`). They are joined by name with
+//! [`connect_hidden`](crate::connection::connect_hidden).
 //!
 //! # Example
 //!
 //! ```no_run
-//! use wifi_proxy::scan::{scan_networks, display_networks};
+//! use wifi_proxy::scan::{scan_networks, display_networks, Band, ScanFilter};
 //!
 //! let networks = scan_networks("wlan1").expect("Scan failed");
-//! display_networks(&networks);
+//! let filter = ScanFilter {
+//!     min_signal: Some(40),
+//!     band: Some(Band::Ghz2_4),
+//!     ..ScanFilter::default()
+//! };
+//! display_networks(&filter.apply(networks));
 //! ```
 
-use anyhow::{Context, Result};
-use std::process::Command;
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
 
+use crate::backend::backend;
+use crate::connection::channel_from_frequency;
 use crate::error::WifiProxyError;
+use crate::mjpeg::unix_timestamp;
 
-/// Represents a discovered WiFi network from a scan.
+/// Represents one access point discovered by a scan.
 ///
 /// Contains the essential information about a network that users need
-/// to decide which network to connect to.
-#[derive(Debug, Clone)]
+/// to decide which network to connect to, and the radio details needed to
+/// tell access points with the same name apart.
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Network {
     /// The SSID (network name) of the WiFi network.
-    /// May be empty for hidden networks.
+    /// Empty for hidden networks.
     pub ssid: String,
 
+    /// MAC address of the access point (e.g., "24:0A:C4:12:34:56").
+    /// Empty if the backend does not report it.
+    pub bssid: String,
+
     /// Signal strength as a percentage (0-100).
     /// Higher values indicate stronger signal and typically better connection quality.
     pub signal: u8,
 
-    /// Security type of the network (e.g., "WPA2", "WPA3", "WEP", "").
-    /// Empty string indicates an open network with no encryption.
-    pub security: String,
+    /// Security the access point requires.
+    pub security: Security,
+
+    /// Center frequency in MHz (e.g., 2437).
+    pub frequency_mhz: Option<u32>,
+
+    /// Channel number (e.g., 6).
+    pub channel: Option<u32>,
+
+    /// Frequency band, derived from the frequency.
+    pub band: Option<Band>,
+
+    /// Highest bitrate the access point advertises, in Mbit/s.
+    pub max_rate_mbps: Option<f64>,
+
+    /// Operating mode of the access point.
+    pub mode: Option<Mode>,
+}
+
+impl Network {
+    /// Returns whether the access point hides its SSID.
+    pub fn is_hidden(&self) -> bool {
+        self.ssid.is_empty()
+    }
 }
 
-/// Scans for WiFi networks visible to the specified interface.
+/// Security of an access point.
+///
+/// Access points offering several modes (e.g., WPA2/WPA3 transition mode)
+/// report the strongest one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Security {
+    /// No encryption.
+    #[default]
+    Open,
+    /// Legacy WEP.
+    Wep,
+    /// WPA (version 1) with a pre-shared key.
+    Wpa,
+    /// WPA2 with a pre-shared key.
+    Wpa2,
+    /// WPA3 (SAE).
+    Wpa3,
+    /// WPA or WPA2 with 802.1X authentication (username and password or certificates).
+    Enterprise,
+}
+
+impl Security {
+    /// Parses an nmcli-style security string such as "WPA1 WPA2" or
+    /// "WPA2 802.1X"; an empty string is an open network.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::scan::Security;
+    ///
+    /// assert_eq!(Security::parse(""), Security::Open);
+    /// assert_eq!(Security::parse("WPA1 WPA2"), Security::Wpa2);
+    /// assert_eq!(Security::parse("WPA2 WPA3"), Security::Wpa3);
+    /// assert_eq!(Security::parse("WPA2 802.1X"), Security::Enterprise);
+    /// ```
+    pub fn parse(flags: &str) -> Self {
+        flags
+            .split_whitespace()
+            .map(|flag| match flag {
+                "WEP" => Security::Wep,
+                "WPA" | "WPA1" => Security::Wpa,
+                "WPA2" => Security::Wpa2,
+                "WPA3" => Security::Wpa3,
+                "802.1X" => Security::Enterprise,
+                _ => Security::Open,
+            })
+            .max()
+            .unwrap_or(Security::Open)
+    }
+
+    /// Returns true if connecting needs a password.
+    pub fn requires_password(&self) -> bool {
+        *self != Security::Open
+    }
+}
+
+impl fmt::Display for Security {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Security::Open => "Open",
+            Security::Wep => "WEP",
+            Security::Wpa => "WPA",
+            Security::Wpa2 => "WPA2",
+            Security::Wpa3 => "WPA3",
+            Security::Enterprise => "802.1X",
+        })
+    }
+}
+
+/// WiFi frequency band.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Band {
+    /// 2.4 GHz, used by the ESP32.
+    #[serde(rename = "2.4ghz")]
+    Ghz2_4,
+    /// 5 GHz.
+    #[serde(rename = "5ghz")]
+    Ghz5,
+    /// 6 GHz.
+    #[serde(rename = "6ghz")]
+    Ghz6,
+}
+
+impl Band {
+    /// Returns the band a center frequency in MHz belongs to.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::scan::Band;
+    ///
+    /// assert_eq!(Band::from_frequency(2437), Some(Band::Ghz2_4));
+    /// assert_eq!(Band::from_frequency(5180), Some(Band::Ghz5));
+    /// assert_eq!(Band::from_frequency(5955), Some(Band::Ghz6));
+    /// assert_eq!(Band::from_frequency(900), None);
+    /// ```
+    pub fn from_frequency(mhz: u32) -> Option<Self> {
+        match mhz {
+            2400..=2500 => Some(Band::Ghz2_4),
+            5150..=5895 => Some(Band::Ghz5),
+            5925..=7125 => Some(Band::Ghz6),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Band::Ghz2_4 => "2.4GHz",
+            Band::Ghz5 => "5GHz",
+            Band::Ghz6 => "6GHz",
+        })
+    }
+}
+
+impl FromStr for Band {
+    type Err = WifiProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().trim_end_matches("ghz") {
+            "2.4" | "2" => Ok(Band::Ghz2_4),
+            "5" => Ok(Band::Ghz5),
+            "6" => Ok(Band::Ghz6),
+            _ => Err(WifiProxyError::InvalidScanOption(format!(
+                "unknown band '{}' (use 2.4, 5 or 6)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Operating mode of an access point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Regular access point (what the robot runs).
+    Infrastructure,
+    /// Ad-hoc (IBSS) network between peers.
+    AdHoc,
+    /// 802.11s mesh point.
+    Mesh,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Mode::Infrastructure => "Infra",
+            Mode::AdHoc => "Ad-Hoc",
+            Mode::Mesh => "Mesh",
+        })
+    }
+}
+
+/// Scans for access points visible to the specified interface.
 ///
 /// Triggers a fresh scan, waits for completion, then retrieves and parses
-/// the list of discovered networks. Duplicate SSIDs are filtered out
-/// (keeping the first occurrence), and results are sorted by signal strength.
+/// the list of discovered access points. Duplicate BSSIDs are filtered out
+/// (keeping the strongest report), missing channels and bands are derived
+/// from the frequency, and results are sorted by signal strength. Hidden
+/// networks are kept with an empty SSID as long as their BSSID is known.
 ///
 /// # Arguments
 /// * `interface` - The name of the WiFi interface to scan with (e.g., "wlan1")
 ///
 /// # Returns
-/// - `Ok(Vec<Network>)` containing discovered networks sorted by signal (strongest first)
-/// - `Err(WifiProxyError::NmcliExecution)` if nmcli commands fail
-///
-/// # Commands Executed
-/// ```bash
-/// nmcli device wifi rescan ifname <interface>
-/// nmcli -t -f SSID,SIGNAL,SECURITY device wifi list ifname <interface>
-/// ```
+/// - `Ok(Vec<Network>)` containing one entry per access point, sorted by signal (strongest first)
+/// - `Err` if the backend fails to scan
 ///
 /// # Note
-/// The rescan command may fail silently if the interface is busy or doesn't
-/// support on-demand scanning. The function will still return cached results
-/// from the last successful scan in this case.
+/// A rescan may fail silently if the interface is busy or doesn't support
+/// on-demand scanning. Backends still return cached results from the last
+/// successful scan in this case.
 pub fn scan_networks(interface: &str) -> Result<Vec<Network>> {
-    // Step 1: Trigger a rescan on the specified interface
-    // This initiates a fresh scan for nearby networks
-    // Result is ignored because rescan can fail if already scanning
-    let _ = Command::new("nmcli")
-        .args(["device", "wifi", "rescan", "ifname", interface])
-        .output();
-
-    // Step 2: Wait briefly for the scan to complete
-    // 500ms is usually sufficient for most WiFi adapters to complete a scan
-    std::thread::sleep(std::time::Duration::from_millis(500));
-
-    // Step 3: Retrieve the list of discovered networks
-    let output = Command::new("nmcli")
-        .args([
-            "-t",               // Terse output (machine-readable)
-            "-f",               // Specify fields to output
-            "SSID,SIGNAL,SECURITY",  // Fields we want
-            "device",           // Device management command
-            "wifi",             // WiFi-specific operation
-            "list",             // List networks
-            "ifname",           // Interface name keyword
-            interface,          // Target interface
-        ])
-        .output()
-        .context("Failed to execute nmcli wifi list")?;
-
-    // Check for command execution errors
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(WifiProxyError::NmcliExecution(stderr.to_string()).into());
-    }
+    // Steps 1-3: Let the selected backend trigger a scan and collect results
+    let mut results = backend().scan(interface)?;
+
+    // Strongest first, so the first report of a BSSID is the one kept
+    results.sort_by_key(|n| std::cmp::Reverse(n.signal));
 
-    // Step 4: Parse the output
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Step 4: Filter the raw results
     let mut networks = Vec::new();
 
-    // Track seen SSIDs to filter duplicates (same network from multiple APs)
-    let mut seen_ssids = std::collections::HashSet::new();
+    // Track seen access points; without a BSSID the SSID is all there is
+    let mut seen = HashSet::new();
 
-    // Process each line of output (format: SSID:SIGNAL:SECURITY)
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split(':').collect();
+    for network in results {
+        let network = complete(network);
 
-        // Need at least 3 parts (SSID, SIGNAL, SECURITY)
-        if parts.len() >= 3 {
-            let ssid = parts[0].to_string();
+        // Skip entries that cannot be told apart (no SSID, no BSSID) and duplicates
+        if network.ssid.is_empty() && network.bssid.is_empty() {
+            continue;
+        }
+        if !seen.insert(access_point_key(&network)) {
+            continue;
+        }
+        networks.push(network);
+    }
 
-            // Skip hidden networks (empty SSID) and duplicates
-            if ssid.is_empty() || seen_ssids.contains(&ssid) {
-                continue;
-            }
-            seen_ssids.insert(ssid.clone());
+    // Step 5: Already sorted by signal strength in descending order
+    // This puts the strongest (best) signals at the top
+    Ok(networks)
+}
+
+/// Identifies an access point across scans: its BSSID, or the SSID when
+/// the backend reports no BSSID.
+fn access_point_key(network: &Network) -> String {
+    if network.bssid.is_empty() {
+        network.ssid.clone()
+    } else {
+        network.bssid.to_ascii_uppercase()
+    }
+}
 
-            // Parse signal strength, defaulting to 0 if parsing fails
-            let signal: u8 = parts[1].parse().unwrap_or(0);
+/// Derives the channel and band of a scan entry from its frequency.
+///
+/// Some drivers report a hidden network's SSID as NUL bytes of the real
+/// name's length; it is cleared like an empty one.
+fn complete(mut network: Network) -> Network {
+    if network.ssid.chars().all(|c| c == '\0') {
+        network.ssid.clear();
+    }
+    if let Some(frequency) = network.frequency_mhz {
+        network.channel = network.channel.or_else(|| channel_from_frequency(frequency));
+        network.band = network.band.or_else(|| Band::from_frequency(frequency));
+    }
+    network
+}
+
+/// Criteria a scan entry must meet to be shown.
+///
+/// Unset criteria match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ScanFilter {
+    /// Lowest signal strength (percent) to keep.
+    pub min_signal: Option<u8>,
 
-            // Security field might contain colons (e.g., "WPA1 WPA2:802.1X")
-            // so join all remaining parts
-            let security = parts[2..].join(":");
+    /// Only keep access points on this band.
+    pub band: Option<Band>,
+
+    /// Only keep SSIDs matching this regular expression.
+    pub ssid_regex: Option<Regex>,
+}
 
-            networks.push(Network {
-                ssid,
-                signal,
-                security,
-            });
+impl ScanFilter {
+    /// Returns true if the access point meets every criterion.
+    ///
+    /// # Example
+    /// ```
+    /// use wifi_proxy::scan::{Network, ScanFilter};
+    ///
+    /// let filter = ScanFilter {
+    ///     min_signal: Some(50),
+    ///     ssid_regex: Some("^WAVESHARE".parse().unwrap()),
+    ///     ..ScanFilter::default()
+    /// };
+    /// let robot = Network { ssid: "WAVESHARE Robot".into(), signal: 78, ..Network::default() };
+    /// assert!(filter.matches(&robot));
+    /// assert!(!filter.matches(&Network { signal: 30, ..robot.clone() }));
+    /// assert!(!filter.matches(&Network { ssid: "HomeNetwork".into(), ..robot }));
+    /// ```
+    pub fn matches(&self, network: &Network) -> bool {
+        self.min_signal.is_none_or(|min| network.signal >= min)
+            && self.band.is_none_or(|band| network.band == Some(band))
+            && self.ssid_regex.as_ref().is_none_or(|re| re.is_match(&network.ssid))
+    }
+
+    /// Keeps only the access points that meet every criterion.
+    pub fn apply(&self, networks: Vec<Network>) -> Vec<Network> {
+        networks.into_iter().filter(|n| self.matches(n)).collect()
+    }
+}
+
+/// Order of scan results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Strongest signal first (default).
+    #[default]
+    Signal,
+    /// Alphabetically by SSID, strongest first within an SSID.
+    Ssid,
+    /// By channel, strongest first within a channel.
+    Channel,
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortKey::Signal => "signal",
+            SortKey::Ssid => "ssid",
+            SortKey::Channel => "channel",
+        })
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = WifiProxyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "signal" => Ok(SortKey::Signal),
+            "ssid" => Ok(SortKey::Ssid),
+            "channel" => Ok(SortKey::Channel),
+            _ => Err(WifiProxyError::InvalidScanOption(format!(
+                "unknown sort key '{}' (use signal, ssid or channel)",
+                s
+            ))),
         }
     }
+}
 
-    // Step 5: Sort networks by signal strength in descending order
-    // This puts the strongest (best) signals at the top
-    networks.sort_by(|a, b| b.signal.cmp(&a.signal));
+/// Sorts scan results in place.
+///
+/// # Arguments
+/// * `networks` - Scan results to sort
+/// * `key` - Order to sort in; ties are broken by signal strength
+pub fn sort_networks(networks: &mut [Network], key: SortKey) {
+    let strongest = |n: &Network| std::cmp::Reverse(n.signal);
+    match key {
+        SortKey::Signal => networks.sort_by_key(strongest),
+        SortKey::Ssid => networks.sort_by(|a, b| {
+            a.ssid.to_lowercase().cmp(&b.ssid.to_lowercase()).then(strongest(a).cmp(&strongest(b)))
+        }),
+        // Unknown channels go last
+        SortKey::Channel => networks.sort_by_key(|n| (n.channel.unwrap_or(u32::MAX), strongest(n))),
+    }
+}
 
-    Ok(networks)
+/// All access points sharing one SSID.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkGroup {
+    /// The shared network name.
+    pub ssid: String,
+
+    /// Access points broadcasting the SSID, in scan result order.
+    pub access_points: Vec<Network>,
+}
+
+impl NetworkGroup {
+    /// Returns the access point with the strongest signal.
+    pub fn strongest(&self) -> Option<&Network> {
+        self.access_points.iter().max_by_key(|n| n.signal)
+    }
+}
+
+/// Groups access points by SSID, keeping the order of first appearance.
+///
+/// Hidden access points cannot be told apart by name and share one group.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::scan::{group_by_ssid, Network};
+///
+/// let ap = |ssid: &str, bssid: &str| Network { ssid: ssid.into(), bssid: bssid.into(), ..Network::default() };
+/// let groups = group_by_ssid(&[
+///     ap("WAVESHARE Robot", "24:0A:C4:00:00:01"),
+///     ap("HomeNetwork", "3C:84:6A:00:00:01"),
+///     ap("WAVESHARE Robot", "24:0A:C4:00:00:02"),
+/// ]);
+/// assert_eq!(groups.len(), 2);
+/// assert_eq!(groups[0].ssid, "WAVESHARE Robot");
+/// assert_eq!(groups[0].access_points.len(), 2);
+/// ```
+pub fn group_by_ssid(networks: &[Network]) -> Vec<NetworkGroup> {
+    let mut groups: Vec<NetworkGroup> = Vec::new();
+    for network in networks {
+        match groups.iter_mut().find(|g| g.ssid == network.ssid) {
+            Some(group) => group.access_points.push(network.clone()),
+            None => groups.push(NetworkGroup {
+                ssid: network.ssid.clone(),
+                access_points: vec![network.clone()],
+            }),
+        }
+    }
+    groups
+}
+
+/// Consecutive scans an access point may be missing from before `scan
+/// --watch` reports it as gone; single scans regularly miss weak ones.
+pub const DISAPPEAR_AFTER_SCANS: u32 = 3;
+
+/// What happened to an access point between two scans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The access point was seen for the first time (or again after disappearing).
+    Appeared,
+    /// The access point was missing from several scans in a row.
+    Disappeared,
+    /// The signal moved by at least the watcher's threshold.
+    Signal,
+}
+
+/// One change reported by a [`ScanWatcher`], as printed and logged by
+/// `scan --watch`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanChange {
+    /// Unix seconds (with milliseconds) of the scan that showed the change.
+    pub time: String,
+
+    /// What changed.
+    pub event: ChangeKind,
+
+    /// The access point, as last seen.
+    #[serde(flatten)]
+    pub network: Network,
+
+    /// Signal at the previous report, for signal changes.
+    pub previous_signal: Option<u8>,
+}
+
+/// An access point the watcher knows about.
+struct Tracked {
+    /// Scan entry as last seen.
+    network: Network,
+
+    /// Signal at the last reported change.
+    reported_signal: u8,
+
+    /// Consecutive scans the access point was missing from.
+    misses: u32,
+}
+
+/// Compares consecutive scans and reports access points appearing,
+/// disappearing and changing signal.
+///
+/// A single scan often misses an access point, so it is only reported as
+/// gone after `grace` consecutive scans without it. Signal changes are
+/// measured from the last reported value, so slow drifts add up until they
+/// cross the threshold.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::scan::{ChangeKind, Network, ScanWatcher};
+///
+/// let robot = Network { ssid: "WAVESHARE Robot".into(), bssid: "24:0A:C4:12:34:56".into(), signal: 78, ..Network::default() };
+/// let mut watcher = ScanWatcher::new(5, 2);
+///
+/// let changes = watcher.update(vec![robot.clone()]);
+/// assert_eq!(changes[0].event, ChangeKind::Appeared);
+///
+/// let changes = watcher.update(vec![Network { signal: 60, ..robot.clone() }]);
+/// assert_eq!(changes[0].event, ChangeKind::Signal);
+/// assert_eq!(changes[0].previous_signal, Some(78));
+///
+/// // One missed scan is tolerated, the second one is not
+/// assert!(watcher.update(vec![]).is_empty());
+/// assert_eq!(watcher.update(vec![])[0].event, ChangeKind::Disappeared);
+/// ```
+pub struct ScanWatcher {
+    /// Smallest signal change (percentage points) that is reported.
+    min_change: u8,
+
+    /// Consecutive missed scans before an access point counts as gone.
+    grace: u32,
+
+    /// Known access points by [`access_point_key`], in order of appearance.
+    known: Vec<(String, Tracked)>,
+}
+
+impl ScanWatcher {
+    /// Creates a watcher that knows no access points yet.
+    ///
+    /// # Arguments
+    /// * `min_change` - Smallest signal change (percentage points) to report
+    /// * `grace` - Consecutive missed scans before an access point is
+    ///   reported as gone (at least 1)
+    pub fn new(min_change: u8, grace: u32) -> Self {
+        Self {
+            min_change,
+            grace: grace.max(1),
+            known: Vec::new(),
+        }
+    }
+
+    /// Compares a new scan with the previous ones.
+    ///
+    /// # Arguments
+    /// * `networks` - Result of the latest scan
+    ///
+    /// # Returns
+    /// The changes, appearances and signal changes first in scan order,
+    /// then disappearances.
+    pub fn update(&mut self, networks: Vec<Network>) -> Vec<ScanChange> {
+        let time = unix_timestamp(SystemTime::now());
+        let change = |event, network: &Network, previous_signal| ScanChange {
+            time: time.clone(),
+            event,
+            network: network.clone(),
+            previous_signal,
+        };
+
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+
+        for network in networks {
+            let key = access_point_key(&network);
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            match self.known.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, tracked)) => {
+                    let previous = tracked.reported_signal;
+                    if network.signal.abs_diff(previous) >= self.min_change {
+                        changes.push(change(ChangeKind::Signal, &network, Some(previous)));
+                        tracked.reported_signal = network.signal;
+                    }
+                    tracked.network = network;
+                    tracked.misses = 0;
+                }
+                None => {
+                    changes.push(change(ChangeKind::Appeared, &network, None));
+                    self.known.push((
+                        key,
+                        Tracked {
+                            reported_signal: network.signal,
+                            network,
+                            misses: 0,
+                        },
+                    ));
+                }
+            }
+        }
+
+        // Access points missing from this scan
+        let grace = self.grace;
+        self.known.retain_mut(|(key, tracked)| {
+            if seen.contains(key.as_str()) {
+                return true;
+            }
+            tracked.misses += 1;
+            if tracked.misses < grace {
+                return true;
+            }
+            changes.push(change(ChangeKind::Disappeared, &tracked.network, None));
+            false
+        });
+
+        changes
+    }
 }
 
-/// Displays a list of networks in a formatted table.
+/// Prints one change in diff style: `+` appeared, `-` disappeared, `~` signal.
 ///
-/// Prints network information including SSID, signal strength (numeric and visual),
-/// and security type in a human-readable table format.
+/// # Output Format
+/// ```text
+/// + 1760000000.123 WAVESHARE Robot          24:0A:C4:12:34:56  ch 6    82% ████
+/// ~ 1760000005.456 WAVESHARE Robot          24:0A:C4:12:34:56  ch 6    64% ███░ (was 82%, -18)
+/// - 1760000020.789 WAVESHARE Robot          24:0A:C4:9A:BC:DE  ch 11    47% ██░░
+/// ```
+pub fn display_change(change: &ScanChange) {
+    let network = &change.network;
+    let (marker, note) = match (change.event, change.previous_signal) {
+        (ChangeKind::Appeared, _) => ('+', String::new()),
+        (ChangeKind::Disappeared, _) => ('-', String::new()),
+        (ChangeKind::Signal, Some(previous)) => {
+            let delta = i32::from(network.signal) - i32::from(previous);
+            ('~', format!(" (was {}%, {:+})", previous, delta))
+        }
+        (ChangeKind::Signal, None) => ('~', String::new()),
+    };
+
+    println!(
+        "{} {:<14} {:<24} {:<17}  ch {:<4} {:>3}% {}{}",
+        marker,
+        change.time,
+        truncate_ssid(&network.ssid, 24),
+        or_dash(Some(&network.bssid).filter(|b| !b.is_empty())),
+        or_dash(network.channel),
+        network.signal,
+        signal_to_bar(network.signal),
+        note
+    );
+}
+
+/// Displays a list of access points in a formatted table.
+///
+/// Prints network information including SSID, BSSID, channel, band, signal
+/// strength (numeric and visual), advertised rate and security type in a
+/// human-readable table format.
 ///
 /// # Arguments
 /// * `networks` - Slice of Network structs to display
 ///
 /// # Output Format
 /// ```text
-/// SSID                             SIGNAL SECURITY
-/// ------------------------------------------------------------
-/// MyHomeNetwork                      95% ████ WPA2
-/// GuestNetwork                       72% ███░ WPA2
-/// OpenCafe                           45% ██░░
+/// SSID                     BSSID              CH BAND   SIGNAL        RATE SECURITY
+/// ------------------------------------------------------------------------------------
+/// WAVESHARE Robot          24:0A:C4:12:34:56   6 2.4GHz  82% ████  54 Mb/s WPA2
+/// WAVESHARE Robot          24:0A:C4:9A:BC:DE  11 2.4GHz  47% ██░░  54 Mb/s WPA2
+/// HomeNetwork              3C:84:6A:01:02:03  36 5GHz    64% ███░ 270 Mb/s WPA3
+/// OpenCafe                 F8:1A:67:AA:BB:CC   1 2.4GHz  22% █░░░  54 Mb/s Open
 /// ```
 ///
 /// # Note
 /// Long SSIDs are truncated to fit within the column width with "..." appended.
+/// Values the backend does not report are shown as `-`.
 pub fn display_networks(networks: &[Network]) {
     // Handle empty results
     if networks.is_empty() {
@@ -172,52 +698,114 @@ pub fn display_networks(networks: &[Network]) {
 
     // Print table header with column alignment
     println!(
-        "{:<32} {:>6} {}",
-        "SSID", "SIGNAL", "SECURITY"
+        "{:<24} {:<17} {:>3} {:<6} {:<8} {:>9} SECURITY",
+        "SSID", "BSSID", "CH", "BAND", "SIGNAL", "RATE"
     );
-    println!("{}", "-".repeat(60));
+    println!("{}", "-".repeat(84));
 
-    // Print each network's information
+    // Print each access point's information
     for network in networks {
         // Convert numeric signal to visual bar representation
         let signal_bar = signal_to_bar(network.signal);
 
         // Print formatted row with truncated SSID if necessary
         println!(
-            "{:<32} {:>3}% {} {}",
-            truncate_ssid(&network.ssid, 32),  // SSID truncated to 32 chars
+            "{:<24} {:<17} {:>3} {:<6} {:>3}% {} {:>9} {}",
+            truncate_ssid(&network.ssid, 24),    // SSID truncated to 24 chars
+            or_dash(Some(&network.bssid).filter(|b| !b.is_empty())),
+            or_dash(network.channel),
+            or_dash(network.band),
             network.signal,                      // Signal percentage
             signal_bar,                          // Visual signal indicator
+            or_dash(network.max_rate_mbps.map(|r| format!("{} Mb/s", r))),
             network.security                     // Security type
         );
     }
 }
 
+/// Displays access points grouped by SSID, one row per network name.
+///
+/// Each row shows how many access points broadcast the SSID and the
+/// details of the strongest one.
+///
+/// # Output Format
+/// ```text
+/// SSID                     APS STRONGEST BSSID     CH BAND   SIGNAL   SECURITY
+/// ------------------------------------------------------------------------------
+/// WAVESHARE Robot            2 24:0A:C4:12:34:56   6 2.4GHz  82% ████ WPA2
+/// HomeNetwork                1 3C:84:6A:01:02:03  36 5GHz    64% ███░ WPA3
+/// ```
+pub fn display_groups(groups: &[NetworkGroup]) {
+    if groups.is_empty() {
+        println!("No networks found.");
+        return;
+    }
+
+    println!(
+        "{:<24} {:>3} {:<17} {:>3} {:<6} {:<8} SECURITY",
+        "SSID", "APS", "STRONGEST BSSID", "CH", "BAND", "SIGNAL"
+    );
+    println!("{}", "-".repeat(78));
+
+    for group in groups {
+        let Some(best) = group.strongest() else {
+            continue;
+        };
+        println!(
+            "{:<24} {:>3} {:<17} {:>3} {:<6} {:>3}% {} {}",
+            truncate_ssid(&group.ssid, 24),
+            group.access_points.len(),
+            or_dash(Some(&best.bssid).filter(|b| !b.is_empty())),
+            or_dash(best.channel),
+            or_dash(best.band),
+            best.signal,
+            signal_to_bar(best.signal),
+            best.security
+        );
+    }
+}
+
+/// Formats an optional table cell, showing `-` when the value is missing.
+pub(crate) fn or_dash<T: fmt::Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
 /// Truncates an SSID to fit within a maximum length.
 ///
-/// If the SSID is longer than `max_len`, it is truncated and "..." is appended.
-/// This ensures SSIDs don't overflow their column in the display table.
+/// If the SSID is longer than `max_len` characters, it is truncated and "..."
+/// is appended. This ensures SSIDs don't overflow their column in the display
+/// table. Lengths are counted in characters, not bytes, so SSIDs with
+/// non-ASCII characters are cut between characters and line up like the
+/// table's other cells. Hidden networks (empty SSID) are shown as `<hidden>`.
 ///
 /// # Arguments
 /// * `ssid` - The SSID string to potentially truncate
-/// * `max_len` - Maximum allowed length including the "..." suffix
+/// * `max_len` - Maximum allowed length in characters, including the "..." suffix
 ///
 /// # Returns
 /// The original SSID if it fits, or a truncated version with "..." appended.
 ///
 /// # Example
 /// ```
-/// # fn truncate_ssid(ssid: &str, max_len: usize) -> String {
-/// #     if ssid.len() > max_len { format!("{}...", &ssid[..max_len - 3]) }
-/// #     else { ssid.to_string() }
-/// # }
+/// use wifi_proxy::scan::truncate_ssid;
+///
 /// assert_eq!(truncate_ssid("Short", 10), "Short");
 /// assert_eq!(truncate_ssid("VeryLongNetworkName", 10), "VeryLon...");
+/// assert_eq!(truncate_ssid("", 10), "<hidden>");
+///
+/// // Multi-byte characters are never split
+/// assert_eq!(truncate_ssid("Café Wi-Fi Gäste", 10), "Café Wi...");
+/// assert_eq!(truncate_ssid("ロボット犬のネットワーク", 8), "ロボット犬...");
+/// assert_eq!(truncate_ssid("Ünïcödé", 7), "Ünïcödé");
 /// ```
-fn truncate_ssid(ssid: &str, max_len: usize) -> String {
-    if ssid.len() > max_len {
+pub fn truncate_ssid(ssid: &str, max_len: usize) -> String {
+    if ssid.is_empty() {
+        return "<hidden>".to_string();
+    }
+    if ssid.chars().count() > max_len {
         // Truncate and add "..." suffix (accounts for 3 chars)
-        format!("{}...", &ssid[..max_len - 3])
+        let kept: String = ssid.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", kept)
     } else {
         ssid.to_string()
     }
@@ -245,7 +833,7 @@ fn truncate_ssid(ssid: &str, max_len: usize) -> String {
 /// - 40-59%: Fair - May experience some slowdowns
 /// - 20-39%: Weak - Connection may be unreliable
 /// - <20%  : Very weak - Connection likely to drop
-fn signal_to_bar(signal: u8) -> &'static str {
+pub(crate) fn signal_to_bar(signal: u8) -> &'static str {
     match signal {
         80..=100 => "████",  // Excellent signal
         60..=79 => "███░",   // Good signal
@@ -21,7 +21,120 @@
 //!
 //! - `GET /` - Serves the control interface HTML page
 //! - `GET /control` - Proxies control commands to the robot's `/control` endpoint
-//! - `GET /stream` - Proxies the MJPEG video stream from the robot's camera
+//! - `GET /stream` - Proxies the MJPEG video stream from the robot's camera,
+//!   sharing one upstream connection among all viewers
+//! - `GET /ws` - WebSocket control channel with ordered delivery and acknowledgements
+//! - `GET /snapshot` - Most recent camera frame as a single JPEG
+//! - `GET /record` - Status of the current and last recording
+//! - `POST /record/start` - Starts recording the camera to disk
+//! - `POST /record/stop` - Stops the recording and returns its summary
+//! - `GET /macro` - Status of command recording and replay
+//! - `POST /macro/record/start` - Starts recording forwarded commands to JSONL
+//! - `POST /macro/record/stop` - Stops recording commands and returns its summary
+//! - `POST /macro/replay` - Replays a command recording against the robot
+//! - `POST /macro/replay/stop` - Aborts the replay (the robot is stopped)
+//! - `POST /heartbeat` - Keeps the client's held movement alive
+//! - `GET /watchdog` - Held movements and recent watchdog stops
+//! - `POST /api/estop` - Emergency stop: halts the robot and locks control
+//! - `POST /api/estop/release` - Releases the emergency stop lock
+//! - `GET /api/estop` - Lock state and timestamped engage/release history
+//! - `GET /api/throttle` - Forwarded, coalesced and dropped command counts
+//! - `GET /api/queue` - Commands waiting for the robot and delivery counts
+//! - `GET /api/link` - State of the WiFi link to the robot and reconnections
+//! - `GET /api/gateway` - Gateway currently targeted, and where it came from
+//! - `POST /api/gateway` - Switches to another gateway or re-resolves it
+//! - `GET /api/status` - Link, gateway and camera health, uptime and clients
+//! - `GET /api/link/quality` - Recent radio samples of the USB adapter's link
+//!
+//! # Command Queue
+//!
+//! Every command bound for the robot, from any client, passes through one
+//! [`CommandQueue`] that sends them one at a time in arrival order, so a
+//! release can never overtake the press before it. Stops skip ahead of
+//! waiting commands and discard waiting moves on their axis. Each queued
+//! command gets a sequence number (`X-Command-Seq` on `/control`, `"seq"` on
+//! `/ws`); when more than [`ServerConfig::queue_depth`] commands are waiting,
+//! new ones are refused with `503 Service Unavailable`.
+//!
+//! # Rate Limiting
+//!
+//! Commands pass through a [`CommandThrottle`] before reaching the robot.
+//! Stops are always forwarded at once. A move repeating what the robot last
+//! accepted on its axis is answered locally (`X-Proxy-Coalesced: 1` on `/control`,
+//! `"coalesced": true` on `/ws`). Other commands beyond
+//! [`ServerConfig::max_rate`] are dropped with `429 Too Many Requests`.
+//!
+//! # Link Supervision
+//!
+//! When serving through the USB adapter (no explicit gateway), a
+//! [`LinkSupervisor`] watches the interface and reconnects with the saved
+//! credentials when it drops, stopping the robot on link loss and again on
+//! reconnection (see [`crate::supervisor`]).
+//!
+//! # Gateway
+//!
+//! [`ServerConfig::gateway`] is only the starting point. The gateway is
+//! replaced when the supervised interface reports a different one (after a
+//! reconnection, a robot may come back on another address) or when an
+//! operator posts one to `/api/gateway`. A change redirects the command
+//! queue, closes every `/stream` response (viewers reconnect to the new
+//! camera) and is pushed to `/ws` clients:
+//!
+//! ```text
+//! {"type": "gateway", "gateway": "192.168.4.1", "stream_url": "http://192.168.4.1:81/stream", "source": "link", ...}
+//! ```
+//!
+//! # Status
+//!
+//! A [`HealthProber`] checks the USB interface, the gateway and the camera
+//! every [`ServerConfig::status_interval`] (see [`crate::health`]).
+//! `GET /api/status` combines its latest results with the link state, the
+//! emergency stop and client counts, and lists what is wrong in `problems`,
+//! so a failing control can be explained without probing on every request.
+//! The radio state comes from a [`LinkMonitor`] sampling the USB adapter;
+//! its recent samples are served by `/api/link/quality`.
+//!
+//! # Emergency Stop
+//!
+//! While the emergency stop is engaged, `/control` answers `423 Locked`,
+//! `/ws` commands are answered with errors, replays are refused and any
+//! running replay is aborted. Only an explicit release restores control.
+//!
+//! # Movement Watchdog
+//!
+//! A client that starts moving the robot must keep talking to the server
+//! until it sends the matching stop: any command, a `POST /heartbeat`, or a
+//! `{"heartbeat": true}` message on `/ws` will do. If it stays silent for
+//! longer than [`ServerConfig::watchdog_timeout`], the server stops the robot
+//! itself (see [`crate::watchdog`]).
+//!
+//! Clients are identified by the `X-Client-Id` header (or the `client` query
+//! parameter of `/ws`), falling back to the peer's IP address.
+//!
+//! # WebSocket Protocol
+//!
+//! Each text message from the client carries one [`RobotCommand`] in its JSON
+//! form and an optional client-chosen `id`:
+//!
+//! ```text
+//! {"id": 7, "command": {"move": "forward"}}
+//! ```
+//!
+//! Commands enter the command queue as soon as they arrive, in message
+//! order. Every message is answered, in the same order, with either an
+//! acknowledgement or an error:
+//!
+//! ```text
+//! {"type": "ack", "id": 7, "seq": 41, "command": "var=move&val=1&cmd=0", "rtt_ms": 12.4}
+//! {"type": "error", "id": 7, "seq": 41, "error": "Failed to fetch URL: ..."}
+//! ```
+//!
+//! Heartbeats (`{"heartbeat": true}`) are not answered. Watchdog stops of any
+//! client are pushed to every socket as they happen:
+//!
+//! ```text
+//! {"type": "watchdog", "client": "a1b2", "silent_ms": 1530, "stopped": [{"move": "stop_linear"}], ...}
+//! ```
 //!
 //! # CORS
 //!
@@ -30,18 +143,43 @@
 
 use axum::{
     body::Body,
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
-    routing::get,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Query, RawQuery, State,
+    },
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Json, Response},
+    routing::{get, post},
     Router,
 };
+use futures_util::{SinkExt, StreamExt};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tera::{Context, Tera};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::estop::{EmergencyStop, EstopEvent, EstopStatus};
+use crate::health::{HealthProber, HealthReport};
+use crate::mjpeg::{self, StreamHub};
+use crate::monitor::{LinkMonitor, LinkSample};
+use crate::queue::{CommandQueue, DeliveryError, QueueStats, Ticket};
+use crate::recorder::{self, RecordFormat, RecordingSummary};
+use crate::replay::{self, CommandRecorder, MacroSummary, ReplaySummary};
+use crate::error::WifiProxyError;
+use crate::robot::RobotCommand;
+use crate::supervisor::{LinkState, LinkStatus, LinkSupervisor, ReconnectConfig};
+use crate::throttle::{query_var, CommandThrottle, ThrottleStats, Verdict};
+use crate::watchdog::{Watchdog, WatchdogEvent, WatchdogStatus};
+
 // Initialize template engine at program startup using lazy_static
 // This ensures templates are loaded once and reused for all requests
 lazy_static! {
@@ -74,13 +212,347 @@ lazy_static! {
 /// Contains all settings needed to start and run the server,
 /// including the target gateway address and the listening port.
 pub struct ServerConfig {
-    /// The IP address of the robot's gateway (e.g., "192.168.4.1").
-    /// All proxy requests will be forwarded to this address.
+    /// The address of the robot's gateway (e.g., "192.168.4.1") at startup.
+    /// All proxy requests are forwarded to this address until the link
+    /// supervisor or `POST /api/gateway` replaces it. A port may be included
+    /// (e.g., "127.0.0.1:8000") to target a mock robot.
     pub gateway: String,
 
+    /// The TCP port of the robot's camera stream (81 on the ESP32-CAM).
+    pub stream_port: u16,
+
     /// The local TCP port to listen on (e.g., 8080).
     /// The server will be accessible at `http://localhost:<port>/`.
     pub port: u16,
+
+    /// Maximum time to establish a TCP connection to the gateway.
+    pub connect_timeout: Duration,
+
+    /// Maximum time for a control request, from sending it to reading the
+    /// robot's full answer. Does not apply to the endless camera stream.
+    pub request_timeout: Duration,
+
+    /// Directory where recordings started through `/record/start` and
+    /// `/macro/record/start` are saved, and replays are loaded from.
+    pub recordings_dir: PathBuf,
+
+    /// JSONL file to record forwarded commands into from startup, if any.
+    pub record_commands: Option<PathBuf>,
+
+    /// Longest a client may stay silent while holding a movement before the
+    /// robot is stopped for it. `None` disables the watchdog.
+    pub watchdog_timeout: Option<Duration>,
+
+    /// File that emergency stop engagements and releases are appended to.
+    pub estop_log: Option<PathBuf>,
+
+    /// Commands per second forwarded per `var`; `None` disables the limit.
+    /// Stop commands are exempt.
+    pub max_rate: Option<f64>,
+
+    /// Window in which a repeated identical move is coalesced; `None`
+    /// forwards every repeat.
+    pub coalesce_window: Option<Duration>,
+
+    /// Commands that may wait for the robot before new ones are refused.
+    /// Stops are never refused.
+    pub queue_depth: usize,
+
+    /// Interface and credentials used to restore the link to the robot when
+    /// it drops; `None` when the gateway was given explicitly.
+    pub reconnect: Option<ReconnectConfig>,
+
+    /// USB interface the robot is reached through, reported by
+    /// `/api/status` and sampled for `/api/link/quality`; `None` when the
+    /// gateway was given explicitly.
+    pub interface: Option<String>,
+
+    /// Time between two rounds of health probes and link quality samples.
+    pub status_interval: Duration,
+}
+
+/// State shared by all request handlers.
+struct ServerState {
+    /// Configuration the server was started with.
+    config: ServerConfig,
+
+    /// Shares one camera connection among all `/stream` viewers.
+    stream: Arc<StreamHub>,
+
+    /// Recording started through the API, and the outcome of the last one.
+    recording: tokio::sync::Mutex<RecordingSlot>,
+
+    /// Command recording in progress, if any.
+    commands: std::sync::Mutex<Option<CommandRecorder>>,
+
+    /// Replay in progress, and the outcome of the last one.
+    replay: tokio::sync::Mutex<ReplaySlot>,
+
+    /// Stops movements whose clients went silent.
+    watchdog: Arc<Watchdog>,
+
+    /// Emergency stop lock checked before every control command.
+    estop: EmergencyStop,
+
+    /// Coalesces and rate-limits commands toward the gateway; told by the
+    /// queue what the robot accepted.
+    throttle: Arc<CommandThrottle>,
+
+    /// Delivers commands to the robot one at a time, in arrival order.
+    queue: Arc<CommandQueue>,
+
+    /// Watches the WiFi link and reconnects when it drops.
+    link: Arc<LinkSupervisor>,
+
+    /// Gateway currently targeted; subscribers are told about changes.
+    gateway: watch::Sender<GatewayInfo>,
+
+    /// Probes the link, gateway and camera in the background.
+    health: Arc<HealthProber>,
+
+    /// Samples the USB interface's radio link, if there is one.
+    monitor: Option<Arc<LinkMonitor>>,
+
+    /// Time the server was started, for the uptime.
+    started: Instant,
+
+    /// Unix seconds (with milliseconds) the server was started at.
+    started_at: String,
+
+    /// Open `/ws` sessions.
+    ws_clients: AtomicUsize,
+}
+
+/// Where the current gateway came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum GatewaySource {
+    /// The gateway the server was started with.
+    Startup,
+    /// Reported by the supervised interface.
+    Link,
+    /// Set through `POST /api/gateway`.
+    Admin,
+}
+
+/// The gateway commands and the camera stream are currently sent to.
+#[derive(Debug, Clone, Serialize)]
+struct GatewayInfo {
+    /// Address `/control` requests are forwarded to.
+    gateway: String,
+
+    /// Camera stream the `/stream` viewers share.
+    stream_url: String,
+
+    /// Where the gateway came from.
+    source: GatewaySource,
+
+    /// Unix seconds (with milliseconds) since the gateway is in use.
+    since: String,
+
+    /// The gateway used before, if it was ever changed.
+    previous: Option<String>,
+}
+
+/// A recording running in the background.
+struct ActiveRecording {
+    /// File or directory being written.
+    output: PathBuf,
+
+    /// Container format.
+    format: RecordFormat,
+
+    /// Time the recording was requested.
+    started: SystemTime,
+
+    /// Cancelled to stop the recording.
+    stop: CancellationToken,
+
+    /// Task writing the recording; yields its summary when done.
+    task: JoinHandle<anyhow::Result<RecordingSummary>>,
+}
+
+/// Server-side recording state.
+#[derive(Default)]
+struct RecordingSlot {
+    /// The recording in progress, if any.
+    active: Option<ActiveRecording>,
+
+    /// Outcome of the most recently finished recording.
+    last: Option<Result<RecordingSummary, String>>,
+}
+
+impl RecordingSlot {
+    /// Moves a recording that ended on its own (duration reached or camera
+    /// failure) into `last`.
+    async fn reap(&mut self) {
+        if self.active.as_ref().is_some_and(|a| a.task.is_finished()) {
+            let active = self.active.take().unwrap();
+            self.last = Some(join_recording(active.task).await);
+        }
+    }
+}
+
+/// A command replay running in the background.
+struct ActiveReplay {
+    /// Recording being played.
+    source: PathBuf,
+
+    /// Playback speed factor.
+    speed: f64,
+
+    /// Time the replay was started.
+    started: SystemTime,
+
+    /// Cancelled to abort the replay.
+    stop: CancellationToken,
+
+    /// Task driving the robot; yields its summary when done.
+    task: JoinHandle<anyhow::Result<ReplaySummary>>,
+}
+
+/// Server-side replay state.
+#[derive(Default)]
+struct ReplaySlot {
+    /// The replay in progress, if any.
+    active: Option<ActiveReplay>,
+
+    /// Outcome of the most recently finished replay.
+    last: Option<Result<ReplaySummary, String>>,
+}
+
+impl ReplaySlot {
+    /// Moves a replay that ended on its own into `last`.
+    async fn reap(&mut self) {
+        if self.active.as_ref().is_some_and(|a| a.task.is_finished()) {
+            let active = self.active.take().unwrap();
+            self.last = Some(join_replay(active.task).await);
+        }
+    }
+}
+
+impl ServerState {
+    /// Builds the shared state and its pooled upstream client.
+    fn new(config: ServerConfig) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_nodelay(true)
+            .build()?;
+
+        // One hub for the camera port of the gateway host
+        let url = stream_url(&config.gateway, config.stream_port);
+        let stream = Arc::new(StreamHub::new(http.clone(), &url));
+
+        // Optionally capture commands from the very first one
+        let commands = match &config.record_commands {
+            Some(path) => Some(CommandRecorder::create(path)?),
+            None => None,
+        };
+        let watchdog = Arc::new(Watchdog::new(config.watchdog_timeout));
+        let estop = EmergencyStop::new(config.estop_log.clone());
+        let throttle = Arc::new(CommandThrottle::new(config.max_rate, config.coalesce_window));
+        let queue = Arc::new(
+            CommandQueue::new(http.clone(), &config.gateway, config.request_timeout, config.queue_depth)
+                .on_sent({
+                    // Coalesce only against moves the robot accepted, and
+                    // forget them on every stop, whoever sent it
+                    let throttle = throttle.clone();
+                    move |command, accepted| throttle.sent(command, accepted)
+                }),
+        );
+        let link = Arc::new(LinkSupervisor::new(config.reconnect.clone(), &config.gateway));
+        let monitor = config
+            .interface
+            .as_deref()
+            .map(|interface| Arc::new(LinkMonitor::new(interface, config.status_interval)));
+        let health = Arc::new(HealthProber::new(
+            queue.clone(),
+            monitor.clone(),
+            &config.gateway,
+            &url,
+            config.status_interval,
+            config.request_timeout,
+        ));
+        let gateway = watch::Sender::new(GatewayInfo {
+            gateway: config.gateway.clone(),
+            stream_url: url,
+            source: GatewaySource::Startup,
+            since: mjpeg::unix_timestamp(SystemTime::now()),
+            previous: None,
+        });
+
+        Ok(Self {
+            config,
+            stream,
+            recording: tokio::sync::Mutex::new(RecordingSlot::default()),
+            commands: std::sync::Mutex::new(commands),
+            replay: tokio::sync::Mutex::new(ReplaySlot::default()),
+            watchdog,
+            estop,
+            throttle,
+            queue,
+            link,
+            gateway,
+            health,
+            monitor,
+            started: Instant::now(),
+            started_at: mjpeg::unix_timestamp(SystemTime::now()),
+            ws_clients: AtomicUsize::new(0),
+        })
+    }
+
+    /// Points commands and the camera stream at another gateway.
+    ///
+    /// Waiting commands are delivered to the new gateway. Every `/stream`
+    /// response is closed, since its frames came from the old camera.
+    /// Nothing happens if `gateway` is already in use.
+    ///
+    /// # Arguments
+    /// * `gateway` - New gateway address, optionally with a port
+    /// * `source` - What asked for the change
+    ///
+    /// # Returns
+    /// The gateway in use after the call.
+    fn set_gateway(&self, gateway: &str, source: GatewaySource) -> GatewayInfo {
+        self.gateway.send_if_modified(|info| {
+            if info.gateway == gateway {
+                return false;
+            }
+
+            // Commands still waiting are delivered to the new gateway
+            self.queue.set_gateway(gateway);
+            let url = stream_url(gateway, self.config.stream_port);
+            let closed = self.stream.retarget(&url);
+            self.health.set_targets(gateway, &url);
+            println!(
+                "Gateway changed: {} -> {} ({:?}, {} viewer(s) disconnected)",
+                info.gateway, gateway, source, closed
+            );
+
+            *info = GatewayInfo {
+                gateway: gateway.to_string(),
+                stream_url: url,
+                source,
+                since: mjpeg::unix_timestamp(SystemTime::now()),
+                previous: Some(info.gateway.clone()),
+            };
+            true
+        });
+
+        self.gateway.borrow().clone()
+    }
+
+    /// Appends a forwarded command to the command recording, if one is running.
+    ///
+    /// A failed write is logged but never blocks the command itself.
+    fn log_command(&self, query: &str) {
+        if let Some(recorder) = self.commands.lock().unwrap().as_ref()
+            && let Err(e) = recorder.record(query)
+        {
+            eprintln!("Failed to record command: {:#}", e);
+        }
+    }
 }
 
 /// Starts the HTTP proxy server with the given configuration.
@@ -99,23 +571,49 @@ pub struct ServerConfig {
 /// - `GET /` - Index page handler (serves the control interface)
 /// - `GET /control` - Control command proxy (forwards to robot)
 /// - `GET /stream` - Video stream proxy (forwards MJPEG from robot camera)
+/// - `GET /ws` - WebSocket control channel (ordered, acknowledged commands)
+/// - `GET /snapshot` - Latest camera frame as a JPEG
+/// - `GET /record`, `POST /record/start`, `POST /record/stop` - Camera recording
+/// - `GET /macro` and `POST /macro/...` - Command recording and replay
+/// - `POST /heartbeat`, `GET /watchdog` - Movement watchdog
+/// - `GET /api/estop`, `POST /api/estop`, `POST /api/estop/release` - Emergency stop
+/// - `GET /api/throttle` - Rate limiting counters
+/// - `GET /api/queue` - Command queue state
+/// - `GET /api/link` - Link supervision state
+/// - `GET /api/gateway`, `POST /api/gateway` - Current gateway and switching it
+/// - `GET /api/status` - Health of the link, gateway and camera
+/// - `GET /api/link/quality` - Recent signal, bitrate and channel samples
 ///
 /// # Example
 /// ```no_run
+/// use std::time::Duration;
 /// use wifi_proxy::server::{run_server, ServerConfig};
 ///
 /// #[tokio::main]
 /// async fn main() {
 ///     let config = ServerConfig {
 ///         gateway: "192.168.4.1".to_string(),
+///         stream_port: 81,
 ///         port: 8080,
+///         connect_timeout: Duration::from_secs(2),
+///         request_timeout: Duration::from_secs(5),
+///         recordings_dir: "recordings".into(),
+///         record_commands: None,
+///         watchdog_timeout: Some(Duration::from_millis(1500)),
+///         estop_log: Some("estop.log".into()),
+///         max_rate: Some(10.0),
+///         coalesce_window: Some(Duration::from_secs(1)),
+///         queue_depth: 16,
+///         reconnect: None,
+///         interface: None,
+///         status_interval: Duration::from_secs(2),
 ///     };
 ///     run_server(config).await.expect("Server failed");
 /// }
 /// ```
 pub async fn run_server(config: ServerConfig) -> anyhow::Result<()> {
-    // Wrap config in Arc for shared ownership across async handlers
-    let state = Arc::new(config);
+    // Wrap state in Arc for shared ownership across async handlers
+    let state = Arc::new(ServerState::new(config)?);
 
     // Configure CORS to allow requests from any origin
     // This is necessary for web-based control interfaces
@@ -129,17 +627,77 @@ pub async fn run_server(config: ServerConfig) -> anyhow::Result<()> {
         .route("/", get(index_handler))           // Main control interface page
         .route("/control", get(control_proxy))    // Robot control commands
         .route("/stream", get(stream_proxy))      // Camera video stream
+        .route("/ws", get(ws_handler))            // WebSocket control channel
+        .route("/snapshot", get(snapshot_handler)) // Latest camera frame
+        .route("/record", get(record_status))     // Recording status
+        .route("/record/start", post(record_start)) // Start recording the camera
+        .route("/record/stop", post(record_stop)) // Stop recording
+        .route("/macro", get(macro_status))       // Command recording/replay status
+        .route("/macro/record/start", post(macro_record_start)) // Record commands
+        .route("/macro/record/stop", post(macro_record_stop)) // Stop recording commands
+        .route("/macro/replay", post(macro_replay)) // Replay a command recording
+        .route("/macro/replay/stop", post(macro_replay_stop)) // Abort the replay
+        .route("/heartbeat", post(heartbeat_handler)) // Keep held movement alive
+        .route("/watchdog", get(watchdog_status)) // Watchdog state and events
+        .route("/api/estop", get(estop_status).post(estop_engage)) // Emergency stop
+        .route("/api/estop/release", post(estop_release)) // Hand control back
+        .route("/api/throttle", get(throttle_stats)) // Rate limiting counters
+        .route("/api/queue", get(queue_stats))    // Command queue state
+        .route("/api/link", get(link_status))     // WiFi link state
+        .route("/api/gateway", get(gateway_status).post(gateway_update)) // Current gateway
+        .route("/api/status", get(server_status)) // Health and client counts
+        .route("/api/link/quality", get(link_quality)) // Signal and bitrate history
         .layer(cors)                              // Apply CORS middleware
-        .with_state(state.clone());               // Share config with handlers
+        .with_state(state.clone());               // Share state with handlers
 
     // Bind to all interfaces (0.0.0.0) on the configured port
-    let addr = format!("0.0.0.0:{}", state.port);
-    println!("Starting server at http://localhost:{}", state.port);
-    println!("Proxying to gateway: {}", state.gateway);
+    let addr = format!("0.0.0.0:{}", state.config.port);
+    println!("Starting server at http://localhost:{}", state.config.port);
+    println!("Proxying to gateway: {}", state.gateway.borrow().gateway);
+    if let Some(path) = &state.config.record_commands {
+        println!("Recording commands to {}", path.display());
+    }
+
+    // Stop the robot for clients that go silent mid-movement
+    match state.config.watchdog_timeout {
+        Some(timeout) => println!("Movement watchdog: {}ms", timeout.as_millis()),
+        None => println!("Movement watchdog: disabled"),
+    }
+    tokio::spawn(state.watchdog.clone().run(state.queue.clone()));
+
+    // One worker delivers the commands of every client in order
+    tokio::spawn(state.queue.clone().run());
+
+    // Restore the WiFi link if it drops while serving
+    if let Some(reconnect) = &state.config.reconnect {
+        println!("Supervising link: {} on {}", reconnect.ssid, reconnect.interface);
+    }
+    tokio::spawn(state.link.clone().run(state.queue.clone()));
+
+    // Probe the robot in the background; /api/status reads the results
+    if let Some(monitor) = &state.monitor {
+        tokio::spawn(monitor.clone().run());
+    }
+    tokio::spawn(state.health.clone().run());
+
+    // Follow the gateway reported by the interface, e.g. after reconnecting
+    tokio::spawn({
+        let state = state.clone();
+        let mut reported = state.link.watch_gateway();
+        async move {
+            while reported.changed().await.is_ok() {
+                let gateway = reported.borrow_and_update().clone();
+                if let Some(gateway) = gateway {
+                    state.set_gateway(&gateway, GatewaySource::Link);
+                }
+            }
+        }
+    });
 
-    // Create TCP listener and start serving requests
+    // Create TCP listener and start serving requests; peer addresses
+    // identify clients that do not send an X-Client-Id header
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -174,111 +732,1259 @@ async fn index_handler() -> impl IntoResponse {
 
 /// Handler for control commands (`GET /control`).
 ///
-/// Proxies control requests to the robot's gateway. Query parameters from
-/// the incoming request are forwarded unchanged to the robot's `/control`
-/// endpoint. This allows the web interface to send motor commands, LED
-/// controls, and other robot functions.
+/// Proxies control requests to the robot's gateway. The query string of the
+/// incoming request is forwarded unchanged (parameter order included) to the
+/// robot's `/control` endpoint. This allows the web interface to send motor
+/// commands, LED controls, and other robot functions. While a command
+/// recording is running, the query string is appended to it.
+///
+/// Movement commands are reported to the watchdog on behalf of the client,
+/// which must then keep the heartbeat going until it releases the movement.
+///
+/// Before forwarding, the command passes the [`CommandThrottle`]: a repeated
+/// move is answered without contacting the robot, and a command over the
+/// rate limit is refused. Stops are never held back.
+///
+/// Forwarded commands enter the [`CommandQueue`], which sends them to the
+/// robot one at a time in arrival order over the shared pooled client. The
+/// command's sequence number is returned in the `X-Command-Seq` header.
 ///
 /// # Arguments
-/// * `State(config)` - Shared server configuration containing gateway address
-/// * `Query(params)` - Query parameters to forward to the robot
+/// * `State(state)` - Shared server state with the gateway address and client
+/// * `ConnectInfo(peer)` - Address of the client, used when it sends no id
+/// * `headers` - Request headers, possibly carrying `X-Client-Id`
+/// * `RawQuery(query)` - Query string to forward to the robot
 ///
 /// # Returns
-/// - `200 OK` with the robot's response body on success
-/// - `502 Bad Gateway` if the proxy request to the robot fails
+/// - The robot's status code and response body if it answered
+/// - `200 OK` with an empty body and `X-Proxy-Coalesced: 1` for a coalesced repeat
+/// - `200 OK` with an empty body and `X-Proxy-Superseded: 1` for a move that
+///   a later stop made obsolete before it was sent
+/// - `423 Locked` while the emergency stop is engaged
+/// - `429 Too Many Requests` if the command's variable is over the rate limit
+/// - `503 Service Unavailable` if the command queue is full, or the queued
+///   command was discarded by the emergency stop or on link loss
+/// - `504 Gateway Timeout` if connecting or the request exceeded its timeout
+/// - `502 Bad Gateway` if the robot could not be reached or the connection failed
 ///
 /// # Example Request Flow
 /// ```text
-/// Browser: GET /control?var=speed&val=100
+/// Browser: GET /control?var=move&val=1&cmd=0
 ///    │
 ///    ▼
-/// Proxy: GET http://192.168.4.1/control?var=speed&val=100
+/// Proxy: GET http://192.168.4.1/control?var=move&val=1&cmd=0
 ///    │
 ///    ▼
 /// Robot: Processes command, returns response
 /// ```
 async fn control_proxy(
-    State(config): State<Arc<ServerConfig>>,
-    Query(params): Query<HashMap<String, String>>,
-) -> impl IntoResponse {
-    // Reconstruct the query string from the parsed parameters
-    let query_string: String = params
-        .iter()
-        .map(|(k, v)| format!("{}={}", k, v))
-        .collect::<Vec<_>>()
-        .join("&");
-
-    // Build the full URL to the robot's control endpoint
-    let url = format!("http://{}/control?{}", config.gateway, query_string);
-
-    // Forward the request to the robot using ureq (blocking HTTP client)
-    match ureq::get(&url).call() {
-        Ok(response) => {
-            // Successfully received response from robot
-            let body = response.into_string().unwrap_or_default();
-            (StatusCode::OK, body)
+    State(state): State<Arc<ServerState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+) -> Response {
+    // Nothing reaches the robot while the emergency stop is engaged
+    if let Err(e) = state.estop.check() {
+        return (StatusCode::LOCKED, e.to_string()).into_response();
+    }
+
+    let query = query.unwrap_or_default();
+    let client = client_id(&headers, peer);
+    let command = RobotCommand::from_query(&query).ok();
+
+    // Protect the robot's web server from floods; stops always pass
+    match state.throttle.admit(&query) {
+        Verdict::Forward => {}
+        Verdict::Coalesced => {
+            if let Some(command) = &command {
+                state.watchdog.observe(&client, command);
+            }
+            return (StatusCode::OK, [("x-proxy-coalesced", "1")], "").into_response();
         }
-        Err(e) => {
-            // Failed to reach the robot or request error
-            (StatusCode::BAD_GATEWAY, format!("Proxy error: {}", e))
+        Verdict::Dropped => {
+            state.watchdog.heartbeat(&client);
+            let error = WifiProxyError::RateLimited(query_var(&query).to_string());
+            return (StatusCode::TOO_MANY_REQUESTS, error.to_string()).into_response();
+        }
+    }
+
+    // Capture the command for replay before it reaches the robot
+    if !query.is_empty() {
+        state.log_command(&query);
+    }
+
+    // Track held movement even if the answer is lost: the robot may have
+    // acted on the command anyway
+    if let Some(command) = &command {
+        state.watchdog.observe(&client, command);
+    }
+
+    // Wait for our turn; the request timeout starts when the command is sent
+    let ticket = state.queue.submit(&query);
+    let seq = ticket.seq();
+    let mut response = match ticket.delivered().await {
+        Ok(delivery) => {
+            // Relay the robot's answer, keeping its status code
+            let status = StatusCode::from_u16(delivery.status).unwrap_or(StatusCode::BAD_GATEWAY);
+            (status, delivery.body).into_response()
+        }
+        Err(DeliveryError::Superseded { .. }) => {
+            (StatusCode::OK, [("x-proxy-superseded", "1")], "").into_response()
+        }
+        Err(e) => delivery_error(e),
+    };
+    response.headers_mut().insert("x-command-seq", HeaderValue::from(seq));
+    response
+}
+
+/// Identifies the client behind a request.
+///
+/// # Returns
+/// The `X-Client-Id` header if present and non-empty, otherwise the IP
+/// address of the peer.
+fn client_id(headers: &HeaderMap, peer: SocketAddr) -> String {
+    headers
+        .get("x-client-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| peer.ip().to_string())
+}
+
+/// Response of `POST /heartbeat`.
+#[derive(Debug, Serialize)]
+struct HeartbeatReply {
+    /// Whether the client still holds a movement. `false` after the
+    /// watchdog has stopped it.
+    holding: bool,
+
+    /// Heartbeat timeout in milliseconds, or `None` when the watchdog is off.
+    timeout_ms: Option<u64>,
+}
+
+/// Handler for `POST /heartbeat`: keeps the client's held movement alive.
+async fn heartbeat_handler(
+    State(state): State<Arc<ServerState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Json<HeartbeatReply> {
+    let holding = state.watchdog.heartbeat(&client_id(&headers, peer));
+    Json(HeartbeatReply {
+        holding,
+        timeout_ms: state.watchdog.timeout().map(|t| t.as_millis() as u64),
+    })
+}
+
+/// Handler for `GET /api/throttle`: reports forwarded, coalesced and dropped counts.
+async fn throttle_stats(State(state): State<Arc<ServerState>>) -> Json<ThrottleStats> {
+    Json(state.throttle.stats())
+}
+
+/// Handler for `GET /watchdog`: reports held movements and recent stops.
+async fn watchdog_status(State(state): State<Arc<ServerState>>) -> Json<WatchdogStatus> {
+    Json(state.watchdog.status())
+}
+
+/// Handler for `GET /api/queue`: reports waiting commands and delivery counts.
+async fn queue_stats(State(state): State<Arc<ServerState>>) -> Json<QueueStats> {
+    Json(state.queue.stats())
+}
+
+/// Handler for `GET /api/link`: reports the WiFi link state and recent changes.
+async fn link_status(State(state): State<Arc<ServerState>>) -> Json<LinkStatus> {
+    Json(state.link.status())
+}
+
+/// Body of a `GET /api/status` response.
+#[derive(Debug, Serialize)]
+struct ServerStatus {
+    /// Whether nothing is listed in `problems`.
+    healthy: bool,
+
+    /// What currently keeps the robot from being controlled or watched.
+    problems: Vec<String>,
+
+    /// Unix seconds (with milliseconds) the server was started at.
+    started: String,
+
+    /// Seconds since the server was started.
+    uptime_secs: f64,
+
+    /// Gateway in use.
+    gateway: GatewayInfo,
+
+    /// State of the supervised WiFi link.
+    link: LinkState,
+
+    /// Whether the emergency stop locks control.
+    estop_engaged: bool,
+
+    /// Latest probe results.
+    #[serde(flatten)]
+    health: HealthReport,
+
+    /// Connected clients.
+    clients: ClientCounts,
+}
+
+/// Clients connected to the server.
+#[derive(Debug, Serialize)]
+struct ClientCounts {
+    /// Open `/ws` control sessions.
+    websocket: usize,
+
+    /// Open `/stream` responses.
+    stream_viewers: usize,
+
+    /// Clients currently holding a movement.
+    holding_movement: usize,
+}
+
+/// Handler for `GET /api/status`.
+///
+/// Answers from the health prober's latest results; the robot is never
+/// contacted by this request.
+async fn server_status(State(state): State<Arc<ServerState>>) -> Json<ServerStatus> {
+    let health = state.health.report();
+    let link = state.link.state();
+    let estop_engaged = state.estop.status().engaged;
+
+    // Lock and link problems first, as they explain the probe failures
+    let mut problems = Vec::new();
+    if estop_engaged {
+        problems.push("Emergency stop engaged".to_string());
+    }
+    match link {
+        LinkState::Reconnecting => problems.push("WiFi link lost, reconnecting".to_string()),
+        LinkState::Failed => problems.push("WiFi link lost, reconnection gave up".to_string()),
+        LinkState::Connected | LinkState::Unmanaged => {}
+    }
+    problems.extend(health.problems());
+
+    Json(ServerStatus {
+        healthy: problems.is_empty(),
+        problems,
+        started: state.started_at.clone(),
+        uptime_secs: state.started.elapsed().as_secs_f64(),
+        gateway: state.gateway.borrow().clone(),
+        link,
+        estop_engaged,
+        health,
+        clients: ClientCounts {
+            websocket: state.ws_clients.load(Ordering::Relaxed),
+            stream_viewers: state.stream.subscriber_count(),
+            holding_movement: state.watchdog.status().held.len(),
+        },
+    })
+}
+
+/// Body of a `GET /api/link/quality` response.
+#[derive(Debug, Serialize)]
+struct LinkQualityReport {
+    /// Interface sampled.
+    interface: String,
+
+    /// Time between two samples, in milliseconds.
+    interval_ms: u64,
+
+    /// Change of the signal over the trend window, in percentage points.
+    signal_trend: Option<i32>,
+
+    /// Recent samples, oldest first.
+    samples: Vec<LinkSample>,
+}
+
+/// Handler for `GET /api/link/quality`.
+///
+/// # Returns
+/// - `200 OK` with the recent samples of the USB adapter's link
+/// - `404 Not Found` when serving without an interface (explicit gateway)
+async fn link_quality(State(state): State<Arc<ServerState>>) -> Response {
+    let Some(monitor) = &state.monitor else {
+        let message = "No interface is monitored (explicit gateway)";
+        return (StatusCode::NOT_FOUND, message).into_response();
+    };
+    Json(LinkQualityReport {
+        interface: monitor.interface().to_string(),
+        interval_ms: monitor.interval().as_millis() as u64,
+        signal_trend: monitor.trend(),
+        samples: monitor.history(),
+    })
+    .into_response()
+}
+
+/// Body of a `POST /api/gateway` request.
+#[derive(Debug, Default, Deserialize)]
+struct GatewayRequest {
+    /// New gateway address; re-read from the supervised interface if absent.
+    #[serde(default)]
+    gateway: Option<String>,
+}
+
+/// Handler for `GET /api/gateway`: reports the gateway in use.
+async fn gateway_status(State(state): State<Arc<ServerState>>) -> Json<GatewayInfo> {
+    Json(state.gateway.borrow().clone())
+}
+
+/// Handler for `POST /api/gateway`.
+///
+/// Switches commands and the camera stream to the given gateway, or, when
+/// the body names none, to the gateway the supervised interface reports now.
+/// Open `/stream` responses are closed if the gateway changes.
+///
+/// # Returns
+/// - `200 OK` with the gateway in use
+/// - `400 Bad Request` if the address is malformed
+/// - `409 Conflict` if no gateway is given and no interface is supervised
+/// - `502 Bad Gateway` if the interface has no working connection
+async fn gateway_update(
+    State(state): State<Arc<ServerState>>,
+    request: Option<Json<GatewayRequest>>,
+) -> Response {
+    let Json(request) = request.unwrap_or_default();
+
+    let info = match request.gateway {
+        Some(gateway) => {
+            let gateway = gateway.trim();
+            if let Err(e) = validate_gateway(gateway) {
+                return (StatusCode::BAD_REQUEST, e).into_response();
+            }
+            state.set_gateway(gateway, GatewaySource::Admin)
+        }
+        None if state.link.state() == LinkState::Unmanaged => {
+            let message = "No interface is supervised; give a gateway explicitly";
+            return (StatusCode::CONFLICT, message).into_response();
+        }
+        None => match state.link.resolve_gateway().await {
+            Ok(gateway) => state.set_gateway(&gateway, GatewaySource::Link),
+            Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+        },
+    };
+    Json(info).into_response()
+}
+
+/// Checks that a gateway address is a bare host with an optional port.
+///
+/// # Returns
+/// - `Ok(())` for addresses such as "192.168.4.1" or "127.0.0.1:8000"
+/// - `Err` describing the problem otherwise
+fn validate_gateway(gateway: &str) -> Result<(), String> {
+    if gateway.is_empty() {
+        return Err("Gateway must not be empty".to_string());
+    }
+    if gateway.contains(|c: char| c.is_whitespace() || c == '/' || c == '?' || c == '#') {
+        return Err(format!("Invalid gateway '{}': expected host or host:port", gateway));
+    }
+    if let Some((host, port)) = gateway.rsplit_once(':')
+        && !host.contains(':')
+        && (host.is_empty() || port.parse::<u16>().is_err())
+    {
+        return Err(format!("Invalid gateway '{}': bad host or port", gateway));
+    }
+    Ok(())
+}
+
+/// Maps a command that was not delivered to a proxy error response.
+///
+/// Upstream timeouts become `504 Gateway Timeout` and other upstream failures
+/// (refused or reset connections, DNS failures, malformed answers) `502 Bad
+/// Gateway`. A full queue or a discarded command gives `503 Service
+/// Unavailable`.
+fn delivery_error(e: DeliveryError) -> Response {
+    match e {
+        DeliveryError::Upstream { timeout: true, message } => {
+            (StatusCode::GATEWAY_TIMEOUT, format!("Proxy timeout: {}", message)).into_response()
+        }
+        DeliveryError::Upstream { timeout: false, message } => {
+            (StatusCode::BAD_GATEWAY, format!("Proxy error: {}", message)).into_response()
+        }
+        DeliveryError::QueueFull(_) | DeliveryError::Cancelled => {
+            (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+        }
+        DeliveryError::Superseded { .. } => (StatusCode::CONFLICT, e.to_string()).into_response(),
+    }
+}
+
+/// A command message received over `/ws`.
+#[derive(Debug, Deserialize)]
+struct WsRequest {
+    /// Client-chosen identifier echoed back in the reply.
+    #[serde(default)]
+    id: Option<u64>,
+
+    /// The command to forward to the robot.
+    command: RobotCommand,
+}
+
+/// A reply pushed to the client over `/ws`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WsReply {
+    /// The robot accepted the command.
+    Ack {
+        id: Option<u64>,
+        /// Sequence number in the command queue; absent for coalesced commands.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>,
+        /// Human-readable form of the command that was sent.
+        command: String,
+        /// Time from queueing the command to the robot's answer.
+        rtt_ms: f64,
+        /// The command repeated what the robot is already doing and was
+        /// not forwarded.
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        coalesced: bool,
+        /// A later stop made the command obsolete before it was sent.
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        superseded: bool,
+    },
+
+    /// The message was malformed or the robot could not be reached.
+    Error {
+        id: Option<u64>,
+        /// Sequence number in the command queue, if the command was queued.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>,
+        error: String,
+    },
+
+    /// The watchdog stopped a movement of some client.
+    Watchdog(WatchdogEvent),
+
+    /// Commands and the camera stream moved to another gateway.
+    Gateway(GatewayInfo),
+}
+
+/// The reply to a `/ws` message, possibly still waiting for the robot.
+enum WsPending {
+    /// Answered without reaching the queue.
+    Ready(WsReply),
+
+    /// Queued; the reply is built once the robot answers.
+    Queued {
+        /// Client-chosen identifier of the message.
+        id: Option<u64>,
+
+        /// The command that was queued.
+        command: RobotCommand,
+
+        /// Time the command was queued.
+        started: Instant,
+
+        /// Resolves to the robot's answer.
+        ticket: Ticket,
+    },
+}
+
+impl WsPending {
+    /// Waits for the robot's answer, if needed, and builds the reply.
+    async fn resolve(self) -> WsReply {
+        let (id, command, started, ticket) = match self {
+            WsPending::Ready(reply) => return reply,
+            WsPending::Queued { id, command, started, ticket } => (id, command, started, ticket),
+        };
+
+        let seq = Some(ticket.seq());
+        let superseded = match ticket.delivered().await {
+            Ok(delivery) if (200..300).contains(&delivery.status) => false,
+            Ok(delivery) => {
+                let error = format!("Robot answered {}: {}", delivery.status, delivery.body);
+                return WsReply::Error { id, seq, error };
+            }
+            Err(DeliveryError::Superseded { .. }) => true,
+            Err(e) => return WsReply::Error { id, seq, error: e.to_string() },
+        };
+
+        WsReply::Ack {
+            id,
+            seq,
+            command: command.to_string(),
+            rtt_ms: started.elapsed().as_secs_f64() * 1000.0,
+            coalesced: false,
+            superseded,
+        }
+    }
+}
+
+/// Query parameters of `GET /ws`.
+#[derive(Debug, Deserialize)]
+struct WsParams {
+    /// Client identifier shared with the page's HTTP requests.
+    #[serde(default)]
+    client: Option<String>,
+}
+
+/// Handler for the WebSocket control channel (`GET /ws`).
+///
+/// Upgrades the connection and hands it to [`ws_session`].
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<ServerState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(params): Query<WsParams>,
+) -> Response {
+    let client = params
+        .client
+        .filter(|id| !id.trim().is_empty())
+        .unwrap_or_else(|| peer.ip().to_string());
+    ws.on_upgrade(move |socket| ws_session(socket, state, client))
+}
+
+/// Runs one WebSocket control session until the client disconnects.
+///
+/// A reader decodes incoming messages and submits their commands to the
+/// command queue right away, so a stop can still overtake a move of the
+/// same socket that is waiting for the robot. A replier then waits for the
+/// answers in message order and pushes back one reply for each.
+///
+/// Heartbeats are handled by the reader directly, so a slow robot never
+/// delays them. Replies, watchdog events and gateway changes share one
+/// writer to the socket.
+async fn ws_session(socket: WebSocket, state: Arc<ServerState>, client: String) {
+    state.ws_clients.fetch_add(1, Ordering::Relaxed);
+    let (mut sink, mut stream) = socket.split();
+    let (pending, mut replies) = mpsc::unbounded_channel::<WsPending>();
+    let (out, mut outgoing) = mpsc::unbounded_channel::<WsReply>();
+
+    // Answer queued commands in message order
+    let replier = {
+        let out = out.clone();
+        async move {
+            while let Some(item) = replies.recv().await {
+                if out.send(item.resolve().await).is_err() {
+                    break; // Client went away
+                }
+            }
+        }
+    };
+
+    // Decode client messages and queue their commands; malformed ones are
+    // answered in turn so that replies stay in the same order as the messages
+    let reader = {
+        let state = state.clone();
+        async move {
+            while let Some(Ok(message)) = stream.next().await {
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Close(_) => break,
+                    _ => continue, // Pings are answered by axum
+                };
+
+                let value = serde_json::from_str::<serde_json::Value>(&text).ok();
+                if value.as_ref().is_some_and(|v| v.get("heartbeat").is_some()) {
+                    state.watchdog.heartbeat(&client);
+                    continue;
+                }
+
+                let item = match serde_json::from_str::<WsRequest>(&text) {
+                    Ok(request) => submit_ws_command(&state, &client, request),
+                    Err(e) => WsPending::Ready(WsReply::Error {
+                        id: value.and_then(|v| v.get("id")?.as_u64()),
+                        seq: None,
+                        error: format!("Invalid message: {}", e),
+                    }),
+                };
+                if pending.send(item).is_err() {
+                    break;
+                }
+            }
+            // Dropping the sender lets the replier finish what is pending
+        }
+    };
+
+    // Push watchdog stops of every client and gateway changes to this socket
+    let mut events = state.watchdog.subscribe();
+    let mut gateway = state.gateway.subscribe();
+    let alerts = async move {
+        loop {
+            let reply = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => WsReply::Watchdog(event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                changed = gateway.changed() => match changed {
+                    Ok(()) => WsReply::Gateway(gateway.borrow_and_update().clone()),
+                    Err(_) => break,
+                },
+            };
+            if out.send(reply).is_err() {
+                break;
+            }
+        }
+    };
+
+    // Write replies and events until every producer is done
+    let writer = async move {
+        while let Some(reply) = outgoing.recv().await {
+            let text = serde_json::to_string(&reply).unwrap_or_default();
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    };
+
+    // The session ends with the socket; alerts stop with it
+    let session = async {
+        tokio::select! {
+            _ = async { tokio::join!(reader, replier) } => {}
+            _ = alerts => {}
+        }
+    };
+    tokio::join!(session, writer);
+    state.ws_clients.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Queues one `/ws` command for the robot.
+///
+/// The command goes through the same checks as `/control`: the emergency
+/// stop lock, validation and the throttle. Commands refused by a check or
+/// coalesced are answered at once.
+fn submit_ws_command(state: &ServerState, client: &str, request: WsRequest) -> WsPending {
+    let command = request.command;
+    let query = command.to_query();
+    let reject = |e: anyhow::Error| {
+        WsPending::Ready(WsReply::Error {
+            id: request.id,
+            seq: None,
+            error: e.to_string(),
+        })
+    };
+
+    if let Err(e) = state.estop.check().and_then(|()| command.validate()) {
+        return reject(e);
+    }
+
+    match state.throttle.admit(&query) {
+        Verdict::Forward => {
+            state.log_command(&query);
+            state.watchdog.observe(client, &command);
+            WsPending::Queued {
+                id: request.id,
+                command,
+                started: Instant::now(),
+                ticket: state.queue.submit(&query),
+            }
+        }
+        Verdict::Coalesced => {
+            state.watchdog.observe(client, &command);
+            WsPending::Ready(WsReply::Ack {
+                id: request.id,
+                seq: None,
+                command: command.to_string(),
+                rtt_ms: 0.0,
+                coalesced: true,
+                superseded: false,
+            })
+        }
+        Verdict::Dropped => {
+            state.watchdog.heartbeat(client);
+            reject(WifiProxyError::RateLimited(query_var(&query).to_string()).into())
         }
     }
 }
 
 /// Handler for video stream proxy (`GET /stream`).
 ///
-/// Proxies the MJPEG video stream from the robot's camera. The ESP32-CAM
-/// typically serves the stream on port 81. This handler establishes a
-/// streaming connection and forwards the multipart MJPEG data to the client.
+/// Serves the robot's MJPEG camera stream through the shared [`StreamHub`].
+/// The ESP32-CAM typically serves the stream on port 81 (see
+/// [`ServerConfig::stream_port`]) and handles only one client well, so all
+/// viewers share a single upstream connection; each gets the frames
+/// re-packaged in its own multipart response.
 ///
 /// # Arguments
-/// * `State(config)` - Shared server configuration containing gateway address
+/// * `State(state)` - Shared server state holding the stream hub
 ///
 /// # Returns
-/// - Streaming `Response` with the video data on success
-/// - `502 Bad Gateway` if the stream connection fails
+/// - Streaming `Response` with the video data once the first frame arrives
+/// - `504 Gateway Timeout` if no frame arrives within the request timeout
+/// - `502 Bad Gateway` if the camera cannot be reached
 ///
 /// # Stream Format
-/// The robot typically sends MJPEG streams using:
+/// Frames are sent as:
 /// - Content-Type: `multipart/x-mixed-replace; boundary=frame`
-/// - Each frame is a JPEG image separated by the boundary marker
-///
-/// # Note
-/// This uses reqwest (async HTTP client) instead of ureq because streaming
-/// requires async support for efficient handling of the continuous data flow.
-async fn stream_proxy(State(config): State<Arc<ServerConfig>>) -> Response {
-    // Build the stream URL - ESP32-CAM typically serves on port 81
-    let stream_url = format!("http://{}:81/stream", config.gateway);
-
-    // Use reqwest async client for streaming support
-    let client = reqwest::Client::new();
-
-    match client.get(&stream_url).send().await {
-        Ok(response) => {
-            // Extract content-type header to preserve MJPEG boundary info
-            let content_type = response
-                .headers()
-                .get("content-type")
-                .and_then(|v| v.to_str().ok())
-                .unwrap_or("multipart/x-mixed-replace; boundary=frame")
-                .to_string();
-
-            // Get the response body as a byte stream
-            let stream = response.bytes_stream();
-
-            // Build and return a streaming response that forwards the video data
-            Response::builder()
-                .status(StatusCode::OK)
-                .header("content-type", content_type)
-                .body(Body::from_stream(stream))
-                .unwrap()
+/// - Each frame is a JPEG image with its own `Content-Length` header
+async fn stream_proxy(State(state): State<Arc<ServerState>>) -> Response {
+    let mut subscription = state.stream.subscribe();
+
+    // Wait for the first frame so that failures still get an error status
+    let first = match subscription.recv_timeout(state.config.request_timeout).await {
+        Ok(frame) => frame,
+        Err(e) => return stream_error(e),
+    };
+
+    // Forward frames until the viewer disconnects (dropping the subscription)
+    let frames = futures_util::stream::unfold(
+        (Some(first), subscription),
+        |(pending, mut subscription)| async move {
+            let frame = match pending {
+                Some(frame) => frame,
+                None => subscription.recv().await?,
+            };
+            let part = mjpeg::encode_part(&frame.jpeg);
+            Some((Ok::<_, Infallible>(part), (None, subscription)))
+        },
+    );
+
+    // Build and return a streaming response that forwards the video data
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", mjpeg::content_type())
+        .body(Body::from_stream(frames))
+        .unwrap()
+}
+
+/// Handler for still images (`GET /snapshot`).
+///
+/// Returns the most recent camera frame as a single JPEG. If nobody is
+/// watching `/stream`, the shared upstream is opened just long enough to
+/// receive one frame.
+///
+/// # Arguments
+/// * `State(state)` - Shared server state holding the stream hub
+///
+/// # Returns
+/// - `200 OK` with an `image/jpeg` body and these headers:
+///   - `X-Frame-Timestamp` - Capture time as Unix seconds with milliseconds
+///   - `X-Frame-Sequence` - Position of the frame in the upstream stream
+/// - `504 Gateway Timeout` if no frame arrives within the request timeout
+/// - `502 Bad Gateway` if the camera cannot be reached
+async fn snapshot_handler(State(state): State<Arc<ServerState>>) -> Response {
+    let frame = match state.stream.snapshot(state.config.request_timeout).await {
+        Ok(frame) => frame,
+        Err(e) => return stream_error(e),
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "image/jpeg")
+        .header("cache-control", "no-store")
+        .header("x-frame-timestamp", mjpeg::unix_timestamp(frame.timestamp))
+        .header("x-frame-sequence", frame.sequence)
+        .body(Body::from(frame.jpeg))
+        .unwrap()
+}
+
+/// Body of a `POST /record/start` request.
+#[derive(Debug, Deserialize)]
+struct RecordStartRequest {
+    /// File or directory name inside the recordings directory
+    /// (e.g., "run.avi" or "run-frames").
+    name: String,
+
+    /// Container format; inferred from `name` when omitted.
+    #[serde(default)]
+    format: Option<RecordFormat>,
+
+    /// Stop automatically after this many seconds.
+    #[serde(default)]
+    duration_secs: Option<f64>,
+}
+
+/// Response of `GET /record`.
+#[derive(Debug, Serialize)]
+struct RecordStatus {
+    /// Whether a recording is in progress.
+    recording: bool,
+
+    /// Output of the recording in progress.
+    output: Option<PathBuf>,
+
+    /// Format of the recording in progress.
+    format: Option<RecordFormat>,
+
+    /// Seconds since the recording in progress was started.
+    elapsed_secs: Option<f64>,
+
+    /// Summary of the last finished recording.
+    last: Option<RecordingSummary>,
+
+    /// Error of the last recording, if it failed.
+    last_error: Option<String>,
+}
+
+/// Handler for `GET /record`: reports the current and last recording.
+async fn record_status(State(state): State<Arc<ServerState>>) -> Json<RecordStatus> {
+    let mut slot = state.recording.lock().await;
+    slot.reap().await;
+
+    let active = slot.active.as_ref();
+    let (last, last_error) = match &slot.last {
+        Some(Ok(summary)) => (Some(summary.clone()), None),
+        Some(Err(e)) => (None, Some(e.clone())),
+        None => (None, None),
+    };
+    Json(RecordStatus {
+        recording: active.is_some(),
+        output: active.map(|a| a.output.clone()),
+        format: active.map(|a| a.format),
+        elapsed_secs: active.map(|a| a.started.elapsed().unwrap_or_default().as_secs_f64()),
+        last,
+        last_error,
+    })
+}
+
+/// Handler for `POST /record/start`.
+///
+/// Starts recording the shared camera stream in the background. Viewers of
+/// `/stream` are unaffected; the recording is one more subscriber.
+///
+/// # Returns
+/// - `200 OK` with the recording status
+/// - `400 Bad Request` if the name is not a plain file name or the duration
+///   is not between 0 and [`recorder::MAX_DURATION_SECS`]
+/// - `409 Conflict` if a recording is already in progress or the name exists
+async fn record_start(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<RecordStartRequest>,
+) -> Response {
+    let output = match recordings_path(&state.config, &request.name) {
+        Ok(output) => output,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let limit = match request.duration_secs.map(recorder::duration_from_secs).transpose() {
+        Ok(limit) => limit,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+
+    // Recordings never overwrite; the writer enforces it too, but only
+    // once the first frame arrives
+    if output.exists() {
+        let message = format!("'{}' already exists", request.name);
+        return (StatusCode::CONFLICT, message).into_response();
+    }
+
+    let mut slot = state.recording.lock().await;
+    slot.reap().await;
+    if slot.active.is_some() {
+        return (StatusCode::CONFLICT, "A recording is already in progress").into_response();
+    }
+
+    let format = request.format.unwrap_or_else(|| RecordFormat::from_path(&output));
+    let stop = CancellationToken::new();
+
+    // Subscribe now so the upstream opens while the task starts
+    let task = tokio::spawn({
+        let subscription = state.stream.subscribe();
+        let output = output.clone();
+        let stop = stop.clone();
+        async move { recorder::record(subscription, &output, format, limit, stop).await }
+    });
+
+    println!("Recording to {} ({})", output.display(), format);
+    slot.active = Some(ActiveRecording {
+        output,
+        format,
+        started: SystemTime::now(),
+        stop,
+        task,
+    });
+    drop(slot);
+
+    record_status(State(state)).await.into_response()
+}
+
+/// Handler for `POST /record/stop`.
+///
+/// # Returns
+/// - `200 OK` with the [`RecordingSummary`] as JSON
+/// - `409 Conflict` if no recording is in progress
+/// - `500 Internal Server Error` if the recording failed
+async fn record_stop(State(state): State<Arc<ServerState>>) -> Response {
+    let mut slot = state.recording.lock().await;
+    let Some(active) = slot.active.take() else {
+        return (StatusCode::CONFLICT, "No recording in progress").into_response();
+    };
+
+    active.stop.cancel();
+    let result = join_recording(active.task).await;
+    slot.last = Some(result.clone());
+
+    match result {
+        Ok(summary) => {
+            println!("Recorded {} frames to {}", summary.frames, summary.output.display());
+            Json(summary).into_response()
         }
-        Err(e) => {
-            // Failed to connect to the camera stream
-            Response::builder()
-                .status(StatusCode::BAD_GATEWAY)
-                .body(Body::from(format!("Stream error: {}", e)))
-                .unwrap()
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// Resolves a name from an API request inside the recordings directory.
+///
+/// Only plain names are accepted, so requests cannot reach files elsewhere.
+///
+/// # Returns
+/// - `Ok(PathBuf)` with the path inside [`ServerConfig::recordings_dir`]
+/// - `Err` with `400 Bad Request` for paths with separators or `..`
+fn recordings_path(config: &ServerConfig, name: &str) -> Result<PathBuf, (StatusCode, String)> {
+    let plain = matches!(
+        Path::new(name).components().collect::<Vec<_>>().as_slice(),
+        [Component::Normal(_)]
+    );
+    if !plain {
+        let message = format!("'{}' is not a plain file name", name);
+        return Err((StatusCode::BAD_REQUEST, message));
+    }
+    Ok(config.recordings_dir.join(name))
+}
+
+/// Waits for a recording task and flattens its outcome.
+async fn join_recording(
+    task: JoinHandle<anyhow::Result<RecordingSummary>>,
+) -> Result<RecordingSummary, String> {
+    match task.await {
+        Ok(Ok(summary)) => Ok(summary),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(e) => Err(format!("recording task failed: {}", e)),
+    }
+}
+
+/// Body of a `POST /macro/record/start` request.
+#[derive(Debug, Deserialize)]
+struct MacroRecordRequest {
+    /// JSONL file name inside the recordings directory (e.g., "demo.jsonl").
+    name: String,
+}
+
+/// Body of a `POST /macro/replay` request.
+#[derive(Debug, Deserialize)]
+struct MacroReplayRequest {
+    /// JSONL file name inside the recordings directory.
+    name: String,
+
+    /// Playback speed factor; 1.0 (original timing) when omitted.
+    #[serde(default = "default_speed")]
+    speed: f64,
+}
+
+/// Default playback speed for `POST /macro/replay`.
+fn default_speed() -> f64 {
+    1.0
+}
+
+/// Response of `GET /macro`.
+#[derive(Debug, Serialize)]
+struct MacroStatus {
+    /// Whether forwarded commands are being recorded.
+    recording: bool,
+
+    /// JSONL file of the command recording in progress.
+    output: Option<PathBuf>,
+
+    /// Commands recorded so far.
+    commands: Option<u64>,
+
+    /// Whether a replay is in progress.
+    replaying: bool,
+
+    /// Recording being replayed.
+    source: Option<PathBuf>,
+
+    /// Speed of the replay in progress.
+    speed: Option<f64>,
+
+    /// Seconds since the replay in progress was started.
+    elapsed_secs: Option<f64>,
+
+    /// Summary of the last finished replay.
+    last_replay: Option<ReplaySummary>,
+
+    /// Error of the last replay, if it failed.
+    last_replay_error: Option<String>,
+}
+
+/// Handler for `GET /macro`: reports command recording and replay state.
+async fn macro_status(State(state): State<Arc<ServerState>>) -> Json<MacroStatus> {
+    let (output, commands) = match state.commands.lock().unwrap().as_ref() {
+        Some(recorder) => (Some(recorder.output().to_path_buf()), Some(recorder.count())),
+        None => (None, None),
+    };
+
+    let mut slot = state.replay.lock().await;
+    slot.reap().await;
+    let active = slot.active.as_ref();
+    let (last_replay, last_replay_error) = match &slot.last {
+        Some(Ok(summary)) => (Some(summary.clone()), None),
+        Some(Err(e)) => (None, Some(e.clone())),
+        None => (None, None),
+    };
+    Json(MacroStatus {
+        recording: output.is_some(),
+        output,
+        commands,
+        replaying: active.is_some(),
+        source: active.map(|a| a.source.clone()),
+        speed: active.map(|a| a.speed),
+        elapsed_secs: active.map(|a| a.started.elapsed().unwrap_or_default().as_secs_f64()),
+        last_replay,
+        last_replay_error,
+    })
+}
+
+/// Handler for `POST /macro/record/start`.
+///
+/// Starts appending every command forwarded through `/control` and `/ws` to
+/// a JSONL file in the recordings directory.
+///
+/// # Returns
+/// - `200 OK` with the macro status
+/// - `400 Bad Request` if the name is not a plain file name
+/// - `409 Conflict` if commands are already being recorded or the name exists
+/// - `500 Internal Server Error` if the file cannot be created
+async fn macro_record_start(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<MacroRecordRequest>,
+) -> Response {
+    let output = match recordings_path(&state.config, &request.name) {
+        Ok(output) => output,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    {
+        let mut commands = state.commands.lock().unwrap();
+        if commands.is_some() {
+            return (StatusCode::CONFLICT, "Commands are already being recorded").into_response();
+        }
+        match CommandRecorder::create(&output) {
+            Ok(recorder) => *commands = Some(recorder),
+            Err(e) => {
+                let exists = e
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::AlreadyExists);
+                if exists {
+                    let message = format!("'{}' already exists", request.name);
+                    return (StatusCode::CONFLICT, message).into_response();
+                }
+                return (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response();
+            }
+        }
+    }
+
+    println!("Recording commands to {}", output.display());
+    macro_status(State(state)).await.into_response()
+}
+
+/// Handler for `POST /macro/record/stop`.
+///
+/// # Returns
+/// - `200 OK` with the [`MacroSummary`] as JSON
+/// - `409 Conflict` if no commands are being recorded
+/// - `500 Internal Server Error` if the file could not be completed
+async fn macro_record_stop(State(state): State<Arc<ServerState>>) -> Response {
+    let Some(recorder) = state.commands.lock().unwrap().take() else {
+        return (StatusCode::CONFLICT, "No command recording in progress").into_response();
+    };
+
+    match recorder.finish() {
+        Ok(summary) => {
+            println!("Recorded {} commands to {}", summary.commands, summary.output.display());
+            Json::<MacroSummary>(summary).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response(),
+    }
+}
+
+/// Handler for `POST /macro/replay`.
+///
+/// Loads a command recording and replays it against the robot in the
+/// background. Commands sent from the browser meanwhile still go through.
+/// While the emergency stop is engaged only the replay's final stops reach
+/// the robot.
+///
+/// # Returns
+/// - `200 OK` with the macro status
+/// - `400 Bad Request` if the name is not a plain file name, the file is
+///   malformed, or the speed fails [`replay::check_speed`]
+/// - `404 Not Found` if the recording does not exist
+/// - `409 Conflict` if a replay is already in progress
+/// - `423 Locked` while the emergency stop is engaged
+async fn macro_replay(
+    State(state): State<Arc<ServerState>>,
+    Json(request): Json<MacroReplayRequest>,
+) -> Response {
+    let source = match recordings_path(&state.config, &request.name) {
+        Ok(source) => source,
+        Err(rejection) => return rejection.into_response(),
+    };
+    if !source.is_file() {
+        let message = format!("No recording named '{}'", request.name);
+        return (StatusCode::NOT_FOUND, message).into_response();
+    }
+    if let Err(e) = replay::check_speed(request.speed) {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    let commands = match replay::load(&source) {
+        Ok(commands) => commands,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("{:#}", e)).into_response(),
+    };
+
+    let mut slot = state.replay.lock().await;
+    slot.reap().await;
+    if slot.active.is_some() {
+        return (StatusCode::CONFLICT, "A replay is already in progress").into_response();
+    }
+    if let Err(e) = state.estop.check() {
+        return (StatusCode::LOCKED, e.to_string()).into_response();
+    }
+
+    let stop = CancellationToken::new();
+    let task = tokio::spawn({
+        let state = state.clone();
+        let stop = stop.clone();
+        let speed = request.speed;
+        async move {
+            // An emergency stop must hold even against a command already due
+            let robot = state.estop.interlock(&state.queue);
+            replay::replay(&robot, &commands, speed, stop).await
         }
+    });
+
+    println!("Replaying {} at {}x", source.display(), request.speed);
+    slot.active = Some(ActiveReplay {
+        source,
+        speed: request.speed,
+        started: SystemTime::now(),
+        stop,
+        task,
+    });
+    drop(slot);
+
+    macro_status(State(state)).await.into_response()
+}
+
+/// Handler for `POST /macro/replay/stop`.
+///
+/// Aborts the replay; it still sends both stop commands before finishing.
+///
+/// # Returns
+/// - `200 OK` with the [`ReplaySummary`] as JSON
+/// - `409 Conflict` if no replay is in progress
+/// - `502 Bad Gateway` if the replay failed (e.g., the robot did not stop)
+async fn macro_replay_stop(State(state): State<Arc<ServerState>>) -> Response {
+    let mut slot = state.replay.lock().await;
+    let Some(active) = slot.active.take() else {
+        return (StatusCode::CONFLICT, "No replay in progress").into_response();
+    };
+
+    active.stop.cancel();
+    let result = join_replay(active.task).await;
+    slot.last = Some(result.clone());
+
+    match result {
+        Ok(summary) => Json(summary).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, e).into_response(),
+    }
+}
+
+/// Waits for a replay task and flattens its outcome.
+async fn join_replay(task: JoinHandle<anyhow::Result<ReplaySummary>>) -> Result<ReplaySummary, String> {
+    match task.await {
+        Ok(Ok(summary)) => Ok(summary),
+        Ok(Err(e)) => Err(format!("{:#}", e)),
+        Err(e) => Err(format!("replay task failed: {}", e)),
+    }
+}
+
+/// Body of a `POST /api/estop` request (optional).
+#[derive(Debug, Default, Deserialize)]
+struct EstopRequest {
+    /// Why the emergency stop was pressed, recorded in the log.
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Handler for `GET /api/estop`: reports the lock state and history.
+async fn estop_status(State(state): State<Arc<ServerState>>) -> Json<EstopStatus> {
+    Json(state.estop.status())
+}
+
+/// Handler for `POST /api/estop`.
+///
+/// Aborts any running replay, locks control and sends both stop moves and
+/// the steady posture to the robot.
+///
+/// # Returns
+/// - `200 OK` with the recorded [`EstopEvent`]
+/// - `502 Bad Gateway` with the event if a stop command could not be
+///   delivered; control is locked regardless
+async fn estop_engage(
+    State(state): State<Arc<ServerState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    request: Option<Json<EstopRequest>>,
+) -> Response {
+    let Json(request) = request.unwrap_or_default();
+
+    // A replay keeps queueing commands, so stop it before anything else
+    if let Some(active) = &state.replay.lock().await.active {
+        active.stop.cancel();
+    }
+
+    let event = state
+        .estop
+        .engage(&state.queue, &client_id(&headers, peer), request.reason)
+        .await;
+    let status = if event.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+    (status, Json::<EstopEvent>(event)).into_response()
+}
+
+/// Handler for `POST /api/estop/release`.
+///
+/// # Returns
+/// - `200 OK` with the recorded [`EstopEvent`]
+/// - `409 Conflict` if the emergency stop is not engaged
+async fn estop_release(
+    State(state): State<Arc<ServerState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    match state.estop.release(&client_id(&headers, peer)) {
+        Some(event) => Json(event).into_response(),
+        None => (StatusCode::CONFLICT, "Emergency stop is not engaged").into_response(),
+    }
+}
+
+/// Maps a camera failure to an error response.
+///
+/// Timeouts become `504 Gateway Timeout`, other failures `502 Bad Gateway`.
+fn stream_error(e: mjpeg::UpstreamError) -> Response {
+    let status = if e.timeout {
+        StatusCode::GATEWAY_TIMEOUT
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+    (status, format!("Stream error: {}", e)).into_response()
+}
+
+/// Returns the robot's camera stream URL for a gateway address.
+///
+/// The camera is served on its own port of the gateway host, so any port
+/// in `gateway` (used for `/control`) is replaced by `stream_port`.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::server::stream_url;
+///
+/// assert_eq!(stream_url("192.168.4.1", 81), "http://192.168.4.1:81/stream");
+/// assert_eq!(stream_url("127.0.0.1:8000", 8001), "http://127.0.0.1:8001/stream");
+/// ```
+pub fn stream_url(gateway: &str, stream_port: u16) -> String {
+    format!("http://{}:{}/stream", gateway_host(gateway), stream_port)
+}
+
+/// Returns the host part of a gateway address, dropping any `:port` suffix.
+///
+/// The stream lives on a different port than `/control`, so a gateway such
+/// as "127.0.0.1:8000" must be reduced to "127.0.0.1" before adding the
+/// stream port.
+fn gateway_host(gateway: &str) -> &str {
+    match gateway.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') && port.parse::<u16>().is_ok() => host,
+        _ => gateway,
     }
 }
@@ -0,0 +1,436 @@
+//! Link supervision and automatic reconnection while serving.
+//!
+//! The proxy reaches the robot through the USB WiFi adapter. When the adapter
+//! drops off the robot's access point (the robot rebooted, walked out of
+//! range, or the adapter was replugged), every command fails until the link
+//! is restored. The [`LinkSupervisor`] polls the interface's connection
+//! status and, once the link is lost, reconnects with the saved credentials,
+//! backing off exponentially between attempts. A single failed check is not
+//! a lost link: status queries can fail while NetworkManager is busy, so the
+//! link counts as lost after [`LOST_AFTER`] failed checks in a row.
+//!
+//! The gateway reported by the interface is published to
+//! [`watch_gateway`](LinkSupervisor::watch_gateway) subscribers whenever it
+//! changes and after every reconnection, so the server can follow a robot
+//! that came back with a different address.
+//!
+//! Movement is stopped twice around an outage: when the link is lost, so
+//! commands queued for the robot are discarded and a stop is attempted, and
+//! again once it is back, since the robot may still be executing the last
+//! command it received.
+//!
+//! # States
+//!
+//! | State          | Meaning                                                  |
+//! |----------------|----------------------------------------------------------|
+//! | `connected`    | The interface is associated and has a gateway            |
+//! | `reconnecting` | The link was lost; attempts are in progress              |
+//! | `failed`       | Attempts are exhausted, or no credentials are saved and  |
+//! |                | connecting without a password failed; the supervisor     |
+//! |                | waits for the link to come back by other means           |
+//! | `unmanaged`    | The gateway was given explicitly; no link is monitored   |
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use wifi_proxy::supervisor::{LinkState, LinkSupervisor, ReconnectConfig};
+//!
+//! let config = ReconnectConfig {
+//!     interface: "wlan1".into(),
+//!     ssid: "WAVESHARE Robot".into(),
+//!     password: Some("1234567890".into()),
+//!     hidden: false,
+//!     max_backoff: Duration::from_secs(30),
+//!     max_attempts: None,
+//! };
+//!
+//! // Serving starts on a working link
+//! let supervisor = LinkSupervisor::new(Some(config), "192.168.4.1");
+//! assert_eq!(supervisor.state(), LinkState::Connected);
+//!
+//! // Without an interface to watch there is nothing to supervise
+//! let unmanaged = LinkSupervisor::new(None, "127.0.0.1:8000");
+//! assert_eq!(unmanaged.state(), LinkState::Unmanaged);
+//! ```
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+
+use crate::connection::{self, ConnectionStatus};
+use crate::error::WifiProxyError;
+use crate::mjpeg::unix_timestamp;
+use crate::robot::CommandSink;
+
+/// Interval between two connection status checks.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Consecutive failed status checks after which the link counts as lost.
+pub const LOST_AFTER: u32 = 3;
+
+/// Delay after the first failed reconnection attempt; doubled after each one.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Number of past events kept for status queries.
+const EVENT_HISTORY: usize = 20;
+
+/// Settings for supervising the link to the robot.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// WiFi interface connected to the robot's access point.
+    pub interface: String,
+
+    /// Network to reconnect to.
+    pub ssid: String,
+
+    /// Saved password of the network; `None` if no credentials are saved.
+    /// The supervisor then tries once without a password, which restores an
+    /// open network (or one whose profile keeps its secrets), and gives up
+    /// if that fails.
+    pub password: Option<String>,
+
+    /// Whether the network hides its SSID, so reconnecting must probe for it.
+    pub hidden: bool,
+
+    /// Longest delay between two reconnection attempts.
+    pub max_backoff: Duration,
+
+    /// Attempts before giving up; `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+/// Health of the link to the robot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkState {
+    /// The interface is associated and has a gateway.
+    Connected,
+    /// The link was lost and reconnection attempts are in progress.
+    Reconnecting,
+    /// Reconnection gave up; waiting for the link to come back by other means.
+    Failed,
+    /// The gateway was given explicitly, so no link is monitored.
+    Unmanaged,
+}
+
+/// A change of the link state.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkEvent {
+    /// Unix seconds (with milliseconds) of the change.
+    pub time: String,
+
+    /// State entered.
+    pub state: LinkState,
+
+    /// What happened (e.g., "link lost: interface disconnected").
+    pub message: String,
+}
+
+/// Snapshot of the supervisor for status endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkStatus {
+    /// Current state.
+    pub state: LinkState,
+
+    /// Supervised interface, if any.
+    pub interface: Option<String>,
+
+    /// Network the interface is kept on, if any.
+    pub ssid: Option<String>,
+
+    /// Gateway reported by the interface when last connected.
+    pub gateway: Option<String>,
+
+    /// Unix seconds (with milliseconds) since the current state was entered.
+    pub since: String,
+
+    /// Reconnection attempts made since the link was lost.
+    pub attempts: u32,
+
+    /// Why the last status check or reconnection attempt failed.
+    pub last_error: Option<String>,
+
+    /// Recent state changes, oldest first.
+    pub events: Vec<LinkEvent>,
+}
+
+/// Mutable state behind the supervisor's lock.
+struct LinkInner {
+    /// Current state.
+    state: LinkState,
+
+    /// Time the current state was entered.
+    since: SystemTime,
+
+    /// Reconnection attempts since the link was lost.
+    attempts: u32,
+
+    /// Why the last check or attempt failed.
+    last_error: Option<String>,
+
+    /// Recent state changes, newest last.
+    history: VecDeque<LinkEvent>,
+}
+
+/// Watches the link to the robot and restores it when it drops.
+pub struct LinkSupervisor {
+    /// What to supervise; `None` when the gateway was given explicitly.
+    config: Option<ReconnectConfig>,
+
+    /// State, counters and history.
+    inner: Mutex<LinkInner>,
+
+    /// Gateway reported by the interface when last connected.
+    gateway: watch::Sender<Option<String>>,
+}
+
+impl LinkSupervisor {
+    /// Creates a supervisor for a link that is up.
+    ///
+    /// # Arguments
+    /// * `config` - Interface and credentials to supervise; `None` for an
+    ///   unmanaged link
+    /// * `gateway` - Gateway the server was started with
+    pub fn new(config: Option<ReconnectConfig>, gateway: &str) -> Self {
+        let state = match config {
+            Some(_) => LinkState::Connected,
+            None => LinkState::Unmanaged,
+        };
+        Self {
+            config,
+            inner: Mutex::new(LinkInner {
+                state,
+                since: SystemTime::now(),
+                attempts: 0,
+                last_error: None,
+                history: VecDeque::new(),
+            }),
+            gateway: watch::Sender::new(Some(gateway.to_string())),
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> LinkState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// Returns the state, counters and recent events.
+    pub fn status(&self) -> LinkStatus {
+        let inner = self.inner.lock().unwrap();
+        LinkStatus {
+            state: inner.state,
+            interface: self.config.as_ref().map(|c| c.interface.clone()),
+            ssid: self.config.as_ref().map(|c| c.ssid.clone()),
+            gateway: self.gateway.borrow().clone(),
+            since: unix_timestamp(inner.since),
+            attempts: inner.attempts,
+            last_error: inner.last_error.clone(),
+            events: inner.history.iter().cloned().collect(),
+        }
+    }
+
+    /// Subscribes to the gateway reported by the interface.
+    ///
+    /// The value changes when a poll finds a different gateway, and is
+    /// republished after every reconnection even if unchanged.
+    pub fn watch_gateway(&self) -> watch::Receiver<Option<String>> {
+        self.gateway.subscribe()
+    }
+
+    /// Reads the gateway from the interface now and publishes it.
+    ///
+    /// # Returns
+    /// - `Ok(String)` with the interface's gateway
+    /// - `Err(WifiProxyError::ConnectionFailed)` if the link is unmanaged or down
+    pub async fn resolve_gateway(&self) -> Result<String> {
+        let Some(config) = &self.config else {
+            let reason = "the link is not supervised (explicit gateway)".to_string();
+            return Err(WifiProxyError::ConnectionFailed(reason).into());
+        };
+        let status = probe(&config.interface).await?;
+        self.gateway.send_replace(status.gateway.clone());
+        Ok(status.gateway.unwrap_or_default())
+    }
+
+    /// Supervises the link until the task is dropped.
+    ///
+    /// Returns immediately for an unmanaged link.
+    ///
+    /// # Arguments
+    /// * `robot` - Client or queue used to stop the robot around outages
+    pub async fn run(self: Arc<Self>, robot: impl CommandSink) {
+        let Some(config) = &self.config else {
+            return;
+        };
+
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut misses = 0;
+        loop {
+            interval.tick().await;
+            let link = probe(&config.interface).await;
+
+            match (self.state(), link) {
+                (LinkState::Connected, Ok(status)) => {
+                    misses = 0;
+                    self.gateway.send_if_modified(|gateway| {
+                        let changed = *gateway != status.gateway;
+                        *gateway = status.gateway;
+                        changed
+                    });
+                }
+                (LinkState::Connected, Err(e)) => {
+                    self.inner.lock().unwrap().last_error = Some(e.to_string());
+
+                    // Ride out a check that failed on its own
+                    misses += 1;
+                    if misses < LOST_AFTER {
+                        continue;
+                    }
+                    misses = 0;
+                    self.transition(LinkState::Reconnecting, format!("link lost: {}", e));
+
+                    // Stop what is queued while the supervisor reconnects
+                    let (_, restored) = tokio::join!(halt(&robot, "link loss"), self.reconnect(config));
+                    if restored {
+                        halt(&robot, "reconnection").await;
+                    }
+                    interval.reset();
+                }
+                (LinkState::Failed, Ok(status)) => {
+                    self.gateway.send_replace(status.gateway);
+                    self.transition(LinkState::Connected, "link restored".into());
+                    halt(&robot, "reconnection").await;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Reconnects with exponential backoff.
+    ///
+    /// # Returns
+    /// `true` once the link is back, `false` after giving up.
+    async fn reconnect(&self, config: &ReconnectConfig) -> bool {
+        self.inner.lock().unwrap().attempts = 0;
+
+        // Without saved credentials only an open network can be rejoined
+        let password = config.password.as_deref().unwrap_or_default();
+
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            // NetworkManager may have restored the link on its own
+            if let Ok(status) = probe(&config.interface).await {
+                self.gateway.send_replace(status.gateway);
+                self.transition(LinkState::Connected, "link restored".into());
+                return true;
+            }
+
+            let attempt = {
+                let mut inner = self.inner.lock().unwrap();
+                inner.attempts += 1;
+                inner.attempts
+            };
+            println!("Link: reconnecting to {} (attempt {})", config.ssid, attempt);
+
+            match connect(config, password).await {
+                Ok(status) => {
+                    self.gateway.send_replace(status.gateway);
+                    let message = format!("reconnected to {} after {} attempt(s)", config.ssid, attempt);
+                    self.transition(LinkState::Connected, message);
+                    return true;
+                }
+                Err(e) => {
+                    eprintln!("Link: attempt {} failed: {:#}", attempt, e);
+                    self.inner.lock().unwrap().last_error = Some(format!("{:#}", e));
+                }
+            }
+
+            if config.password.is_none() {
+                let message = format!(
+                    "no saved credentials for '{}' and connecting without a password failed",
+                    config.ssid
+                );
+                self.transition(LinkState::Failed, message);
+                return false;
+            }
+            if config.max_attempts.is_some_and(|max| attempt >= max) {
+                let message = format!("gave up after {} attempt(s)", attempt);
+                self.transition(LinkState::Failed, message);
+                return false;
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(config.max_backoff.max(MIN_BACKOFF));
+        }
+    }
+
+    /// Enters a new state, logging and remembering the change.
+    fn transition(&self, state: LinkState, message: String) {
+        println!("Link: {}", message);
+
+        let now = SystemTime::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = state;
+        inner.since = now;
+        if inner.history.len() == EVENT_HISTORY {
+            inner.history.pop_front();
+        }
+        inner.history.push_back(LinkEvent {
+            time: unix_timestamp(now),
+            state,
+            message,
+        });
+    }
+}
+
+/// Queries the interface and checks that the link is usable.
+///
+/// # Returns
+/// - `Ok(ConnectionStatus)` if the interface is connected and has a gateway
+/// - `Err(WifiProxyError::ConnectionFailed)` describing the state otherwise
+/// - `Err` if the backend cannot be queried
+async fn probe(interface: &str) -> Result<ConnectionStatus> {
+    // Backends block (nmcli spawns a process), so keep them off the runtime
+    let name = interface.to_string();
+    let status = tokio::task::spawn_blocking(move || connection::status(&name)).await??;
+
+    if status.connection.is_some() && status.gateway.is_some() {
+        Ok(status)
+    } else {
+        let reason = format!("{} is {}", status.interface, status.state);
+        Err(WifiProxyError::ConnectionFailed(reason).into())
+    }
+}
+
+/// Makes one reconnection attempt and waits for the gateway.
+async fn connect(config: &ReconnectConfig, password: &str) -> Result<ConnectionStatus> {
+    let (interface, ssid, password, hidden) = (
+        config.interface.clone(),
+        config.ssid.clone(),
+        password.to_string(),
+        config.hidden,
+    );
+    tokio::task::spawn_blocking(move || {
+        if hidden {
+            connection::connect_hidden(&interface, &ssid, &password)
+        } else {
+            connection::connect(&interface, &ssid, &password)
+        }
+    })
+    .await??;
+    probe(&config.interface).await
+}
+
+/// Discards queued commands and stops the robot, logging the outcome.
+async fn halt(robot: &impl CommandSink, cause: &str) {
+    let discarded = robot.cancel_pending();
+    match robot.stop().await {
+        Ok(()) => println!("Link: stopped movement on {} ({} queued commands discarded)", cause, discarded),
+        Err(e) => eprintln!("Link: failed to stop movement on {}: {:#}", cause, e),
+    }
+}
@@ -0,0 +1,294 @@
+//! Rate limiting and coalescing of commands toward the gateway.
+//!
+//! The ESP32's web server handles only a few requests at a time; when
+//! gamepad polling or key repeat floods it, requests get dropped, and a
+//! dropped stop leaves the robot walking. The [`CommandThrottle`] sits in
+//! front of the gateway and decides for every command whether to forward it:
+//!
+//! - **Stops are always forwarded**, immediately, and never count against
+//!   the rate limit.
+//! - **Repeated moves are coalesced**: a move identical to the last one the
+//!   robot accepted on the same axis within the coalesce window changes
+//!   nothing on the robot, so it is answered locally instead of being
+//!   forwarded. The throttle learns what the robot accepted from the command
+//!   queue through [`sent`](CommandThrottle::sent), which also reports every
+//!   stop the queue sends on its own (watchdog, emergency stop, link loss,
+//!   end of a replay). A move that was lost or refused is never remembered,
+//!   so retrying it reaches the robot.
+//! - **Everything else is rate limited per variable** (`move`, `funcMode`,
+//!   `sconfig`, ...) with a token bucket; commands over the limit are dropped
+//!   rather than queued, since a late command is worse than none.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use wifi_proxy::robot::{MoveDirection, RobotCommand};
+//! use wifi_proxy::throttle::{CommandThrottle, Verdict};
+//!
+//! let throttle = CommandThrottle::new(Some(10.0), Some(Duration::from_secs(1)));
+//! let forward = RobotCommand::Move(MoveDirection::Forward);
+//!
+//! // Key repeat: once the robot accepted "move forward", repeats stay local
+//! assert_eq!(throttle.admit("var=move&val=1&cmd=0"), Verdict::Forward);
+//! throttle.sent(&forward, true);
+//! assert_eq!(throttle.admit("var=move&val=1&cmd=0"), Verdict::Coalesced);
+//!
+//! // The release always goes through, and the next press is forwarded again
+//! assert_eq!(throttle.admit("var=move&val=3&cmd=0"), Verdict::Forward);
+//! assert_eq!(throttle.admit("var=move&val=1&cmd=0"), Verdict::Forward);
+//!
+//! // A move the robot never answered is not remembered
+//! throttle.sent(&forward, false);
+//! assert_eq!(throttle.admit("var=move&val=1&cmd=0"), Verdict::Forward);
+//! ```
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::robot::{MoveDirection, RobotCommand};
+
+/// Commands a variable may send in a burst before the rate applies.
+const BURST: f64 = 4.0;
+
+/// What to do with a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Send the command to the robot.
+    Forward,
+    /// Answer locally: the robot is already doing this.
+    Coalesced,
+    /// Refuse: the variable is over its rate limit.
+    Dropped,
+}
+
+/// Counters for one variable (or the total).
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ThrottleCounts {
+    /// Commands forwarded to the robot, stops included.
+    pub forwarded: u64,
+
+    /// Stop commands forwarded (always, bypassing the limit).
+    pub stops: u64,
+
+    /// Repeated commands answered without reaching the robot.
+    pub coalesced: u64,
+
+    /// Commands refused for exceeding the rate limit.
+    pub dropped: u64,
+}
+
+impl ThrottleCounts {
+    /// Counts one verdict.
+    fn add(&mut self, verdict: Verdict, stop: bool) {
+        match verdict {
+            Verdict::Forward => {
+                self.forwarded += 1;
+                self.stops += u64::from(stop);
+            }
+            Verdict::Coalesced => self.coalesced += 1,
+            Verdict::Dropped => self.dropped += 1,
+        }
+    }
+}
+
+/// Snapshot of the throttle for status endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThrottleStats {
+    /// Commands per second allowed per variable, or `None` if unlimited.
+    pub max_rate: Option<f64>,
+
+    /// Coalesce window in milliseconds, or `None` if coalescing is off.
+    pub coalesce_ms: Option<u64>,
+
+    /// Counters over all variables.
+    pub total: ThrottleCounts,
+
+    /// Counters per `var` value.
+    pub vars: BTreeMap<String, ThrottleCounts>,
+}
+
+/// Token bucket and counters of one variable.
+struct VarState {
+    /// Commands that may be sent right now.
+    tokens: f64,
+
+    /// Time `tokens` was last topped up.
+    refilled: Instant,
+
+    /// Counters for this variable.
+    counts: ThrottleCounts,
+}
+
+/// Mutable state behind the throttle's lock.
+#[derive(Default)]
+struct ThrottleState {
+    /// Per-variable buckets and counters.
+    vars: HashMap<String, VarState>,
+
+    /// Last move the robot accepted on each axis (keyed by the axis' stop
+    /// direction). Cleared by stops and failed moves.
+    last_move: HashMap<MoveDirection, (MoveDirection, Instant)>,
+}
+
+/// Decides which commands reach the gateway.
+pub struct CommandThrottle {
+    /// Commands per second per variable; `None` disables rate limiting.
+    max_rate: Option<f64>,
+
+    /// How long an accepted move makes identical repeats redundant;
+    /// `None` disables coalescing.
+    coalesce: Option<Duration>,
+
+    /// Buckets, counters and recent moves.
+    state: Mutex<ThrottleState>,
+}
+
+impl CommandThrottle {
+    /// Creates a throttle.
+    ///
+    /// # Arguments
+    /// * `max_rate` - Commands per second allowed per variable; `None` for no limit
+    /// * `coalesce` - Window in which identical moves are coalesced; `None` to forward all
+    pub fn new(max_rate: Option<f64>, coalesce: Option<Duration>) -> Self {
+        Self {
+            max_rate,
+            coalesce,
+            state: Mutex::new(ThrottleState::default()),
+        }
+    }
+
+    /// Decides what to do with a `/control` query and counts the verdict.
+    ///
+    /// Queries that do not decode into a [`RobotCommand`] are still rate
+    /// limited under their `var` value, but never treated as stops.
+    pub fn admit(&self, query: &str) -> Verdict {
+        let command = RobotCommand::from_query(query).ok();
+        let var = query_var(query);
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        let verdict = match command {
+            // Stops go straight through and reset the axis
+            Some(RobotCommand::Move(dir)) if dir.is_stop() => {
+                state.last_move.remove(&dir);
+                Verdict::Forward
+            }
+            Some(RobotCommand::Move(dir)) if self.is_repeat(&state, dir, now) => Verdict::Coalesced,
+            _ if self.take_token(&mut state, var, now) => Verdict::Forward,
+            _ => Verdict::Dropped,
+        };
+
+        let stop = command.is_some_and(|c| c.is_stop());
+        self.var_state(&mut state, var, now).counts.add(verdict, stop);
+        verdict
+    }
+
+    /// Records the outcome of a command the queue sent to the robot.
+    ///
+    /// Called for every command the queue sends, including the stops it
+    /// sends on behalf of the watchdog, the emergency stop, the link
+    /// supervisor and replays, none of which pass through [`admit`](Self::admit).
+    ///
+    /// # Arguments
+    /// * `command` - The command that was sent
+    /// * `delivered` - Whether the robot accepted it (answered with a 2xx status)
+    pub fn sent(&self, command: &RobotCommand, delivered: bool) {
+        let RobotCommand::Move(dir) = *command else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+
+        // Only a move the robot accepted tells what its axis is doing; after a
+        // stop or a failure the next move must reach the robot
+        if delivered && !dir.is_stop() {
+            state.last_move.insert(dir.stop(), (dir, Instant::now()));
+        } else {
+            state.last_move.remove(&dir.stop());
+        }
+    }
+
+    /// Returns the counters.
+    pub fn stats(&self) -> ThrottleStats {
+        let state = self.state.lock().unwrap();
+        let mut total = ThrottleCounts::default();
+        let vars = state
+            .vars
+            .iter()
+            .map(|(var, s)| {
+                total.forwarded += s.counts.forwarded;
+                total.stops += s.counts.stops;
+                total.coalesced += s.counts.coalesced;
+                total.dropped += s.counts.dropped;
+                (var.clone(), s.counts)
+            })
+            .collect();
+
+        ThrottleStats {
+            max_rate: self.max_rate,
+            coalesce_ms: self.coalesce.map(|c| c.as_millis() as u64),
+            total,
+            vars,
+        }
+    }
+
+    /// Returns true if `dir` is what its axis is already doing.
+    fn is_repeat(&self, state: &ThrottleState, dir: MoveDirection, now: Instant) -> bool {
+        let Some(window) = self.coalesce else {
+            return false;
+        };
+        matches!(
+            state.last_move.get(&dir.stop()),
+            Some((last, at)) if *last == dir && now.duration_since(*at) < window
+        )
+    }
+
+    /// Takes a token from the variable's bucket, if one is available.
+    fn take_token(&self, state: &mut ThrottleState, var: &str, now: Instant) -> bool {
+        let Some(rate) = self.max_rate else {
+            return true;
+        };
+        let bucket = self.var_state(state, var, now);
+
+        // Top up for the time since the last command
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(BURST);
+        bucket.refilled = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the state of a variable, creating it with a full bucket.
+    fn var_state<'a>(&self, state: &'a mut ThrottleState, var: &str, now: Instant) -> &'a mut VarState {
+        state.vars.entry(var.to_string()).or_insert(VarState {
+            tokens: BURST,
+            refilled: now,
+            counts: ThrottleCounts::default(),
+        })
+    }
+}
+
+/// Returns the `var` parameter of a `/control` query, or an empty string.
+///
+/// # Example
+/// ```
+/// use wifi_proxy::throttle::query_var;
+///
+/// assert_eq!(query_var("var=funcMode&val=4&cmd=0"), "funcMode");
+/// assert_eq!(query_var("val=4"), "");
+/// ```
+pub fn query_var(query: &str) -> &str {
+    query
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("var="))
+        .unwrap_or_default()
+}
@@ -0,0 +1,320 @@
+//! Dead-man's-switch for movement commands.
+//!
+//! Movement on the robot is "press and release": `move val=1` starts walking
+//! and only `move val=3` stops it. If the controlling browser crashes or its
+//! WiFi drops in between, the release never arrives and the robot walks on.
+//!
+//! The [`Watchdog`] tracks which movements each client is holding. While a
+//! movement is held the client must keep sending heartbeats (any command
+//! counts as one); when a client stays silent for longer than the timeout,
+//! the watchdog sends the matching stop commands itself and records a
+//! [`WatchdogEvent`].
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use wifi_proxy::robot::{MoveDirection, RobotCommand};
+//! use wifi_proxy::watchdog::Watchdog;
+//!
+//! let watchdog = Watchdog::new(Some(Duration::from_millis(1500)));
+//!
+//! // Walking forward: the client now has to keep the heartbeat going
+//! watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::Forward));
+//! assert!(watchdog.heartbeat("browser-1"));
+//!
+//! // Releasing the key ends the obligation
+//! watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::StopLinear));
+//! assert!(!watchdog.heartbeat("browser-1"));
+//! ```
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+// The runtime's clock, so silence is measured on the same clock as the checks
+use tokio::time::Instant;
+
+use crate::mjpeg::unix_timestamp;
+use crate::robot::{CommandSink, MoveDirection, RobotCommand};
+
+/// Number of past events kept for status queries.
+const EVENT_HISTORY: usize = 20;
+
+/// Capacity of the event channel to connected clients.
+const EVENT_BUFFER: usize = 16;
+
+/// Shortest interval between two expiry checks.
+const MIN_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Movements one client is currently holding.
+#[derive(Debug, Clone, Copy)]
+struct Held {
+    /// Forward/backward motion in progress.
+    linear: Option<MoveDirection>,
+
+    /// Turn in progress.
+    turn: Option<MoveDirection>,
+
+    /// Last command or heartbeat from the client.
+    last_seen: Instant,
+}
+
+impl Held {
+    /// Returns the stop commands that end every held movement.
+    fn stops(&self) -> Vec<RobotCommand> {
+        [self.linear, self.turn]
+            .into_iter()
+            .flatten()
+            .map(|dir| RobotCommand::Move(dir.stop()))
+            .collect()
+    }
+}
+
+/// A movement the watchdog stopped because its client went silent.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchdogEvent {
+    /// Client that was holding the movement.
+    pub client: String,
+
+    /// Unix seconds (with milliseconds) when the stop was sent.
+    pub time: String,
+
+    /// Time since the client's last command or heartbeat, in milliseconds.
+    pub silent_ms: u64,
+
+    /// Stop commands sent to the robot.
+    pub stopped: Vec<RobotCommand>,
+
+    /// Why a stop command could not be delivered, if one failed.
+    pub error: Option<String>,
+}
+
+/// A client with movement in progress, as reported by [`Watchdog::status`].
+#[derive(Debug, Clone, Serialize)]
+pub struct HeldMovement {
+    /// Client identifier.
+    pub client: String,
+
+    /// Forward/backward motion in progress.
+    pub linear: Option<MoveDirection>,
+
+    /// Turn in progress.
+    pub turn: Option<MoveDirection>,
+
+    /// Time since the client's last command or heartbeat, in milliseconds.
+    pub silent_ms: u64,
+}
+
+/// Snapshot of the watchdog for status endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchdogStatus {
+    /// Heartbeat timeout in milliseconds, or `None` when the watchdog is off.
+    pub timeout_ms: Option<u64>,
+
+    /// Clients currently holding a movement.
+    pub held: Vec<HeldMovement>,
+
+    /// Most recent events, oldest first.
+    pub events: Vec<WatchdogEvent>,
+}
+
+/// Tracks held movements per client and stops the robot when a client goes silent.
+pub struct Watchdog {
+    /// Allowed silence while holding a movement; `None` disables the watchdog.
+    timeout: Option<Duration>,
+
+    /// Held movements, keyed by client identifier.
+    clients: Mutex<HashMap<String, Held>>,
+
+    /// Recent events, newest last.
+    history: Mutex<VecDeque<WatchdogEvent>>,
+
+    /// Pushes events to subscribed clients.
+    events: broadcast::Sender<WatchdogEvent>,
+}
+
+impl Watchdog {
+    /// Creates a watchdog.
+    ///
+    /// # Arguments
+    /// * `timeout` - Allowed silence while a movement is held; `None` disables
+    ///   tracking entirely
+    pub fn new(timeout: Option<Duration>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            timeout,
+            clients: Mutex::new(HashMap::new()),
+            history: Mutex::new(VecDeque::new()),
+            events,
+        }
+    }
+
+    /// Returns the heartbeat timeout, or `None` if the watchdog is disabled.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Notes a command sent on behalf of a client.
+    ///
+    /// Movement commands start or end a held movement; every command also
+    /// counts as a heartbeat.
+    pub fn observe(&self, client: &str, command: &RobotCommand) {
+        if self.timeout.is_none() {
+            return;
+        }
+        let RobotCommand::Move(dir) = *command else {
+            self.heartbeat(client);
+            return;
+        };
+
+        let mut clients = self.clients.lock().unwrap();
+        let held = clients.entry(client.to_string()).or_insert(Held {
+            linear: None,
+            turn: None,
+            last_seen: Instant::now(),
+        });
+        held.last_seen = Instant::now();
+
+        // Start or release the axis the direction belongs to
+        let axis = match dir.stop() {
+            MoveDirection::StopLinear => &mut held.linear,
+            _ => &mut held.turn,
+        };
+        *axis = if dir.is_stop() { None } else { Some(dir) };
+
+        if held.linear.is_none() && held.turn.is_none() {
+            clients.remove(client);
+        }
+    }
+
+    /// Records a heartbeat from a client.
+    ///
+    /// # Returns
+    /// `true` if the client is holding a movement, `false` otherwise (including
+    /// when the watchdog has just stopped it)
+    pub fn heartbeat(&self, client: &str) -> bool {
+        match self.clients.lock().unwrap().get_mut(client) {
+            Some(held) => {
+                held.last_seen = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Subscribes to events as they happen.
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchdogEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns the held movements and recent events.
+    pub fn status(&self) -> WatchdogStatus {
+        let mut held: Vec<HeldMovement> = self
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(client, held)| HeldMovement {
+                client: client.clone(),
+                linear: held.linear,
+                turn: held.turn,
+                silent_ms: held.last_seen.elapsed().as_millis() as u64,
+            })
+            .collect();
+        held.sort_by(|a, b| a.client.cmp(&b.client));
+
+        WatchdogStatus {
+            timeout_ms: self.timeout.map(|t| t.as_millis() as u64),
+            held,
+            events: self.history.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+
+    /// Checks for silent clients until the task is dropped.
+    ///
+    /// Returns immediately if the watchdog is disabled.
+    ///
+    /// # Arguments
+    /// * `robot` - Client or queue used to send the stop commands
+    pub async fn run(self: Arc<Self>, robot: impl CommandSink) {
+        let Some(timeout) = self.timeout else {
+            return;
+        };
+
+        // Check several times per timeout so a stop is never much late
+        let mut interval = tokio::time::interval((timeout / 4).max(MIN_CHECK_INTERVAL));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            for (client, held) in self.take_expired(timeout) {
+                self.trip(&robot, client, held).await;
+            }
+        }
+    }
+
+    /// Removes and returns the clients that have been silent too long.
+    fn take_expired(&self, timeout: Duration) -> Vec<(String, Held)> {
+        let mut clients = self.clients.lock().unwrap();
+        let expired: Vec<String> = clients
+            .iter()
+            .filter(|(_, held)| held.last_seen.elapsed() > timeout)
+            .map(|(client, _)| client.clone())
+            .collect();
+
+        expired
+            .into_iter()
+            .filter_map(|client| clients.remove_entry(&client))
+            .collect()
+    }
+
+    /// Stops the movements of a silent client and reports the event.
+    async fn trip(&self, robot: &impl CommandSink, client: String, held: Held) {
+        let silent_ms = held.last_seen.elapsed().as_millis() as u64;
+        let stopped = held.stops();
+
+        // Attempt every stop even if one fails
+        let mut error = None;
+        for command in &stopped {
+            if let Err(e) = robot.send(command).await {
+                error.get_or_insert(e.to_string());
+            }
+        }
+
+        let names: Vec<String> = stopped.iter().map(|c| c.to_string()).collect();
+        match &error {
+            None => println!(
+                "Watchdog: client {} silent for {}ms, sent {}",
+                client,
+                silent_ms,
+                names.join(", ")
+            ),
+            Some(e) => eprintln!(
+                "Watchdog: client {} silent for {}ms, failed to send {}: {}",
+                client,
+                silent_ms,
+                names.join(", "),
+                e
+            ),
+        }
+
+        let event = WatchdogEvent {
+            client,
+            time: unix_timestamp(SystemTime::now()),
+            silent_ms,
+            stopped,
+            error,
+        };
+
+        let mut history = self.history.lock().unwrap();
+        if history.len() == EVENT_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(event.clone());
+        drop(history);
+
+        // Nobody listening is fine; the event stays in the history
+        let _ = self.events.send(event);
+    }
+}
@@ -5,11 +5,12 @@ set -e
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 BINARY="$SCRIPT_DIR/target/release/wifi-proxy"
 
-# Default values
-SSID="WAVESHARE Robot"
-PASSWORD="1234567890"
-INTERFACE="wlxdceae760e328"
-PORT=8080
+# Default values, overridable from the environment
+# Leave SSID empty to discover the robot, INTERFACE empty to auto-detect the USB adapter
+SSID="${SSID:-}"
+PASSWORD="${PASSWORD:-1234567890}"
+INTERFACE="${INTERFACE:-}"
+PORT="${PORT:-8080}"
 
 # Colors
 RED='\033[0;31m'
@@ -42,18 +43,35 @@ if lsof -ti:$PORT >/dev/null 2>&1; then
     sleep 1
 fi
 
+INTERFACE_ARGS=()
+if [ -n "$INTERFACE" ]; then
+    INTERFACE_ARGS=(--interface "$INTERFACE")
+fi
+
 # Connect to WiFi
-print_status "Connecting to '$SSID' on interface $INTERFACE..."
-if "$BINARY" connect "$SSID" --password "$PASSWORD" --interface "$INTERFACE" 2>/dev/null; then
-    print_success "Connected to $SSID"
-else
-    print_error "Failed to connect. Checking if already connected..."
-    if "$BINARY" status --interface "$INTERFACE" 2>/dev/null | grep -q "$SSID"; then
-        print_success "Already connected to $SSID"
+if [ -z "$SSID" ]; then
+    # Saved credentials take precedence over the factory password
+    print_status "Looking for the robot's access point..."
+    if "$BINARY" discover --connect "${INTERFACE_ARGS[@]}" >/dev/null 2>&1 \
+        || "$BINARY" discover --connect --password "$PASSWORD" "${INTERFACE_ARGS[@]}"; then
+        print_success "Connected to the robot"
     else
-        print_error "Connection failed"
+        print_error "No robot found. Set SSID to connect to a known network."
         exit 1
     fi
+else
+    print_status "Connecting to '$SSID'..."
+    if "$BINARY" connect "$SSID" --password "$PASSWORD" "${INTERFACE_ARGS[@]}" 2>/dev/null; then
+        print_success "Connected to $SSID"
+    else
+        print_error "Failed to connect. Checking if already connected..."
+        if "$BINARY" status "${INTERFACE_ARGS[@]}" 2>/dev/null | grep -q "$SSID"; then
+            print_success "Already connected to $SSID"
+        else
+            print_error "Connection failed"
+            exit 1
+        fi
+    fi
 fi
 
 # Start server
@@ -61,4 +79,4 @@ print_status "Starting web server on port $PORT..."
 print_success "Access the control panel at: http://localhost:$PORT/"
 echo ""
 
-exec "$BINARY" serve --port "$PORT" --interface "$INTERFACE"
+exec "$BINARY" serve --port "$PORT" "${INTERFACE_ARGS[@]}"
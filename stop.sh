@@ -3,9 +3,9 @@
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 BINARY="$SCRIPT_DIR/target/release/wifi-proxy"
 
-# Default values
-INTERFACE="wlxdceae760e328"
-PORT=8080
+# Default values, overridable from the environment (same as start.sh)
+INTERFACE="${INTERFACE:-}"
+PORT="${PORT:-8080}"
 
 # Colors
 RED='\033[0;31m'
@@ -31,9 +31,13 @@ else
 fi
 
 # Disconnect WiFi
+INTERFACE_ARGS=()
+if [ -n "$INTERFACE" ]; then
+    INTERFACE_ARGS=(--interface "$INTERFACE")
+fi
 if [ -f "$BINARY" ]; then
-    print_status "Disconnecting interface $INTERFACE..."
-    "$BINARY" disconnect --interface "$INTERFACE" 2>/dev/null && \
+    print_status "Disconnecting interface ${INTERFACE:-(auto-detected)}..."
+    "$BINARY" disconnect "${INTERFACE_ARGS[@]}" 2>/dev/null && \
         print_success "Disconnected" || \
         print_status "Interface was not connected"
 fi
@@ -0,0 +1,222 @@
+//! CLI tests against the in-memory backend.
+//!
+//! Each test runs the `wifi-proxy` binary with `--backend memory`, which
+//! simulates a built-in adapter (`wlan0`), a USB adapter (`wlan1`) and two
+//! access points: the robot ("WAVESHARE Robot", password "1234567890") and
+//! "HomeNetwork". The simulated state lives in the process, so every
+//! invocation starts disconnected.
+//!
+//! `XDG_CONFIG_HOME` points to a fresh directory per test so saved
+//! credentials never touch the user's real config file.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Runs the CLI on the memory backend with an isolated config directory.
+///
+/// # Arguments
+/// * `config_home` - Directory used as `XDG_CONFIG_HOME`
+/// * `args` - Arguments after `--backend memory`
+fn run(config_home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wifi-proxy"))
+        .args(["--backend", "memory"])
+        .args(args)
+        .env("XDG_CONFIG_HOME", config_home)
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .expect("failed to run wifi-proxy")
+}
+
+/// Runs the CLI in JSON mode and parses its standard output.
+fn run_json(config_home: &Path, args: &[&str]) -> (Output, Value) {
+    let mut full = vec!["--output", "json"];
+    full.extend_from_slice(args);
+    let output = run(config_home, &full);
+    let value = serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        panic!(
+            "stdout is not JSON ({}): {}",
+            e,
+            String::from_utf8_lossy(&output.stdout)
+        )
+    });
+    (output, value)
+}
+
+/// Returns the standard output as text.
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Returns the standard error as text.
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Creates an empty config directory unique to one test.
+fn config_home(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wifi-proxy-cli-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn list_interfaces_marks_usb_adapter() {
+    let home = config_home("list");
+    let output = run(&home, &["list-interfaces"]);
+
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    let wlan1 = stdout.lines().find(|l| l.starts_with("wlan1")).unwrap_or_else(|| panic!("{}", stdout));
+    assert!(wlan1.contains("disconnected") && wlan1.ends_with("USB"), "{}", wlan1);
+}
+
+#[test]
+fn scan_lists_robot_before_weaker_network() {
+    let home = config_home("scan");
+    let output = run(&home, &["scan", "-i", "wlan1"]);
+
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    let robot = stdout.find("WAVESHARE Robot").unwrap_or_else(|| panic!("{}", stdout));
+    let home_network = stdout.find("HomeNetwork").unwrap_or_else(|| panic!("{}", stdout));
+    assert!(robot < home_network, "{}", stdout);
+}
+
+#[test]
+fn connect_with_password_reports_status() {
+    let home = config_home("connect");
+    let output = run(&home, &["connect", "WAVESHARE Robot", "-p", "1234567890", "-i", "wlan1"]);
+
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Connected successfully!"), "{}", stdout);
+    assert!(stdout.contains("Gateway:   192.168.4.1"), "{}", stdout);
+}
+
+#[test]
+fn connect_with_wrong_password_fails() {
+    let home = config_home("wrong-password");
+    let output = run(&home, &["connect", "WAVESHARE Robot", "-p", "wrong-pass", "-i", "wlan1"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Connection failed"), "{}", stderr(&output));
+}
+
+#[test]
+fn status_of_unknown_interface_fails() {
+    let home = config_home("unknown");
+    let output = run(&home, &["status", "-i", "wlan7"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("'wlan7' not found"), "{}", stderr(&output));
+}
+
+#[test]
+fn status_of_fresh_adapter_is_disconnected() {
+    let home = config_home("status");
+    let output = run(&home, &["status", "-i", "wlan1"]);
+
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Interface: wlan1"), "{}", stdout);
+    assert!(stdout.contains("Connected: (none)"), "{}", stdout);
+    assert!(!stdout.contains("Gateway:"), "{}", stdout);
+}
+
+#[test]
+fn saved_credentials_are_used_to_connect() {
+    let home = config_home("saved");
+    let output = run(&home, &["save-network", "WAVESHARE Robot", "-p", "1234567890", "-i", "wlan1"]);
+    assert!(output.status.success());
+
+    // No password and no interface given: both come from the config file
+    let output = run(&home, &["connect", "WAVESHARE Robot"]);
+
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    assert!(stdout.contains("Using saved password for 'WAVESHARE Robot'"), "{}", stdout);
+    assert!(stdout.contains("on interface wlan1"), "{}", stdout);
+    assert!(stdout.contains("Gateway:   192.168.4.1"), "{}", stdout);
+}
+
+#[test]
+fn list_interfaces_json_reports_usb_adapter() {
+    let home = config_home("list-json");
+    let (output, json) = run_json(&home, &["list-interfaces"]);
+
+    assert!(output.status.success());
+    let interfaces = json.as_array().unwrap();
+    assert_eq!(interfaces.len(), 2);
+    assert_eq!(json[1]["name"], "wlan1");
+    assert_eq!(json[1]["is_usb"], true);
+    assert_eq!(json[1]["state"], "disconnected");
+}
+
+#[test]
+fn connect_with_wrong_password_fails_with_kind() {
+    let home = config_home("wrong-password-json");
+    let (output, json) = run_json(&home, &["connect", "WAVESHARE Robot", "-p", "wrong-pass", "-i", "wlan1"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(json["error"]["kind"], "connection_failed");
+}
+
+#[test]
+fn status_of_unknown_interface_fails_with_kind() {
+    let home = config_home("unknown-json");
+    let (output, json) = run_json(&home, &["status", "-i", "wlan7"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(json["error"]["kind"], "interface_not_found");
+}
+
+#[test]
+fn status_json_of_fresh_adapter_has_no_connection() {
+    let home = config_home("status-json");
+    let (output, json) = run_json(&home, &["status", "-i", "wlan1"]);
+
+    assert!(output.status.success());
+    assert_eq!(json["interface"], "wlan1");
+    assert!(json["connection"].is_null());
+    assert!(json["gateway"].is_null());
+}
+
+#[test]
+fn output_flag_is_accepted_after_the_command() {
+    let home = config_home("output-after");
+    let output = run(&home, &["status", "-i", "wlan1", "--output", "json"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["interface"], "wlan1");
+}
+
+#[test]
+fn saved_credentials_connect_reports_json_status() {
+    let home = config_home("saved-json");
+    let output = run(&home, &["save-network", "WAVESHARE Robot", "-p", "1234567890", "-i", "wlan1"]);
+    assert!(output.status.success());
+
+    let (output, json) = run_json(&home, &["connect", "WAVESHARE Robot"]);
+
+    assert!(output.status.success());
+    assert_eq!(json["interface"], "wlan1");
+    assert_eq!(json["connection"], "WAVESHARE Robot");
+    assert_eq!(json["gateway"], "192.168.4.1");
+}
+
+#[test]
+fn scan_json_reports_bssid_channel_and_band() {
+    let home = config_home("scan-json");
+    let (output, json) = run_json(&home, &["scan", "-i", "wlan1"]);
+
+    assert!(output.status.success());
+    assert_eq!(json[0]["ssid"], "WAVESHARE Robot");
+    assert_eq!(json[0]["bssid"], "24:0A:C4:12:34:56");
+    assert_eq!(json[0]["channel"], 6);
+    assert_eq!(json[0]["band"], "2.4ghz");
+    assert_eq!(json[1]["ssid"], "HomeNetwork");
+    assert_eq!(json[1]["band"], "5ghz");
+}
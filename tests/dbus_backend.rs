@@ -0,0 +1,552 @@
+//! D-Bus backend tests against a mock NetworkManager.
+//!
+//! Each test starts a private `dbus-daemon` session bus, serves a small mock
+//! of the `org.freedesktop.NetworkManager` objects the backend uses on it,
+//! and drives [`DbusBackend`] through a connection to that bus. Tests are
+//! skipped when `dbus-daemon` is not installed.
+//!
+//! # Mock Objects
+//!
+//! | Path                  | Contents                                                  |
+//! |-----------------------|-----------------------------------------------------------|
+//! | `Devices/1`           | `wlan1`, WiFi, connected, with wireless interface         |
+//! | `Devices/2`           | `eth0`, Ethernet                                          |
+//! | `AccessPoint/1`, `/2` | "WAVESHARE Robot" (WPA2) and "HomeNetwork" (WPA3)         |
+//! | `AccessPoint/3`       | Listed by `GetAllAccessPoints` but gone (never served)    |
+//! | `AccessPoint/4`       | A second, weaker "WAVESHARE Robot" (another robot)        |
+//! | `AccessPoint/5`       | "RobotSetup", an open network                             |
+//! | `Settings/1`          | Saved "WAVESHARE Robot" profile with a static IPv4 setup  |
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use wifi_proxy::backend::dbus::DbusBackend;
+use wifi_proxy::backend::NetworkBackend;
+use wifi_proxy::scan::Security;
+use zbus::blocking::connection::Builder;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+const DEVICE_WIFI: &str = "/org/freedesktop/NetworkManager/Devices/1";
+const DEVICE_ETHERNET: &str = "/org/freedesktop/NetworkManager/Devices/2";
+const AP_ROBOT: &str = "/org/freedesktop/NetworkManager/AccessPoint/1";
+const AP_HOME: &str = "/org/freedesktop/NetworkManager/AccessPoint/2";
+const AP_VANISHED: &str = "/org/freedesktop/NetworkManager/AccessPoint/3";
+const AP_OTHER_ROBOT: &str = "/org/freedesktop/NetworkManager/AccessPoint/4";
+const AP_OPEN: &str = "/org/freedesktop/NetworkManager/AccessPoint/5";
+const IP4_CONFIG: &str = "/org/freedesktop/NetworkManager/IP4Config/1";
+const ACTIVE: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+const PROFILE: &str = "/org/freedesktop/NetworkManager/Settings/1";
+
+/// Connection settings as exchanged by `GetSettings` and `Update`.
+type Settings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// State shared by the mock objects and inspected by the tests.
+#[derive(Default)]
+struct MockState {
+    /// Settings of the saved profile, replaced by `Update`.
+    profile: Settings,
+
+    /// Incremented by every `RequestScan`.
+    last_scan: i64,
+
+    /// `(connection, device, specific_object)` of the last `ActivateConnection`.
+    activated: Option<(String, String, String)>,
+}
+
+type Shared = Arc<Mutex<MockState>>;
+
+/// Converts a value into an owned one for a settings or property map.
+fn owned<'a>(value: impl Into<Value<'a>>) -> OwnedValue {
+    value.into().try_to_owned().unwrap()
+}
+
+fn path(path: &str) -> OwnedObjectPath {
+    ObjectPath::try_from(path).unwrap().into()
+}
+
+/// `org.freedesktop.NetworkManager` on the root object.
+struct NetworkManager(Shared);
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager")]
+impl NetworkManager {
+    fn get_devices(&self) -> Vec<OwnedObjectPath> {
+        vec![path(DEVICE_ETHERNET), path(DEVICE_WIFI)]
+    }
+
+    fn activate_connection(
+        &self,
+        connection: OwnedObjectPath,
+        device: OwnedObjectPath,
+        specific_object: OwnedObjectPath,
+    ) -> OwnedObjectPath {
+        self.0.lock().unwrap().activated = Some((
+            connection.to_string(),
+            device.to_string(),
+            specific_object.to_string(),
+        ));
+        path(ACTIVE)
+    }
+}
+
+/// `org.freedesktop.NetworkManager.Device` on both devices.
+struct Device {
+    name: &'static str,
+    device_type: u32,
+}
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.Device")]
+impl Device {
+    #[zbus(property)]
+    fn interface(&self) -> String {
+        self.name.to_string()
+    }
+
+    #[zbus(property)]
+    fn device_type(&self) -> u32 {
+        self.device_type
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        100
+    }
+
+    #[zbus(property)]
+    fn active_connection(&self) -> OwnedObjectPath {
+        path(ACTIVE)
+    }
+
+    #[zbus(property, name = "Ip4Config")]
+    fn ip4_config(&self) -> OwnedObjectPath {
+        path(IP4_CONFIG)
+    }
+}
+
+/// `org.freedesktop.NetworkManager.Device.Wireless` on the WiFi device.
+struct Wireless(Shared);
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+impl Wireless {
+    fn request_scan(&self, _options: HashMap<String, OwnedValue>) {
+        self.0.lock().unwrap().last_scan += 1;
+    }
+
+    fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+        vec![path(AP_ROBOT), path(AP_VANISHED), path(AP_HOME), path(AP_OTHER_ROBOT), path(AP_OPEN)]
+    }
+
+    #[zbus(property)]
+    fn last_scan(&self) -> i64 {
+        self.0.lock().unwrap().last_scan
+    }
+
+    #[zbus(property)]
+    fn active_access_point(&self) -> OwnedObjectPath {
+        path(AP_ROBOT)
+    }
+}
+
+/// `org.freedesktop.NetworkManager.AccessPoint`.
+struct AccessPoint {
+    ssid: &'static str,
+    bssid: &'static str,
+    strength: u8,
+    rsn_flags: u32,
+    frequency: u32,
+}
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+impl AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> Vec<u8> {
+        self.ssid.as_bytes().to_vec()
+    }
+
+    #[zbus(property)]
+    fn hw_address(&self) -> String {
+        self.bssid.to_string()
+    }
+
+    #[zbus(property)]
+    fn strength(&self) -> u8 {
+        self.strength
+    }
+
+    /// Privacy is advertised by every access point with RSN flags.
+    #[zbus(property)]
+    fn flags(&self) -> u32 {
+        u32::from(self.rsn_flags != 0)
+    }
+
+    #[zbus(property)]
+    fn wpa_flags(&self) -> u32 {
+        0
+    }
+
+    #[zbus(property)]
+    fn rsn_flags(&self) -> u32 {
+        self.rsn_flags
+    }
+
+    #[zbus(property)]
+    fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    #[zbus(property)]
+    fn max_bitrate(&self) -> u32 {
+        72_200
+    }
+
+    #[zbus(property)]
+    fn mode(&self) -> u32 {
+        2
+    }
+}
+
+/// `org.freedesktop.NetworkManager.IP4Config`.
+struct Ip4Config;
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.IP4Config")]
+impl Ip4Config {
+    #[zbus(property)]
+    fn address_data(&self) -> Vec<HashMap<String, OwnedValue>> {
+        vec![HashMap::from([
+            ("address".to_string(), owned("192.168.4.2")),
+            ("prefix".to_string(), owned(24u32)),
+        ])]
+    }
+
+    #[zbus(property)]
+    fn nameserver_data(&self) -> Vec<HashMap<String, OwnedValue>> {
+        vec![HashMap::from([("address".to_string(), owned("192.168.4.1"))])]
+    }
+
+    #[zbus(property)]
+    fn gateway(&self) -> String {
+        "192.168.4.1".to_string()
+    }
+}
+
+/// `org.freedesktop.NetworkManager.Connection.Active`, always activated.
+///
+/// Its profile is named like NetworkManager names a second profile for the
+/// same network, so the name and the SSID differ.
+struct Active;
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+impl Active {
+    #[zbus(property)]
+    fn id(&self) -> String {
+        "WAVESHARE Robot 1".to_string()
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        2
+    }
+}
+
+/// `org.freedesktop.NetworkManager.Settings`.
+struct SettingsService;
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.Settings")]
+impl SettingsService {
+    fn list_connections(&self) -> Vec<OwnedObjectPath> {
+        vec![path(PROFILE)]
+    }
+}
+
+/// `org.freedesktop.NetworkManager.Settings.Connection` for the saved profile.
+struct Profile(Shared);
+
+#[zbus::interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+impl Profile {
+    fn get_settings(&self) -> Settings {
+        let state = self.0.lock().unwrap();
+        state
+            .profile
+            .iter()
+            .map(|(group, values)| {
+                let values = values
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.try_clone().unwrap()))
+                    .collect();
+                (group.clone(), values)
+            })
+            .collect()
+    }
+
+    fn update(&self, settings: Settings) {
+        self.0.lock().unwrap().profile = settings;
+    }
+}
+
+/// Saved profile as NetworkManager returns it: secrets are not included.
+fn saved_profile() -> Settings {
+    HashMap::from([
+        (
+            "connection".to_string(),
+            HashMap::from([
+                ("id".to_string(), owned("WAVESHARE Robot")),
+                ("uuid".to_string(), owned("4d1c5f8e-0000-4000-8000-000000000001")),
+                ("type".to_string(), owned("802-11-wireless")),
+            ]),
+        ),
+        (
+            "802-11-wireless".to_string(),
+            HashMap::from([("ssid".to_string(), owned(b"WAVESHARE Robot".as_slice()))]),
+        ),
+        (
+            "802-11-wireless-security".to_string(),
+            HashMap::from([("key-mgmt".to_string(), owned("wpa-psk"))]),
+        ),
+        (
+            "ipv4".to_string(),
+            HashMap::from([
+                ("method".to_string(), owned("manual")),
+                ("gateway".to_string(), owned("192.168.4.1")),
+            ]),
+        ),
+        (
+            "ipv6".to_string(),
+            HashMap::from([("method".to_string(), owned("disabled"))]),
+        ),
+    ])
+}
+
+/// A private session bus, killed on drop.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// Starts a private session bus, or returns `None` if `dbus-daemon` is missing.
+fn start_bus() -> Option<Bus> {
+    let mut daemon = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // The first line of output is the bus address
+    let mut address = String::new();
+    BufReader::new(daemon.stdout.take()?).read_line(&mut address).ok()?;
+
+    Some(Bus {
+        daemon,
+        address: address.trim().to_string(),
+    })
+}
+
+/// Serves the mock NetworkManager on the bus.
+///
+/// # Returns
+/// The service's connection (keep it alive for the duration of the test)
+/// and the state its objects share.
+fn serve_mock(bus: &Bus) -> (zbus::blocking::Connection, Shared) {
+    let state: Shared = Arc::new(Mutex::new(MockState {
+        profile: saved_profile(),
+        ..MockState::default()
+    }));
+
+    let access_point = |ssid, bssid, strength, rsn_flags, frequency| AccessPoint {
+        ssid,
+        bssid,
+        strength,
+        rsn_flags,
+        frequency,
+    };
+
+    let connection = Builder::address(bus.address.as_str())
+        .unwrap()
+        .name("org.freedesktop.NetworkManager")
+        .unwrap()
+        .serve_at("/org/freedesktop/NetworkManager", NetworkManager(state.clone()))
+        .unwrap()
+        .serve_at(DEVICE_WIFI, Device { name: "wlan1", device_type: 2 })
+        .unwrap()
+        .serve_at(DEVICE_WIFI, Wireless(state.clone()))
+        .unwrap()
+        .serve_at(DEVICE_ETHERNET, Device { name: "eth0", device_type: 1 })
+        .unwrap()
+        .serve_at(AP_ROBOT, access_point("WAVESHARE Robot", "24:0A:C4:12:34:56", 78, 0x188, 2437))
+        .unwrap()
+        .serve_at(AP_HOME, access_point("HomeNetwork", "3C:84:6A:01:02:03", 64, 0x488, 5180))
+        .unwrap()
+        .serve_at(AP_OTHER_ROBOT, access_point("WAVESHARE Robot", "30:AE:A4:00:11:22", 41, 0x188, 2462))
+        .unwrap()
+        .serve_at(AP_OPEN, access_point("RobotSetup", "30:AE:A4:00:33:44", 35, 0, 2412))
+        .unwrap()
+        .serve_at(IP4_CONFIG, Ip4Config)
+        .unwrap()
+        .serve_at(ACTIVE, Active)
+        .unwrap()
+        .serve_at("/org/freedesktop/NetworkManager/Settings", SettingsService)
+        .unwrap()
+        .serve_at(PROFILE, Profile(state.clone()))
+        .unwrap()
+        .build()
+        .unwrap();
+
+    (connection, state)
+}
+
+/// Creates a backend talking to the mock over a second connection.
+fn client(bus: &Bus) -> DbusBackend {
+    let conn = Builder::address(bus.address.as_str()).unwrap().build().unwrap();
+    DbusBackend::with_connection(conn)
+}
+
+/// Starts the bus and mock, or skips the test if `dbus-daemon` is missing.
+macro_rules! setup {
+    () => {{
+        let Some(bus) = start_bus() else {
+            eprintln!("dbus-daemon not found; skipping");
+            return;
+        };
+        let (service, state) = serve_mock(&bus);
+        let backend = client(&bus);
+        (bus, service, state, backend)
+    }};
+}
+
+#[test]
+fn lists_only_wifi_devices() {
+    let (_bus, _service, _state, backend) = setup!();
+
+    let interfaces = backend.list_interfaces().unwrap();
+
+    assert_eq!(interfaces.len(), 1);
+    assert_eq!(interfaces[0].name, "wlan1");
+    assert_eq!(interfaces[0].state, "connected");
+}
+
+#[test]
+fn scan_skips_access_points_that_vanish() {
+    let (_bus, _service, state, backend) = setup!();
+
+    let networks = backend.scan("wlan1").unwrap();
+
+    assert_eq!(state.lock().unwrap().last_scan, 1);
+    assert_eq!(networks.len(), 4);
+    assert_eq!(networks[0].ssid, "WAVESHARE Robot");
+    assert_eq!(networks[0].bssid, "24:0A:C4:12:34:56");
+    assert_eq!(networks[0].security, Security::Wpa2);
+    assert_eq!(networks[0].max_rate_mbps, Some(72.2));
+    assert_eq!(networks[1].ssid, "HomeNetwork");
+    assert_eq!(networks[1].security, Security::Wpa3);
+}
+
+#[test]
+fn connect_updates_saved_profile_without_dropping_settings() {
+    let (_bus, _service, state, backend) = setup!();
+
+    backend.connect("wlan1", "WAVESHARE Robot", "1234567890").unwrap();
+
+    let state = state.lock().unwrap();
+    let (connection, device, ap) = state.activated.clone().unwrap();
+    assert_eq!(connection, PROFILE);
+    assert_eq!(device, DEVICE_WIFI);
+    assert_eq!(ap, AP_ROBOT);
+
+    // The new key is set and the IP configuration survives the update
+    let profile = &state.profile;
+    let security = &profile["802-11-wireless-security"];
+    assert_eq!(String::try_from(security["psk"].try_clone().unwrap()).unwrap(), "1234567890");
+    assert_eq!(String::try_from(profile["ipv4"]["method"].try_clone().unwrap()).unwrap(), "manual");
+    assert_eq!(String::try_from(profile["ipv6"]["method"].try_clone().unwrap()).unwrap(), "disabled");
+    assert_eq!(
+        String::try_from(profile["connection"]["uuid"].try_clone().unwrap()).unwrap(),
+        "4d1c5f8e-0000-4000-8000-000000000001"
+    );
+}
+
+#[test]
+fn connect_bssid_activates_that_access_point() {
+    let (_bus, _service, state, backend) = setup!();
+
+    backend
+        .connect_bssid("wlan1", "WAVESHARE Robot", "30:ae:a4:00:11:22", "1234567890")
+        .unwrap();
+
+    // The weaker robot is joined, and the profile sticks to it
+    {
+        let state = state.lock().unwrap();
+        let (_, _, ap) = state.activated.clone().unwrap();
+        assert_eq!(ap, AP_OTHER_ROBOT);
+        let bssid = Vec::<u8>::try_from(state.profile["802-11-wireless"]["bssid"].try_clone().unwrap()).unwrap();
+        assert_eq!(bssid, [0x30, 0xAE, 0xA4, 0x00, 0x11, 0x22]);
+    }
+
+    // A later plain connect joins the strongest robot and lifts the restriction
+    backend.connect("wlan1", "WAVESHARE Robot", "1234567890").unwrap();
+
+    let state = state.lock().unwrap();
+    let (_, _, ap) = state.activated.clone().unwrap();
+    assert_eq!(ap, AP_ROBOT);
+    assert!(!state.profile["802-11-wireless"].contains_key("bssid"));
+}
+
+#[test]
+fn connect_without_password_keeps_saved_security() {
+    let (_bus, _service, state, backend) = setup!();
+
+    backend.connect("wlan1", "WAVESHARE Robot", "").unwrap();
+
+    // The network still advertises WPA2, so the saved key stays in use
+    let state = state.lock().unwrap();
+    let security = &state.profile["802-11-wireless-security"];
+    assert_eq!(String::try_from(security["key-mgmt"].try_clone().unwrap()).unwrap(), "wpa-psk");
+}
+
+#[test]
+fn connect_to_open_network_drops_saved_security() {
+    let (_bus, _service, state, backend) = setup!();
+
+    // The network was secured when the profile was saved and is open now
+    let mut profile = saved_profile();
+    profile.get_mut("connection").unwrap().insert("id".to_string(), owned("RobotSetup"));
+    state.lock().unwrap().profile = profile;
+
+    backend.connect("wlan1", "RobotSetup", "").unwrap();
+
+    let state = state.lock().unwrap();
+    let (_, _, ap) = state.activated.clone().unwrap();
+    assert_eq!(ap, AP_OPEN);
+    assert!(!state.profile.contains_key("802-11-wireless-security"));
+    assert!(state.profile.contains_key("ipv4"));
+}
+
+#[test]
+fn status_reads_active_connection_ssid_and_ip4_config() {
+    let (_bus, _service, _state, backend) = setup!();
+
+    let status = backend.status("wlan1").unwrap();
+
+    assert_eq!(status.state, "100 (connected)");
+    assert_eq!(status.connection.as_deref(), Some("WAVESHARE Robot 1"));
+    assert_eq!(status.ssid.as_deref(), Some("WAVESHARE Robot"));
+    assert_eq!(status.addresses, vec!["192.168.4.2/24".to_string()]);
+    assert_eq!(status.dns, vec!["192.168.4.1".to_string()]);
+    assert_eq!(status.gateway.as_deref(), Some("192.168.4.1"));
+}
+
+#[test]
+fn unknown_interface_is_reported() {
+    let (_bus, _service, _state, backend) = setup!();
+
+    let err = backend.status("wlan7").unwrap_err();
+
+    assert!(err.to_string().contains("wlan7"), "{}", err);
+}
@@ -0,0 +1,233 @@
+//! Emergency stop tests.
+//!
+//! The lock and the replay interlock run on tokio's paused clock against a
+//! [`RecordingSink`] that keeps every query it is asked to deliver. The
+//! server tests start the proxy and the mock robot on free local ports and
+//! use real time, since they wait on sockets.
+
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+use wifi_proxy::estop::{EmergencyStop, STOP_SEQUENCE};
+use wifi_proxy::mock_robot::{run_mock_robot, MockRobotConfig};
+use wifi_proxy::replay::{replay, RecordedCommand};
+use wifi_proxy::robot::{CommandSink, MoveDirection, RobotCommand};
+use wifi_proxy::server::{run_server, ServerConfig};
+
+/// Robot stand-in that records every query and always succeeds.
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<String>>>);
+
+impl RecordingSink {
+    /// Returns the queries delivered so far.
+    fn sent(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl CommandSink for RecordingSink {
+    async fn send_query(&self, query: &str) -> Result<String> {
+        self.0.lock().unwrap().push(query.to_string());
+        Ok("ok".to_string())
+    }
+}
+
+/// Returns the query string the robot receives for a move.
+fn query(dir: MoveDirection) -> String {
+    RobotCommand::Move(dir).to_query()
+}
+
+/// Returns a recorded command at `t` seconds.
+fn recorded(t: f64, dir: MoveDirection) -> RecordedCommand {
+    RecordedCommand {
+        t,
+        time: String::new(),
+        query: query(dir),
+        command: Some(RobotCommand::Move(dir)),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn engage_sends_stop_sequence_and_locks_until_release() {
+    let robot = RecordingSink::default();
+    let estop = EmergencyStop::new(None);
+
+    let event = estop.engage(&robot, "test", Some("runaway".to_string())).await;
+
+    assert!(event.errors.is_empty());
+    let stops: Vec<String> = STOP_SEQUENCE.iter().map(RobotCommand::to_query).collect();
+    assert_eq!(robot.sent(), stops);
+    let err = estop.check().unwrap_err();
+    assert!(err.to_string().contains("locked"), "{}", err);
+
+    // While locked only stops get through the interlock
+    let guarded = estop.interlock(&robot);
+    assert!(guarded.send_query(&query(MoveDirection::Forward)).await.is_err());
+    guarded.send_query(&query(MoveDirection::StopTurn)).await.unwrap();
+
+    assert!(estop.release("test").is_some());
+    estop.check().unwrap();
+    guarded.send_query(&query(MoveDirection::Forward)).await.unwrap();
+    assert_eq!(robot.sent()[3..], [query(MoveDirection::StopTurn), query(MoveDirection::Forward)]);
+    assert!(estop.release("test").is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn replay_sends_nothing_due_after_an_estop() {
+    let robot = RecordingSink::default();
+    let estop = EmergencyStop::new(None);
+    let stop = CancellationToken::new();
+    let commands = [recorded(0.0, MoveDirection::Forward), recorded(1.0, MoveDirection::Left)];
+
+    // The stop comes at the very instant the second command is due, aborting
+    // the replay first as the server does
+    let guarded = estop.interlock(&robot);
+    let (_, summary) = tokio::join!(
+        biased;
+        async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            stop.cancel();
+            estop.engage(&robot, "test", None).await;
+        },
+        replay(&guarded, &commands, 1.0, stop.clone()),
+    );
+
+    let summary = summary.unwrap();
+    assert!(summary.aborted);
+    assert_eq!(summary.sent, 1);
+    let sent = robot.sent();
+    assert_eq!(sent[0], query(MoveDirection::Forward));
+    assert!(!sent.contains(&query(MoveDirection::Left)), "{:?}", sent);
+    assert_eq!(sent[sent.len() - 2..], [query(MoveDirection::StopLinear), query(MoveDirection::StopTurn)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn interlock_refuses_replayed_moves_while_engaged() {
+    let robot = RecordingSink::default();
+    let estop = EmergencyStop::new(None);
+    let commands = [recorded(0.0, MoveDirection::Forward), recorded(1.0, MoveDirection::Left)];
+
+    // Engaged without aborting the replay: the interlock alone holds
+    let guarded = estop.interlock(&robot);
+    let (_, result) = tokio::join!(
+        biased;
+        async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            estop.engage(&robot, "test", None).await;
+        },
+        replay(&guarded, &commands, 1.0, CancellationToken::new()),
+    );
+
+    let err = result.unwrap_err();
+    assert!(format!("{:#}", err).contains("locked"), "{:#}", err);
+    let sent = robot.sent();
+    assert!(!sent.contains(&query(MoveDirection::Left)), "{:?}", sent);
+    assert_eq!(sent[sent.len() - 2..], [query(MoveDirection::StopLinear), query(MoveDirection::StopTurn)]);
+}
+
+/// Returns a local port that was free a moment ago.
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// Starts the mock robot and a proxy server in front of it.
+///
+/// # Returns
+/// The proxy's base URL and the mock robot's control base URL.
+async fn start_proxy() -> (String, String) {
+    let (control_port, stream_port, port) = (free_port(), free_port(), free_port());
+    tokio::spawn(run_mock_robot(MockRobotConfig {
+        control_port,
+        stream_port,
+        fps: 5,
+    }));
+    tokio::spawn(run_server(ServerConfig {
+        gateway: format!("127.0.0.1:{}", control_port),
+        stream_port,
+        port,
+        connect_timeout: Duration::from_secs(2),
+        request_timeout: Duration::from_secs(5),
+        recordings_dir: std::env::temp_dir(),
+        record_commands: None,
+        watchdog_timeout: None,
+        estop_log: None,
+        max_rate: None,
+        coalesce_window: None,
+        queue_depth: 16,
+        reconnect: None,
+        interface: None,
+        status_interval: Duration::from_secs(2),
+    }));
+
+    // Wait until both listen
+    let proxy = format!("http://127.0.0.1:{}", port);
+    let robot = format!("http://127.0.0.1:{}", control_port);
+    for _ in 0..100 {
+        let proxy_up = reqwest::get(format!("{}/api/estop", proxy)).await.is_ok();
+        let robot_up = reqwest::get(format!("{}/commands", robot)).await.is_ok();
+        if proxy_up && robot_up {
+            return (proxy, robot);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("proxy or mock robot did not start");
+}
+
+/// Sends one command over `/ws` and returns the reply with the same id.
+async fn ws_command(proxy: &str, id: u64, command: &str) -> Value {
+    let url = format!("{}/ws?client=test", proxy.replace("http://", "ws://"));
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    let message = format!(r#"{{"id": {}, "command": {}}}"#, id, command);
+    socket.send(Message::Text(message)).await.unwrap();
+
+    while let Some(message) = socket.next().await {
+        if let Message::Text(text) = message.unwrap() {
+            let reply: Value = serde_json::from_str(&text).unwrap();
+            if reply["id"] == id {
+                return reply;
+            }
+        }
+    }
+    panic!("socket closed without a reply to {}", id);
+}
+
+#[tokio::test]
+async fn control_and_ws_are_locked_until_release() {
+    let (proxy, robot) = start_proxy().await;
+    let http = reqwest::Client::new();
+    let forward = format!("{}/control?var=move&val=1&cmd=0", proxy);
+
+    let response = http.post(format!("{}/api/estop", proxy)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Both control paths refuse to move the robot
+    let response = http.get(&forward).send().await.unwrap();
+    assert_eq!(response.status(), 423);
+    let reply = ws_command(&proxy, 1, r#"{"move": "forward"}"#).await;
+    assert_eq!(reply["type"], "error");
+    assert!(reply["error"].as_str().unwrap().contains("locked"), "{}", reply);
+
+    let response = http.post(format!("{}/api/estop/release", proxy)).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Control is back on both paths
+    let response = http.get(&forward).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let reply = ws_command(&proxy, 2, r#"{"move": "left"}"#).await;
+    assert_eq!(reply["type"], "ack", "{}", reply);
+
+    // The robot got the stop sequence and only then the moves
+    let body = reqwest::get(format!("{}/commands", robot)).await.unwrap().bytes().await.unwrap();
+    let received: Vec<Value> = serde_json::from_slice(&body).unwrap();
+    let vals: Vec<(&str, &str)> = received
+        .iter()
+        .map(|c| (c["var"].as_str().unwrap(), c["val"].as_str().unwrap()))
+        .collect();
+    assert_eq!(vals, [("move", "3"), ("move", "6"), ("funcMode", "1"), ("move", "1"), ("move", "2")]);
+}
@@ -0,0 +1,100 @@
+//! Movement watchdog tests on tokio's paused clock.
+//!
+//! The watchdog runs against a [`RecordingSink`] that keeps every query it
+//! is asked to deliver, and time only moves when the runtime is idle, so
+//! timeouts elapse instantly and deterministically.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use wifi_proxy::robot::{CommandSink, MoveDirection, RobotCommand};
+use wifi_proxy::watchdog::Watchdog;
+
+const TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Robot stand-in that records every query and always succeeds.
+#[derive(Clone, Default)]
+struct RecordingSink(Arc<Mutex<Vec<String>>>);
+
+impl RecordingSink {
+    /// Returns the queries delivered so far.
+    fn sent(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl CommandSink for RecordingSink {
+    async fn send_query(&self, query: &str) -> Result<String> {
+        self.0.lock().unwrap().push(query.to_string());
+        Ok("ok".to_string())
+    }
+}
+
+/// Returns the query string the robot receives for a move.
+fn query(dir: MoveDirection) -> String {
+    RobotCommand::Move(dir).to_query()
+}
+
+#[tokio::test(start_paused = true)]
+async fn silent_client_is_stopped_and_reported() {
+    let robot = RecordingSink::default();
+    let watchdog = Arc::new(Watchdog::new(Some(TIMEOUT)));
+    let mut events = watchdog.subscribe();
+    tokio::spawn(watchdog.clone().run(robot.clone()));
+
+    // Walking and turning, then the client goes silent
+    watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::Forward));
+    watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::Left));
+    let event = events.recv().await.unwrap();
+
+    assert_eq!(event.client, "browser-1");
+    assert_eq!(
+        event.stopped,
+        vec![
+            RobotCommand::Move(MoveDirection::StopLinear),
+            RobotCommand::Move(MoveDirection::StopTurn),
+        ]
+    );
+    assert!(event.error.is_none());
+    assert!((1500..2000).contains(&event.silent_ms), "{}", event.silent_ms);
+    assert_eq!(robot.sent(), [query(MoveDirection::StopLinear), query(MoveDirection::StopTurn)]);
+
+    // The movement is over, so there is nothing left to keep alive
+    assert!(!watchdog.heartbeat("browser-1"));
+    assert_eq!(watchdog.status().events.len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn heartbeats_keep_a_movement_alive() {
+    let robot = RecordingSink::default();
+    let watchdog = Arc::new(Watchdog::new(Some(TIMEOUT)));
+    let mut events = watchdog.subscribe();
+    tokio::spawn(watchdog.clone().run(robot.clone()));
+
+    watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::Forward));
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(watchdog.heartbeat("browser-1"));
+    }
+    assert!(robot.sent().is_empty());
+
+    // Once the heartbeats stop, only the held axis is stopped
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.stopped, vec![RobotCommand::Move(MoveDirection::StopLinear)]);
+    assert_eq!(robot.sent(), [query(MoveDirection::StopLinear)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn released_movement_is_not_stopped() {
+    let robot = RecordingSink::default();
+    let watchdog = Arc::new(Watchdog::new(Some(TIMEOUT)));
+    tokio::spawn(watchdog.clone().run(robot.clone()));
+
+    watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::Forward));
+    watchdog.observe("browser-1", &RobotCommand::Move(MoveDirection::StopLinear));
+    tokio::time::sleep(TIMEOUT * 4).await;
+
+    assert!(robot.sent().is_empty());
+    assert!(watchdog.status().events.is_empty());
+}
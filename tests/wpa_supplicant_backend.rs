@@ -0,0 +1,264 @@
+//! wpa_supplicant backend tests against a fake control socket.
+//!
+//! Each test binds a datagram socket named after the interface in a fresh
+//! control directory and answers the ctrl_iface commands from a thread,
+//! keeping the network blocks in memory and logging every command so the
+//! tests can check what the backend sent and in which order.
+
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use wifi_proxy::backend::wpa_supplicant::WpaSupplicantBackend;
+use wifi_proxy::backend::NetworkBackend;
+use wifi_proxy::scan::Security;
+
+/// `SCAN_RESULTS` reply: the robot, a WPA2/WPA3 network and a hidden BSS.
+const SCAN_RESULTS: &str = "bssid / frequency / signal level / flags / ssid\n\
+    24:0a:c4:12:34:56\t2437\t-50\t[WPA2-PSK-CCMP][ESS]\tWAVESHARE Robot\n\
+    3c:84:6a:01:02:03\t5180\t-62\t[WPA2-PSK+SAE-CCMP][ESS]\tHome\\xe2\\x80\\x99s\n\
+    24:0a:c4:9a:bc:de\t2462\t-70\t[WPA2-PSK-CCMP][ESS]\t\n";
+
+/// What the fake daemon knows and has been asked.
+#[derive(Default)]
+struct FakeState {
+    /// Network blocks as `(id, ssid)`.
+    networks: Vec<(u32, String)>,
+
+    /// Next id handed out by `ADD_NETWORK`.
+    next_id: u32,
+
+    /// Network block selected with `SELECT_NETWORK`.
+    selected: Option<u32>,
+
+    /// Reply to `SET_NETWORK <id> psk ...`.
+    psk_reply: String,
+
+    /// Every command received, in order.
+    log: Vec<String>,
+}
+
+type Shared = Arc<Mutex<FakeState>>;
+
+impl FakeState {
+    /// Answers one command.
+    fn reply(&mut self, command: &str) -> String {
+        self.log.push(command.to_string());
+        let words: Vec<&str> = command.split(' ').collect();
+
+        match words.as_slice() {
+            ["LIST_NETWORKS"] => {
+                let mut reply = "network id / ssid / bssid / flags\n".to_string();
+                for (id, ssid) in &self.networks {
+                    reply.push_str(&format!("{}\t{}\tany\t\n", id, ssid));
+                }
+                reply
+            }
+            ["ADD_NETWORK"] => {
+                let id = self.next_id;
+                self.next_id += 1;
+                self.networks.push((id, String::new()));
+                format!("{}\n", id)
+            }
+            ["SET_NETWORK", id, "ssid", hex] => {
+                let ssid = (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap() as char)
+                    .collect();
+                if let Some(network) = self.networks.iter_mut().find(|(n, _)| n.to_string() == *id) {
+                    network.1 = ssid;
+                }
+                "OK\n".to_string()
+            }
+            ["SET_NETWORK", _, "psk", ..] => format!("{}\n", self.psk_reply),
+            ["SET_NETWORK", ..] | ["SAVE_CONFIG"] | ["ATTACH"] | ["DETACH"] | ["SCAN"] => {
+                "OK\n".to_string()
+            }
+            ["SELECT_NETWORK", id] => {
+                self.selected = id.parse().ok();
+                "OK\n".to_string()
+            }
+            ["REMOVE_NETWORK", id] => {
+                self.networks.retain(|(n, _)| n.to_string() != *id);
+                "OK\n".to_string()
+            }
+            ["STATUS"] => match self.selected.and_then(|id| self.networks.iter().find(|(n, _)| *n == id)) {
+                Some((_, ssid)) => {
+                    format!("bssid=24:0a:c4:12:34:56\nssid={}\nwpa_state=COMPLETED\nip_address=192.168.4.2\n", ssid)
+                }
+                _ => "wpa_state=SCANNING\n".to_string(),
+            },
+            ["SCAN_RESULTS"] => SCAN_RESULTS.to_string(),
+            _ => "UNKNOWN COMMAND\n".to_string(),
+        }
+    }
+
+    /// Returns the logged commands starting with the given word.
+    fn commands(&self, word: &str) -> Vec<String> {
+        self.log.iter().filter(|c| c.starts_with(word)).cloned().collect()
+    }
+}
+
+/// Binds a fake control socket for `wlan1` and serves it from a thread.
+///
+/// # Returns
+/// The control directory to hand to the backend and the fake's state.
+fn fake_daemon(test: &str, state: FakeState) -> (PathBuf, Shared) {
+    let dir = std::env::temp_dir().join(format!("wifi-proxy-wpa-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let socket = UnixDatagram::bind(dir.join("wlan1")).unwrap();
+    let state = Arc::new(Mutex::new(state));
+    let shared = state.clone();
+
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut attached: Option<PathBuf> = None;
+        loop {
+            let Ok((len, client)) = socket.recv_from(&mut buf) else {
+                return;
+            };
+            let Some(client) = client.as_pathname().map(PathBuf::from) else {
+                continue;
+            };
+            let command = String::from_utf8_lossy(&buf[..len]).into_owned();
+            let reply = shared.lock().unwrap().reply(&command);
+            let _ = socket.send_to(reply.as_bytes(), &client);
+
+            // Scans complete immediately; tell the attached client
+            if command == "ATTACH" {
+                attached = Some(client);
+            } else if command == "SCAN"
+                && let Some(events) = &attached
+            {
+                let _ = socket.send_to(b"<2>CTRL-EVENT-SCAN-RESULTS ", events);
+            }
+        }
+    });
+
+    (dir, state)
+}
+
+/// A daemon with one saved block for the robot that accepts any password.
+fn robot_saved() -> FakeState {
+    FakeState {
+        networks: vec![(0, "WAVESHARE Robot".to_string())],
+        next_id: 1,
+        psk_reply: "OK".to_string(),
+        ..FakeState::default()
+    }
+}
+
+#[test]
+fn connect_saves_only_after_completed_and_replaces_old_block() {
+    let (dir, state) = fake_daemon("connect", robot_saved());
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    backend.connect("wlan1", "WAVESHARE Robot", "1234567890").unwrap();
+
+    let state = state.lock().unwrap();
+    assert_eq!(state.networks, vec![(1, "WAVESHARE Robot".to_string())]);
+    assert_eq!(state.commands("SET_NETWORK 1 psk"), vec!["SET_NETWORK 1 psk \"1234567890\""]);
+
+    // The configuration is written last, after the link came up
+    let completed = state.log.iter().position(|c| c == "STATUS").unwrap();
+    let saved = state.log.iter().position(|c| c == "SAVE_CONFIG").unwrap();
+    assert!(saved > completed);
+    assert_eq!(state.log.last().map(String::as_str), Some("SAVE_CONFIG"));
+}
+
+#[test]
+fn rejected_password_removes_new_block_and_keeps_old_one() {
+    let (dir, state) = fake_daemon(
+        "rejected",
+        FakeState {
+            psk_reply: "FAIL".to_string(),
+            ..robot_saved()
+        },
+    );
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    let err = backend.connect("wlan1", "WAVESHARE Robot", "short").unwrap_err();
+
+    assert!(err.to_string().contains("rejected the password: FAIL"), "{}", err);
+    let state = state.lock().unwrap();
+    assert_eq!(state.networks, vec![(0, "WAVESHARE Robot".to_string())]);
+    assert_eq!(state.commands("REMOVE_NETWORK"), vec!["REMOVE_NETWORK 1"]);
+    assert!(state.commands("SELECT_NETWORK").is_empty());
+    assert!(state.commands("SAVE_CONFIG").is_empty());
+}
+
+#[test]
+fn connect_hidden_sets_scan_ssid_and_open_key_mgmt() {
+    let (dir, state) = fake_daemon("hidden", robot_saved());
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    backend.connect_hidden("wlan1", "Hidden Robot", "").unwrap();
+
+    let state = state.lock().unwrap();
+    assert!(state.log.contains(&"SET_NETWORK 1 scan_ssid 1".to_string()));
+    assert!(state.log.contains(&"SET_NETWORK 1 key_mgmt NONE".to_string()));
+    assert_eq!(
+        state.networks,
+        vec![(0, "WAVESHARE Robot".to_string()), (1, "Hidden Robot".to_string())]
+    );
+}
+
+#[test]
+fn connect_bssid_restricts_block_to_access_point() {
+    let (dir, state) = fake_daemon("bssid", robot_saved());
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    backend
+        .connect_bssid("wlan1", "WAVESHARE Robot", "24:0A:C4:12:34:56", "1234567890")
+        .unwrap();
+
+    let state = state.lock().unwrap();
+    assert_eq!(state.commands("SET_NETWORK 1 bssid"), vec!["SET_NETWORK 1 bssid 24:0a:c4:12:34:56"]);
+    assert!(state.commands("SET_NETWORK 1 scan_ssid").is_empty());
+    assert_eq!(state.networks, vec![(1, "WAVESHARE Robot".to_string())]);
+}
+
+#[test]
+fn status_reports_ssid_once_completed() {
+    let (dir, _state) = fake_daemon("status", robot_saved());
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    let before = backend.status("wlan1").unwrap();
+    assert_eq!(before.connection, None);
+
+    backend.connect("wlan1", "WAVESHARE Robot", "1234567890").unwrap();
+    let after = backend.status("wlan1").unwrap();
+
+    assert_eq!(after.state, "COMPLETED (connected)");
+    assert_eq!(after.connection.as_deref(), Some("WAVESHARE Robot"));
+    assert_eq!(after.ip_address.as_deref(), Some("192.168.4.2"));
+}
+
+#[test]
+fn scan_parses_results_after_scan_event() {
+    let (dir, state) = fake_daemon("scan", robot_saved());
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    let networks = backend.scan("wlan1").unwrap();
+
+    assert_eq!(networks.len(), 3);
+    assert_eq!(networks[0].ssid, "WAVESHARE Robot");
+    assert_eq!(networks[0].bssid, "24:0a:c4:12:34:56");
+    assert_eq!(networks[0].security, Security::Wpa2);
+    assert_eq!(networks[1].ssid, "Home\u{2019}s");
+    assert_eq!(networks[1].security, Security::Wpa3);
+    assert!(networks[2].is_hidden());
+    assert_eq!(state.lock().unwrap().commands("DETACH").len(), 1);
+}
+
+#[test]
+fn missing_socket_is_unknown_interface() {
+    let (dir, _state) = fake_daemon("missing", robot_saved());
+    let backend = WpaSupplicantBackend::with_ctrl_dir(&dir);
+
+    let err = backend.status("wlan7").unwrap_err();
+
+    assert!(err.to_string().contains("wlan7"), "{}", err);
+}